@@ -0,0 +1,184 @@
+extern crate criterion;
+extern crate mikrodb;
+extern crate tempfile;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use std::mem;
+use std::ops::Bound;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const TRANSACTIONS: u64 = 1000;
+const RECORDS: u64 = 10_000;
+
+/// データベースの格納先を表す
+#[derive(Clone, Copy)]
+enum Storage {
+    /// メモリ上のみ(ディスクへの入出力を除いたCPUのコストを計測する)
+    InMemory,
+    /// 一時ディレクトリ上のファイル
+    File,
+}
+
+impl Storage {
+    fn config(self, dir: &TempDir) -> DatabaseConfig {
+        match self {
+            Storage::InMemory => DatabaseConfig::builder().in_memory(true).build(),
+            Storage::File => DatabaseConfig::builder().data_dir(dir.path()).build(),
+        }
+    }
+
+    /// recordsの件数のキーを書き込んだデータベースを作成する
+    fn open(self, records: u64) -> (TempDir, Database<u64, u64>) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = Database::new(self.config(&dir)).unwrap();
+        db.extend_transaction((0..records).map(|x| (x, x))).unwrap();
+        (dir, db)
+    }
+}
+
+/// 1件ずつのトランザクションによるcreate・read・update・deleteと、1つのトランザクションでの作成を計測する
+fn single_key(c: &mut Criterion, name: &str, storage: Storage) {
+    let mut group = c.benchmark_group(format!("{}/single_key", name));
+    group.throughput(Throughput::Elements(TRANSACTIONS));
+    group.bench_function("create", |b| {
+        b.iter_batched(
+            || storage.open(0),
+            |(_dir, mut db)| {
+                for x in 0..TRANSACTIONS {
+                    let mut tx = db.begin_transaction().unwrap();
+                    tx.create(x, x).unwrap();
+                    tx.commit_silent().unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("create_in_one_transaction", |b| {
+        b.iter_batched(
+            || storage.open(0),
+            |(_dir, mut db)| {
+                let mut tx = db.begin_transaction().unwrap();
+                for x in 0..TRANSACTIONS {
+                    tx.create(x, x).unwrap();
+                }
+                tx.commit_silent().unwrap();
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("read", |b| {
+        b.iter_batched(
+            || storage.open(TRANSACTIONS),
+            |(_dir, mut db)| {
+                for x in 0..TRANSACTIONS {
+                    let mut tx = db.begin_transaction().unwrap();
+                    tx.read_silent(x).unwrap();
+                    tx.commit_silent().unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("update", |b| {
+        b.iter_batched(
+            || storage.open(TRANSACTIONS),
+            |(_dir, mut db)| {
+                for x in 0..TRANSACTIONS {
+                    let mut tx = db.begin_transaction().unwrap();
+                    tx.update(x, x + 1).unwrap();
+                    tx.commit_silent().unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("delete", |b| {
+        b.iter_batched(
+            || storage.open(TRANSACTIONS),
+            |(_dir, mut db)| {
+                for x in 0..TRANSACTIONS {
+                    let mut tx = db.begin_transaction().unwrap();
+                    tx.delete(x).unwrap();
+                    tx.commit_silent().unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.finish();
+}
+
+/// 10k件を対象とするチェックポイント・クラッシュリカバリ・範囲の走査を計測する
+///
+/// メモリ上のみの場合、クラッシュリカバリは再び開くログが存在しないため計測しない。
+fn bulk(c: &mut Criterion, name: &str, storage: Storage) {
+    let mut group = c.benchmark_group(format!("{}/bulk", name));
+    group.throughput(Throughput::Elements(RECORDS));
+    group.bench_function("checkpoint", |b| {
+        b.iter_batched(
+            || storage.open(RECORDS),
+            |(_dir, mut db)| {
+                db.compact_wal().unwrap();
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    if let Storage::File = storage {
+        group.bench_function("crash_recovery", |b| {
+            b.iter_batched(
+                || {
+                    let (dir, db) = storage.open(0);
+                    let mut db = db;
+                    for x in 0..RECORDS {
+                        let mut tx = db.begin_transaction().unwrap();
+                        tx.create(x, x).unwrap();
+                        tx.commit_silent().unwrap();
+                    }
+                    // チェックポイントを作成せずに終了し、ロックファイルのみを取り除く
+                    let lock_path = db.lock_path().unwrap().to_path_buf();
+                    mem::forget(db);
+                    std::fs::remove_file(lock_path).unwrap();
+                    dir
+                },
+                |dir| {
+                    let db: Database<u64, u64> = Database::new(storage.config(&dir)).unwrap();
+                    assert_eq!(db.len() as u64, RECORDS);
+                    dir
+                },
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    let (_dir, mut db) = storage.open(RECORDS);
+    group.bench_function("scan_range", |b| {
+        b.iter(|| {
+            let mut tx = db.begin_transaction().unwrap();
+            let count = tx.scan_range(Bound::Unbounded, Bound::Unbounded).count();
+            assert_eq!(count as u64, RECORDS);
+            tx.abort().unwrap();
+        });
+    });
+    group.finish();
+}
+
+fn in_memory(c: &mut Criterion) {
+    single_key(c, "crud_in_memory", Storage::InMemory);
+    bulk(c, "crud_in_memory", Storage::InMemory);
+}
+
+fn file_backed(c: &mut Criterion) {
+    single_key(c, "crud_file", Storage::File);
+    bulk(c, "crud_file", Storage::File);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(2));
+    targets = in_memory, file_backed
+}
+criterion_main!(benches);
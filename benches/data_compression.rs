@@ -0,0 +1,57 @@
+extern crate criterion;
+extern crate mikrodb;
+extern crate tempfile;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::serialization::CompressionLevel;
+
+const ENTRIES: u64 = 100_000;
+
+/// 100k件のデータファイルからの初期化に要する時間を、圧縮の度合いごとに計測する
+///
+/// 初期化はCrash-recovery後にチェックポイントを作成するため、展開に加えて圧縮に要する時間も含まれる。
+/// 各度合いのデータファイルのサイズは標準出力に表示する。
+fn data_compression(c: &mut Criterion) {
+    let mut group = c.benchmark_group("data_compression");
+    group.sample_size(10);
+    for &level in &[
+        CompressionLevel::None,
+        CompressionLevel::Fast,
+        CompressionLevel::Default,
+        CompressionLevel::Best,
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig::builder()
+            .data_dir(dir.path())
+            .data_compression(level)
+            .build();
+        {
+            let mut db: Database<u64, String> = Database::new(config.clone()).unwrap();
+            let mut tx = db.begin_transaction().unwrap();
+            for x in 0..ENTRIES {
+                tx.upsert(x, format!("value-{}", x % 1000)).unwrap();
+            }
+            tx.commit().unwrap();
+            db.compact_wal().unwrap();
+        }
+        let size = std::fs::metadata(config.data_path()).unwrap().len();
+        println!("data_compression/{:?}: {} bytes", level, size);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{:?}", level)),
+            &config,
+            |b, config| {
+                b.iter(|| {
+                    let db: Database<u64, String> = Database::new(config.clone()).unwrap();
+                    assert_eq!(db.len() as u64, ENTRIES);
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, data_compression);
+criterion_main!(benches);
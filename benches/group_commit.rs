@@ -0,0 +1,57 @@
+extern crate criterion;
+extern crate mikrodb;
+extern crate tempfile;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::shared::SharedDatabase;
+use std::thread;
+
+const THREADS: i32 = 10;
+const TRANSACTIONS: i32 = 1000;
+
+/// 複数のスレッドから並行してCommitした際のスループットを、グループコミットの有無ごとに計測する
+fn group_commit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("group_commit");
+    group.throughput(Throughput::Elements(TRANSACTIONS as u64));
+    group.sample_size(10);
+    for &enabled in &[false, true] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(enabled),
+            &enabled,
+            |b, &enabled| {
+                b.iter_batched(
+                    || {
+                        let dir = tempfile::tempdir().unwrap();
+                        let config = DatabaseConfig::builder()
+                            .data_dir(dir.path())
+                            .enable_group_commit(enabled)
+                            .build();
+                        let db: Database<i32, i32> = Database::new(config).unwrap();
+                        (dir, SharedDatabase::new(db))
+                    },
+                    |(_dir, db)| {
+                        thread::scope(|s| {
+                            for t in 0..THREADS {
+                                let db = &db;
+                                s.spawn(move || {
+                                    for i in 0..TRANSACTIONS / THREADS {
+                                        let mut tx = db.begin_transaction().unwrap();
+                                        tx.create(t * TRANSACTIONS + i, i).unwrap();
+                                        tx.commit().unwrap();
+                                    }
+                                });
+                            }
+                        });
+                    },
+                    BatchSize::PerIteration,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, group_commit);
+criterion_main!(benches);
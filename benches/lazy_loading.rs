@@ -0,0 +1,42 @@
+extern crate criterion;
+extern crate mikrodb;
+extern crate tempfile;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+
+const ENTRIES: u64 = 50_000;
+
+/// 50k件のデータファイルからの初期化に要する時間を、値を初期化時に復元する場合と参照時に復元する場合で計測する
+///
+/// 初期化はCrash-recovery後にチェックポイントを作成するため、データファイルの書き出しに要する時間も含まれる。
+fn lazy_loading(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lazy_loading");
+    group.sample_size(10);
+    let dir = tempfile::tempdir().unwrap();
+    let config = DatabaseConfig::builder().data_dir(dir.path()).build();
+    {
+        let mut db: Database<u64, Vec<u64>> = Database::new(config.clone()).unwrap();
+        db.extend_transaction((0..ENTRIES).map(|x| (x, (x..x + 16).collect())))
+            .unwrap();
+        db.compact_wal().unwrap();
+    }
+    for &lazy in &[false, true] {
+        let config = DatabaseConfig {
+            lazy_loading: lazy,
+            ..config.clone()
+        };
+        let name = if lazy { "lazy" } else { "eager" };
+        group.bench_with_input(BenchmarkId::from_parameter(name), &config, |b, config| {
+            b.iter(|| {
+                let db: Database<u64, Vec<u64>> = Database::new(config.clone()).unwrap();
+                assert_eq!(db.len() as u64, ENTRIES);
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lazy_loading);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+extern crate criterion;
+extern crate mikrodb;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::store::StorageBackend;
+
+const ENTRIES: u64 = 100_000;
+const OPERATIONS: u64 = 10_000;
+
+/// ベンチマークの再現性のため、固定のシードから擬似乱数のキーを生成する(xorshift64)
+fn random_keys(count: u64) -> Vec<u64> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % ENTRIES
+        })
+        .collect()
+}
+
+fn open(backend: StorageBackend) -> Database<u64, u64> {
+    let config = DatabaseConfig::builder()
+        .in_memory(true)
+        .storage_backend(backend)
+        .build();
+    Database::new(config).unwrap()
+}
+
+/// 100k件のデータベースに対するランダムなキーの読み取り・書き込みのスループットを、バックエンドごとに計測する
+fn storage_backend(c: &mut Criterion) {
+    let keys = random_keys(OPERATIONS);
+    let mut group = c.benchmark_group("storage_backend");
+    group.throughput(Throughput::Elements(OPERATIONS));
+    for &backend in &[StorageBackend::Sorted, StorageBackend::Hashed] {
+        let mut db = open(backend);
+        db.extend_transaction((0..ENTRIES).map(|x| (x, x))).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("random_read", format!("{:?}", backend)),
+            &keys,
+            |b, keys| {
+                b.iter(|| {
+                    let tx = db.begin_read_transaction().unwrap();
+                    for key in keys {
+                        tx.read(*key).unwrap();
+                    }
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("random_write", format!("{:?}", backend)),
+            &keys,
+            |b, keys| {
+                b.iter_batched(
+                    || {
+                        let mut db = open(backend);
+                        db.extend_transaction((0..ENTRIES).map(|x| (x, x))).unwrap();
+                        db
+                    },
+                    |mut db| {
+                        let mut tx = db.begin_transaction().unwrap();
+                        for key in keys {
+                            tx.upsert(*key, key + 1).unwrap();
+                        }
+                        tx.commit().unwrap();
+                        db
+                    },
+                    BatchSize::PerIteration,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, storage_backend);
+criterion_main!(benches);
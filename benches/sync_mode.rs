@@ -0,0 +1,48 @@
+extern crate criterion;
+extern crate mikrodb;
+extern crate tempfile;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::log::SyncMode;
+
+const TRANSACTIONS: u64 = 10_000;
+
+/// 1件ずつCommitする10kのトランザクションの時間を、SyncModeごとに計測する
+fn sync_mode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sync_mode");
+    group.throughput(Throughput::Elements(TRANSACTIONS));
+    group.sample_size(10);
+    for &mode in &[SyncMode::Full, SyncMode::DataOnly, SyncMode::None] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{:?}", mode)),
+            &mode,
+            |b, &mode| {
+                b.iter_batched(
+                    || {
+                        let dir = tempfile::tempdir().unwrap();
+                        let config = DatabaseConfig::builder()
+                            .data_dir(dir.path())
+                            .sync_mode(mode)
+                            .build();
+                        let db: Database<u64, u64> = Database::new(config).unwrap();
+                        (dir, db)
+                    },
+                    |(_dir, mut db)| {
+                        for x in 0..TRANSACTIONS {
+                            let mut tx = db.begin_transaction().unwrap();
+                            tx.create(x, x).unwrap();
+                            tx.commit_silent().unwrap();
+                        }
+                    },
+                    BatchSize::PerIteration,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sync_mode);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+extern crate criterion;
+extern crate mikrodb;
+extern crate tempfile;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use mikrodb::log::{LogRecord, WALManager};
+use mikrodb::segment::SegmentedLog;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+const RECORDS: u64 = 10_000;
+
+/// fsyncを伴わない書き込みのスループットを、書き込みバッファのサイズごとに計測する
+fn wal_write(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wal_write");
+    group.throughput(Throughput::Elements(RECORDS));
+    for &buffer_size in &[0, 4 * 1024, 64 * 1024] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(buffer_size),
+            &buffer_size,
+            |b, &buffer_size| {
+                b.iter_batched(
+                    || {
+                        let dir = tempfile::tempdir().unwrap();
+                        let mut wal = WALManager::new(dir.path().join("wal")).unwrap();
+                        wal.set_buffer_size(buffer_size).unwrap();
+                        (dir, wal)
+                    },
+                    |(_dir, mut wal)| {
+                        for x in 0..RECORDS {
+                            let record: LogRecord<u64, u64> =
+                                LogRecord::Create { key: x, value: x };
+                            wal.write_log(&record, false).unwrap();
+                        }
+                        wal.flush_buffer().unwrap();
+                    },
+                    BatchSize::PerIteration,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+/// 1フレーム分の書き込みに要する時間を、追記モードのファイルとセグメント(pwrite)とで比較する
+fn segment_write(c: &mut Criterion) {
+    let frame = [0u8; 64];
+    let mut group = c.benchmark_group("segment_write");
+    group.throughput(Throughput::Elements(RECORDS));
+    group.bench_function("append", |b| {
+        b.iter_batched(
+            || {
+                let dir = tempfile::tempdir().unwrap();
+                let file = OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(dir.path().join("wal"))
+                    .unwrap();
+                (dir, file)
+            },
+            |(_dir, mut file)| {
+                for _ in 0..RECORDS {
+                    file.write_all(&frame).unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("segmented", |b| {
+        b.iter_batched(
+            || {
+                let dir = tempfile::tempdir().unwrap();
+                let log = SegmentedLog::open(dir.path().join("wal")).unwrap();
+                (dir, log)
+            },
+            |(_dir, mut log)| {
+                for _ in 0..RECORDS {
+                    log.write_all(&frame).unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(benches, wal_write, segment_write);
+criterion_main!(benches);
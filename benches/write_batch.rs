@@ -0,0 +1,55 @@
+extern crate criterion;
+extern crate mikrodb;
+extern crate tempfile;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use tempfile::TempDir;
+
+const RECORDS: u64 = 10_000;
+
+fn open() -> (TempDir, Database<u64, u64>) {
+    let dir = tempfile::tempdir().unwrap();
+    let config = DatabaseConfig::builder()
+        .log_file(dir.path().join("wal"))
+        .data_file(dir.path().join("data"))
+        .build();
+    let db = Database::new(config).unwrap();
+    (dir, db)
+}
+
+/// 1つのトランザクションで10k件を書き込む時間を、個々のupsertと1回のwrite_batchとで比較する
+fn write_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("write_batch");
+    group.throughput(Throughput::Elements(RECORDS));
+    group.bench_function("individual", |b| {
+        b.iter_batched(
+            open,
+            |(_dir, mut db)| {
+                let mut tx = db.begin_transaction().unwrap();
+                for x in 0..RECORDS {
+                    tx.upsert(x, x).unwrap();
+                }
+                tx.commit_silent().unwrap();
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("batch", |b| {
+        b.iter_batched(
+            open,
+            |(_dir, mut db)| {
+                let mut tx = db.begin_transaction().unwrap();
+                tx.write_batch((0..RECORDS).map(|x| (x, Option::Some(x))))
+                    .unwrap();
+                tx.commit_silent().unwrap();
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(benches, write_batch);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+extern crate mikrodb;
+
+use mikrodb::database::Database;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const ENTRIES: u64 = 10_000;
+
+/// 確保中のバイト数を数えるアロケータ
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// 10k件のキーを削除したトランザクションが、Commitの前に保持しているメモリのバイト数を計測する
+///
+/// 書き込みセットの大きさを値の大きさと比較できるよう、値には128 bytesの配列を用いる。
+/// メモリ上のログに書き込まれたDeleteレコードの分も含まれる。
+fn main() {
+    let mut db: Database<u64, [u64; 16]> = Database::in_memory().unwrap();
+    db.extend_transaction((0..ENTRIES).map(|x| (x, [x; 16])))
+        .unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    for key in 0..ENTRIES {
+        tx.delete(key).unwrap();
+    }
+    let after = ALLOCATED.load(Ordering::Relaxed);
+    tx.commit().unwrap();
+    assert!(db.is_empty());
+    println!(
+        "writeset_memory/delete_{}: {} bytes ({:.1} bytes per key)",
+        ENTRIES,
+        after - before,
+        (after - before) as f64 / ENTRIES as f64
+    );
+}
@@ -0,0 +1,53 @@
+//! データファイルを失った場合に、ログからデータファイルを再構築する手順を示す
+//!
+//! `cargo run --example disaster_recovery`で実行する。
+extern crate mikrodb;
+extern crate tempfile;
+
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::error::DatabaseError;
+use std::mem;
+use std::path::Path;
+
+fn main() -> Result<(), DatabaseError> {
+    let dir = tempfile::tempdir()?;
+    let logpath = dir.path().join("mikrodb.log");
+    let datapath = dir.path().join("mikrodb.db");
+    let config = DatabaseConfig::builder().data_dir(dir.path()).build();
+
+    // 1. 通常通り書き込む。ここではチェックポイントを作成せずに終了したものとする
+    {
+        let mut db: Database<String, String> = Database::new(config.clone())?;
+        let mut tx = db.begin_transaction()?;
+        tx.create("alice".to_string(), "admin".to_string())?;
+        tx.create("bob".to_string(), "member".to_string())?;
+        tx.commit()?;
+        // 同じプロセス内で開き直すため、クラッシュしたプロセスと同様にロックを残さない
+        let lock_path = db.lock_path().map(Path::to_path_buf);
+        mem::forget(db);
+        if let Option::Some(lock_path) = lock_path {
+            std::fs::remove_file(lock_path)?;
+        }
+    }
+
+    // 2. データファイルを失う
+    let _ = std::fs::remove_file(&datapath);
+
+    // 3. ログからデータファイルを再構築する(ログは変更されない)
+    let rebuilt = dir.path().join("rebuilt.db");
+    Database::<String, String>::rebuild_from_wal(
+        logpath.to_str().unwrap(),
+        rebuilt.to_str().unwrap(),
+    )?;
+
+    // 4. 再構築したデータファイルを配置し、通常通り開く
+    std::fs::rename(&rebuilt, &datapath)?;
+    let db: Database<String, String> = Database::new(config)?;
+    let tx = db.begin_read_transaction()?;
+    for (key, value) in db.scan_all()? {
+        println!("{} = {}", key, value);
+    }
+    assert_eq!(tx.read("alice".to_string())?, "admin");
+    Result::Ok(())
+}
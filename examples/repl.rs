@@ -0,0 +1,195 @@
+//! 標準入力から読み取ったコマンドにより、`Database<String, String>`を対話的に操作する
+//!
+//! `cargo run --example repl -- [データファイルのディレクトリ]`で実行する。ディレクトリを省略した場合は
+//! メモリ上のみで動作する。コマンドは常に実行中のトランザクションに対して行われ、`commit`・`rollback`の
+//! 後には新しいトランザクションが開始される。`help`で使用できるコマンドを表示する。
+//!
+//! 行編集・履歴には対応せず、標準入力から1行ずつ読み取る。行編集が必要な場合は`rlwrap`などを併用する。
+extern crate mikrodb;
+
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::{Database, Transaction};
+use mikrodb::error::DatabaseError;
+use mikrodb::stats::Statistics;
+use std::io::{self, BufRead, Write};
+use std::ops::Bound;
+
+const HELP: &str = "\
+get <key>            キーの値を表示する
+set <key> <value>    キーに値を書き込む(値には空白を含められる)
+del <key>            キーを削除する
+scan                 すべてのキーバリューペアを表示する
+scan <start> <end>   start以上end未満のキーバリューペアを表示する
+commit               トランザクションをCommitし、新しいトランザクションを開始する
+rollback             トランザクションをAbortし、新しいトランザクションを開始する
+stats                統計情報を表示する
+quit                 Commitされていない変更を破棄して終了する";
+
+/// 1行分のコマンドを表す
+enum Command {
+    Get(String),
+    Set(String, String),
+    Del(String),
+    Scan(Bound<String>, Bound<String>),
+    Commit,
+    Rollback,
+    Stats,
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// lineを解釈する(空行の場合はNone)
+    fn parse(line: &str) -> Result<Option<Command>, String> {
+        let line = line.trim();
+        let (name, rest) = match line.split_once(char::is_whitespace) {
+            Option::Some((name, rest)) => (name, rest.trim()),
+            Option::None => (line, ""),
+        };
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let command = match (name, args.as_slice()) {
+            ("", _) => return Result::Ok(Option::None),
+            ("get", [key]) => Command::Get(key.to_string()),
+            ("set", [key, ..]) => {
+                let value = rest[key.len()..].trim_start();
+                Command::Set(key.to_string(), value.to_string())
+            }
+            ("del", [key]) => Command::Del(key.to_string()),
+            ("scan", []) => Command::Scan(Bound::Unbounded, Bound::Unbounded),
+            ("scan", [start, end]) => Command::Scan(
+                Bound::Included(start.to_string()),
+                Bound::Excluded(end.to_string()),
+            ),
+            ("commit", []) => Command::Commit,
+            ("rollback", []) => Command::Rollback,
+            ("stats", []) => Command::Stats,
+            ("help", []) => Command::Help,
+            ("quit", []) | ("exit", []) => Command::Quit,
+            ("get" | "set" | "del" | "scan" | "commit" | "rollback" | "stats", _) => {
+                return Result::Err(format!("invalid arguments for `{}` (see `help`)", name))
+            }
+            _ => return Result::Err(format!("unknown command `{}` (see `help`)", name)),
+        };
+        Result::Ok(Option::Some(command))
+    }
+}
+
+/// トランザクションを終了させるコマンドの結果を表す
+enum Outcome {
+    Commit,
+    Rollback,
+    Quit,
+}
+
+/// txに対してcommandを実行し、トランザクションを終了させる場合はその結果を返す
+fn execute(
+    tx: &mut Transaction<'_, String, String>,
+    stats: &Statistics,
+    command: Command,
+) -> Result<Option<Outcome>, DatabaseError> {
+    match command {
+        Command::Get(key) => match tx.get_ref(&key)? {
+            Option::Some(value) => println!("{}", value),
+            Option::None => println!("(not found)"),
+        },
+        Command::Set(key, value) => {
+            if tx.upsert(key, value)? {
+                println!("updated");
+            } else {
+                println!("created");
+            }
+        }
+        Command::Del(key) => match tx.delete(key) {
+            Result::Ok(()) => println!("deleted"),
+            Result::Err(DatabaseError::KeyNotFoundError) => println!("(not found)"),
+            Result::Err(e) => return Result::Err(e),
+        },
+        Command::Scan(start, end) => {
+            let mut count = 0;
+            for pair in tx.scan_range(start, end) {
+                let (key, value) = pair?;
+                println!("{} = {}", key, value);
+                count += 1;
+            }
+            println!("({} pairs)", count);
+        }
+        Command::Commit => return Result::Ok(Option::Some(Outcome::Commit)),
+        Command::Rollback => return Result::Ok(Option::Some(Outcome::Rollback)),
+        Command::Stats => {
+            println!("records:                {}", stats.current_record_count());
+            println!("uncommitted writes:     {}", tx.len());
+            println!(
+                "committed transactions: {}",
+                stats.total_transactions_committed()
+            );
+            println!(
+                "aborted transactions:   {}",
+                stats.total_transactions_aborted()
+            );
+            println!("checkpoints:            {}", stats.total_checkpoints());
+            println!(
+                "WAL bytes written:      {}",
+                stats.total_wal_bytes_written()
+            );
+        }
+        Command::Help => println!("{}", HELP),
+        Command::Quit => return Result::Ok(Option::Some(Outcome::Quit)),
+    }
+    Result::Ok(Option::None)
+}
+
+fn main() -> Result<(), DatabaseError> {
+    let config = match std::env::args().nth(1) {
+        Option::Some(dir) => DatabaseConfig::builder().data_dir(dir).build(),
+        Option::None => DatabaseConfig::builder().in_memory(true).build(),
+    };
+    let mut db: Database<String, String> = Database::new(config)?;
+    let stats = db.stats();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        let mut tx = db.begin_transaction()?;
+        let outcome = loop {
+            print!("mikrodb> ");
+            io::stdout().flush()?;
+            let line = match lines.next() {
+                Option::Some(line) => line?,
+                // 入力の終端はquitと同様に扱う
+                Option::None => {
+                    println!();
+                    break Outcome::Quit;
+                }
+            };
+            let command = match Command::parse(&line) {
+                Result::Ok(Option::Some(command)) => command,
+                Result::Ok(Option::None) => continue,
+                Result::Err(message) => {
+                    println!("error: {}", message);
+                    continue;
+                }
+            };
+            match execute(&mut tx, &stats, command) {
+                Result::Ok(Option::Some(outcome)) => break outcome,
+                Result::Ok(Option::None) => {}
+                Result::Err(e) => println!("error: {}", e),
+            }
+        };
+        match outcome {
+            Outcome::Commit => {
+                let changes = tx.commit()?;
+                println!("committed ({} changes)", changes.len());
+            }
+            Outcome::Rollback => {
+                tx.abort()?;
+                println!("rolled back");
+            }
+            Outcome::Quit => {
+                if tx.is_dirty() {
+                    println!("discarding {} uncommitted writes", tx.len());
+                }
+                tx.abort()?;
+                return Result::Ok(());
+            }
+        }
+    }
+}
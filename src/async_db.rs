@@ -0,0 +1,200 @@
+use crate::database::{Database, Transaction};
+use crate::error::DatabaseError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::Bound;
+use std::panic;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+type BlockingTransaction<K, V> = Transaction<'static, K, V, OwnedMutexGuard<Database<K, V>>>;
+
+/// 非同期コードから利用可能なデータベースを表す
+///
+/// 内部で`tokio::sync::Mutex`によりデータベースを保護しており、トランザクションはロックを
+/// 保持したまま実行される。ログへの書き込みなどのI/Oは`spawn_blocking`で実行されるため、
+/// executorのスレッドをブロックしない。
+pub struct AsyncDatabase<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash + Send + 'static,
+    V: Debug + Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    inner: Arc<Mutex<Database<K, V>>>,
+}
+
+/// `AsyncDatabase`から開始されたトランザクション
+///
+/// 操作の完了を待たずにfutureを破棄した場合、トランザクションはAbortされる。
+pub struct AsyncTransaction<'tx, K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash + Send + 'static,
+    V: Debug + Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    tx: Option<BlockingTransaction<K, V>>,
+    _marker: PhantomData<&'tx AsyncDatabase<K, V>>,
+}
+
+impl<K, V> AsyncDatabase<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash + Send + 'static,
+    V: Debug + Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    /// データベースを非同期コードから利用可能にする
+    pub fn new(database: Database<K, V>) -> Self {
+        AsyncDatabase {
+            inner: Arc::new(Mutex::new(database)),
+        }
+    }
+
+    /// ロックを取得し、トランザクションを開始する
+    ///
+    /// 他のトランザクションが実行中の場合、それが終了するまで待機する。
+    pub async fn begin_transaction(&self) -> AsyncTransaction<'_, K, V> {
+        let guard = Arc::clone(&self.inner).lock_owned().await;
+        AsyncTransaction {
+            tx: Option::Some(Transaction::new(guard)),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Clone for AsyncDatabase<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash + Send + 'static,
+    V: Debug + Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        AsyncDatabase {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<'tx, K, V> AsyncTransaction<'tx, K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash + Send + 'static,
+    V: Debug + Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    /// トランザクションに対する操作をブロッキング用のスレッドで実行する
+    async fn run<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut BlockingTransaction<K, V>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let mut tx = self.tx.take().expect("transaction is already finished");
+        let task = tokio::task::spawn_blocking(move || {
+            let result = f(&mut tx);
+            (tx, result)
+        });
+        let (tx, result) = match task.await {
+            Result::Ok(output) => output,
+            Result::Err(e) => panic::resume_unwind(e.into_panic()),
+        };
+        self.tx = Option::Some(tx);
+        result
+    }
+
+    /// トランザクションを消費する操作をブロッキング用のスレッドで実行する
+    ///
+    /// ロックは操作の完了後、ブロッキング用のスレッド上で解放される。
+    async fn finish<F>(mut self, f: F) -> Result<(), DatabaseError>
+    where
+        F: FnOnce(BlockingTransaction<K, V>) -> Result<(), DatabaseError> + Send + 'static,
+    {
+        let tx = self.tx.take().expect("transaction is already finished");
+        match tokio::task::spawn_blocking(move || f(tx)).await {
+            Result::Ok(result) => result,
+            Result::Err(e) => panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// keyに対応する値をvalueとして新規設定する
+    pub async fn create(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
+        self.run(move |tx| tx.create(key, value)).await
+    }
+
+    /// keyに対応する値を読み取り、Readレコードをログに書き込む
+    #[deprecated(note = "Readレコードをログに書き込まない`read_silent`を使用すること")]
+    pub async fn read(&mut self, key: K) -> Result<V, DatabaseError> {
+        #[allow(deprecated)]
+        self.run(move |tx| tx.read(key)).await
+    }
+
+    /// keyに対応する値を読み取る(ログには書き込まない)
+    pub async fn read_silent(&mut self, key: K) -> Result<V, DatabaseError> {
+        self.run(move |tx| tx.read_silent(key)).await
+    }
+
+    /// 指定された範囲のキーバリューペアをキーの昇順に読み取る
+    pub async fn scan_range(
+        &mut self,
+        start: Bound<K>,
+        end: Bound<K>,
+    ) -> Result<Vec<(K, V)>, DatabaseError> {
+        self.run(move |tx| tx.scan_range(start, end).collect())
+            .await
+    }
+
+    /// keyに対応する値をvalueとして更新する
+    pub async fn update(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
+        self.run(move |tx| tx.update(key, value)).await
+    }
+
+    /// keyに対応する値をvalueとして設定する
+    ///
+    /// 戻り値は、keyが既に存在していたかどうかを表す。
+    pub async fn upsert(&mut self, key: K, value: V) -> Result<bool, DatabaseError> {
+        self.run(move |tx| tx.upsert(key, value)).await
+    }
+
+    /// keyに対応する値がexpectedと一致する場合に限り、new_valueとして更新する
+    pub async fn compare_and_swap(
+        &mut self,
+        key: K,
+        expected: V,
+        new_value: V,
+    ) -> Result<bool, DatabaseError>
+    where
+        V: PartialEq,
+    {
+        self.run(move |tx| tx.compare_and_swap(key, &expected, new_value))
+            .await
+    }
+
+    /// keyに対応する値を削除する
+    pub async fn delete(&mut self, key: K) -> Result<(), DatabaseError> {
+        self.run(move |tx| tx.delete(key)).await
+    }
+
+    /// Commitする(トランザクションを反映する)
+    pub async fn commit(self) -> Result<(), DatabaseError> {
+        self.finish(|tx| tx.commit_silent()).await
+    }
+
+    /// Abortする(トランザクションを破棄する)
+    pub async fn abort(self) -> Result<(), DatabaseError> {
+        self.finish(|tx| tx.abort()).await
+    }
+}
+
+impl<'tx, K, V> Drop for AsyncTransaction<'tx, K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash + Send + 'static,
+    V: Debug + Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    /// 明示的にCommitされないままDropした場合、ブロッキング用のスレッドでAbortする
+    fn drop(&mut self) {
+        if let Option::Some(tx) = self.tx.take() {
+            match Handle::try_current() {
+                Result::Ok(handle) => {
+                    handle.spawn_blocking(move || drop(tx));
+                }
+                Result::Err(_) => drop(tx),
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::database::{Database, Transaction};
+use crate::error::DatabaseError;
+use crate::prefix::HasPrefix;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// キー・値ともにバイト列であるデータベース
+pub type BytesDatabase = Database<Bytes, Bytes>;
+
+/// `BytesDatabase`から開始されたトランザクション
+pub type BytesTransaction<'tx> = Transaction<'tx, Bytes, Bytes>;
+
+/// データベースのキー・値として用いるバイト列を表す
+///
+/// JSONなどの人間が読める形式(JSONのWAL・データファイル)ではbase64の文字列として、
+/// bincodeなどのバイナリ形式では長さを前置したバイト列としてそのまま直列化される。
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+    /// バイト列をスライスとして返す
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        Bytes(bytes.to_vec())
+    }
+}
+
+impl From<Bytes> for Vec<u8> {
+    fn from(bytes: Bytes) -> Self {
+        bytes.0
+    }
+}
+
+impl HasPrefix for Bytes {
+    fn starts_with(&self, prefix: &Self) -> bool {
+        self.as_slice().starts_with(prefix.as_slice())
+    }
+}
+
+impl serde::Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(&self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BytesVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+}
+
+/// base64の文字列・バイト列・u8の列から`Bytes`を復元する
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a base64 string or a byte array")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Bytes, E> {
+        STANDARD.decode(v).map(Bytes).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Bytes, E> {
+        Result::Ok(Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Bytes, E> {
+        Result::Ok(Bytes(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Option::Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Result::Ok(Bytes(bytes))
+    }
+}
+
+impl<'tx, D> Transaction<'tx, Bytes, Bytes, D>
+where
+    D: DerefMut<Target = BytesDatabase>,
+{
+    /// keyに対応する値をvalueとして設定する(`upsert`と同じ)
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DatabaseError> {
+        self.upsert(Bytes::from(key), Bytes::from(value))
+            .map(|_| ())
+    }
+
+    /// keyに対応する値を複製せずに読み取る(`get_ref`と同じ)
+    pub fn get(&self, key: &[u8]) -> Result<Option<&[u8]>, DatabaseError> {
+        Result::Ok(self.get_ref(&Bytes::from(key))?.map(Bytes::as_slice))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::Bytes;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn serialization() {
+        let mut map = BTreeMap::new();
+        map.insert(Bytes(vec![0, 255]), Bytes(b"value".to_vec()));
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, r#"{"AP8=":"dmFsdWU="}"#);
+        assert_eq!(
+            serde_json::from_str::<BTreeMap<Bytes, Bytes>>(&json).unwrap(),
+            map
+        );
+
+        // bincodeでは長さ(8 bytes)の後にバイト列がそのまま記録される
+        let bincode = bincode::serialize(&Bytes(vec![1, 2, 3])).unwrap();
+        assert_eq!(bincode, vec![3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3]);
+        assert_eq!(
+            bincode::deserialize::<Bytes>(&bincode).unwrap(),
+            Bytes(vec![1, 2, 3])
+        );
+    }
+}
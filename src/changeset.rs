@@ -0,0 +1,63 @@
+use std::slice::Iter;
+use std::vec::IntoIter;
+
+/// トランザクションによる1つのキーへの変更を表す
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Change<K, V> {
+    /// キーバリューペアの新規作成
+    Create(K, V),
+    /// キーに紐付くバリューの更新
+    Update(K, V),
+    /// キーバリューペアの削除
+    Delete(K),
+}
+
+/// トランザクションがCommit時に反映する変更の一覧を表す
+///
+/// 変更はキーの昇順に並ぶ。`Database::apply_changeset`により、他のデータベースに同じ変更を
+/// 反映することができる。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Changeset<K, V> {
+    changes: Vec<Change<K, V>>,
+}
+
+impl<K, V> Changeset<K, V> {
+    /// 変更の数を返す
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// 変更を1つも含まないかどうかを返す
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// 変更を順に走査する
+    pub fn iter(&self) -> Iter<'_, Change<K, V>> {
+        self.changes.iter()
+    }
+}
+
+impl<K, V> From<Vec<Change<K, V>>> for Changeset<K, V> {
+    fn from(changes: Vec<Change<K, V>>) -> Self {
+        Changeset { changes }
+    }
+}
+
+impl<K, V> IntoIterator for Changeset<K, V> {
+    type Item = Change<K, V>;
+    type IntoIter = IntoIter<Change<K, V>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a Changeset<K, V> {
+    type Item = &'a Change<K, V>;
+    type IntoIter = Iter<'a, Change<K, V>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
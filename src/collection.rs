@@ -0,0 +1,149 @@
+use crate::database::{Database, Transaction};
+use crate::error::DatabaseError;
+
+use std::marker::PhantomData;
+use std::result::Result;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// 1つのデータベース内で独立したキー空間(コレクション)を表す
+///
+/// キーは`"<コレクション名>:<キーのJSON>"`として、バリューはJSONの文字列としてデータベースに格納される。
+/// (`serde_json::Value`はbincodeで記録されたログから復元できないため、文字列として格納する)
+/// コレクション名はキーの一部としてログにも記録される。
+/// キーはJSONとしての文字列の順に並ぶため、走査の結果はKの順序と一致するとは限らない。
+pub struct Collection<'db, K, V> {
+    database: &'db mut Database<String, String>,
+    prefix: String,
+    _marker: PhantomData<(K, V)>,
+}
+
+/// コレクションに対するトランザクションを表す
+pub struct CollectionTransaction<'tx, K, V> {
+    tx: Transaction<'tx, String, String>,
+    prefix: &'tx str,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl Database<String, String> {
+    /// nameで識別されるコレクションを返す
+    ///
+    /// # Panics
+    /// nameに`:`が含まれる場合はpanicする(他のコレクションのキー空間と重なるため)。
+    pub fn collection<K, V>(&mut self, name: &str) -> Collection<'_, K, V>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+    {
+        assert!(
+            !name.contains(':'),
+            "collection name must not contain ':': {}",
+            name
+        );
+        Collection {
+            database: self,
+            prefix: format!("{}:", name),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'db, K, V> Collection<'db, K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// コレクションの名前を返す
+    pub fn name(&self) -> &str {
+        &self.prefix[..self.prefix.len() - 1]
+    }
+
+    /// コミット済みのキーバリューペアの数を返す
+    pub fn len(&self) -> usize {
+        self.database
+            .keys()
+            .filter(|key| key.starts_with(&self.prefix))
+            .count()
+    }
+
+    /// コミット済みのキーバリューペアが存在しないかどうかを返す
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// コレクションに対するトランザクションを発行する
+    pub fn begin_transaction(&mut self) -> Result<CollectionTransaction<'_, K, V>, DatabaseError> {
+        Result::Ok(CollectionTransaction {
+            tx: self.database.begin_transaction()?,
+            prefix: &self.prefix,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<'tx, K, V> CollectionTransaction<'tx, K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// keyに対応する値をvalueとして新規設定する
+    pub fn create(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
+        let key = self.encode_key(&key)?;
+        self.tx.create(key, serde_json::to_string(&value)?)
+    }
+
+    /// keyに対応する値を読み取る(ログには書き込まない)
+    pub fn read_silent(&mut self, key: K) -> Result<V, DatabaseError> {
+        let key = self.encode_key(&key)?;
+        let value = self.tx.read_silent(key)?;
+        Result::Ok(serde_json::from_str(&value)?)
+    }
+
+    /// keyに対応する値をvalueとして更新する
+    pub fn update(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
+        let key = self.encode_key(&key)?;
+        self.tx.update(key, serde_json::to_string(&value)?)
+    }
+
+    /// keyに対応する値をvalueとして設定する
+    ///
+    /// 戻り値は、keyが既に存在していたかどうかを表す。
+    pub fn upsert(&mut self, key: K, value: V) -> Result<bool, DatabaseError> {
+        let key = self.encode_key(&key)?;
+        self.tx.upsert(key, serde_json::to_string(&value)?)
+    }
+
+    /// keyに対応する値を削除する
+    pub fn delete(&mut self, key: K) -> Result<(), DatabaseError> {
+        let key = self.encode_key(&key)?;
+        self.tx.delete(key)
+    }
+
+    /// コレクション内のすべてのキーバリューペアを読み取る
+    pub fn scan(&mut self) -> Result<Vec<(K, V)>, DatabaseError> {
+        let prefix = self.prefix.to_string();
+        let mut entries = Vec::new();
+        for entry in self.tx.scan_prefix(&prefix) {
+            let (key, value) = entry?;
+            let key = serde_json::from_str(&key[prefix.len()..])?;
+            entries.push((key, serde_json::from_str(&value)?));
+        }
+        Result::Ok(entries)
+    }
+
+    /// トランザクションをコミットする
+    pub fn commit(self) -> Result<(), DatabaseError> {
+        self.tx.commit_silent()
+    }
+
+    /// トランザクションをアボートする
+    pub fn abort(self) -> Result<(), DatabaseError> {
+        self.tx.abort()
+    }
+
+    /// キーをデータベース上のキーに変換する
+    fn encode_key(&self, key: &K) -> Result<String, DatabaseError> {
+        Result::Ok(format!("{}{}", self.prefix, serde_json::to_string(key)?))
+    }
+}
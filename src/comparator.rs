@@ -0,0 +1,160 @@
+use crate::database::{Database, Transaction};
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// キーの順序をCにより定めるデータベース
+///
+/// `Database`に比較の型パラメータを持たせる代わりに、キーを`ComparatorKey`で包んで順序を与える。
+/// キーは`ComparatorKey::new`または`into()`で包んで渡す。
+pub type ComparatorDatabase<K, V, C> = Database<ComparatorKey<K, C>, V>;
+
+/// `ComparatorDatabase`から開始されたトランザクション
+pub type ComparatorTransaction<'tx, K, V, C> = Transaction<'tx, ComparatorKey<K, C>, V>;
+
+/// キーの順序を表す
+///
+/// `compare`が`Ordering::Equal`を返すキーは同じキーとして扱われる。
+/// `hash`は`compare`が等しいとみなすキーに対して同じ値を書き込まなければならない。
+pub trait Comparator<K>: Default {
+    /// aとbの順序を返す
+    fn compare(a: &K, b: &K) -> Ordering;
+
+    /// keyのハッシュをstateに書き込む(`StorageBackend::Hashed`などで使用する)
+    fn hash<H: Hasher>(key: &K, state: &mut H);
+}
+
+/// キーの`Ord`・`Hash`をそのまま用いる順序
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultComparator;
+
+impl<K: Ord + Hash> Comparator<K> for DefaultComparator {
+    fn compare(a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+
+    fn hash<H: Hasher>(key: &K, state: &mut H) {
+        key.hash(state);
+    }
+}
+
+/// 小文字に変換した文字列の辞書順による、大文字と小文字を区別しない順序
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaseInsensitiveComparator;
+
+impl CaseInsensitiveComparator {
+    fn lowercase(key: &str) -> impl Iterator<Item = char> + '_ {
+        key.chars().flat_map(char::to_lowercase)
+    }
+}
+
+impl Comparator<String> for CaseInsensitiveComparator {
+    fn compare(a: &String, b: &String) -> Ordering {
+        Self::lowercase(a).cmp(Self::lowercase(b))
+    }
+
+    fn hash<H: Hasher>(key: &String, state: &mut H) {
+        for c in Self::lowercase(key) {
+            c.hash(state);
+        }
+        // 接頭辞の関係にあるキーを区別するため、strのHashと同様に終端を書き込む
+        state.write_u8(0xff);
+    }
+}
+
+/// 比較・ハッシュをCに委ねるキー
+///
+/// WAL・データファイルには元のキーと同じ形式で直列化される。同じキーとみなされる複数のキーが
+/// 書き込まれた場合、最初に書き込まれたキーが保持される。
+pub struct ComparatorKey<K, C> {
+    key: K,
+    comparator: PhantomData<fn() -> C>,
+}
+
+impl<K, C> ComparatorKey<K, C> {
+    /// keyを比較・ハッシュをCに委ねるキーとして包む
+    pub fn new(key: K) -> Self {
+        ComparatorKey {
+            key,
+            comparator: PhantomData,
+        }
+    }
+
+    /// 元のキーを返す
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// 元のキーを取り出す
+    pub fn into_inner(self) -> K {
+        self.key
+    }
+}
+
+impl<K, C> Deref for ComparatorKey<K, C> {
+    type Target = K;
+
+    fn deref(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K, C> From<K> for ComparatorKey<K, C> {
+    fn from(key: K) -> Self {
+        ComparatorKey::new(key)
+    }
+}
+
+impl<K: Clone, C> Clone for ComparatorKey<K, C> {
+    fn clone(&self) -> Self {
+        ComparatorKey::new(self.key.clone())
+    }
+}
+
+impl<K: Debug, C> Debug for ComparatorKey<K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.key.fmt(f)
+    }
+}
+
+impl<K, C: Comparator<K>> PartialEq for ComparatorKey<K, C> {
+    fn eq(&self, other: &Self) -> bool {
+        C::compare(&self.key, &other.key) == Ordering::Equal
+    }
+}
+
+impl<K, C: Comparator<K>> Eq for ComparatorKey<K, C> {}
+
+impl<K, C: Comparator<K>> PartialOrd for ComparatorKey<K, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Option::Some(self.cmp(other))
+    }
+}
+
+impl<K, C: Comparator<K>> Ord for ComparatorKey<K, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        C::compare(&self.key, &other.key)
+    }
+}
+
+impl<K, C: Comparator<K>> Hash for ComparatorKey<K, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        C::hash(&self.key, state);
+    }
+}
+
+impl<K: Serialize, C> Serialize for ComparatorKey<K, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.key.serialize(serializer)
+    }
+}
+
+impl<'de, K: Deserialize<'de>, C> Deserialize<'de> for ComparatorKey<K, C> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        K::deserialize(deserializer).map(ComparatorKey::new)
+    }
+}
@@ -0,0 +1,294 @@
+use crate::log::{ChecksumAlgorithm, SyncMode, WalRecoveryMode};
+use crate::serialization::{CompressionLevel, DataFormat};
+use crate::store::StorageBackend;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// データベースの動作設定を表す
+///
+/// `DatabaseConfig::builder()`からメソッドチェーンで組み立てることができる。
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// ログファイル・データファイルを格納するディレクトリ
+    pub data_dir: PathBuf,
+    /// ログのセグメントファイルを格納するディレクトリのパス(data_dirからの相対パス)
+    pub log_file: PathBuf,
+    /// データファイルのパス(data_dirからの相対パス)
+    pub data_file: PathBuf,
+    /// データファイルの形式
+    ///
+    /// 既存のデータファイルの形式と異なる場合、初期化は`DatabaseError::DataFormatMismatch`となる。
+    pub data_format: DataFormat,
+    /// データファイルの圧縮の度合い
+    pub data_compression: CompressionLevel,
+    /// コミット済みの内容を保持するデータ構造
+    ///
+    /// データファイルの形式には影響しないため、既存のデータファイルを異なる設定で開くことができる。
+    pub storage_backend: StorageBackend,
+    /// 初期化時にデータファイルの値を復元せず、最初に参照された時点で復元するかどうか
+    ///
+    /// データファイルの形式が`DataFormat::Json`かつ`StorageBackend::Sorted`の場合のみ有効である。
+    /// 変更されていない値はチェックポイントの作成時にも復元せず、読み込んだ内容のまま書き出す。
+    /// 値の型がデータファイルの内容と一致しない場合、初期化ではなく参照の時点で失敗する。
+    /// 読み取りは`DatabaseError::JSONError`を返す(`peek`は`None`を返す)。走査・カーソル・エントリなどの
+    /// 値への参照を返す操作は、参照を返す前に対象の値を復元し、復元できない場合は同じエラーを返す。
+    pub lazy_loading: bool,
+    /// ファイルを一切使用せず、メモリ上のみでデータベースを扱うかどうか
+    pub in_memory: bool,
+    /// Commit時にfsyncを行うかどうか
+    pub sync_on_commit: bool,
+    /// Commit時のfsyncをバックグラウンドのスレッドでまとめて行うかどうか
+    ///
+    /// 有効な場合、Commitはデータベースへのアクセスを手放してからfsyncの完了を待つため、
+    /// その間に他のトランザクションのCommitが同じfsyncにまとめられる。
+    /// `sync_on_commit`が無効な場合は何もしない。
+    pub enable_group_commit: bool,
+    /// ログ上のレコード数がこの値に達した場合、Commit後に自動でチェックポイントを作成する(0の場合は無効)
+    pub auto_checkpoint_after_n_records: usize,
+    /// Commitされたトランザクションがこの数に達した場合、Commit後に自動でチェックポイントを作成する(0の場合は無効)
+    pub auto_checkpoint_after_n_commits: usize,
+    /// ログの容量の上限(bytes)。これを超える場合、書き込みの前に自動でチェックポイントを作成する(0の場合は無制限)
+    pub max_wal_bytes: u64,
+    /// ログの書き込みバッファのサイズ(bytes)(0の場合はバッファリングしない)
+    ///
+    /// fsyncを伴わない書き込みはバッファに蓄積され、fsyncを伴う書き込みの時点で書き出される。
+    pub wal_buffer_size: usize,
+    /// ログの1つのセグメントファイルの容量の上限(bytes)。これを超える場合、新たなセグメントに書き込む(0の場合は分割しない)
+    pub wal_segment_size: u64,
+    /// ログのフレームの整合性の検証に用いるチェックサムのアルゴリズム
+    pub checksum_algorithm: ChecksumAlgorithm,
+    /// ログやデータファイルをfsyncにより永続化する際の方法
+    ///
+    /// `SyncMode::None`の場合、OSのクラッシュや電源断の際にCommit済みのトランザクションが失われることがある。
+    pub sync_mode: SyncMode,
+    /// クラッシュリカバリの際のログの破損の扱い
+    pub wal_recovery_mode: WalRecoveryMode,
+    /// キーごとの書き込み(create/update/delete等)の直前に、変更前の値をBeforeImageレコードとしてログに書き込むかどうか
+    ///
+    /// BeforeImageレコードはRedoには使用せず、トランザクション開始前の状態の検証などに用いる。
+    /// 範囲を対象とする削除(`delete_range`・`truncate`)では書き込まない。
+    pub undo_logging: bool,
+    /// 書き込むキーの大きさの上限(bytes)(Noneの場合は制限しない)
+    ///
+    /// 大きさはキーをJSONにシリアライズした際のバイト数とし、超える場合は`DatabaseError::KeyTooLarge`となる。
+    pub max_key_size: Option<usize>,
+    /// 書き込む値の大きさの上限(bytes)(Noneの場合は制限しない)
+    ///
+    /// 大きさは値をJSONにシリアライズした際のバイト数とし、超える場合は`DatabaseError::ValueTooLarge`となる。
+    pub max_value_size: Option<usize>,
+    /// 期限切れのキーを削除するスレッドが確認を行う間隔
+    pub expiry_check_interval: Duration,
+    /// ログにハートビートを書き込むスレッドが書き込みを行う間隔(Noneの場合はスレッドを開始しない)
+    ///
+    /// ハートビートはデータの整合性には影響しないが、ログ上の位置の推定を容易にする。
+    /// スレッドは`SharedDatabase::start_heartbeat_thread`により開始する。
+    pub heartbeat_interval: Option<Duration>,
+}
+
+impl DatabaseConfig {
+    /// 設定を組み立てるビルダーを返す
+    pub fn builder() -> DatabaseConfigBuilder {
+        DatabaseConfigBuilder {
+            config: DatabaseConfig::default(),
+        }
+    }
+
+    /// ログのセグメントファイルを格納するディレクトリのパスを返す
+    pub fn log_path(&self) -> PathBuf {
+        self.data_dir.join(&self.log_file)
+    }
+
+    /// データファイルのパスを返す
+    pub fn data_path(&self) -> PathBuf {
+        self.data_dir.join(&self.data_file)
+    }
+
+    /// ロックファイルのパス(データファイルのパスに`.lock`を付加したもの)を返す
+    pub fn lock_path(&self) -> PathBuf {
+        let mut path = self.data_path().into_os_string();
+        path.push(".lock");
+        PathBuf::from(path)
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            data_dir: PathBuf::new(),
+            log_file: PathBuf::from("mikrodb.log"),
+            data_file: PathBuf::from("mikrodb.db"),
+            data_format: DataFormat::default(),
+            data_compression: CompressionLevel::default(),
+            storage_backend: StorageBackend::default(),
+            lazy_loading: false,
+            in_memory: false,
+            sync_on_commit: true,
+            enable_group_commit: false,
+            auto_checkpoint_after_n_records: 0,
+            auto_checkpoint_after_n_commits: 0,
+            max_wal_bytes: 0,
+            wal_buffer_size: 64 * 1024,
+            wal_segment_size: 16 * 1024 * 1024,
+            checksum_algorithm: ChecksumAlgorithm::default(),
+            sync_mode: SyncMode::default(),
+            wal_recovery_mode: WalRecoveryMode::default(),
+            undo_logging: false,
+            max_key_size: Option::None,
+            max_value_size: Option::None,
+            expiry_check_interval: Duration::from_secs(1),
+            heartbeat_interval: Option::None,
+        }
+    }
+}
+
+/// `DatabaseConfig`のビルダー
+#[derive(Debug, Clone)]
+pub struct DatabaseConfigBuilder {
+    config: DatabaseConfig,
+}
+
+impl DatabaseConfigBuilder {
+    /// ログファイル・データファイルを格納するディレクトリを設定する
+    pub fn data_dir<P: Into<PathBuf>>(mut self, data_dir: P) -> Self {
+        self.config.data_dir = data_dir.into();
+        self
+    }
+
+    /// ログのセグメントファイルを格納するディレクトリのパスを設定する
+    pub fn log_file<P: Into<PathBuf>>(mut self, log_file: P) -> Self {
+        self.config.log_file = log_file.into();
+        self
+    }
+
+    /// データファイルのパスを設定する
+    pub fn data_file<P: Into<PathBuf>>(mut self, data_file: P) -> Self {
+        self.config.data_file = data_file.into();
+        self
+    }
+
+    /// データファイルの形式を設定する
+    pub fn data_format(mut self, format: DataFormat) -> Self {
+        self.config.data_format = format;
+        self
+    }
+
+    /// データファイルの圧縮の度合いを設定する
+    pub fn data_compression(mut self, level: CompressionLevel) -> Self {
+        self.config.data_compression = level;
+        self
+    }
+
+    /// コミット済みの内容を保持するデータ構造を設定する
+    pub fn storage_backend(mut self, backend: StorageBackend) -> Self {
+        self.config.storage_backend = backend;
+        self
+    }
+
+    /// データファイルの値を参照時に復元するかどうかを設定する
+    pub fn lazy_loading(mut self, lazy_loading: bool) -> Self {
+        self.config.lazy_loading = lazy_loading;
+        self
+    }
+
+    /// メモリ上のみでデータベースを扱うかどうかを設定する
+    pub fn in_memory(mut self, in_memory: bool) -> Self {
+        self.config.in_memory = in_memory;
+        self
+    }
+
+    /// Commit時にfsyncを行うかどうかを設定する
+    pub fn sync_on_commit(mut self, sync_on_commit: bool) -> Self {
+        self.config.sync_on_commit = sync_on_commit;
+        self
+    }
+
+    /// Commit時のfsyncをまとめて行うかどうかを設定する
+    pub fn enable_group_commit(mut self, enable: bool) -> Self {
+        self.config.enable_group_commit = enable;
+        self
+    }
+
+    /// 自動でチェックポイントを作成するレコード数を設定する
+    pub fn auto_checkpoint_after_n_records(mut self, n: usize) -> Self {
+        self.config.auto_checkpoint_after_n_records = n;
+        self
+    }
+
+    /// 自動でチェックポイントを作成するCommit数を設定する
+    pub fn auto_checkpoint_after_n_commits(mut self, n: usize) -> Self {
+        self.config.auto_checkpoint_after_n_commits = n;
+        self
+    }
+
+    /// ログの容量の上限を設定する
+    pub fn max_wal_bytes(mut self, bytes: u64) -> Self {
+        self.config.max_wal_bytes = bytes;
+        self
+    }
+
+    /// ログの書き込みバッファのサイズを設定する
+    pub fn wal_buffer_size(mut self, size: usize) -> Self {
+        self.config.wal_buffer_size = size;
+        self
+    }
+
+    /// ログのセグメントファイルの容量の上限を設定する
+    pub fn wal_segment_size(mut self, size: u64) -> Self {
+        self.config.wal_segment_size = size;
+        self
+    }
+
+    /// ログのフレームのチェックサムのアルゴリズムを設定する
+    pub fn checksum_algorithm(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.config.checksum_algorithm = algorithm;
+        self
+    }
+
+    /// fsyncによる永続化の方法を設定する
+    pub fn sync_mode(mut self, mode: SyncMode) -> Self {
+        self.config.sync_mode = mode;
+        self
+    }
+
+    /// クラッシュリカバリの際のログの破損の扱いを設定する
+    pub fn wal_recovery_mode(mut self, mode: WalRecoveryMode) -> Self {
+        self.config.wal_recovery_mode = mode;
+        self
+    }
+
+    /// 変更前の値をBeforeImageレコードとしてログに書き込むかどうかを設定する
+    pub fn undo_logging(mut self, enabled: bool) -> Self {
+        self.config.undo_logging = enabled;
+        self
+    }
+
+    /// 書き込むキーの大きさの上限(bytes)を設定する
+    pub fn max_key_size(mut self, size: usize) -> Self {
+        self.config.max_key_size = Option::Some(size);
+        self
+    }
+
+    /// 書き込む値の大きさの上限(bytes)を設定する
+    pub fn max_value_size(mut self, size: usize) -> Self {
+        self.config.max_value_size = Option::Some(size);
+        self
+    }
+
+    /// 期限切れのキーを削除するスレッドが確認を行う間隔を設定する
+    pub fn expiry_check_interval(mut self, interval: Duration) -> Self {
+        self.config.expiry_check_interval = interval;
+        self
+    }
+
+    /// ハートビートを書き込む間隔を設定する
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.config.heartbeat_interval = Option::Some(interval);
+        self
+    }
+
+    /// 設定を確定する
+    pub fn build(self) -> DatabaseConfig {
+        self.config
+    }
+}
@@ -0,0 +1,57 @@
+use crate::database::Database;
+use crate::error::DatabaseError;
+use std::path::Path;
+
+impl Database<String, String> {
+    /// CSVファイルのkey_column列目をキー、value_column列目を値として、1つのトランザクションで書き込む
+    ///
+    /// 1行目は見出しとして読み飛ばす。既に存在するキー(ファイル内で重複するキーを含む)は値を更新する。
+    /// 読み込んだ行の数を返す。CSVとして読み込めない場合や、指定された列を持たない行がある場合は
+    /// `DatabaseError::InvalidInputFormat`となり、何も書き込まない。
+    pub fn import_csv<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        key_column: usize,
+        value_column: usize,
+    ) -> Result<usize, DatabaseError> {
+        let mut reader = csv::Reader::from_path(path)?;
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let column = |index: usize| {
+                record.get(index).map(str::to_string).ok_or_else(|| {
+                    let line = record.position().map_or(0, |position| position.line());
+                    DatabaseError::InvalidInputFormat {
+                        message: format!("line {} has no column {}", line, index),
+                    }
+                })
+            };
+            rows.push((column(key_column)?, column(value_column)?));
+        }
+        self.transaction_with(|tx| {
+            for (key, value) in &rows {
+                if tx.get_ref(key)?.is_some() {
+                    tx.update(key.clone(), value.clone())?;
+                } else {
+                    tx.create(key.clone(), value.clone())?;
+                }
+            }
+            Result::Ok(rows.len())
+        })
+    }
+
+    /// コミット済みのキーバリューペアを、`key,value`の見出しを持つ2列のCSVファイルとして書き出す
+    ///
+    /// 期限切れのキーは含まれない。書き出した行の数(見出しを除く)を返す。
+    pub fn export_csv<P: AsRef<Path>>(&self, path: P) -> Result<usize, DatabaseError> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["key", "value"])?;
+        let mut count = 0;
+        for (key, value) in self.scan_all()?.filter(|(key, _)| !self.is_expired(key)) {
+            writer.write_record([key, value])?;
+            count += 1;
+        }
+        writer.flush()?;
+        Result::Ok(count)
+    }
+}
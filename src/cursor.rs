@@ -0,0 +1,107 @@
+use crate::store::{KVStore, Store};
+use serde::de::DeserializeOwned;
+use std::hash::Hash;
+use std::ops::Bound;
+
+/// データベース上のキーバリューペアを前後に走査するカーソルを表す
+///
+/// カーソルはキーバリューペアの「間」を指しており、`next`は直後のペアを、`prev`は直前のペアを
+/// 返してその分だけ位置を移動する。カーソルは作成時点のコミット済みの内容を参照する。
+pub struct Cursor<'tx, K, V>
+where
+    K: Ord + Clone + Hash,
+    V: DeserializeOwned,
+{
+    data: &'tx Store<K, V>,
+    position: Position<K>,
+}
+
+/// カーソルの位置を表す
+enum Position<K> {
+    /// 先頭のペアの直前
+    First,
+    /// 末尾のペアの直後
+    Last,
+    /// 指定されたキーの直前
+    Before(K),
+    /// 指定されたキーの直後
+    After(K),
+}
+
+impl<'tx, K, V> Cursor<'tx, K, V>
+where
+    K: Ord + Clone + Hash,
+    V: DeserializeOwned,
+{
+    pub(crate) fn new(data: &'tx Store<K, V>) -> Self {
+        Cursor {
+            data,
+            position: Position::First,
+        }
+    }
+
+    /// key以上の最小のキーの直前に移動する
+    ///
+    /// 移動後、`next`はkey以上の最小のキーを、`prev`はkey未満の最大のキーを返す。
+    pub fn seek(&mut self, key: &K) {
+        self.position = Position::Before(key.clone());
+    }
+
+    /// 先頭のペアの直前に移動する
+    pub fn seek_to_first(&mut self) {
+        self.position = Position::First;
+    }
+
+    /// 末尾のペアの直後に移動する
+    pub fn seek_to_last(&mut self) {
+        self.position = Position::Last;
+    }
+
+    /// 直前のペアを返し、その直前に移動する
+    pub fn prev(&mut self) -> Option<(&'tx K, &'tx V)> {
+        let upper = match &self.position {
+            Position::First => return Option::None,
+            Position::Last => Bound::Unbounded,
+            Position::Before(k) => Bound::Excluded(k),
+            Position::After(k) => Bound::Included(k),
+        };
+        let entry = self
+            .data
+            .range(Bound::Unbounded, upper.cloned())
+            .ok()
+            .and_then(|mut range| range.next_back());
+        self.position = match entry {
+            Option::Some((k, _)) => Position::Before(k.clone()),
+            Option::None => Position::First,
+        };
+        entry
+    }
+}
+
+impl<'tx, K, V> Iterator for Cursor<'tx, K, V>
+where
+    K: Ord + Clone + Hash,
+    V: DeserializeOwned,
+{
+    type Item = (&'tx K, &'tx V);
+
+    /// 直後のペアを返し、その直後に移動する
+    fn next(&mut self) -> Option<Self::Item> {
+        let lower = match &self.position {
+            Position::First => Bound::Unbounded,
+            Position::Last => return Option::None,
+            Position::Before(k) => Bound::Included(k),
+            Position::After(k) => Bound::Excluded(k),
+        };
+        let entry = self
+            .data
+            .range(lower.cloned(), Bound::Unbounded)
+            .ok()
+            .and_then(|mut range| range.next());
+        self.position = match entry {
+            Option::Some((k, _)) => Position::After(k.clone()),
+            Option::None => Position::Last,
+        };
+        entry
+    }
+}
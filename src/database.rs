@@ -1,257 +1,3417 @@
-use crate::error::DatabaseError;
-use crate::log::{LogRecord, WALManager};
+use crate::changeset::{Change, Changeset};
+use crate::config::DatabaseConfig;
+use crate::cursor::Cursor;
+pub use crate::datafile::DATA_FORMAT_VERSION;
+use crate::datafile::{self, DataFile, DataFileHeader};
+use crate::entry::{Entry, EntryTarget};
+use crate::error::{DatabaseError, DatabaseErrorExt};
+use crate::event::{DatabaseEvent, Subscribers, SubscriptionHandle};
+use crate::group_commit::{GroupCommitManager, PendingSync};
+use crate::iter::{is_valid_range, MergeIter};
+use crate::lock::LockFile;
+use crate::log::{
+    BatchOp, CorruptionEvent, ExportedRecord, LogRecord, LsnRecord, SyncMode, WALManager,
+    WalRecoveryMode,
+};
+use crate::numeric::Numeric;
+use crate::paginator::Paginator;
+use crate::prefix::HasPrefix;
+use crate::replication::{ReplicationEvent, ReplicationStream};
+use crate::segment::sync_dir;
+use crate::serialization::{CompressionLevel, DataFormat};
+use crate::snapshot::Snapshot;
+use crate::stats::{DiskUsage, Statistics};
+use crate::store::{KVStore, StorageBackend, Store};
+use crate::writeset::WriteSet;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use serde_json::value::RawValue;
 
 use std::cmp::Ord;
-use std::collections::{BTreeMap, VecDeque};
-use std::fmt::Debug;
-use std::fs::File;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Debug, Display};
+use std::fs::OpenOptions;
+use std::hash::Hash;
 use std::io::prelude::*;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::{Bound, Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tempfile::NamedTempFile;
 
 use std::option::Option;
 use std::result::Result;
 
+/// データファイルから読み込んだヘッダ・コミット済みの内容・有効期限
+type LoadedData<K, V> = (DataFileHeader, Store<K, V>, BTreeMap<K, u64>);
+
 /// データベースを表す
 pub struct Database<K, V>
 where
-    K: Debug + Clone + Serialize + DeserializeOwned + Ord,
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
     V: Debug + Clone + Serialize + DeserializeOwned,
 {
-    wal: WALManager,
-    datapath: String,
-    data: BTreeMap<K, V>,
+    /// ログ(読み取り専用の場合はNone)
+    wal: Option<WALManager>,
+    datapath: Option<PathBuf>,
+    data: Store<K, V>,
+    expiry: BTreeMap<K, u64>,
+    config: DatabaseConfig,
+    checkpoint_lsn: u64,
+    stats: Arc<Statistics>,
+    global_version: AtomicU64,
+    /// データベースの作成以降にCommitされたトランザクションの数(チェックポイントとともに永続化される)
+    committed_transactions: AtomicU64,
+    /// データベースの作成以降にAbortされたトランザクションの数(チェックポイントとともに永続化される)
+    aborted_transactions: AtomicU64,
+    /// データベース単位のメタデータ(チェックポイントとともに永続化される)
+    metadata: BTreeMap<String, String>,
+    previous: Option<(u64, WriteSet<K, V>)>,
+    /// キーごとに、最後にそのキーを変更したCommitの後のバージョン(一度も変更されていないキーは0)
+    ///
+    /// 永続化されず、初期化の時点では空となる。Commitは他のトランザクションが存在しない状態で
+    /// 行われるため、削除されたキーは読み取り済みのバージョンと比較されることがなく、取り除かれる。
+    versions: BTreeMap<K, u64>,
+    /// データファイルを排他的に使用するためのロック(`Database::new`以外で初期化した場合はNone)
+    lock: Option<LockFile>,
+    group_commit: Option<GroupCommitManager>,
+    corruptions: Vec<CorruptionEvent>,
+    subscribers: Subscribers<K, V>,
 }
 
 /// トランザクションを表す
-pub struct Transaction<'tx, K, V>
+///
+/// `D`はデータベースへの排他的なアクセスを表す型で、通常は`&mut Database`である。
+pub struct Transaction<'tx, K, V, D = &'tx mut Database<K, V>>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: DerefMut<Target = Database<K, V>>,
+{
+    database: D,
+    writeset: WriteSet<K, V>,
+    dirty: BTreeSet<K>,
+    savepoints: Vec<Savepoint<K, V>>,
+    next_savepoint: u32,
+    relogged_bytes: u64,
+    snapshot_version: u64,
+    ttl: BTreeMap<K, u64>,
+    /// このトランザクションで削除したキー(再び書き込んでもコミット済みの有効期限を引き継がない)
+    deleted: BTreeSet<K>,
+    /// 読み取ったキーと、最初に読み取った時点のキーのバージョン
+    read_versions: BTreeMap<K, u64>,
+    start_lsn: u64,
+    start_offset: u64,
+    /// この時刻を過ぎた後の操作は`DatabaseError::TransactionTimeout`となる
+    deadline: Option<SystemTime>,
+    finished: bool,
+    /// トランザクションの開始からCommit/Abortまでを表すspan
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    _marker: PhantomData<&'tx ()>,
+}
+
+/// 2相コミットの第1相(`Transaction::prepare`)を終えたトランザクションを表す
+///
+/// `commit`・`rollback`のいずれも呼ばれないままDropした場合、Abort扱いとなる。
+pub struct PreparedTransaction<'tx, K, V, D = &'tx mut Database<K, V>>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: DerefMut<Target = Database<K, V>>,
+{
+    transaction: Transaction<'tx, K, V, D>,
+    revived: Vec<K>,
+}
+
+/// トランザクション内のセーブポイントを識別する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SavepointId(u32);
+
+/// `Database::merge_with`で同じキーの値が異なる場合の解決方法を表す
+pub enum MergeStrategy<K, V> {
+    /// 常に統合する側(other)の値を採用する
+    LastWriteWins,
+    /// 常に統合される側(self)の値を維持する
+    KeepSelf,
+    /// キー・selfの値・otherの値から採用する値を決定する
+    Custom(Box<dyn Fn(K, V, V) -> V>),
+}
+
+impl<K, V> MergeStrategy<K, V> {
+    /// keyについて、oursとtheirsのどちらを採用するかを決定する
+    fn resolve(&self, key: K, ours: V, theirs: V) -> V {
+        match self {
+            MergeStrategy::LastWriteWins => theirs,
+            MergeStrategy::KeepSelf => ours,
+            MergeStrategy::Custom(f) => f(key, ours, theirs),
+        }
+    }
+}
+
+/// セーブポイントの作成時点のトランザクションの状態を表す
+struct Savepoint<K, V> {
+    id: SavepointId,
+    name: String,
+    writeset: WriteSet<K, V>,
+    dirty: BTreeSet<K>,
+    ttl: BTreeMap<K, u64>,
+    deleted: BTreeSet<K>,
+}
+
+/// 読み取り専用トランザクションを表す
+///
+/// WALへの書き込みを一切行わず、データベースの内容を共有参照として保持する。
+/// 更新系の操作(create/update/delete)は提供されない。
+///
+/// 読み取りはトランザクションの開始時点のバージョンに対して行われる。データベースは現在と直前の
+/// 2つのバージョンのみを保持するため、開始後に2回以上Commitされた場合は
+/// `DatabaseError::StaleSnapshotError`となる。
+///
+/// `D`はデータベースへの共有アクセスを表す型で、通常は`&Database`である。
+pub struct ReadTransaction<'tx, K, V, D = &'tx Database<K, V>>
 where
-    K: Debug + Clone + Serialize + DeserializeOwned + Ord,
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
     V: Debug + Clone + Serialize + DeserializeOwned,
+    D: Deref<Target = Database<K, V>>,
 {
-    database: &'tx mut Database<K, V>,
-    writeset: BTreeMap<K, Option<V>>,
+    database: D,
+    snapshot_version: u64,
+    _marker: PhantomData<&'tx ()>,
 }
 
 impl<K, V> Database<K, V>
 where
-    K: Debug + Clone + DeserializeOwned + Serialize + Ord,
+    K: Debug + Clone + DeserializeOwned + Serialize + Ord + Hash,
     V: Debug + Clone + DeserializeOwned + Serialize,
 {
     /// データベースを初期化する
     ///
     /// これには、以下の手続きが含まれる
-    /// - ファイルシステム上に永続化されたデータベースの読み込み
+    /// - ファイルシステム上に永続化されたデータベースの読み込み(チェックサムの検証を含む)
     /// - ファイルシステム上に永続化されたログファイルの読み込み
     /// - ログファイル上の未反映の操作のRedo(Crash-recovery)
     /// - Crash-recovery後のデータベースの永続化
-    pub fn new(logpath: &str, datapath: &str) -> Result<Self, DatabaseError> {
-        let wal = WALManager::new(logpath)?;
-        let content = std::fs::read_to_string(datapath);
-        let data: BTreeMap<K, V> = match content {
-            Result::Ok(v) => serde_json::from_str(&v)?,
-            Result::Err(_) => BTreeMap::new(),
+    ///
+    /// `DatabaseConfig::in_memory`が設定されている場合、ファイルは一切使用されない。
+    ///
+    /// 初期化に先立ってロックファイル(`DatabaseConfig::lock_path`)を作成し、Dropまでロックを保持する。
+    /// 他のインスタンスがロックを保持している場合は`DatabaseError::DatabaseLocked`となる。
+    pub fn new(config: DatabaseConfig) -> Result<Self, DatabaseError> {
+        let lock = if config.in_memory {
+            Option::None
+        } else {
+            Option::Some(LockFile::acquire(config.lock_path())?)
+        };
+        let mut db = Database::new_unchecked(config)?;
+        db.lock = lock;
+        Result::Ok(db)
+    }
+
+    /// ロックファイルを使用せずにデータベースを初期化する
+    ///
+    /// 同じファイルを他のインスタンスが使用していないことは、呼び出し側が保証しなければならない。
+    /// それ以外は`Database::new`と同じ。
+    pub fn new_unchecked(config: DatabaseConfig) -> Result<Self, DatabaseError> {
+        let empty = || {
+            let data = Store::from_map(config.storage_backend, BTreeMap::new());
+            (DataFileHeader::default(), data, BTreeMap::new())
+        };
+        let (wal, datapath, (header, data, expiry)) = if config.in_memory {
+            (WALManager::in_memory(), Option::None, empty())
+        } else {
+            let wal = WALManager::new(config.log_path())?;
+            let datapath = config.data_path();
+            let reading = |e: DatabaseError| {
+                DatabaseError::from(
+                    e.context(format!("reading the data file {}", datapath.display())),
+                )
+            };
+            let content = std::fs::read(&datapath);
+            let file = match content {
+                Result::Ok(v) => {
+                    let v = datafile::decompress(v).map_err(reading)?;
+                    let found = datafile::format(&v)?;
+                    if found != config.data_format {
+                        return Result::Err(DatabaseError::DataFormatMismatch {
+                            found,
+                            expected: config.data_format,
+                        });
+                    }
+                    datafile::verify(&v)?;
+                    Database::decode_store(&config, &v)?
+                }
+                Result::Err(e) if e.kind() == std::io::ErrorKind::NotFound => empty(),
+                Result::Err(e) => return Result::Err(reading(e.into())),
+            };
+            (wal, Option::Some(datapath), file)
         };
+        let mut wal = wal;
+        let stats: Arc<Statistics> = Arc::default();
+        wal.advance_lsn(header.checkpoint_lsn);
+        wal.set_statistics(Arc::clone(&stats));
+        wal.set_checksum_algorithm(config.checksum_algorithm);
+        wal.set_sync_mode(config.sync_mode);
+        wal.set_buffer_size(config.wal_buffer_size)?;
+        wal.set_segment_size(config.wal_segment_size);
         let mut db = Database {
-            wal: wal,
-            datapath: datapath.to_string(),
-            data: data,
+            wal: Option::Some(wal),
+            datapath,
+            data,
+            expiry,
+            config,
+            checkpoint_lsn: header.checkpoint_lsn,
+            stats,
+            global_version: AtomicU64::new(0),
+            committed_transactions: AtomicU64::new(header.committed_transactions),
+            aborted_transactions: AtomicU64::new(header.aborted_transactions),
+            metadata: header.metadata,
+            previous: Option::None,
+            versions: BTreeMap::new(),
+            lock: Option::None,
+            group_commit: Option::None,
+            corruptions: Vec::new(),
+            subscribers: Subscribers::new(),
         };
 
-        db.crash_recover()?;
+        if let Result::Err(e) = db
+            .crash_recover(header.wal_offset)
+            .context("recovering from the WAL")
+        {
+            // Drop時のチェックポイントにより、読み取れなかったログを破棄しないようにする
+            db.wal = Option::None;
+            return Result::Err(e.into());
+        }
+        db.stats.set_record_count(db.data.len());
         db.exec_checkpointing()?;
+        let max_wal_bytes = db.config.max_wal_bytes;
+        db.wal_mut()?.set_size_limit(max_wal_bytes);
+        if db.config.enable_group_commit && !db.config.in_memory {
+            db.group_commit = Option::Some(GroupCommitManager::new());
+        }
         Result::Ok(db)
     }
 
-    /// ファイルシステムおよびメモリ上からデータベースに関する内容を消去する
+    /// 検証済みのデータファイルの内容を、configに応じたデータ構造に読み込む
     ///
-    /// これは主にテストコードの開始時に前回のテストの影響を無視できるように実装されたもので、
-    /// 実際の運用時の使用は想定されない
-    pub fn clear(&mut self) -> Result<(), DatabaseError> {
-        self.wal.clear()?;
-        self.data.clear();
-        std::fs::remove_file(&self.datapath)?;
-        Result::Ok(())
+    /// `DatabaseConfig::lazy_loading`が有効で、JSON形式のデータファイルを`StorageBackend::Sorted`で
+    /// 開く場合は、値を復元せずに読み込む。
+    fn decode_store(
+        config: &DatabaseConfig,
+        content: &[u8],
+    ) -> Result<LoadedData<K, V>, DatabaseError> {
+        let lazy = config.lazy_loading
+            && config.data_format == DataFormat::Json
+            && config.storage_backend == StorageBackend::Sorted;
+        if lazy {
+            let file: DataFile<K, Box<RawValue>> = datafile::decode(content)?;
+            return Result::Ok((file.header, Store::from_raw(file.data), file.expiry));
+        }
+        let file: DataFile<K, V> = datafile::decode(content)?;
+        let data = Store::from_map(config.storage_backend, file.data);
+        Result::Ok((file.header, data, file.expiry))
     }
 
-    fn exec_checkpointing(&mut self) -> Result<(), DatabaseError> {
-        let mut file = NamedTempFile::new_in(std::env::current_dir()?)?;
-        let content = serde_json::to_string(&self.data)?;
-        let content = content.as_bytes();
+    /// データベースを初期化する(`Database::new`の別名)
+    pub fn open(config: DatabaseConfig) -> Result<Self, DatabaseError> {
+        Database::new(config)
+    }
 
-        file.write_all(content)?;
-        file.persist(&self.datapath)?;
+    /// ログファイル・データファイルのパスのみを指定し、その他は既定の設定でデータベースを初期化する
+    pub fn with_defaults<P, Q>(logpath: P, datapath: Q) -> Result<Self, DatabaseError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let config = DatabaseConfig::builder()
+            .log_file(logpath.as_ref())
+            .data_file(datapath.as_ref())
+            .build();
+        Database::new(config)
+    }
 
-        let file = File::open(&self.datapath)?;
-        file.sync_all()?;
-        self.wal.clear()?;
-        Result::Ok(())
+    /// メモリ上のみで動作するデータベースを初期化する
+    ///
+    /// ログはメモリ上に記録され、チェックポイントの作成時にはデータファイルへの書き込みを行わない。
+    pub fn in_memory() -> Result<Self, DatabaseError> {
+        Database::new(DatabaseConfig::builder().in_memory(true).build())
     }
 
-    /// クラッシュリカバリを行う
-    fn crash_recover(&mut self) -> Result<(), DatabaseError> {
-        let logs: Vec<LogRecord<K, V>> = self.wal.read_log()?;
-        let mut queue: VecDeque<LogRecord<K, V>> = VecDeque::new();
-        let mut commit: VecDeque<LogRecord<K, V>> = VecDeque::new();
-        for log in logs {
-            match log {
-                LogRecord::Commit => {
-                    while let Option::Some(v) = queue.pop_front() {
-                        commit.push_back(v);
-                    }
-                }
-                LogRecord::Abort => {
-                    queue.clear();
-                }
-                _ => {
-                    queue.push_back(log);
-                }
-            };
-        }
-        for log in commit {
-            match log {
-                LogRecord::Create { key, value } => {
-                    self.data.insert(key, value);
+    /// データファイルのみを読み込み、読み取り専用のデータベースを初期化する
+    ///
+    /// ファイルは読み取りのみで開かれ、ログは一切使用しないため、チェックポイントに反映されていない
+    /// 操作は含まれない。書き込みを伴う操作は`DatabaseError::ReadOnlyDatabase`となる。
+    /// データファイルの形式はヘッダから判別する。
+    pub fn open_read_only<P: AsRef<Path>>(datapath: P) -> Result<Self, DatabaseError> {
+        let datapath = datapath.as_ref();
+        let mut content = Vec::new();
+        OpenOptions::new()
+            .read(true)
+            .open(datapath)?
+            .read_to_end(&mut content)?;
+        let content = datafile::decompress(content)?;
+        let format = datafile::format(&content)?;
+        datafile::verify(&content)?;
+        let file: DataFile<K, V> = datafile::decode(&content)?;
+        let stats: Arc<Statistics> = Arc::default();
+        stats.set_record_count(file.data.len());
+        Result::Ok(Database {
+            wal: Option::None,
+            datapath: Option::Some(PathBuf::from(datapath)),
+            data: Store::from_map(StorageBackend::default(), file.data),
+            expiry: file.expiry,
+            config: DatabaseConfig::builder()
+                .data_file(datapath)
+                .data_format(format)
+                .build(),
+            checkpoint_lsn: file.header.checkpoint_lsn,
+            stats,
+            global_version: AtomicU64::new(0),
+            committed_transactions: AtomicU64::new(file.header.committed_transactions),
+            aborted_transactions: AtomicU64::new(file.header.aborted_transactions),
+            metadata: file.header.metadata,
+            previous: Option::None,
+            versions: BTreeMap::new(),
+            lock: Option::None,
+            group_commit: Option::None,
+            corruptions: Vec::new(),
+            subscribers: Subscribers::new(),
+        })
+    }
+
+    /// 設定に従ってデータベースを初期化し、iterの内容を1つのトランザクションで書き込む
+    ///
+    /// 同じキーが複数回現れた場合、最後の値が書き込まれる。
+    pub fn from_iter_fallible<I>(iter: I, config: DatabaseConfig) -> Result<Self, DatabaseError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut db = Database::new(config)?;
+        db.extend_transaction(iter)?;
+        Result::Ok(db)
+    }
+
+    /// 変更の一覧を1つのトランザクションで反映する
+    ///
+    /// いずれかの変更を反映できない場合(既に存在するキーのCreate、存在しないキーのUpdate・Deleteなど)、
+    /// トランザクションをAbortしてそのエラーを返す。
+    pub fn apply_changeset(&mut self, changeset: Changeset<K, V>) -> Result<(), DatabaseError> {
+        self.transaction_with(|tx| {
+            for change in changeset {
+                match change {
+                    Change::Create(key, value) => tx.create(key, value)?,
+                    Change::Update(key, value) => tx.update(key, value)?,
+                    Change::Delete(key) => tx.delete(key)?,
                 }
-                LogRecord::Update { key, value } => {
-                    self.data.insert(key, value);
+            }
+            Result::Ok(())
+        })
+    }
+
+    /// keyが存在しない場合に限り、1つのトランザクションでvalueとして新規作成する
+    ///
+    /// 作成した場合は`true`を、既に存在していた場合は何も変更せずに`false`を返す。
+    pub fn create_if_absent(&mut self, key: K, value: V) -> Result<bool, DatabaseError> {
+        self.transaction_with(|tx| tx.create_if_absent(key, value))
+    }
+
+    /// iterの内容を1つのトランザクションで書き込む
+    ///
+    /// 既に存在するキーの値は上書きされる。
+    pub fn extend_transaction<I>(&mut self, iter: I) -> Result<(), DatabaseError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.transaction_with(|tx| {
+            for (key, value) in iter {
+                tx.upsert(key, value)?;
+            }
+            Result::Ok(())
+        })
+    }
+
+    /// otherのすべてのキーバリューペアを1つのトランザクションで統合する
+    ///
+    /// selfに存在しないキーは新規作成し、値が異なるキーはconflict(キー・selfの値・otherの値)が
+    /// 返した値で更新する。otherで期限切れのキーは統合せず、otherの有効期限は引き継がない。
+    pub fn merge<F>(&mut self, other: Database<K, V>, conflict: F) -> Result<(), DatabaseError>
+    where
+        V: PartialEq,
+        F: Fn(K, V, V) -> V,
+    {
+        self.transaction_with(|tx| {
+            for (key, theirs) in other.data.iter()? {
+                if other.is_expired(key) {
+                    continue;
                 }
-                LogRecord::Delete { key } => {
-                    self.data.remove(&key);
+                match tx.get_ref(key)?.cloned() {
+                    Option::None => tx.create(key.clone(), theirs.clone())?,
+                    Option::Some(ours) if ours == *theirs => {}
+                    Option::Some(ours) => {
+                        let value = conflict(key.clone(), ours.clone(), theirs.clone());
+                        if value != ours {
+                            tx.update(key.clone(), value)?;
+                        }
+                    }
                 }
-                _ => {}
             }
-        }
-        Result::Ok(())
+            Result::Ok(())
+        })
     }
 
-    /// トランザクションを発行する
-    pub fn begin_transaction<'tx>(&'tx mut self) -> Result<Transaction<'tx, K, V>, DatabaseError> {
-        return Result::Ok(Transaction {
-            writeset: BTreeMap::new(),
-            database: self,
-        });
+    /// `merge`と同様にotherを統合し、値が異なるキーはstrategyに従って解決する
+    pub fn merge_with(
+        &mut self,
+        other: Database<K, V>,
+        strategy: MergeStrategy<K, V>,
+    ) -> Result<(), DatabaseError>
+    where
+        V: PartialEq,
+    {
+        self.merge(other, |key, ours, theirs| {
+            strategy.resolve(key, ours, theirs)
+        })
     }
-}
 
-impl<K, V> Drop for Database<K, V>
-where
-    K: Debug + Clone + Serialize + DeserializeOwned + Ord,
-    V: Debug + Clone + Serialize + DeserializeOwned,
-{
-    /// データベースの永続化を行います
-    fn drop(&mut self) {
-        if let Result::Err(e) = self.exec_checkpointing() {
-            println!("Error: {}", e.to_string());
+    /// ログにハートビート(Noopレコード)を書き込む
+    ///
+    /// 詳細は`WALManager::write_heartbeat`を参照。
+    pub fn write_heartbeat(&mut self) -> Result<(), DatabaseError> {
+        self.wal_mut()?.write_heartbeat()
+    }
+
+    /// Commit・Abort・チェックポイントの作成の通知を受け取るチャネルを登録する
+    ///
+    /// 受信側を破棄したチャネルは、次の通知の際に登録を解除される。
+    pub fn subscribe(&mut self) -> (SubscriptionHandle, Receiver<DatabaseEvent<K, V>>) {
+        self.subscribers.subscribe()
+    }
+
+    /// `subscribe`により登録したチャネルの登録を解除する
+    ///
+    /// 既に登録が解除されている場合はfalseを返す。
+    pub fn unsubscribe(&mut self, handle: SubscriptionHandle) -> bool {
+        self.subscribers.unsubscribe(handle)
+    }
+
+    /// 最後に作成されたチェックポイントの時点でログに書き込まれていた最後のレコードのLSNを返す
+    pub fn checkpoint_lsn(&self) -> u64 {
+        self.checkpoint_lsn
+    }
+
+    /// ファイルシステム上のデータファイルがチェックサムと一致するかを検証する
+    ///
+    /// 一致しない場合は`DatabaseError::DataFileCorrupted`を返す。データファイルが存在しない場合や
+    /// チェックサムを持たない旧形式の場合、メモリ上のみのデータベースでは何も検証しない。
+    pub fn verify_integrity(&self) -> Result<(), DatabaseError> {
+        let datapath = match &self.datapath {
+            Option::Some(datapath) => datapath,
+            Option::None => return Result::Ok(()),
+        };
+        match std::fs::read(datapath) {
+            Result::Ok(content) => datafile::verify(&datafile::decompress(content)?),
+            Result::Err(e) if e.kind() == std::io::ErrorKind::NotFound => Result::Ok(()),
+            Result::Err(e) => Result::Err(e.into()),
         }
     }
-}
 
-impl<'tx, K, V> Transaction<'tx, K, V>
-where
-    K: Debug + Clone + Serialize + DeserializeOwned + Ord,
-    V: Debug + Clone + Serialize + DeserializeOwned,
-{
-    /// ログに書き込まず、keyに対応する値を読み取る
-    fn get_content(&mut self, key: &K) -> Option<V> {
-        return match self.writeset.get(&key) {
-            None => self.database.data.get(&key).map(|v| v.clone()),
-            Some(v) => v.clone(),
+    /// 現在のバージョンを返す
+    ///
+    /// バージョンはCommitのたびに1ずつ増加する。
+    pub fn version(&self) -> u64 {
+        self.global_version.load(Ordering::Relaxed)
+    }
+
+    /// versionの時点でコミットされていた内容から、keyに対応する値を読み取る
+    ///
+    /// 保持しているのは現在と直前のバージョンのみであり、それより古いバージョンを指定した場合は
+    /// `DatabaseError::StaleSnapshotError`を返す。現在の時刻で期限切れのキーは存在しないものとして扱う。
+    pub fn read_at(&self, key: &K, version: u64) -> Result<Option<&V>, DatabaseError> {
+        let value = match self
+            .overlay_at(version)?
+            .and_then(|overlay| overlay.get(key))
+        {
+            Option::Some(old) => old,
+            Option::None => self.data.get(key)?,
         };
+        Result::Ok(value.filter(|_| !self.is_expired(key)))
     }
 
-    /// keyに対応する値をvalueとして新規設定する
-    pub fn create(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
-        if self.get_content(&key).is_some() {
-            return Result::Err(DatabaseError::KeyDuplicationError);
+    /// keyが有効期限を持ち、それを過ぎているかどうかを返す
+    pub(crate) fn is_expired(&self, key: &K) -> bool {
+        self.expiry
+            .get(key)
+            .is_some_and(|expiry_secs| is_past(*expiry_secs))
+    }
+
+    /// コミット済みの内容から、期限切れでないkeyに対応する値を返す
+    ///
+    /// 値を参照時に復元する場合、復元できない値に対しては`DatabaseError::JSONError`を返す。
+    fn live_value(&self, key: &K) -> Result<Option<&V>, DatabaseError> {
+        Result::Ok(self.data.get(key)?.filter(|_| !self.is_expired(key)))
+    }
+
+    /// predicateを満たすコミット済みのキーバリューペアを1つのトランザクションで削除し、削除したペアを
+    /// キーの昇順に返す
+    ///
+    /// Commitに失敗した場合は何も削除されず、元のエラーを`source`とする`DatabaseError::TransactionError`を返す。
+    pub fn drain<F>(&mut self, predicate: F) -> Result<Vec<(K, V)>, DatabaseError>
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        let mut tx = self.begin_transaction()?;
+        let pairs = match tx.drain(predicate) {
+            Result::Ok(pairs) => pairs,
+            Result::Err(e) => {
+                tx.abort()?;
+                return Result::Err(e);
+            }
+        };
+        tx.commit_silent()
+            .map_err(|e| DatabaseError::TransactionError {
+                source: Box::new(e),
+            })?;
+        Result::Ok(pairs)
+    }
+
+    /// key_aとkey_bに対応するコミット済みの値を1つのトランザクションで交換する
+    ///
+    /// `Transaction::swap`と同様、いずれかのキーが存在しない場合は`DatabaseError::KeyNotFoundError`を返す。
+    pub fn swap(&mut self, key_a: K, key_b: K) -> Result<(), DatabaseError> {
+        self.transaction_with(|tx| tx.swap(key_a, key_b))
+    }
+
+    /// 期限切れのキーを1つのトランザクションで削除し、削除した数を返す
+    ///
+    /// 期限切れのキーは読み取りの際には存在しないものとして扱われるが、削除されるまでは
+    /// 走査やカーソルの結果に含まれる。
+    pub fn purge_expired(&mut self) -> Result<usize, DatabaseError> {
+        let keys: Vec<K> = self
+            .expiry
+            .iter()
+            .filter(|(_, expiry_secs)| is_past(**expiry_secs))
+            .map(|(key, _)| key.clone())
+            .collect();
+        if keys.is_empty() {
+            return Result::Ok(0);
         }
-        {
-            let log = LogRecord::Create {
-                key: key.clone(),
-                value: value.clone(),
+        let mut tx = self.begin_transaction()?;
+        for key in &keys {
+            tx.remove_expired(key.clone())?;
+        }
+        tx.commit_silent()?;
+        Result::Ok(keys.len())
+    }
+
+    /// すべてのキーバリューペアにtransformを適用し、1つのトランザクションで書き込む
+    ///
+    /// transformが`Some`を返した場合はその値に更新し、`None`を返した場合はキーを削除する。
+    /// 値が変わらなかったキーは書き込まず、更新・削除したキーの数を返す。
+    /// 期限切れのキーは対象としない。すべての変更をメモリ上に保持するため、件数が多い場合は
+    /// `migrate_chunked`を使用すること。
+    pub fn migrate<F>(&mut self, transform: F) -> Result<usize, DatabaseError>
+    where
+        F: Fn(K, V) -> Option<V>,
+    {
+        let keys: Vec<K> = self.data.keys().cloned().collect();
+        self.migrate_keys(&keys, &transform)
+    }
+
+    /// `migrate`と同様にtransformを適用し、chunk_size件ごとに別のトランザクションで書き込む
+    ///
+    /// 先にすべてのキーを複製した上で、値はchunk_size件ずつ読み取る。chunk_sizeが0の場合は1として扱う。
+    /// 途中で失敗した場合やクラッシュした場合、それまでにCommitされたトランザクションの変更のみが残る。
+    pub fn migrate_chunked<F>(
+        &mut self,
+        chunk_size: usize,
+        transform: F,
+    ) -> Result<usize, DatabaseError>
+    where
+        F: Fn(K, V) -> Option<V>,
+    {
+        let keys: Vec<K> = self.data.keys().cloned().collect();
+        let mut modified = 0;
+        for chunk in keys.chunks(chunk_size.max(1)) {
+            modified += self.migrate_keys(chunk, &transform)?;
+        }
+        Result::Ok(modified)
+    }
+
+    /// keysの値にtransformを適用し、変更を1つのトランザクションで書き込む
+    fn migrate_keys<F>(&mut self, keys: &[K], transform: &F) -> Result<usize, DatabaseError>
+    where
+        F: Fn(K, V) -> Option<V>,
+    {
+        let mut pairs: Vec<(K, Option<V>)> = Vec::new();
+        for key in keys {
+            let value = match self.live_value(key)? {
+                Option::Some(value) => value,
+                Option::None => continue,
             };
-            self.database.wal.write_log(&log, false)?;
+            match transform(key.clone(), value.clone()) {
+                Option::Some(new_value) if same_value(&new_value, value) => {}
+                new_value => pairs.push((key.clone(), new_value)),
+            }
+        }
+        let modified = pairs.len();
+        if modified > 0 {
+            self.transaction_with(|tx| tx.write_batch(pairs))?;
         }
-        self.writeset.insert(key, Option::Some(value));
-        return Result::Ok(());
+        Result::Ok(modified)
     }
 
-    /// keyに対応する値を読み取る
-    pub fn read(&mut self, key: K) -> Result<V, DatabaseError> {
-        {
-            let log: LogRecord<K, V> = LogRecord::Read { key: key.clone() };
-            self.database.wal.write_log(&log, false)?;
+    /// keyを最後に変更したCommitの後のバージョンを返す(初期化以降に変更されていない場合は0)
+    fn key_version(&self, key: &K) -> u64 {
+        self.versions.get(key).copied().unwrap_or(0)
+    }
+
+    /// versionの時点の内容を得るために、現在の内容に重ねる差分を返す
+    ///
+    /// versionが現在のバージョンであれば`None`を返す。
+    fn overlay_at(&self, version: u64) -> Result<Option<&WriteSet<K, V>>, DatabaseError> {
+        let current = self.version();
+        if version == current {
+            return Result::Ok(Option::None);
+        }
+        match &self.previous {
+            Option::Some((previous, overlay)) if *previous == version => {
+                Result::Ok(Option::Some(overlay))
+            }
+            _ => Result::Err(DatabaseError::StaleSnapshotError {
+                snapshot: version,
+                current,
+            }),
         }
-        return self
-            .get_content(&key)
-            .ok_or(DatabaseError::KeyNotFoundError);
     }
 
-    /// keyに対応する値をvalueとして更新する
-    pub fn update(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
-        if self.get_content(&key).is_none() {
-            return Result::Err(DatabaseError::KeyNotFoundError);
+    /// データベースの統計情報を返す
+    pub fn stats(&self) -> Arc<Statistics> {
+        Arc::clone(&self.stats)
+    }
+
+    /// データファイルとログがファイルシステム上で使用しているバイト数を返す
+    ///
+    /// 書き込みバッファに蓄積され、まだ書き出されていない内容は含まれない。
+    pub fn size_on_disk(&self) -> Result<DiskUsage, DatabaseError> {
+        let data_bytes = self.data_size()?;
+        let wal_bytes = self.wal_size()?;
+        Result::Ok(DiskUsage {
+            data_bytes,
+            wal_bytes,
+            total_bytes: data_bytes + wal_bytes,
+        })
+    }
+
+    /// データファイルのバイト数を返す(メモリ上のみのデータベースでは0)
+    pub fn data_size(&self) -> Result<u64, DatabaseError> {
+        match &self.datapath {
+            Option::Some(datapath) => Result::Ok(std::fs::metadata(datapath)?.len()),
+            Option::None => Result::Ok(0),
         }
-        {
-            let log = LogRecord::Update {
-                key: key.clone(),
-                value: value.clone(),
-            };
-            self.database.wal.write_log(&log, false)?;
+    }
+
+    /// ログのセグメントファイルの合計のバイト数を返す(ファイルにログを記録しない場合は0)
+    pub fn wal_size(&self) -> Result<u64, DatabaseError> {
+        let dir = match self.log_path() {
+            Option::Some(dir) => dir,
+            Option::None => return Result::Ok(0),
+        };
+        let mut bytes = 0;
+        for entry in std::fs::read_dir(dir)? {
+            bytes += entry?.metadata()?.len();
         }
-        self.writeset.insert(key, Option::Some(value));
-        return Result::Ok(());
+        Result::Ok(bytes)
     }
 
-    /// keyに対応する値を削除する
-    pub fn delete(&mut self, key: K) -> Result<(), DatabaseError> {
-        if self.get_content(&key).is_none() {
-            return Result::Err(DatabaseError::KeyNotFoundError);
+    /// データベースの設定を返す
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    /// 初期化時のクラッシュリカバリで読み飛ばしたログの破損を返す
+    ///
+    /// `WalRecoveryMode::Lenient`の場合のみ記録される。
+    pub fn corruption_events(&self) -> &[CorruptionEvent] {
+        &self.corruptions
+    }
+
+    /// `open_read_only`により読み取り専用で初期化されたかどうかを返す
+    pub fn is_read_only(&self) -> bool {
+        self.wal.is_none()
+    }
+
+    /// ログを返す(読み取り専用の場合は`DatabaseError::ReadOnlyDatabase`)
+    ///
+    /// グループコミットのfsyncに失敗している場合は`DatabaseError::SyncFailed`を返す。
+    /// 永続化されていない変更がチェックポイントによりデータファイルに書き込まれないよう、
+    /// 以降のログへの書き込みとチェックポイントはすべて失敗する。
+    fn wal_mut(&mut self) -> Result<&mut WALManager, DatabaseError> {
+        if let Option::Some(manager) = &self.group_commit {
+            manager.check()?;
         }
-        {
-            let log: LogRecord<K, V> = LogRecord::Delete { key: key.clone() };
-            self.database.wal.write_log(&log, false)?;
+        self.wal.as_mut().ok_or(DatabaseError::ReadOnlyDatabase)
+    }
+
+    /// ログのセグメントファイルを格納するディレクトリのパスを返す
+    ///
+    /// メモリ上のみのデータベースと読み取り専用のデータベースではNoneを返す。
+    pub fn log_path(&self) -> Option<&Path> {
+        self.wal.as_ref().and_then(WALManager::log_path)
+    }
+
+    /// データファイルのパスを返す(メモリ上のみのデータベースではNone)
+    pub fn data_path(&self) -> Option<&Path> {
+        self.datapath.as_deref()
+    }
+
+    /// ファイルシステムおよびメモリ上からデータベースに関する内容を消去する
+    ///
+    /// これは主にテストコードの開始時に前回のテストの影響を無視できるように実装されたもので、
+    /// 実際の運用時の使用は想定されない。データファイルが存在しない場合も成功する。
+    pub fn clear(&mut self) -> Result<(), DatabaseError> {
+        self.try_clear().map(|_| ())
+    }
+
+    /// `clear`と同様に内容を消去し、データファイルが存在して削除されたかどうかを返す
+    ///
+    /// データファイルが存在しない場合(メモリ上のみのデータベースを含む)は`Ok(false)`を返す。
+    pub fn try_clear(&mut self) -> Result<bool, DatabaseError> {
+        self.wal_mut()?.clear()?;
+        self.data.clear();
+        self.expiry.clear();
+        self.versions.clear();
+        self.stats.set_record_count(0);
+        self.previous = Option::None;
+        self.global_version.fetch_add(1, Ordering::Relaxed);
+        let datapath = match &self.datapath {
+            Option::Some(datapath) => datapath,
+            Option::None => return Result::Ok(false),
+        };
+        match std::fs::remove_file(datapath) {
+            Result::Ok(()) => Result::Ok(true),
+            Result::Err(e) if e.kind() == std::io::ErrorKind::NotFound => Result::Ok(false),
+            Result::Err(e) => Result::Err(e.into()),
         }
-        self.writeset.insert(key, Option::None);
-        return Result::Ok(());
     }
 
-    /// Commitする(トランザクションを反映する)
-    pub fn commit(self) -> Result<(), DatabaseError> {
-        let log: LogRecord<K, V> = LogRecord::Commit;
-        self.database.wal.write_log(&log, true)?;
-        for (key, op) in &self.writeset {
-            match op {
-                Option::None => {
-                    self.database.data.remove(&key);
-                }
-                Option::Some(v) => {
-                    self.database.data.insert(key.clone(), v.clone());
-                }
-            }
+    /// すべてのキーバリューペアを削除し、チェックポイントを作成する
+    ///
+    /// 1つのトランザクションでTruncateレコードを書き込んでCommitする。`clear`と異なりファイルは
+    /// 削除しないため、以降もそのまま使用することができる。
+    pub fn truncate(&mut self) -> Result<(), DatabaseError> {
+        let mut tx = self.begin_transaction()?;
+        tx.truncate()?;
+        tx.commit_silent()?;
+        self.versions.clear();
+        self.exec_checkpointing()
+    }
+
+    /// チェックポイントを作成する
+    ///
+    /// データファイルと同じディレクトリに一時ファイルを作成して内容を書き込み、fsyncした上で
+    /// rename(2)によりデータファイルを置き換える。これにより、書き込み途中でクラッシュしても
+    /// 直前のデータファイルがそのまま残ることが保証される。
+    /// データファイルの置き換え後、CheckpointMarkerレコードを書き込んでからログを破棄する。
+    ///
+    /// メモリ上のみで動作している場合、データファイルへの書き込みは行わずログの破棄のみを行う。
+    fn exec_checkpointing(&mut self) -> Result<(), DatabaseError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("checkpoint").entered();
+        let wal = self.wal_mut()?;
+        // CheckpointMarkerレコードはログの現在の末尾に書き込まれる
+        let (checkpoint_lsn, wal_offset) = (wal.current_lsn(), wal.current_offset());
+        let header = DataFileHeader {
+            checkpoint_lsn,
+            wal_offset,
+            committed_transactions: self.committed_transactions.load(Ordering::Relaxed),
+            aborted_transactions: self.aborted_transactions.load(Ordering::Relaxed),
+            metadata: self.metadata.clone(),
+        };
+        if let Option::Some(datapath) = &self.datapath {
+            self.write_data_file(datapath, &header)
+                .map_err(|e| e.context(format!("writing the data file {}", datapath.display())))?;
+
+            // ログの破棄に失敗した場合でも、Redoがこれ以前のレコードを読み飛ばせるようにする
+            let marker: LogRecord<K, V> = LogRecord::CheckpointMarker { checkpoint_lsn };
+            self.wal_mut()?.write_log_unchecked(&marker, true)?;
         }
-        std::mem::forget(self); // Prevent abort caused by Drop
-        return Result::Ok(());
+        self.checkpoint_lsn = checkpoint_lsn;
+
+        self.wal_mut()?
+            .clear()
+            .context("discarding the WAL after the checkpoint")?;
+        debug_event!(checkpoint_lsn = header.checkpoint_lsn, "checkpoint");
+        self.stats.record_checkpoint();
+        self.subscribers.publish(DatabaseEvent::Checkpointed);
+        Result::Ok(())
     }
 
-    /// Abortする(トランザクションを破棄する)
-    pub fn abort(self) -> Result<(), DatabaseError> {
-        // Drop時に自動でAbortされる
-        return Result::Ok(());
+    /// 内容を一時ファイルに書き込み、rename(2)によりdatapathのデータファイルを置き換える
+    fn write_data_file(
+        &self,
+        datapath: &Path,
+        header: &DataFileHeader,
+    ) -> Result<(), DatabaseError> {
+        let dir = data_dir(datapath);
+        let mut file = NamedTempFile::new_in(dir)?;
+        let content = self
+            .data
+            .encode(self.config.data_format, header, &self.expiry)?;
+        let content = datafile::compress(content, self.config.data_compression)?;
+
+        file.write_all(&content)?;
+        self.config.sync_mode.sync_file(file.as_file())?;
+        file.persist(datapath)?;
+        if self.config.sync_mode != SyncMode::None {
+            sync_dir(dir)?;
+        }
+        Result::Ok(())
     }
-}
 
-impl<'tx, K, V> Drop for Transaction<'tx, K, V>
-where
-    K: Debug + Clone + Serialize + DeserializeOwned + Ord,
-    V: Debug + Clone + Serialize + DeserializeOwned,
-{
-    /// 明示的にCommitされないままDropした場合、Abort扱いとなる
-    fn drop(&mut self) {
-        let log: LogRecord<K, V> = LogRecord::Abort;
-        if let Result::Err(e) = self.database.wal.write_log(&log, true) {
-            println!("Error: {}", e.to_string());
+    /// チェックポイントを作成してログを破棄する
+    ///
+    /// 破棄されたログのバイト数と、データファイルに書き込まれたキーバリューペアの数を返す。
+    /// `&mut self`を取るため、トランザクションの実行中に呼び出すことはできない。
+    pub fn compact_wal(&mut self) -> Result<(u64, usize), DatabaseError> {
+        let bytes_freed = self.wal_mut()?.bytes_since_checkpoint();
+        self.exec_checkpointing()?;
+        Result::Ok((bytes_freed, self.data.len()))
+    }
+
+    /// チェックポイントを作成した上で、データファイルの複製をpathに作成する
+    ///
+    /// 複製は同じディレクトリの一時ファイルに書き込んでfsyncした上でrename(2)により配置されるため、
+    /// 書き込み途中の複製が残ることはない。書き込まれたバイト数を返す。
+    /// メモリ上のみで動作している場合、データファイルと同じ形式で内容を書き込む。
+    /// 読み取り専用の場合、チェックポイントは作成せずにデータファイルを複製する。
+    pub fn backup_to<P: AsRef<Path>>(&mut self, path: P) -> Result<u64, DatabaseError> {
+        if !self.is_read_only() {
+            self.exec_checkpointing()?;
+        }
+        let path = path.as_ref();
+        let dir = data_dir(path);
+        let mut file = NamedTempFile::new_in(dir)?;
+        let bytes = match &self.datapath {
+            Option::Some(datapath) => std::fs::copy(datapath, file.path())?,
+            Option::None => {
+                let header = DataFileHeader {
+                    checkpoint_lsn: self.checkpoint_lsn,
+                    committed_transactions: self.committed_transactions(),
+                    aborted_transactions: self.aborted_transactions(),
+                    metadata: self.metadata.clone(),
+                    ..DataFileHeader::default()
+                };
+                let content = self
+                    .data
+                    .encode(self.config.data_format, &header, &self.expiry)?;
+                let content = datafile::compress(content, self.config.data_compression)?;
+                file.write_all(&content)?;
+                content.len() as u64
+            }
+        };
+        file.as_file().sync_all()?;
+        file.persist(path)?;
+        sync_dir(dir)?;
+        Result::Ok(bytes)
+    }
+
+    /// ログのみからデータファイルを再構築し、output_datapathに書き込む
+    ///
+    /// データファイルを失った場合のためのもので、既存のデータファイルは読み込まず、ログの先頭から
+    /// Commitされたすべてのトランザクションを反映する。ログは変更しない。
+    /// ログには最後のチェックポイント以降の操作のみが残るため、それ以前の内容は再構築されない。
+    /// 書き込んだデータファイルのチェックポイントのLSNはログの最後のレコードのLSNとなるため、
+    /// 同じログと共に開いた場合に操作が二重に反映されることはない。
+    pub fn rebuild_from_wal<P, Q>(logpath: P, output_datapath: Q) -> Result<(), DatabaseError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        if !logpath.as_ref().exists() {
+            return Result::Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
         }
+        let logs: Vec<LsnRecord<K, V>> = WALManager::new(logpath)?.read_log_with_lsn()?;
+        let last_lsn = logs.last().map_or(0, |(lsn, _)| *lsn);
+        let mut db: Database<K, V> = Database::in_memory()?;
+        db.redo(logs, &BTreeSet::new())?;
+        db.wal_mut()?.advance_lsn(last_lsn);
+        db.backup_to(output_datapath)?;
+        Result::Ok(())
+    }
+
+    /// ログのすべてのレコードを、フレームの開始位置と共にJSONの配列としてoutput_pathに書き出す
+    ///
+    /// デバッグや監査のためのもので、ログは変更しない。書き出したレコードの数を返す。
+    /// 書き出した内容は`WALManager::import_from_json`によりログに書き込むことができる。
+    pub fn export_wal_as_json<P: AsRef<Path>>(
+        &mut self,
+        output_path: P,
+    ) -> Result<usize, DatabaseError> {
+        let mut records: Vec<ExportedRecord<K, V>> = Vec::new();
+        let mut iter = self.wal_mut()?.iter_records();
+        while let Option::Some(record) = iter.next() {
+            records.push(ExportedRecord {
+                offset: iter.offset(),
+                record: record?,
+            });
+        }
+        drop(iter);
+        std::fs::write(output_path, serde_json::to_vec_pretty(&records)?)?;
+        Result::Ok(records.len())
+    }
+
+    /// `backup_to`により作成された複製から内容を復元する
+    ///
+    /// 現在の内容はすべて複製の内容に置き換えられ、ログは破棄される。
+    /// 復元した内容はチェックポイントとしてデータファイルに書き込まれる。
+    pub fn restore_from<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DatabaseError> {
+        let content = datafile::decompress(std::fs::read(path)?)?;
+        datafile::verify(&content)?;
+        let file: DataFile<K, V> = datafile::decode(&content)?;
+        self.wal_mut()?.advance_lsn(file.header.checkpoint_lsn);
+        self.data = Store::from_map(self.config.storage_backend, file.data);
+        self.expiry = file.expiry;
+        self.metadata = file.header.metadata;
+        self.versions.clear();
+        self.stats.set_record_count(self.data.len());
+        self.previous = Option::None;
+        self.global_version.fetch_add(1, Ordering::Relaxed);
+        self.exec_checkpointing()
+    }
+
+    /// データファイルの形式をfrom_versionからto_versionへ移行する
+    ///
+    /// to_versionは`DATA_FORMAT_VERSION`である必要がある。バージョン1のデータファイルは、同じ形式
+    /// (JSON・バイナリ)のまま現在の形式で書き直す。圧縮されていた場合は`CompressionLevel::Default`で
+    /// 圧縮する。書き直しは同じディレクトリの一時ファイルに書き込んだ上でrename(2)により行う。
+    /// from_versionとto_versionが等しい場合は、データファイルのバージョンがfrom_versionであることのみを
+    /// 確認する。データファイルのバージョンがfrom_versionと異なる場合や、それ以外の組み合わせは
+    /// `DatabaseError::UnsupportedDataFormatVersion`となる。
+    pub fn migrate_data_file<P: AsRef<Path>>(
+        from_version: u32,
+        to_version: u32,
+        path: P,
+    ) -> Result<(), DatabaseError> {
+        if to_version != DATA_FORMAT_VERSION {
+            return Result::Err(DatabaseError::UnsupportedDataFormatVersion {
+                found: to_version,
+                expected: DATA_FORMAT_VERSION,
+            });
+        }
+        let path = path.as_ref();
+        let content = std::fs::read(path)?;
+        let level = if datafile::is_compressed(&content) {
+            CompressionLevel::Default
+        } else {
+            CompressionLevel::None
+        };
+        let content = datafile::decompress(content)?;
+        let found = datafile::version(&content)?;
+        if found != from_version || !(from_version == to_version || from_version == 1) {
+            return Result::Err(DatabaseError::UnsupportedDataFormatVersion {
+                found,
+                expected: to_version,
+            });
+        }
+        if from_version == to_version {
+            return Result::Ok(());
+        }
+        let content = datafile::migrate::<K, V>(&content, from_version)?;
+        let content = datafile::compress(content, level)?;
+        let dir = data_dir(path);
+        let mut file = NamedTempFile::new_in(dir)?;
+        file.write_all(&content)?;
+        file.as_file().sync_all()?;
+        file.persist(path)?;
+        sync_dir(dir)?;
+        Result::Ok(())
+    }
+
+    /// グループコミットが有効であれば、ログのfsyncを要求する
+    fn request_group_sync(&mut self) -> Result<Option<PendingSync>, DatabaseError> {
+        match (&self.group_commit, &mut self.wal) {
+            (Option::Some(manager), Option::Some(wal)) => {
+                let target = wal.sync_target()?;
+                Result::Ok(target.map(|target| manager.request(target)))
+            }
+            _ => Result::Ok(Option::None),
+        }
+    }
+
+    /// ログ上のレコード数・Commit数が設定された閾値に達していれば、チェックポイントを作成する
+    fn auto_checkpoint(&mut self) -> Result<(), DatabaseError> {
+        let records = self.config.auto_checkpoint_after_n_records;
+        let commits = self.config.auto_checkpoint_after_n_commits;
+        let wal = match &self.wal {
+            Option::Some(wal) => wal,
+            Option::None => return Result::Ok(()),
+        };
+        if (records > 0 && wal.record_count() >= records)
+            || (commits > 0 && wal.commit_count() >= commits)
+        {
+            self.exec_checkpointing()?;
+        }
+        Result::Ok(())
+    }
+
+    /// Redoに使用するログのレコードを読み取る
+    ///
+    /// データファイルに記録されたwal_offsetの位置に、チェックポイントに対応するCheckpointMarker
+    /// レコードがある場合(ログの破棄に失敗していた場合)、それより前のレコードは読み取らない。
+    /// そうでない場合はログの先頭から読み取る。読み取れないフレームの後に内容が残っている場合は
+    /// `DatabaseError::CorruptWALOnOpen`を返す。
+    fn read_log_after_checkpoint(
+        &mut self,
+        wal_offset: u64,
+    ) -> Result<Vec<LsnRecord<K, V>>, DatabaseError> {
+        if wal_offset > 0 {
+            match self.wal_mut()?.read_log_with_lsn_checked(wal_offset) {
+                Result::Ok(logs) => {
+                    if let Option::Some((_, LogRecord::CheckpointMarker { checkpoint_lsn })) =
+                        logs.first()
+                    {
+                        if *checkpoint_lsn == self.checkpoint_lsn {
+                            return Result::Ok(logs);
+                        }
+                    }
+                }
+                // offsetがフレームの先頭を指していない場合も含め、先頭から読み直す
+                Result::Err(DatabaseError::CorruptWALOnOpen { .. }) => {}
+                Result::Err(e) => return Result::Err(e),
+            }
+        }
+        self.wal_mut()?.read_log_with_lsn_checked(0)
+    }
+
+    /// クラッシュリカバリを行う
+    ///
+    /// チェックポイントのLSN以下のレコードと、最後のCheckpointMarkerレコード以前のレコードは
+    /// 既にデータファイルに反映されているため、読み飛ばす。
+    fn crash_recover(&mut self, wal_offset: u64) -> Result<(), DatabaseError> {
+        let (mut logs, corruptions) = match self.config.wal_recovery_mode {
+            WalRecoveryMode::Strict => (self.read_log_after_checkpoint(wal_offset)?, Vec::new()),
+            WalRecoveryMode::Lenient => self.wal_mut()?.read_log_lenient_with_lsn()?,
+        };
+        // 破損の直後のレコード。これを含むトランザクションは一部のレコードを失っている可能性がある
+        let broken: BTreeSet<u64> = corruptions
+            .iter()
+            .filter_map(|event| {
+                let index = logs.partition_point(|(lsn, _)| *lsn <= event.last_valid_lsn);
+                logs.get(index).map(|(lsn, _)| *lsn)
+            })
+            .collect();
+        self.corruptions = corruptions;
+        let marker = logs
+            .iter()
+            .rposition(|(_, log)| matches!(log, LogRecord::CheckpointMarker { .. }));
+        if let Option::Some(index) = marker {
+            if let (_, LogRecord::CheckpointMarker { checkpoint_lsn }) = logs[index] {
+                if checkpoint_lsn > self.checkpoint_lsn {
+                    return Result::Err(DatabaseError::CheckpointMismatchError {
+                        data_file: self.checkpoint_lsn,
+                        log: checkpoint_lsn,
+                    });
+                }
+            }
+            logs.drain(..=index);
+        }
+        self.redo(logs, &broken)
+    }
+
+    /// ログのレコードのうち、Commitされたトランザクションの操作を内容に反映する
+    ///
+    /// チェックポイントのLSN以下のレコードは読み飛ばす。brokenに含まれるLSNのレコード(破損の直後の
+    /// レコード)から次のCommit/Abortまでのトランザクションは反映しない。
+    /// 値を参照時に復元する場合、反映に必要な値を復元できなければ`DatabaseError::JSONError`を返す。
+    fn redo(
+        &mut self,
+        logs: Vec<LsnRecord<K, V>>,
+        broken: &BTreeSet<u64>,
+    ) -> Result<(), DatabaseError> {
+        let mut queue: VecDeque<LogRecord<K, V>> = VecDeque::new();
+        let mut commit: VecDeque<LogRecord<K, V>> = VecDeque::new();
+        let checkpoint_lsn = self.checkpoint_lsn;
+        let logs = logs.into_iter().filter(|(lsn, _)| *lsn > checkpoint_lsn);
+        // 破損の直後から、次のCommit/Abortまでのトランザクションを破棄する
+        let mut discarding = false;
+        for (lsn, log) in logs {
+            if broken.contains(&lsn) {
+                queue.clear();
+                discarding = true;
+            }
+            match log {
+                LogRecord::Commit if discarding => {
+                    queue.clear();
+                    discarding = false;
+                }
+                LogRecord::Commit => {
+                    while let Option::Some(v) = queue.pop_front() {
+                        commit.push_back(v);
+                    }
+                    *self.committed_transactions.get_mut() += 1;
+                }
+                LogRecord::Abort => {
+                    queue.clear();
+                    discarding = false;
+                    *self.aborted_transactions.get_mut() += 1;
+                }
+                LogRecord::Prepare | LogRecord::Noop { .. } => {}
+                _ if discarding => {}
+                LogRecord::RollbackToSavepoint { id } => {
+                    // 対応するセーブポイントのレコードは、再度戻る場合に備えて残しておく
+                    while let Option::Some(v) = queue.back() {
+                        match v {
+                            LogRecord::Savepoint { id: saved, .. } if *saved == id => break,
+                            _ => queue.pop_back(),
+                        };
+                    }
+                }
+                _ => {
+                    queue.push_back(log);
+                }
+            };
+        }
+        for log in commit {
+            match log {
+                LogRecord::Create { key, value } => {
+                    self.data.insert(key, value);
+                }
+                LogRecord::CreateWithTTL {
+                    key,
+                    value,
+                    expiry_secs,
+                } => {
+                    self.expiry.insert(key.clone(), expiry_secs);
+                    self.data.insert(key, value);
+                }
+                LogRecord::ClearExpiry { key } => {
+                    self.expiry.remove(&key);
+                }
+                LogRecord::Update { key, value } => {
+                    self.data.insert(key, value);
+                }
+                LogRecord::Upsert { key, value } => {
+                    self.data.insert(key, value);
+                }
+                LogRecord::CAS {
+                    key,
+                    expected,
+                    new_value,
+                } => {
+                    let matched = match self.data.get(&key)? {
+                        Option::Some(current) => same_value(current, &expected),
+                        Option::None => false,
+                    };
+                    if matched {
+                        self.data.insert(key, new_value);
+                    }
+                }
+                LogRecord::Increment { key, value, .. }
+                | LogRecord::Decrement { key, value, .. } => {
+                    self.data.insert(key, value);
+                }
+                LogRecord::Delete { key } => {
+                    self.expiry.remove(&key);
+                    self.data.remove(&key);
+                }
+                LogRecord::Truncate => {
+                    self.expiry.clear();
+                    self.data.clear();
+                }
+                LogRecord::Batch { ops } => {
+                    for op in ops {
+                        match op {
+                            BatchOp::Upsert(key, value) => {
+                                self.data.insert(key, value);
+                            }
+                            BatchOp::Delete(key) => {
+                                self.expiry.remove(&key);
+                                self.data.remove(&key);
+                            }
+                        }
+                    }
+                }
+                LogRecord::Rename { old_key, new_key } => {
+                    if let Option::Some(value) = self.data.get(&old_key)?.cloned() {
+                        self.data.remove(&old_key);
+                        if let Option::Some(expiry_secs) = self.expiry.remove(&old_key) {
+                            self.expiry.insert(new_key.clone(), expiry_secs);
+                        }
+                        self.data.insert(new_key, value);
+                    }
+                }
+                LogRecord::Swap { key_a, key_b } => {
+                    let a = self.data.get(&key_a)?.cloned();
+                    let b = self.data.get(&key_b)?.cloned();
+                    if let (Option::Some(a), Option::Some(b)) = (a, b) {
+                        self.data.insert(key_a, b);
+                        self.data.insert(key_b, a);
+                    }
+                }
+                LogRecord::DeleteRange { start, end } if is_valid_range(&start, &end) => {
+                    let keys = self.data.keys_in_range(start, end);
+                    for key in keys {
+                        self.expiry.remove(&key);
+                        self.data.remove(&key);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Result::Ok(())
+    }
+
+    /// トランザクションを発行する
+    ///
+    /// グループコミットのfsyncに失敗している場合は`DatabaseError::SyncFailed`を返す。
+    pub fn begin_transaction<'tx>(&'tx mut self) -> Result<Transaction<'tx, K, V>, DatabaseError> {
+        if let Option::Some(manager) = &self.group_commit {
+            manager.check()?;
+        }
+        Result::Ok(Transaction::new(self))
+    }
+
+    /// `Transaction::set_timeout`によりdurationの期限を設定したトランザクションを発行する
+    pub fn begin_transaction_with_timeout(
+        &mut self,
+        duration: Duration,
+    ) -> Result<Transaction<'_, K, V>, DatabaseError> {
+        let mut tx = self.begin_transaction()?;
+        tx.set_timeout(duration);
+        Result::Ok(tx)
+    }
+
+    /// トランザクションの発行を試みる
+    ///
+    /// `None`は他のトランザクションが実行中であることを表す。`&mut self`により排他性が保証されるため、
+    /// 常に`Some`を返す。(`SharedDatabase::try_begin_transaction`と同じ形で使用するためのもの)
+    pub fn try_begin_transaction(&mut self) -> Option<Transaction<'_, K, V>> {
+        Option::Some(Transaction::new(self))
+    }
+
+    /// トランザクションを発行してfを実行する
+    ///
+    /// fが`Ok`を返した場合はCommitし、`Err`を返した場合はAbortした上でそのエラーを返す。
+    pub fn transaction_with<F, R>(&mut self, f: F) -> Result<R, DatabaseError>
+    where
+        F: FnOnce(&mut Transaction<'_, K, V>) -> Result<R, DatabaseError>,
+    {
+        let mut tx = self.begin_transaction()?;
+        match f(&mut tx) {
+            Result::Ok(result) => {
+                tx.commit_silent()?;
+                Result::Ok(result)
+            }
+            Result::Err(e) => {
+                tx.abort()?;
+                Result::Err(e)
+            }
+        }
+    }
+
+    /// `transaction_with`と同様にfを実行し、`DatabaseError::TransientError`で失敗した場合は
+    /// 最大max_retries回まで再試行する
+    ///
+    /// 再試行しても成功しなかった場合は、最後のエラーを返す。
+    pub fn transaction_retry<F, R>(
+        &mut self,
+        max_retries: usize,
+        mut f: F,
+    ) -> Result<R, DatabaseError>
+    where
+        F: FnMut(&mut Transaction<'_, K, V>) -> Result<R, DatabaseError>,
+    {
+        let mut retries = 0;
+        loop {
+            match self.transaction_with(&mut f) {
+                Result::Err(DatabaseError::TransientError { .. }) if retries < max_retries => {
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// コミット済みの内容を前後に走査するカーソルを作成する
+    ///
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となる。
+    /// 値を参照時に復元する場合は作成時にすべての値を復元し、復元できなければ`DatabaseError::JSONError`を返す。
+    pub fn cursor(&self) -> Result<Cursor<'_, K, V>, DatabaseError> {
+        self.data.ensure_sorted("cursor")?;
+        // 値を参照時に復元する場合は、カーソルが値を返す前にすべての値を復元しておく
+        self.data.iter()?;
+        Result::Ok(Cursor::new(&self.data))
+    }
+
+    /// コミット済みの内容をpage_size件ずつ読み取るページネータを作成する
+    ///
+    /// ログには何も書き込まない。
+    /// `StorageBackend::Hashed`の場合、`Paginator::next_page`は`DatabaseError::UnsupportedOperation`となる。
+    pub fn paginate(&self, page_size: usize) -> Paginator<'_, K, V> {
+        Paginator::new(&self.data, page_size)
+    }
+
+    /// start_lsn以降にCommitされたトランザクションを、ログから順に読み取るストリームを返す
+    ///
+    /// 各`ReplicationEvent`はCommitレコードのLSNを持つため、最後に反映したイベントのLSNに1を加えた値を
+    /// 次のstart_lsnとすることで続きから読み取ることができる。Abortされたトランザクションは含まれない。
+    /// チェックポイントにより破棄されたレコードは読み取れないため、start_lsnが`checkpoint_lsn`以下の
+    /// 場合は最初に`DatabaseError::ReplicationGap`を返す。その場合はデータファイルの複製から始める必要がある。
+    pub fn replication_stream(&mut self, start_lsn: u64) -> ReplicationStream<'_, K, V> {
+        let checkpoint_lsn = self.checkpoint_lsn;
+        if start_lsn <= checkpoint_lsn {
+            return ReplicationStream::failed(DatabaseError::ReplicationGap {
+                start_lsn,
+                checkpoint_lsn,
+            });
+        }
+        let wal = match self.wal.as_mut() {
+            Option::Some(wal) => wal,
+            Option::None => return ReplicationStream::failed(DatabaseError::ReadOnlyDatabase),
+        };
+        if let Result::Err(e) = wal.flush_buffer() {
+            return ReplicationStream::failed(e);
+        }
+        ReplicationStream::new(wal.iter_records(), start_lsn, checkpoint_lsn)
+    }
+
+    /// `replication_stream`により読み取ったeventを1つのトランザクションとして反映する
+    ///
+    /// 反映した内容は、このデータベースのログにも書き込まれる。作成・更新の操作は反映先の内容に
+    /// 関わらず値を書き込み、存在しないキーの削除は無視する。失効したキーは値の書き込みにより復活するため、
+    /// ClearExpiryレコードは反映しない。
+    pub fn apply_replication_event(
+        &mut self,
+        event: ReplicationEvent<K, V>,
+    ) -> Result<(), DatabaseError> {
+        self.transaction_with(|tx| {
+            for record in event.into_records() {
+                match record {
+                    LogRecord::Create { key, value }
+                    | LogRecord::Update { key, value }
+                    | LogRecord::Upsert { key, value }
+                    | LogRecord::Increment { key, value, .. }
+                    | LogRecord::Decrement { key, value, .. } => {
+                        tx.upsert(key, value)?;
+                    }
+                    LogRecord::CAS { key, new_value, .. } => {
+                        tx.upsert(key, new_value)?;
+                    }
+                    LogRecord::CreateWithTTL {
+                        key,
+                        value,
+                        expiry_secs,
+                    } => {
+                        if tx.peek_internal(&key)?.is_some() {
+                            tx.delete(key.clone())?;
+                        }
+                        tx.create_with_expiry(key, value, expiry_secs)?;
+                    }
+                    LogRecord::Delete { key } => {
+                        if tx.peek_internal(&key)?.is_some() {
+                            tx.delete(key)?;
+                        } else {
+                            // 反映先でも失効している場合は、削除のみを行う
+                            tx.remove_expired(key)?;
+                        }
+                    }
+                    LogRecord::DeleteRange { start, end } => {
+                        tx.delete_range(start, end)?;
+                    }
+                    LogRecord::Truncate => {
+                        tx.truncate()?;
+                    }
+                    LogRecord::Batch { ops } => {
+                        tx.write_batch(ops.into_iter().map(|op| match op {
+                            BatchOp::Upsert(key, value) => (key, Option::Some(value)),
+                            BatchOp::Delete(key) => (key, Option::None),
+                        }))?;
+                    }
+                    LogRecord::Rename { old_key, new_key } => {
+                        tx.rename(old_key, new_key)?;
+                    }
+                    LogRecord::Swap { key_a, key_b } => {
+                        tx.swap(key_a, key_b)?;
+                    }
+                    _ => {}
+                }
+            }
+            Result::Ok(())
+        })
+    }
+
+    /// 保持しているロックファイルのパスを返す(ロックを保持していない場合はNone)
+    pub fn lock_path(&self) -> Option<&Path> {
+        self.lock.as_ref().map(LockFile::path)
+    }
+
+    /// コミット済みのキーバリューペアの数を返す
+    ///
+    /// 最後のチェックポイントとそれ以降にCommitされた変更を反映した数であり、
+    /// 実行中のトランザクションの書き込みセットは含まれない。
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// データベースの作成以降にCommitされたトランザクションの数を返す
+    ///
+    /// `Statistics::total_transactions_committed`とは異なり、チェックポイントとともにデータファイルに
+    /// 記録され、再起動後も引き継がれる。チェックポイント以降の数はクラッシュリカバリの際にログから数え直す。
+    pub fn committed_transactions(&self) -> u64 {
+        self.committed_transactions.load(Ordering::Relaxed)
+    }
+
+    /// データベースの作成以降にAbortされたトランザクションの数を返す
+    ///
+    /// `committed_transactions`と同様、再起動後も引き継がれる。
+    pub fn aborted_transactions(&self) -> u64 {
+        self.aborted_transactions.load(Ordering::Relaxed)
+    }
+
+    /// keyに対応するメタデータとしてvalueを設定する
+    ///
+    /// メタデータはスキーマのバージョンなど、データベース単位の情報を利用者のデータとは別に保持する。
+    /// ログには書き込まず、次のチェックポイントの際にデータファイルのヘッダとして永続化される。
+    /// それまでにクラッシュした場合、変更は失われる。
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.insert(key.to_string(), value.to_string());
+    }
+
+    /// keyに対応するメタデータを返す
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
+    /// keyに対応するメタデータを削除する
+    ///
+    /// `set_metadata`と同様、次のチェックポイントの際に永続化される。
+    pub fn remove_metadata(&mut self, key: &str) {
+        self.metadata.remove(key);
+    }
+
+    /// コミット済みの最小のキーを返す
+    ///
+    /// `len`と同様、実行中のトランザクションの書き込みセットは含まれない。
+    pub fn first_key(&self) -> Option<K> {
+        self.data.first_key().cloned()
+    }
+
+    /// コミット済みの最大のキーを返す
+    ///
+    /// `len`と同様、実行中のトランザクションの書き込みセットは含まれない。
+    pub fn last_key(&self) -> Option<K> {
+        self.data.last_key().cloned()
+    }
+
+    /// 指定された範囲のコミット済みのキーの数を返す
+    ///
+    /// 範囲を走査して数えるのみで、内容は複製しない。`len`と同様、実行中のトランザクションの
+    /// 書き込みセットは含まれない。`StorageBackend::Hashed`の場合はすべてのキーを確認する。
+    pub fn count_in_range(&self, start: Bound<K>, end: Bound<K>) -> usize {
+        if !is_valid_range(&start, &end) {
+            return 0;
+        }
+        self.data.count_in_range(start, end)
+    }
+
+    /// コミット済みのキーバリューペアが存在しないかどうかを返す
+    ///
+    /// `len`と同様、実行中のトランザクションの書き込みセットは含まれない。
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// コミット済みのキーバリューペアをキーの昇順に走査する
+    ///
+    /// トランザクションを介さないためログには何も書き込まない。返される内容は最後のチェックポイントと
+    /// それ以降にCommitされた変更を反映したもので、実行中のトランザクションの書き込みセットは含まれない。
+    /// `StorageBackend::Hashed`の場合、走査の順序は保証しない。
+    /// 値を参照時に復元する場合は走査の前にすべての値を復元し、復元できない値が含まれる場合は
+    /// `DatabaseError::JSONError`を返す。
+    pub fn scan_all(&self) -> Result<impl Iterator<Item = (&K, &V)>, DatabaseError> {
+        self.data.iter()
+    }
+
+    /// コミット済みのキーを昇順に走査する
+    ///
+    /// `scan_all`と同様、実行中のトランザクションの書き込みセットは含まれない。
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.data.keys()
+    }
+
+    /// コミット済みの値をキーの昇順に走査する
+    ///
+    /// `scan_all`と同様、実行中のトランザクションの書き込みセットは含まれず、値を復元できない場合は
+    /// `DatabaseError::JSONError`を返す。
+    pub fn values(&self) -> Result<impl Iterator<Item = &V>, DatabaseError> {
+        Result::Ok(self.data.iter()?.map(|(_, v)| v))
+    }
+
+    /// predicateを満たすコミット済みのキーバリューペアのうち、キーが最小のものを返す
+    ///
+    /// ログには何も書き込まない。期限切れのキーは含まれず、`scan_all`と同様に実行中のトランザクションの
+    /// 書き込みセットも含まれない。値を復元できない場合は`scan_all`と同様に`DatabaseError::JSONError`を返す。
+    pub fn find<F>(&self, predicate: F) -> Result<Option<(K, V)>, DatabaseError>
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        let matches = |(key, value): &(&K, &V)| !self.is_expired(key) && predicate(key, value);
+        let pair = if self.data.ensure_sorted("find").is_ok() {
+            self.data
+                .range(Bound::Unbounded, Bound::Unbounded)?
+                .find(matches)
+        } else {
+            self.data.iter()?.filter(matches).min_by_key(|(k, _)| *k)
+        };
+        Result::Ok(pair.map(|(k, v)| (k.clone(), v.clone())))
+    }
+
+    /// predicateを満たすコミット済みのキーバリューペアをすべて、キーの昇順に返す
+    ///
+    /// `find`と同様、ログには何も書き込まず、期限切れのキーと実行中のトランザクションの書き込みセットは含まれない。
+    pub fn find_all<F>(&self, predicate: F) -> Result<Vec<(K, V)>, DatabaseError>
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        let mut pairs: Vec<(K, V)> = self
+            .data
+            .iter()?
+            .filter(|(key, value)| !self.is_expired(key) && predicate(key, value))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        if self.data.ensure_sorted("find_all").is_err() {
+            pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+        Result::Ok(pairs)
+    }
+
+    /// 現在のコミット済みの内容を複製したスナップショットを作成する
+    ///
+    /// 期限切れのキーは含まれない。ログには何も書き込まない。
+    /// 値を復元できない場合は`scan_all`と同様に`DatabaseError::JSONError`を返す。
+    pub fn snapshot(&self) -> Result<Snapshot<K, V>, DatabaseError> {
+        Result::Ok(Snapshot::new(
+            self.data
+                .iter()?
+                .filter(|(key, _)| !self.is_expired(key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ))
+    }
+
+    /// 最後に作成されたチェックポイントの時点の内容のスナップショットを作成する
+    ///
+    /// メモリ上の内容ではなくデータファイルを読み込んで作成し、読み込んだ内容は保持しない。
+    /// `checkpoint_snapshot()?.diff(&snapshot())`により、チェックポイント以降の変更を求めることができる。
+    /// 期限切れのキーは含まれない。データファイルが存在しない場合(メモリ上のみのデータベースを含む)は
+    /// 空のスナップショットを返す。
+    pub fn checkpoint_snapshot(&self) -> Result<Snapshot<K, V>, DatabaseError> {
+        let datapath = match &self.datapath {
+            Option::Some(datapath) if datapath.exists() => datapath,
+            _ => return Result::Ok(Snapshot::new(BTreeMap::new())),
+        };
+        let content = datafile::decompress(std::fs::read(datapath)?)?;
+        datafile::verify(&content)?;
+        let file: DataFile<K, V> = datafile::decode(&content)?;
+        let expiry = file.expiry;
+        Result::Ok(Snapshot::new(
+            file.data
+                .into_iter()
+                .filter(|(key, _)| !expiry.get(key).is_some_and(|secs| is_past(*secs)))
+                .collect(),
+        ))
+    }
+
+    /// 読み取り専用トランザクションを発行する
+    pub fn begin_read_transaction(&self) -> Result<ReadTransaction<'_, K, V>, DatabaseError> {
+        Result::Ok(ReadTransaction::new(self))
+    }
+
+    /// 読み取り専用トランザクションの発行を試みる
+    ///
+    /// `None`は更新トランザクションが実行中であることを表す。`&self`を借用できる時点で
+    /// 更新トランザクションは存在しないため、常に`Some`を返す。
+    pub fn try_begin_read_transaction(&self) -> Option<ReadTransaction<'_, K, V>> {
+        Option::Some(ReadTransaction::new(self))
+    }
+}
+
+/// 2つの値が等しいかどうかを、シリアライズした結果を比較することで判定する
+///
+/// `V`には`PartialEq`を要求していないため、Redo時の比較にはこの関数を用いる。
+fn same_value<V: Serialize>(a: &V, b: &V) -> bool {
+    match (bincode::serialize(a), bincode::serialize(b)) {
+        (Result::Ok(a), Result::Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// 書き込みセットの内容を再現するレコードを返す
+///
+/// 有効期限を伴って作成されたキーは、CreateWithTTLレコードとして再現する。
+fn writeset_records<K, V>(writeset: &WriteSet<K, V>, ttl: &BTreeMap<K, u64>) -> Vec<LogRecord<K, V>>
+where
+    K: Debug + Clone + Ord,
+    V: Debug + Clone,
+{
+    writeset
+        .iter()
+        .map(|(key, op)| match (op, ttl.get(key)) {
+            (Option::Some(value), Option::Some(expiry_secs)) => LogRecord::CreateWithTTL {
+                key: key.clone(),
+                value: value.clone(),
+                expiry_secs: *expiry_secs,
+            },
+            (Option::Some(value), Option::None) => LogRecord::Upsert {
+                key: key.clone(),
+                value: value.clone(),
+            },
+            (Option::None, _) => LogRecord::Delete { key: key.clone() },
+        })
+        .collect()
+}
+
+/// 走査の対象とエラーに分ける
+///
+/// 走査の結果の先頭にエラーを返すイテレータを作成するために使用する。
+fn split_error<T>(result: Result<Option<T>, DatabaseError>) -> (Option<T>, Option<DatabaseError>) {
+    match result {
+        Result::Ok(value) => (value, Option::None),
+        Result::Err(e) => (Option::None, Option::Some(e)),
+    }
+}
+
+/// 現在時刻からttl後の時刻をUNIX時間(秒)として返す
+///
+/// 期限より早く失効することがないよう、秒未満は切り上げる。
+fn expiry_secs(ttl: Duration) -> u64 {
+    let expiry = SystemTime::now() + ttl;
+    let since_epoch = expiry.duration_since(UNIX_EPOCH).unwrap_or_default();
+    since_epoch.as_secs() + u64::from(since_epoch.subsec_nanos() > 0)
+}
+
+/// UNIX時間(秒)で表された有効期限を過ぎているかどうかを返す
+fn is_past(expiry_secs: u64) -> bool {
+    SystemTime::now() >= UNIX_EPOCH + Duration::from_secs(expiry_secs)
+}
+
+/// データファイルを格納するディレクトリを返す
+fn data_dir(datapath: &Path) -> &Path {
+    match datapath.parent() {
+        Option::Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for Database<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    /// メモリ上のみで動作するデータベースを初期化し、iterの内容を1つのトランザクションで書き込む
+    ///
+    /// # Panics
+    /// 初期化・書き込みに失敗した場合はpanicする。エラーを扱う場合は
+    /// `Database::from_iter_fallible`を使用すること。
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let config = DatabaseConfig::builder().in_memory(true).build();
+        Database::from_iter_fallible(iter, config).expect("failed to build the database")
+    }
+}
+
+impl<K, V> Drop for Database<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    /// データベースの永続化を行います(読み取り専用の場合は何もしない)
+    fn drop(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        if let Result::Err(e) = self.exec_checkpointing().context("checkpointing on drop") {
+            ::log::error!("mikrodb: {}", e);
+        }
+    }
+}
+
+impl<K, V> Debug for Database<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Database")
+            .field("datapath", &self.datapath)
+            .field("len", &self.data.len())
+            .field("wal", &self.log_path())
+            .field("checkpoint_lsn", &self.checkpoint_lsn)
+            .field("read_only", &self.is_read_only())
+            .finish_non_exhaustive()
+    }
+}
+
+/// 格納先とキーバリューペアの数を1行で表示する
+impl<K, V> Display for Database<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.datapath, self.log_path()) {
+            (Option::Some(datapath), Option::Some(wal)) => write!(
+                f,
+                "Database at {} (WAL {})",
+                datapath.display(),
+                wal.display()
+            )?,
+            (Option::Some(datapath), Option::None) => {
+                write!(f, "read-only Database at {}", datapath.display())?
+            }
+            (Option::None, _) => write!(f, "in-memory Database")?,
+        }
+        write!(f, ": {} records", self.data.len())
+    }
+}
+
+impl<'tx, K, V, D> Transaction<'tx, K, V, D>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: DerefMut<Target = Database<K, V>>,
+{
+    pub(crate) fn new(database: D) -> Self {
+        let snapshot_version = database.version();
+        let start_lsn = database.wal.as_ref().map_or(0, WALManager::current_lsn);
+        let start_offset = database.wal.as_ref().map_or(0, WALManager::current_offset);
+        Transaction {
+            database,
+            writeset: WriteSet::new(),
+            dirty: BTreeSet::new(),
+            savepoints: Vec::new(),
+            next_savepoint: 0,
+            relogged_bytes: 0,
+            snapshot_version,
+            ttl: BTreeMap::new(),
+            deleted: BTreeSet::new(),
+            read_versions: BTreeMap::new(),
+            start_lsn,
+            start_offset,
+            deadline: Option::None,
+            finished: false,
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!("transaction", start_lsn),
+            _marker: PhantomData,
+        }
+    }
+
+    /// トランザクションの開始時点でログに書き込まれていた最後のレコードのLSNを返す
+    pub fn start_lsn(&self) -> u64 {
+        self.start_lsn
+    }
+
+    /// トランザクションの開始時点のログの末尾の位置(bytes)を返す
+    ///
+    /// このトランザクションのレコードはこの位置以降に書き込まれる。ただし、トランザクションの途中で
+    /// チェックポイントが作成された場合、ログの破棄によりこの位置は無効となる。
+    pub fn start_offset(&self) -> u64 {
+        self.start_offset
+    }
+
+    /// 現在の時刻からdurationが経過した後の操作を`DatabaseError::TransactionTimeout`とする
+    ///
+    /// 期限を過ぎた後に最初に行われた操作がAbortレコードを書き込み、トランザクションはAbort扱いとなる。
+    /// 以降の操作(Commitを含む)もすべて`DatabaseError::TransactionTimeout`となる。
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.deadline = Option::Some(SystemTime::now() + duration);
+    }
+
+    /// 期限を過ぎている場合、Abortした上で`DatabaseError::TransactionTimeout`を返す
+    fn check_deadline(&mut self) -> Result<(), DatabaseError> {
+        let result = self.ensure_before_deadline();
+        if result.is_err() && !self.finished {
+            self.write_abort();
+        }
+        result
+    }
+
+    /// 期限を過ぎている場合は`DatabaseError::TransactionTimeout`を返す(Abortレコードは書き込まない)
+    fn ensure_before_deadline(&self) -> Result<(), DatabaseError> {
+        match self.deadline {
+            Option::Some(deadline) if SystemTime::now() > deadline => {
+                Result::Err(DatabaseError::TransactionTimeout)
+            }
+            _ => Result::Ok(()),
+        }
+    }
+
+    /// Abortレコードを書き込み、トランザクションを終了済みとする
+    fn write_abort(&mut self) {
+        self.finished = true;
+        if let Option::Some(wal) = &mut self.database.wal {
+            let log: LogRecord<K, V> = LogRecord::Abort;
+            if let Result::Err(e) = wal
+                .write_log_unchecked(&log, true)
+                .context("writing abort record")
+            {
+                ::log::error!("mikrodb: {}", e);
+            }
+        }
+        self.database.stats.record_abort();
+        self.database
+            .aborted_transactions
+            .fetch_add(1, Ordering::Relaxed);
+        debug_event!(parent: &self.span, outcome = "abort");
+        self.database.subscribers.publish(DatabaseEvent::Aborted);
+    }
+
+    /// ログレコードを書き込む
+    ///
+    /// ログの容量が上限に達している場合はチェックポイントを作成し、破棄されたログに含まれていた
+    /// このトランザクションの書き込みセット(およびセーブポイント)を改めて記録し直した上で書き込む。
+    /// 記録し直した分のバイト数は上限の判定から除外される。
+    /// `set_timeout`による期限を過ぎている場合は、何も書き込まずに`DatabaseError::TransactionTimeout`を返す。
+    fn write_log(&mut self, log: &LogRecord<K, V>, sync: bool) -> Result<(), DatabaseError> {
+        self.check_deadline()?;
+        let max_wal_bytes = self.database.config.max_wal_bytes;
+        let wal = self.database.wal_mut()?;
+        match wal.write_log(log, sync) {
+            Result::Err(DatabaseError::CheckpointRequired)
+                if self.relogged_bytes > 0
+                    && wal.bytes_since_checkpoint() - self.relogged_bytes < max_wal_bytes =>
+            {
+                wal.write_log_unchecked(log, sync)
+            }
+            Result::Err(DatabaseError::CheckpointRequired) => {
+                self.database.exec_checkpointing()?;
+                for log in self.relog_records() {
+                    self.database.wal_mut()?.write_log_unchecked(&log, false)?;
+                }
+                let wal = self.database.wal_mut()?;
+                self.relogged_bytes = wal.bytes_since_checkpoint();
+                wal.write_log_unchecked(log, sync)
+            }
+            result => result,
+        }
+    }
+
+    /// `write_log`と同様にログを書き込み、書き込みに失敗した場合はdescribeの説明を付与する
+    ///
+    /// 期限切れ・fsyncの失敗・読み取り専用など、トランザクションやデータベースの状態を表すエラーは
+    /// 呼び出し側が判別できるよう、説明を付与せずにそのまま返す。
+    fn write_log_with<F>(
+        &mut self,
+        log: &LogRecord<K, V>,
+        sync: bool,
+        describe: F,
+    ) -> Result<(), DatabaseError>
+    where
+        F: FnOnce() -> String,
+    {
+        self.write_log(log, sync).map_err(|e| match e {
+            DatabaseError::TransactionTimeout
+            | DatabaseError::SyncFailed { .. }
+            | DatabaseError::ReadOnlyDatabase
+            | DatabaseError::LockPoisonedError => e,
+            e => e.context(describe()).into(),
+        })
+    }
+
+    /// チェックポイントにより破棄されたログの代わりに記録するレコードを返す
+    ///
+    /// 各セーブポイントについて、その時点の書き込みセットとセーブポイントのレコードを順に並べ、
+    /// 最後に現在の書き込みセットを並べる。
+    fn relog_records(&self) -> Vec<LogRecord<K, V>> {
+        let mut records = Vec::new();
+        for savepoint in &self.savepoints {
+            records.extend(writeset_records(&savepoint.writeset, &savepoint.ttl));
+            records.push(LogRecord::Savepoint {
+                id: savepoint.id.0,
+                name: savepoint.name.clone(),
+            });
+        }
+        records.extend(writeset_records(&self.writeset, &self.ttl));
+        records
+    }
+
+    /// `DatabaseConfig::undo_logging`が有効な場合、keyの変更前の値をBeforeImageレコードとしてログに書き込む
+    ///
+    /// チェックポイントによりログが破棄された場合、それ以前のBeforeImageレコードは記録し直さない。
+    fn write_before_image(&mut self, key: &K, old_value: Option<&V>) -> Result<(), DatabaseError> {
+        if !self.database.config.undo_logging {
+            return Result::Ok(());
+        }
+        let log = LogRecord::BeforeImage {
+            key: key.clone(),
+            old_value: old_value.cloned(),
+        };
+        self.write_log(&log, false)
+    }
+
+    /// keyやvalueが`DatabaseConfig::max_key_size`・`max_value_size`を超える場合はエラーを返す
+    ///
+    /// 大きさはJSONにシリアライズした際のバイト数とする。上限が設定されていない場合はシリアライズしない。
+    fn check_size(&self, key: &K, value: &V) -> Result<(), DatabaseError> {
+        let config = &self.database.config;
+        if let Option::Some(max) = config.max_key_size {
+            let size = serde_json::to_vec(key)?.len();
+            if size > max {
+                return Result::Err(DatabaseError::KeyTooLarge { size, max });
+            }
+        }
+        if let Option::Some(max) = config.max_value_size {
+            let size = serde_json::to_vec(value)?.len();
+            if size > max {
+                return Result::Err(DatabaseError::ValueTooLarge { size, max });
+            }
+        }
+        Result::Ok(())
+    }
+
+    /// keyを読み取ったことを、その時点のキーのバージョンと共に記録する
+    ///
+    /// 同じキーを繰り返し読み取った場合は、最初に読み取った時点のバージョンを保持する。
+    fn record_read(&mut self, key: &K) {
+        if !self.read_versions.contains_key(key) {
+            let version = self.database.key_version(key);
+            self.read_versions.insert(key.clone(), version);
+        }
+    }
+
+    /// keyが書き込みセットに含まれるか、期限切れでないコミット済みのキーであるかを返す
+    ///
+    /// 走査の結果から期限切れのコミット済みのキーを除くために使用する。
+    fn is_live(&self, key: &K) -> bool {
+        self.writeset.contains_key(key) || !self.database.is_expired(key)
+    }
+
+    /// 読み取ったキーのうち、読み取った後に他のトランザクションにより変更されたキーを検出する
+    ///
+    /// 存在する場合は、それらのキーをJSONで表した`DatabaseError::ConflictError`を返す。
+    ///
+    /// トランザクションはデータベースへの排他的なアクセス(`&mut Database`や`SharedDatabase`の
+    /// 書き込みロック)を保持するため、現在は他のトランザクションのCommitが割り込むことはなく、
+    /// 競合は検出されない。
+    fn check_conflicts(&self) -> Result<(), DatabaseError> {
+        let conflicting_keys = self
+            .read_versions
+            .iter()
+            .filter(|(key, version)| self.database.key_version(key) != **version)
+            .map(|(key, _)| serde_json::to_string(key))
+            .collect::<Result<Vec<String>, _>>()?;
+        if conflicting_keys.is_empty() {
+            Result::Ok(())
+        } else {
+            Result::Err(DatabaseError::ConflictError { conflicting_keys })
+        }
+    }
+
+    /// ログに書き込まず、keyに対応する値を読み取る
+    fn peek_internal(&self, key: &K) -> Result<Option<V>, DatabaseError> {
+        match self.writeset.get(key) {
+            None => Result::Ok(self.database.live_value(key)?.cloned()),
+            Some(v) => Result::Ok(v.cloned()),
+        }
+    }
+
+    /// keyに対応する値をvalueとして新規設定する
+    #[must_use = "create errors (such as a duplicated key) must be handled"]
+    pub fn create(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
+        if self.peek_internal(&key)?.is_some() {
+            return Result::Err(DatabaseError::KeyDuplicationError);
+        }
+        self.check_size(&key, &value)?;
+        debug_event!(parent: &self.span, ?key, ?value, "create");
+        self.write_before_image(&key, Option::None)?;
+        {
+            let log = LogRecord::Create {
+                key: key.clone(),
+                value: value.clone(),
+            };
+            self.write_log_with(&log, false, || {
+                format!("writing the create record for key {:?}", key)
+            })?;
+        }
+        self.writeset.insert(key, value);
+        Result::Ok(())
+    }
+
+    /// keyが存在しない場合に限り、keyに対応する値をvalueとして新規設定する
+    ///
+    /// 新規設定した場合は`true`を返す。既に存在する場合は`create`と異なりエラーとせず、
+    /// ログには何も書き込まずに`false`を返す。
+    pub fn create_if_absent(&mut self, key: K, value: V) -> Result<bool, DatabaseError> {
+        if self.peek_internal(&key)?.is_some() {
+            return Result::Ok(false);
+        }
+        self.create(key, value)?;
+        Result::Ok(true)
+    }
+
+    /// keyに対応する値をvalueとして、ttl後に失効するように新規設定する
+    ///
+    /// 失効したキーは読み取りの際には存在しないものとして扱われ、`Database::purge_expired`により
+    /// 削除される。有効期限はUNIX時間(秒)として記録される。
+    pub fn create_with_ttl(
+        &mut self,
+        key: K,
+        value: V,
+        ttl: Duration,
+    ) -> Result<(), DatabaseError> {
+        self.create_with_expiry(key, value, expiry_secs(ttl))
+    }
+
+    /// keyに対応する値をvalueとして、UNIX時間(秒)でexpiry_secsに失効するように新規設定する
+    fn create_with_expiry(
+        &mut self,
+        key: K,
+        value: V,
+        expiry_secs: u64,
+    ) -> Result<(), DatabaseError> {
+        if self.peek_internal(&key)?.is_some() {
+            return Result::Err(DatabaseError::KeyDuplicationError);
+        }
+        self.check_size(&key, &value)?;
+        self.write_before_image(&key, Option::None)?;
+        {
+            let log = LogRecord::CreateWithTTL {
+                key: key.clone(),
+                value: value.clone(),
+                expiry_secs,
+            };
+            self.write_log(&log, false)?;
+        }
+        self.ttl.insert(key.clone(), expiry_secs);
+        self.writeset.insert(key, value);
+        Result::Ok(())
+    }
+
+    /// keyに対応する値を読み取り、Readレコードをログに書き込む
+    ///
+    /// ReadレコードはRedoに使用されないため、ログの容量を不要に消費する。
+    /// (i32のキーの場合、SHA256のチェックサムで1件あたり57 bytes)
+    #[deprecated(note = "Readレコードをログに書き込まない`read_silent`を使用すること")]
+    #[must_use = "read returns the value, and errors must be handled"]
+    pub fn read(&mut self, key: K) -> Result<V, DatabaseError> {
+        {
+            let log: LogRecord<K, V> = LogRecord::Read { key: key.clone() };
+            self.write_log(&log, false)?;
+        }
+        self.read_silent(key)
+    }
+
+    /// keyに対応する値を読み取る(ログには書き込まない)
+    pub fn read_silent(&mut self, key: K) -> Result<V, DatabaseError> {
+        self.check_deadline()?;
+        debug_event!(parent: &self.span, ?key, "read");
+        self.record_read(&key);
+        let value = match self.writeset.get(&key) {
+            Option::Some(v) => v.cloned(),
+            Option::None => self.database.read_at(&key, self.snapshot_version)?.cloned(),
+        };
+        value.ok_or(DatabaseError::KeyNotFoundError)
+    }
+
+    /// keyに対応する値を読み取り、存在しない場合はdefaultを返す(ログには書き込まない)
+    ///
+    /// `read_silent`と同様にトランザクションの開始時点のバージョンから読み取る。keyは作成しない。
+    pub fn get_or_default(&mut self, key: K, default: V) -> Result<V, DatabaseError> {
+        match self.read_silent(key) {
+            Result::Err(DatabaseError::KeyNotFoundError) => Result::Ok(default),
+            result => result,
+        }
+    }
+
+    /// keyに対応する値を読み取り、存在しない場合はdefaultを新規設定した上でそれを返す
+    ///
+    /// `Entry::or_insert`と同様の操作を行う。読み取りはログに書き込まず、新規設定した場合のみ
+    /// Createレコードを書き込む。
+    pub fn get_or_insert(&mut self, key: K, default: V) -> Result<V, DatabaseError> {
+        match self.read_silent(key.clone()) {
+            Result::Err(DatabaseError::KeyNotFoundError) => {
+                self.create(key, default.clone())?;
+                Result::Ok(default)
+            }
+            result => result,
+        }
+    }
+
+    /// keyに対応する値を読み取る(ログには書き込まない)
+    ///
+    /// 値の読み取りには以下の違いがある。
+    /// - `read`: Readレコードをログに書き込み、存在しない場合は`DatabaseError::KeyNotFoundError`を返す
+    /// - `read_silent`: ログに書き込まず、トランザクションの開始時点のバージョンから読み取る。
+    ///   存在しない場合や、そのバージョンが既に失われている場合はエラーを返す
+    /// - `peek`: ログに書き込まず、現在のコミット済みの内容から読み取る(書き込みセットの内容は反映する)。
+    ///   エラーを返さず、存在しない場合は`None`を返す
+    ///
+    /// `peek`は以下の場合も`None`を返す。これらを区別する必要がある場合は`get_ref`を使用する。
+    /// - `Transaction::set_timeout`の期限を過ぎている場合(`&self`を取るため、Abortレコードは
+    ///   トランザクションの終了時(Drop時)に書き込まれる)
+    /// - 値を参照時に復元する設定で、値を復元できない場合
+    pub fn peek(&self, key: &K) -> Option<V> {
+        if self.ensure_before_deadline().is_err() {
+            return Option::None;
+        }
+        self.peek_internal(key).unwrap_or(Option::None)
+    }
+
+    /// keyに対応する値への参照を返す(ログには書き込まない)
+    ///
+    /// 値を複製しないため、読み取りの多い処理に適する。
+    /// `peek`と異なり、期限を過ぎている場合は`DatabaseError::TransactionTimeout`を、値を復元できない場合は
+    /// `DatabaseError::JSONError`を返す。
+    pub fn get_ref(&self, key: &K) -> Result<Option<&V>, DatabaseError> {
+        self.ensure_before_deadline()?;
+        match self.writeset.get(key) {
+            Option::Some(v) => Result::Ok(v),
+            Option::None => self.database.read_at(key, self.snapshot_version),
+        }
+    }
+
+    /// keyが存在するかどうかを返し、Existsレコードをログに書き込む
+    ///
+    /// 値の複製は行わない。
+    pub fn contains_key(&mut self, key: &K) -> Result<bool, DatabaseError> {
+        self.check_deadline()?;
+        {
+            let log: LogRecord<K, V> = LogRecord::Exists { key: key.clone() };
+            self.write_log(&log, false)?;
+        }
+        self.record_read(key);
+        Result::Ok(self.get_ref(key)?.is_some())
+    }
+
+    /// 複数のkeyに対応する値をまとめて読み取る
+    ///
+    /// 戻り値は引数と同じ順に並び、存在しないキーに対しては`None`となる。
+    /// キーの数によらず、ReadBatchレコードを1つだけログに書き込む。
+    pub fn get_many(&mut self, keys: &[K]) -> Result<Vec<Option<V>>, DatabaseError> {
+        self.check_deadline()?;
+        {
+            let log: LogRecord<K, V> = LogRecord::ReadBatch {
+                keys: keys.to_vec(),
+            };
+            self.write_log(&log, false)?;
+        }
+        for key in keys {
+            self.record_read(key);
+        }
+        keys.iter().map(|key| self.peek_internal(key)).collect()
+    }
+
+    /// 指定された範囲のキーバリューペアをキーの昇順に読み取る
+    ///
+    /// コミット済みのデータに書き込みセットの内容を反映した結果を返す。期限切れのコミット済みのキーは含まない。
+    /// 呼び出し時に範囲を表すScanレコードを1つだけログに書き込む。
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となり、ログには何も書き込まない。
+    pub fn scan_range(
+        &mut self,
+        start: Bound<K>,
+        end: Bound<K>,
+    ) -> impl Iterator<Item = Result<(K, V), DatabaseError>> + '_ {
+        let result = self.database.data.ensure_sorted("scan_range");
+        let result = result.and_then(|()| {
+            let log: LogRecord<K, V> = LogRecord::Scan {
+                start: start.clone(),
+                end: end.clone(),
+            };
+            self.write_log(&log, false)
+        });
+        let this = &*self;
+        let ranges = match result {
+            Result::Ok(()) if is_valid_range(&start, &end) => this
+                .database
+                .data
+                .range(start.clone(), end.clone())
+                .map(move |data| Option::Some((data, this.writeset.range((start, end))))),
+            result => result.map(|()| Option::None),
+        };
+        let (ranges, error) = split_error(ranges);
+        error.map(Result::Err).into_iter().chain(
+            ranges
+                .into_iter()
+                .flat_map(|(data, writeset)| MergeIter::new(data, writeset, false))
+                .filter(move |(k, _)| this.is_live(k))
+                .map(|(k, v)| Result::Ok((k.clone(), v.clone()))),
+        )
+    }
+
+    /// 指定された範囲のキーバリューペアをキーの降順に読み取る
+    ///
+    /// endからstartへ向かって、コミット済みのデータに書き込みセットの内容を反映した結果を返す。
+    /// `scan_range`と同様、期限切れのコミット済みのキーは含まない。
+    /// 呼び出し時に範囲を表すScanReverseレコードを1つだけログに書き込む。
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となり、ログには何も書き込まない。
+    pub fn scan_reverse(
+        &mut self,
+        start: Bound<K>,
+        end: Bound<K>,
+    ) -> impl Iterator<Item = Result<(K, V), DatabaseError>> + '_ {
+        let result = self.database.data.ensure_sorted("scan_reverse");
+        let result = result.and_then(|()| {
+            let log: LogRecord<K, V> = LogRecord::ScanReverse {
+                start: start.clone(),
+                end: end.clone(),
+            };
+            self.write_log(&log, false)
+        });
+        let this = &*self;
+        let ranges = match result {
+            Result::Ok(()) if is_valid_range(&start, &end) => this
+                .database
+                .data
+                .range(start.clone(), end.clone())
+                .map(move |data| Option::Some((data, this.writeset.range((start, end))))),
+            result => result.map(|()| Option::None),
+        };
+        let (ranges, error) = split_error(ranges);
+        error.map(Result::Err).into_iter().chain(
+            ranges
+                .into_iter()
+                .flat_map(|(data, writeset)| MergeIter::new(data.rev(), writeset.rev(), true))
+                .filter(move |(k, _)| this.is_live(k))
+                .map(|(k, v)| Result::Ok((k.clone(), v.clone()))),
+        )
+    }
+
+    /// 書き込みセットの内容を反映した最小のキーを返す(ログには書き込まない)
+    pub fn first_key(&self) -> Option<K> {
+        self.edge_key(false)
+    }
+
+    /// 書き込みセットの内容を反映した最大のキーを返す(ログには書き込まない)
+    pub fn last_key(&self) -> Option<K> {
+        self.edge_key(true)
+    }
+
+    /// 書き込みセットの内容を反映した最小(reverseの場合は最大)のキーを返す
+    ///
+    /// 書き込みセット上で削除されたキーは読み飛ばす。キーのみを参照し、値は復元しない。
+    fn edge_key(&self, reverse: bool) -> Option<K> {
+        let mut committed = self
+            .database
+            .data
+            .ordered_keys(reverse)
+            .filter(|k| !self.writeset.contains_key(*k));
+        let written = self
+            .writeset
+            .iter()
+            .filter(|(_, op)| op.is_some())
+            .map(|(k, _)| k);
+        let key = match (self.database.data.ensure_sorted("edge_key"), reverse) {
+            (Result::Ok(()), true) => committed.next().into_iter().chain(written.rev()).max(),
+            (Result::Ok(()), false) => committed.next().into_iter().chain(written).min(),
+            (Result::Err(_), true) => committed.chain(written).max(),
+            (Result::Err(_), false) => committed.chain(written).min(),
+        };
+        key.cloned()
+    }
+
+    /// predicateを満たすキーバリューペアのうち、キーが最小のものを返す(ログには書き込まない)
+    ///
+    /// コミット済みのデータに書き込みセットの内容を反映した結果から探す。書き込みセット上で上書きされた
+    /// キーは書き込みセットの値で判定し、削除されたキーは含まない。期限切れのコミット済みのキーは含まない。
+    /// 値を参照時に復元する場合、復元できない値が含まれると`DatabaseError::JSONError`を返す。
+    pub fn find<F>(&self, predicate: F) -> Result<Option<(K, V)>, DatabaseError>
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        Result::Ok(
+            self.matching_pairs(&predicate)?
+                .next()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        )
+    }
+
+    /// predicateを満たすキーバリューペアを、書き込みセットの内容を反映してキーの昇順に走査する
+    ///
+    /// 期限切れのコミット済みのキーは含まない。`StorageBackend::Hashed`の場合は、条件を満たすペアを
+    /// すべて集めて整列した上で走査する。
+    fn matching_pairs<'a, F>(
+        &'a self,
+        predicate: &'a F,
+    ) -> Result<Box<dyn Iterator<Item = (&'a K, &'a V)> + 'a>, DatabaseError>
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        match self.database.data.ensure_sorted("find") {
+            Result::Ok(()) => {
+                let data = self
+                    .database
+                    .data
+                    .range(Bound::Unbounded, Bound::Unbounded)?;
+                Result::Ok(Box::new(
+                    MergeIter::new(data, self.writeset.iter(), false)
+                        .filter(move |(key, value)| self.is_live(key) && predicate(key, value)),
+                ))
+            }
+            Result::Err(_) => {
+                let committed = self.database.data.iter()?.filter(|(key, _)| {
+                    !self.writeset.contains_key(key) && !self.database.is_expired(key)
+                });
+                let written = self
+                    .writeset
+                    .iter()
+                    .filter_map(|(key, op)| op.map(|value| (key, value)));
+                let mut pairs: Vec<(&K, &V)> = committed
+                    .chain(written)
+                    .filter(|(key, value)| predicate(key, value))
+                    .collect();
+                pairs.sort_by_key(|(k, _)| *k);
+                Result::Ok(Box::new(pairs.into_iter()))
+            }
+        }
+    }
+
+    /// predicateを満たすキーバリューペアをすべて削除し、削除したペアをキーの昇順に返す
+    ///
+    /// コミット済みのデータに書き込みセットの内容を反映した結果から、`find`と同様に探す。
+    /// キーごとのDeleteレコードではなく、1つのBatchレコードのみをログに書き込む(該当するペアが
+    /// ない場合は何も書き込まない)。
+    pub fn drain<F>(&mut self, predicate: F) -> Result<Vec<(K, V)>, DatabaseError>
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        let pairs: Vec<(K, V)> = self
+            .matching_pairs(&predicate)?
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        self.write_batch(pairs.iter().map(|(key, _)| (key.clone(), Option::None)))?;
+        Result::Ok(pairs)
+    }
+
+    /// 指定された範囲のキーの数を返す(ログには書き込まない)
+    ///
+    /// コミット済みのキーの数に、書き込みセットで範囲内に作成されたキーを加え、削除されたキーを除く。
+    pub fn count_in_range(&self, start: Bound<K>, end: Bound<K>) -> usize {
+        if !is_valid_range(&start, &end) {
+            return 0;
+        }
+        let committed = self.database.count_in_range(start.clone(), end.clone());
+        let (added, removed) =
+            self.writeset
+                .range((start, end))
+                .fold((0, 0), |(added, removed), (key, op)| {
+                    match (op, self.database.data.contains_key(key)) {
+                        (Option::Some(_), false) => (added + 1, removed),
+                        (Option::None, true) => (added, removed + 1),
+                        _ => (added, removed),
+                    }
+                });
+        committed + added - removed
+    }
+
+    /// prefixを接頭辞として持つキーのキーバリューペアをキーの昇順に読み取る
+    ///
+    /// コミット済みのデータに書き込みセットの内容を反映した結果を返す。期限切れのコミット済みのキーは含まない。
+    /// 呼び出し時にScanPrefixレコードを1つだけログに書き込む。
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となり、ログには何も書き込まない。
+    pub fn scan_prefix(
+        &mut self,
+        prefix: &K,
+    ) -> impl Iterator<Item = Result<(K, V), DatabaseError>> + '_
+    where
+        K: HasPrefix,
+    {
+        let prefix = prefix.clone();
+        let result = self.database.data.ensure_sorted("scan_prefix");
+        let result = result.and_then(|()| {
+            let log: LogRecord<K, V> = LogRecord::ScanPrefix {
+                prefix: prefix.clone(),
+            };
+            self.write_log(&log, false)
+        });
+        let start = Bound::Included(prefix.clone());
+        let this = &*self;
+        let ranges = result.and_then(|()| {
+            let data = this.database.data.range(start, Bound::Unbounded)?;
+            let range = (Bound::Included(prefix.clone()), Bound::Unbounded);
+            Result::Ok(Option::Some((data, this.writeset.range(range))))
+        });
+        let (ranges, error) = split_error(ranges);
+        error.map(Result::Err).into_iter().chain(
+            ranges
+                .into_iter()
+                .flat_map(|(data, writeset)| MergeIter::new(data, writeset, false))
+                .take_while(move |(k, _)| k.starts_with(&prefix))
+                .filter(move |(k, _)| this.is_live(k))
+                .map(|(k, v)| Result::Ok((k.clone(), v.clone()))),
+        )
+    }
+
+    /// keyに対応する値をvalueとして更新する
+    #[must_use = "update errors (such as a missing key) must be handled"]
+    pub fn update(&mut self, key: K, value: V) -> Result<(), DatabaseError> {
+        let old_value = self
+            .peek_internal(&key)?
+            .ok_or(DatabaseError::KeyNotFoundError)?;
+        self.check_size(&key, &value)?;
+        debug_event!(parent: &self.span, ?key, ?value, "update");
+        self.write_before_image(&key, Option::Some(&old_value))?;
+        {
+            let log = LogRecord::Update {
+                key: key.clone(),
+                value: value.clone(),
+            };
+            self.write_log_with(&log, false, || {
+                format!("writing the update record for key {:?}", key)
+            })?;
+        }
+        self.writeset.insert(key, value);
+        Result::Ok(())
+    }
+
+    /// keyに対応する値にfを適用した結果で更新する
+    ///
+    /// 読み取りと書き込みを分けずに、Updateレコードを1つだけログに書き込む。
+    pub fn update_with<F>(&mut self, key: K, f: F) -> Result<(), DatabaseError>
+    where
+        F: FnOnce(V) -> V,
+    {
+        self.update_with_result(key, |v| Result::Ok(f(v)))
+    }
+
+    /// keyに対応する値にfを適用した結果で更新する
+    ///
+    /// fがエラーを返した場合は何も変更せず、ログにも書き込まずにそのエラーを返す。
+    pub fn update_with_result<F>(&mut self, key: K, f: F) -> Result<(), DatabaseError>
+    where
+        F: FnOnce(V) -> Result<V, DatabaseError>,
+    {
+        let current = self
+            .peek_internal(&key)?
+            .ok_or(DatabaseError::KeyNotFoundError)?;
+        let old_value = if self.database.config.undo_logging {
+            Option::Some(current.clone())
+        } else {
+            Option::None
+        };
+        let value = f(current)?;
+        self.check_size(&key, &value)?;
+        self.write_before_image(&key, old_value.as_ref())?;
+        {
+            let log = LogRecord::Update {
+                key: key.clone(),
+                value: value.clone(),
+            };
+            self.write_log(&log, false)?;
+        }
+        self.writeset.insert(key, value);
+        Result::Ok(())
+    }
+
+    /// keyに対応する値をvalueとして設定する
+    ///
+    /// keyが既に存在する場合は更新し、存在しない場合は新規作成する。
+    /// 戻り値は、keyが既に存在していたかどうかを表す。
+    pub fn upsert(&mut self, key: K, value: V) -> Result<bool, DatabaseError> {
+        self.check_size(&key, &value)?;
+        let old_value = self.peek_internal(&key)?;
+        self.write_before_image(&key, old_value.as_ref())?;
+        {
+            let log = LogRecord::Upsert {
+                key: key.clone(),
+                value: value.clone(),
+            };
+            self.write_log(&log, false)?;
+        }
+        self.writeset.insert(key, value);
+        Result::Ok(old_value.is_some())
+    }
+
+    /// pairsのキーバリューペアをまとめて書き込む(値が`None`の場合はキーを削除する)
+    ///
+    /// 個々の操作ごとにレコードを書き込む代わりに、1つのBatchレコードのみをログに書き込む。
+    /// `upsert`と同様にキーの有無に関わらず値を書き込み、存在しないキーの削除はエラーとせずに無視する。
+    /// 同じキーが複数回含まれる場合は、最後の操作が反映される。上限(`DatabaseConfig::max_value_size`等)を
+    /// 超えるキーや値が含まれる場合は、何も書き込まずにエラーを返す。
+    pub fn write_batch<I>(&mut self, pairs: I) -> Result<(), DatabaseError>
+    where
+        I: IntoIterator<Item = (K, Option<V>)>,
+    {
+        let ops: Vec<BatchOp<K, V>> = pairs
+            .into_iter()
+            .map(|(key, value)| match value {
+                Option::Some(value) => BatchOp::Upsert(key, value),
+                Option::None => BatchOp::Delete(key),
+            })
+            .collect();
+        if ops.is_empty() {
+            return Result::Ok(());
+        }
+        for op in &ops {
+            match op {
+                BatchOp::Upsert(key, value) => self.check_size(key, value)?,
+                BatchOp::Delete(_) => {}
+            }
+        }
+        debug_event!(parent: &self.span, ops = ops.len(), "write_batch");
+        if self.database.config.undo_logging {
+            for op in &ops {
+                let old_value = self.peek_internal(op.key())?;
+                self.write_before_image(op.key(), old_value.as_ref())?;
+            }
+        }
+        let log = LogRecord::Batch { ops };
+        self.write_log(&log, false)?;
+        if let LogRecord::Batch { ops } = log {
+            for op in ops {
+                match op {
+                    BatchOp::Upsert(key, value) => self.writeset.insert(key, value),
+                    BatchOp::Delete(key) => {
+                        self.ttl.remove(&key);
+                        self.deleted.insert(key.clone());
+                        self.writeset.delete(key);
+                    }
+                }
+            }
+        }
+        Result::Ok(())
+    }
+
+    /// keyに対応する値がexpectedと一致する場合に限り、new_valueとして更新する
+    ///
+    /// 更新できた場合は`true`を、値が一致しなかった場合は`false`を返す。
+    /// 一致しなかった場合、ログには何も書き込まない。
+    pub fn compare_and_swap(
+        &mut self,
+        key: K,
+        expected: &V,
+        new_value: V,
+    ) -> Result<bool, DatabaseError>
+    where
+        V: PartialEq,
+    {
+        let current = match self.peek_internal(&key)? {
+            Option::None => return Result::Err(DatabaseError::KeyNotFoundError),
+            Option::Some(current) if current != *expected => return Result::Ok(false),
+            Option::Some(current) => current,
+        };
+        self.check_size(&key, &new_value)?;
+        self.write_before_image(&key, Option::Some(&current))?;
+        {
+            let log = LogRecord::CAS {
+                key: key.clone(),
+                expected: expected.clone(),
+                new_value: new_value.clone(),
+            };
+            self.write_log(&log, false)?;
+        }
+        self.writeset.insert(key, new_value);
+        Result::Ok(true)
+    }
+
+    /// keyに対応する値を削除する
+    #[must_use = "delete errors (such as a missing key) must be handled"]
+    pub fn delete(&mut self, key: K) -> Result<(), DatabaseError> {
+        let old_value = self
+            .peek_internal(&key)?
+            .ok_or(DatabaseError::KeyNotFoundError)?;
+        debug_event!(parent: &self.span, ?key, "delete");
+        self.write_before_image(&key, Option::Some(&old_value))?;
+        {
+            let log: LogRecord<K, V> = LogRecord::Delete { key: key.clone() };
+            self.write_log_with(&log, false, || {
+                format!("writing the delete record for key {:?}", key)
+            })?;
+        }
+        self.ttl.remove(&key);
+        self.deleted.insert(key.clone());
+        self.writeset.delete(key);
+        Result::Ok(())
+    }
+
+    /// old_keyに対応する値を有効期限と共にnew_keyへ移動する
+    ///
+    /// old_keyが存在しない場合は`DatabaseError::KeyNotFoundError`を、new_keyが既に存在する場合は
+    /// `DatabaseError::KeyDuplicationError`を返す。読み取り・削除・作成を分けずに、Renameレコードを
+    /// 1つだけログに書き込む。
+    pub fn rename(&mut self, old_key: K, new_key: K) -> Result<(), DatabaseError> {
+        let value = self
+            .peek_internal(&old_key)?
+            .ok_or(DatabaseError::KeyNotFoundError)?;
+        if self.peek_internal(&new_key)?.is_some() {
+            return Result::Err(DatabaseError::KeyDuplicationError);
+        }
+        self.check_size(&new_key, &value)?;
+        debug_event!(parent: &self.span, ?old_key, ?new_key, "rename");
+        self.write_before_image(&old_key, Option::Some(&value))?;
+        self.write_before_image(&new_key, Option::None)?;
+        {
+            let log: LogRecord<K, V> = LogRecord::Rename {
+                old_key: old_key.clone(),
+                new_key: new_key.clone(),
+            };
+            self.write_log_with(&log, false, || {
+                format!(
+                    "writing the rename record from key {:?} to key {:?}",
+                    old_key, new_key
+                )
+            })?;
+        }
+        let expiry_secs = match self.ttl.remove(&old_key) {
+            Option::Some(expiry_secs) => Option::Some(expiry_secs),
+            Option::None => self.database.expiry.get(&old_key).copied(),
+        };
+        if let Option::Some(expiry_secs) = expiry_secs {
+            self.ttl.insert(new_key.clone(), expiry_secs);
+        }
+        // entryによる未記録の変更は、移動先のキーのUpdateレコードとしてCommit時に書き込む
+        if self.dirty.remove(&old_key) {
+            self.dirty.insert(new_key.clone());
+        }
+        self.deleted.insert(old_key.clone());
+        self.writeset.delete(old_key);
+        self.writeset.insert(new_key, value);
+        Result::Ok(())
+    }
+
+    /// key_aとkey_bに対応する値を交換する
+    ///
+    /// いずれかのキーが存在しない場合は`DatabaseError::KeyNotFoundError`を返す。有効期限はキーに
+    /// 紐付いたまま交換しない。同じキーを指定した場合は何もせず、ログにも書き込まない。
+    /// 読み取りと書き込みを分けずに、Swapレコードを1つだけログに書き込む。
+    pub fn swap(&mut self, key_a: K, key_b: K) -> Result<(), DatabaseError> {
+        let value_a = self
+            .peek_internal(&key_a)?
+            .ok_or(DatabaseError::KeyNotFoundError)?;
+        let value_b = self
+            .peek_internal(&key_b)?
+            .ok_or(DatabaseError::KeyNotFoundError)?;
+        if key_a == key_b {
+            return Result::Ok(());
+        }
+        debug_event!(parent: &self.span, ?key_a, ?key_b, "swap");
+        self.write_before_image(&key_a, Option::Some(&value_a))?;
+        self.write_before_image(&key_b, Option::Some(&value_b))?;
+        {
+            let log: LogRecord<K, V> = LogRecord::Swap {
+                key_a: key_a.clone(),
+                key_b: key_b.clone(),
+            };
+            self.write_log(&log, false)?;
+        }
+        // entryによる未記録の変更は交換先に移るため、両方のキーのUpdateレコードをCommit時に書き込む
+        let dirty_a = self.dirty.contains(&key_a);
+        if dirty_a || self.dirty.contains(&key_b) {
+            self.dirty.insert(key_a.clone());
+            self.dirty.insert(key_b.clone());
+        }
+        self.writeset.insert(key_a, value_b);
+        self.writeset.insert(key_b, value_a);
+        Result::Ok(())
+    }
+
+    /// 期限切れのkeyを削除する
+    fn remove_expired(&mut self, key: K) -> Result<(), DatabaseError> {
+        {
+            let log: LogRecord<K, V> = LogRecord::Delete { key: key.clone() };
+            self.write_log(&log, false)?;
+        }
+        self.dirty.remove(&key);
+        self.deleted.insert(key.clone());
+        self.writeset.delete(key);
+        Result::Ok(())
+    }
+
+    /// keyに対応する数値にdeltaを加算し、加算後の値を返す
+    ///
+    /// keyが存在しない場合は、deltaを値として新規作成する。加算がオーバーフローする場合は
+    /// 何も変更せずに`DatabaseError::NumericOverflowError`を返す。
+    /// 読み取りと書き込みを分けずに、Incrementレコードを1つだけログに書き込む。
+    pub fn atomic_increment(&mut self, key: K, delta: V) -> Result<V, DatabaseError>
+    where
+        V: Numeric,
+    {
+        let old_value = self.peek_internal(&key)?;
+        let value = old_value
+            .unwrap_or_else(V::zero)
+            .checked_add(delta)
+            .ok_or(DatabaseError::NumericOverflowError)?;
+        self.write_before_image(&key, old_value.as_ref())?;
+        {
+            let log = LogRecord::Increment {
+                key: key.clone(),
+                delta,
+                value,
+            };
+            self.write_log(&log, false)?;
+        }
+        self.writeset.insert(key, value);
+        Result::Ok(value)
+    }
+
+    /// keyに対応する数値からdeltaを減算し、減算後の値を返す
+    ///
+    /// keyが存在しない場合は、0からdeltaを減算した値として新規作成する。減算がオーバーフローする
+    /// 場合は何も変更せずに`DatabaseError::NumericOverflowError`を返す。
+    pub fn atomic_decrement(&mut self, key: K, delta: V) -> Result<V, DatabaseError>
+    where
+        V: Numeric,
+    {
+        let old_value = self.peek_internal(&key)?;
+        let value = old_value
+            .unwrap_or_else(V::zero)
+            .checked_sub(delta)
+            .ok_or(DatabaseError::NumericOverflowError)?;
+        self.write_before_image(&key, old_value.as_ref())?;
+        {
+            let log = LogRecord::Decrement {
+                key: key.clone(),
+                delta,
+                value,
+            };
+            self.write_log(&log, false)?;
+        }
+        self.writeset.insert(key, value);
+        Result::Ok(value)
+    }
+
+    /// まだCommitされていない書き込みの数(書き込みセットに含まれるキーの数)を返す
+    pub fn pending_writes(&self) -> usize {
+        self.writeset.len()
+    }
+
+    /// まだCommitされていない書き込みの対象となるキーの数を返す
+    ///
+    /// 作成・更新されたキーと削除されたキーの両方を数える。同じキーへの複数の書き込みは1件と数える。
+    /// `pending_writes`と同じ値であり、O(1)で求まる。
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.writeset.len()
+    }
+
+    /// まだCommitされていない書き込みが存在するかどうかを返す
+    pub fn is_dirty(&self) -> bool {
+        !self.writeset.is_empty()
+    }
+
+    /// まだCommitされていない削除が存在するかどうかを返す
+    pub fn has_uncommitted_deletes(&self) -> bool {
+        self.writeset.has_deletes()
+    }
+
+    /// Commit時に反映される変更の一覧を返す
+    ///
+    /// トランザクション内で作成したのちに削除したキーのように、コミット済みの内容を変更しない
+    /// 書き込みは含まれない。
+    pub fn diff(&self) -> Changeset<K, V> {
+        let changes: Vec<Change<K, V>> = self
+            .writeset
+            .iter()
+            .filter_map(|(key, op)| {
+                let exists = self.database.data.contains_key(key);
+                match op {
+                    Option::Some(value) if exists => {
+                        Option::Some(Change::Update(key.clone(), value.clone()))
+                    }
+                    Option::Some(value) => Option::Some(Change::Create(key.clone(), value.clone())),
+                    Option::None if exists => Option::Some(Change::Delete(key.clone())),
+                    Option::None => Option::None,
+                }
+            })
+            .collect();
+        Changeset::from(changes)
+    }
+
+    /// 現在の状態をセーブポイントとして記録する
+    ///
+    /// `rollback_to_savepoint`により、トランザクションの状態をこの時点まで戻すことができる。
+    pub fn savepoint(&mut self, name: &str) -> Result<SavepointId, DatabaseError> {
+        let id = SavepointId(self.next_savepoint);
+        {
+            let log: LogRecord<K, V> = LogRecord::Savepoint {
+                id: id.0,
+                name: name.to_string(),
+            };
+            self.write_log(&log, false)?;
+        }
+        self.next_savepoint += 1;
+        self.savepoints.push(Savepoint {
+            id,
+            name: name.to_string(),
+            writeset: self.writeset.clone(),
+            dirty: self.dirty.clone(),
+            ttl: self.ttl.clone(),
+            deleted: self.deleted.clone(),
+        });
+        Result::Ok(id)
+    }
+
+    /// トランザクションの状態をセーブポイントの作成時点まで戻す
+    ///
+    /// 以降に作成されたセーブポイントは破棄されるが、指定したセーブポイントは残る。
+    pub fn rollback_to_savepoint(&mut self, id: SavepointId) -> Result<(), DatabaseError> {
+        let index = self
+            .savepoints
+            .iter()
+            .position(|savepoint| savepoint.id == id)
+            .ok_or(DatabaseError::SavepointNotFoundError)?;
+        {
+            let log: LogRecord<K, V> = LogRecord::RollbackToSavepoint { id: id.0 };
+            self.write_log(&log, false)?;
+        }
+        self.savepoints.truncate(index + 1);
+        let savepoint = &self.savepoints[index];
+        self.writeset = savepoint.writeset.clone();
+        self.dirty = savepoint.dirty.clone();
+        self.ttl = savepoint.ttl.clone();
+        self.deleted = savepoint.deleted.clone();
+        Result::Ok(())
+    }
+
+    /// keyに対する操作を表すエントリを返す
+    ///
+    /// エントリの取得自体はログに書き込まない。値を参照時に復元する場合はこの時点でコミット済みの値を
+    /// 復元し、復元できなければ`DatabaseError::JSONError`を返す。
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, V>, DatabaseError> {
+        if !self.writeset.contains_key(&key) {
+            self.database.live_value(&key)?;
+        }
+        Result::Ok(Entry::new(self, key))
+    }
+
+    /// 指定された範囲のキーバリューペアをまとめて削除し、削除した数を返す
+    ///
+    /// 期限切れのコミット済みのキーも削除するが、削除した数には含まない(`drain_range`の戻り値の数と一致する)。
+    /// キーごとのDeleteレコードではなく、範囲を表すDeleteRangeレコードを1つだけログに書き込む。
+    /// Redo時はその時点のデータから範囲内のキーを改めて探して削除する。トランザクションは直列に
+    /// 実行されるため、通常は元のトランザクションが削除したキーの集合と一致する。
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となり、ログには何も書き込まない。
+    pub fn delete_range(&mut self, start: Bound<K>, end: Bound<K>) -> Result<usize, DatabaseError> {
+        self.database.data.ensure_sorted("delete_range")?;
+        if !is_valid_range(&start, &end) {
+            return Result::Ok(0);
+        }
+        let keys: Vec<K> = MergeIter::new(
+            self.database.data.range(start.clone(), end.clone())?,
+            self.writeset.range((start.clone(), end.clone())),
+            false,
+        )
+        .map(|(k, _)| k.clone())
+        .collect();
+        {
+            let log: LogRecord<K, V> = LogRecord::DeleteRange { start, end };
+            self.write_log(&log, false)?;
+        }
+        let mut count = 0;
+        for key in &keys {
+            if self.is_live(key) {
+                count += 1;
+            }
+            self.dirty.remove(key);
+            self.ttl.remove(key);
+            self.deleted.insert(key.clone());
+            self.writeset.delete(key.clone());
+        }
+        Result::Ok(count)
+    }
+
+    /// 指定された範囲のキーバリューペアをまとめて削除し、削除したペアをキーの昇順に返す
+    ///
+    /// `delete_range`と同様に、DeleteRangeレコードを1つだけログに書き込む。期限切れのコミット済みの
+    /// キーは削除するが、戻り値には含まない。
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となり、ログには何も書き込まない。
+    pub fn drain_range(
+        &mut self,
+        start: Bound<K>,
+        end: Bound<K>,
+    ) -> Result<Vec<(K, V)>, DatabaseError> {
+        self.database.data.ensure_sorted("drain_range")?;
+        if !is_valid_range(&start, &end) {
+            return Result::Ok(Vec::new());
+        }
+        let pairs: Vec<(K, V)> = MergeIter::new(
+            self.database.data.range(start.clone(), end.clone())?,
+            self.writeset.range((start.clone(), end.clone())),
+            false,
+        )
+        .filter(|(key, _)| self.is_live(key))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+        self.delete_range(start, end)?;
+        Result::Ok(pairs)
+    }
+
+    /// すべてのキーバリューペアを削除し、削除した数を返す
+    ///
+    /// キーごとのDeleteレコードではなく、Truncateレコードを1つだけログに書き込む。
+    /// Redo時はその時点のデータをすべて削除する。
+    pub fn truncate(&mut self) -> Result<usize, DatabaseError> {
+        // コミット済みのキーの順序に依存しないよう、書き込みセットを優先して重複を除く
+        let mut keys: Vec<K> = self
+            .database
+            .data
+            .keys()
+            .filter(|k| !self.writeset.contains_key(*k))
+            .cloned()
+            .collect();
+        keys.extend(
+            self.writeset
+                .iter()
+                .filter(|(_, op)| op.is_some())
+                .map(|(k, _)| k.clone()),
+        );
+        {
+            let log: LogRecord<K, V> = LogRecord::Truncate;
+            self.write_log(&log, false)?;
+        }
+        for key in &keys {
+            self.dirty.remove(key);
+            self.ttl.remove(key);
+            self.deleted.insert(key.clone());
+            self.writeset.delete(key.clone());
+        }
+        Result::Ok(keys.len())
+    }
+
+    /// Commitする(トランザクションを反映する)
+    ///
+    /// エントリを通じて変更された値は、Commitレコードの前にUpdateレコードとして書き込まれる。
+    /// Commitの時点で期限切れのキーに値を書き込んだ場合、そのキーは有効期限を持たないキーとなり、
+    /// Commitレコードの前にClearExpiryレコードが書き込まれる。
+    ///
+    /// グループコミットが有効な場合、変更をデータベースに反映してアクセスを手放した後にfsyncの完了を待つ。
+    /// そのため、fsyncの完了前に他のトランザクションから変更が見えることがある。
+    /// fsyncに失敗した場合は`DatabaseError::SyncFailed`を返し、以降のログへの書き込みと
+    /// チェックポイント(Drop時のものを含む)はすべて失敗する。開き直すと、ログに永続化された
+    /// 内容のみが復元される。
+    ///
+    /// 反映した変更の一覧(Commit直前の`diff`と同じもの)を返す。
+    /// `read_silent`・`contains_key`・`get_many`により読み取ったキーが、読み取った後に他の
+    /// トランザクションにより変更されていた場合は、Commitせずに`DatabaseError::ConflictError`を返す。
+    /// ただし、トランザクションはデータベースへの排他的なアクセスを保持するため、現在は他の
+    /// トランザクションのCommitが割り込むことはなく、このエラーは返されない。
+    #[must_use = "commit errors must be handled"]
+    pub fn commit(mut self) -> Result<Changeset<K, V>, DatabaseError> {
+        let revived = self.write_pending_logs()?;
+        let changes = self.commit_with(revived, true)?;
+        Result::Ok(Changeset::from(changes.unwrap_or_default()))
+    }
+
+    /// 反映した変更の一覧を返さずにCommitする
+    ///
+    /// 変更の一覧を作成するための複製を行わない点を除き、`commit`と同じ。
+    pub fn commit_silent(mut self) -> Result<(), DatabaseError> {
+        let revived = self.write_pending_logs()?;
+        self.commit_with(revived, false).map(|_| ())
+    }
+
+    /// 2相コミットの第1相として、Commitの準備を行う
+    ///
+    /// Commitレコードの前に書き込まれるレコードとPrepareレコードを書き込み、fsyncを行う。
+    /// 返された`PreparedTransaction`の`commit`・`rollback`により結果を確定する。
+    /// いずれも行われないままクラッシュした場合、クラッシュリカバリではAbort扱いとなる。
+    pub fn prepare(mut self) -> Result<PreparedTransaction<'tx, K, V, D>, DatabaseError> {
+        let revived = self.write_pending_logs()?;
+        let log: LogRecord<K, V> = LogRecord::Prepare;
+        self.write_log(&log, true)?;
+        Result::Ok(PreparedTransaction {
+            transaction: self,
+            revived,
+        })
+    }
+
+    /// Commitレコードの前に書き込まれるUpdateレコードとClearExpiryレコードを書き込む
+    ///
+    /// 期限切れのキー、またはこのトランザクションで削除したキーを有効期限なしで書き込んだ場合に、
+    /// コミット済みの有効期限を解除する。有効期限を解除されるキーを返す。読み取ったキーが他のトランザクションにより変更されていた場合は
+    /// 何も書き込まずに`DatabaseError::ConflictError`を返す。
+    fn write_pending_logs(&mut self) -> Result<Vec<K>, DatabaseError> {
+        self.check_conflicts()?;
+        for key in std::mem::take(&mut self.dirty) {
+            if let Option::Some(Option::Some(value)) = self.writeset.get(&key) {
+                let log = LogRecord::Update {
+                    key: key.clone(),
+                    value: value.clone(),
+                };
+                self.write_log(&log, false)?;
+            }
+        }
+        let revived: Vec<K> = self
+            .writeset
+            .iter()
+            .filter(|(key, op)| {
+                op.is_some()
+                    && !self.ttl.contains_key(*key)
+                    && (self.database.is_expired(key)
+                        || (self.deleted.contains(*key) && self.database.expiry.contains_key(*key)))
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &revived {
+            let log: LogRecord<K, V> = LogRecord::ClearExpiry { key: key.clone() };
+            self.write_log(&log, false)?;
+        }
+        Result::Ok(revived)
+    }
+
+    /// Commitレコードを書き込み、トランザクションを反映する
+    ///
+    /// collectを設定した場合、または購読者が存在する場合は、反映した変更の一覧を返す。
+    fn commit_with(
+        mut self,
+        revived: Vec<K>,
+        collect: bool,
+    ) -> Result<Option<Vec<Change<K, V>>>, DatabaseError> {
+        // スナップショットのための変更前の値は、値を復元できない場合にCommitレコードを書き込まずに
+        // 失敗するよう、先に求めておく
+        let overlay = self
+            .writeset
+            .keys()
+            .map(|key| Result::Ok((key.clone(), self.database.data.get(key)?.cloned())))
+            .collect::<Result<_, DatabaseError>>()?;
+        let log: LogRecord<K, V> = LogRecord::Commit;
+        let sync = self.database.config.sync_on_commit;
+        let group_commit = sync && self.database.group_commit.is_some();
+        let written = self.writeset.len();
+        self.write_log_with(&log, sync && !group_commit, || {
+            format!("writing the commit record for {} written keys", written)
+        })?;
+        let pending = if group_commit {
+            self.database.request_group_sync()?
+        } else {
+            Option::None
+        };
+        let changes: Option<Vec<Change<K, V>>> = if collect || !self.database.subscribers.is_empty()
+        {
+            Option::Some(self.diff().into_iter().collect())
+        } else {
+            Option::None
+        };
+        let version = self.database.version();
+        self.database.previous = Option::Some((version, overlay));
+        for key in revived {
+            self.database.expiry.remove(&key);
+        }
+        for (key, expiry_secs) in std::mem::take(&mut self.ttl) {
+            self.database.expiry.insert(key, expiry_secs);
+        }
+        for (key, op) in std::mem::take(&mut self.writeset) {
+            match op {
+                Option::None => {
+                    self.database.versions.remove(&key);
+                    self.database.expiry.remove(&key);
+                    self.database.data.remove(&key);
+                }
+                Option::Some(v) => {
+                    self.database.versions.insert(key.clone(), version + 1);
+                    self.database.data.insert(key, v);
+                }
+            }
+        }
+        self.database.global_version.fetch_add(1, Ordering::Relaxed);
+        if let Option::Some(changes) = &changes {
+            if !self.database.subscribers.is_empty() {
+                self.database
+                    .subscribers
+                    .publish(DatabaseEvent::Committed(changes.clone()));
+            }
+        }
+        self.finished = true; // Prevent abort caused by Drop
+        debug_event!(parent: &self.span, outcome = "commit");
+        self.database.stats.record_commit();
+        self.database
+            .committed_transactions
+            .fetch_add(1, Ordering::Relaxed);
+        self.database
+            .stats
+            .set_record_count(self.database.data.len());
+        let result = self.database.auto_checkpoint();
+        drop(self);
+        if let Option::Some(pending) = pending {
+            pending.wait()?;
+        }
+        result.map(|()| changes)
+    }
+
+    /// Abortする(トランザクションを破棄する)
+    #[must_use = "abort errors must be handled"]
+    pub fn abort(self) -> Result<(), DatabaseError> {
+        // Drop時に自動でAbortされる
+        Result::Ok(())
+    }
+}
+
+impl<'tx, K, V, D> EntryTarget<K, V> for Transaction<'tx, K, V, D>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: DerefMut<Target = Database<K, V>>,
+{
+    /// コミット済みの値は`Transaction::entry`により復元済みのため、復元に失敗することはない
+    fn value(&self, key: &K) -> Option<&V> {
+        match self.writeset.get(key) {
+            Option::None => self.database.live_value(key).ok().flatten(),
+            Option::Some(v) => v,
+        }
+    }
+
+    fn value_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.writeset.contains_key(key) {
+            let value = self.database.live_value(key).ok().flatten()?.clone();
+            self.writeset.insert(key.clone(), value);
+        }
+        let value = self.writeset.get_mut(key)?;
+        self.dirty.insert(key.clone());
+        Option::Some(value)
+    }
+
+    fn write_entry_log(&mut self, log: &LogRecord<K, V>) -> Result<(), DatabaseError> {
+        self.write_log(log, false)
+    }
+
+    fn set_value(&mut self, key: K, value: Option<V>) {
+        self.dirty.remove(&key);
+        self.writeset.set(key, value);
+    }
+}
+
+impl<'tx, K, V, D> Drop for Transaction<'tx, K, V, D>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: DerefMut<Target = Database<K, V>>,
+{
+    /// 明示的にCommitされないままDropした場合、Abort扱いとなる
+    fn drop(&mut self) {
+        if !self.finished {
+            self.write_abort();
+        }
+    }
+}
+
+impl<'tx, K, V, D> Debug for Transaction<'tx, K, V, D>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: DerefMut<Target = Database<K, V>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("writes", &self.writeset.len())
+            .field("has_deletes", &self.writeset.has_deletes())
+            .field("savepoints", &self.savepoints.len())
+            .field("start_lsn", &self.start_lsn)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'tx, K, V, D> PreparedTransaction<'tx, K, V, D>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: DerefMut<Target = Database<K, V>>,
+{
+    /// Commitレコードを書き込み、トランザクションを反映する
+    pub fn commit(self) -> Result<(), DatabaseError> {
+        self.transaction
+            .commit_with(self.revived, false)
+            .map(|_| ())
+    }
+
+    /// Abortレコードを書き込み、トランザクションを破棄する
+    pub fn rollback(self) -> Result<(), DatabaseError> {
+        // Drop時に自動でAbortされる
+        Result::Ok(())
+    }
+}
+
+impl<'tx, K, V, D> ReadTransaction<'tx, K, V, D>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+    D: Deref<Target = Database<K, V>>,
+{
+    pub(crate) fn new(database: D) -> Self {
+        let snapshot_version = database.version();
+        ReadTransaction {
+            database,
+            snapshot_version,
+            _marker: PhantomData,
+        }
+    }
+
+    /// トランザクションの開始時点のバージョンを返す
+    pub fn snapshot_version(&self) -> u64 {
+        self.snapshot_version
+    }
+
+    /// 書き込みの対象となるキーの数を返す(書き込みを行わないため常に0)
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        0
+    }
+
+    /// 書き込みが存在するかどうかを返す(常に`false`)
+    pub fn is_dirty(&self) -> bool {
+        false
+    }
+
+    /// 削除が存在するかどうかを返す(常に`false`)
+    pub fn has_uncommitted_deletes(&self) -> bool {
+        false
+    }
+
+    /// keyに対応する値を読み取る(ログには書き込まない)
+    ///
+    /// トランザクションの開始時点でコミットされていた内容を返す。
+    pub fn read(&self, key: K) -> Result<V, DatabaseError> {
+        self.database
+            .read_at(&key, self.snapshot_version)?
+            .cloned()
+            .ok_or(DatabaseError::KeyNotFoundError)
+    }
+
+    /// 指定された範囲のキーバリューペアをキーの昇順に読み取る(ログには書き込まない)
+    ///
+    /// トランザクションの開始時点でコミットされていた内容を返す。`read`と同様、期限切れのキーは含まない。
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となる。
+    pub fn scan_range(
+        &self,
+        start: Bound<K>,
+        end: Bound<K>,
+    ) -> impl Iterator<Item = Result<(K, V), DatabaseError>> + '_ {
+        let overlay = self
+            .database
+            .data
+            .ensure_sorted("scan_range")
+            .and_then(|()| self.database.overlay_at(self.snapshot_version));
+        let data = match &overlay {
+            Result::Ok(_) if is_valid_range(&start, &end) => self
+                .database
+                .data
+                .range(start.clone(), end.clone())
+                .map(Option::Some),
+            _ => Result::Ok(Option::None),
+        };
+        let (data, error) = split_error(data);
+        let range: Option<Box<dyn Iterator<Item = (&K, &V)>>> = match (&overlay, data) {
+            (Result::Ok(Option::None), Option::Some(data)) => Option::Some(Box::new(data)),
+            (Result::Ok(Option::Some(overlay)), Option::Some(data)) => Option::Some(Box::new(
+                MergeIter::new(data, overlay.range((start, end)), false),
+            )),
+            _ => Option::None,
+        };
+        overlay.err().or(error).map(Result::Err).into_iter().chain(
+            range
+                .into_iter()
+                .flatten()
+                .filter(move |(k, _)| !self.database.is_expired(k))
+                .map(|(k, v)| Result::Ok((k.clone(), v.clone()))),
+        )
+    }
+
+    /// Commitする(読み取り専用のため何も行わない)
+    pub fn commit(self) -> Result<(), DatabaseError> {
+        Result::Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::DatabaseConfig;
+    use crate::database::Database;
+    use crate::error::DatabaseError;
+    use crate::group_commit::SyncTarget;
+    use std::sync::Arc;
+
+    #[test]
+    fn conflict_detection() {
+        let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+        db.extend_transaction(vec![(1, 10), (2, 20), (3, 30)])
+            .unwrap();
+
+        // 同じキーを読み取って変更するトランザクションも、順に実行される限り競合しない
+        for _ in 0..2 {
+            let mut tx = db.begin_transaction().unwrap();
+            let value = tx.read_silent(1).unwrap();
+            tx.update(1, value + 1).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(db.key_version(&1), db.version());
+
+        let mut tx = db.begin_transaction().unwrap();
+        let value = tx.read_silent(1).unwrap();
+        assert!(tx.contains_key(&2).unwrap());
+        tx.get_many(&[3, 4]).unwrap();
+        tx.update(1, value * 2).unwrap();
+        // トランザクションは排他的なアクセスを保持するため、公開されたAPIでは他のトランザクションの
+        // Commitを割り込ませられない。読み取った後に、1と4を変更するCommitがあったものとして扱う
+        let version = tx.database.version() + 1;
+        tx.database.versions.insert(1, version);
+        tx.database.versions.insert(4, version);
+        match tx.commit() {
+            Result::Err(error) => {
+                assert_eq!(error.conflicting_keys::<i32>(), Option::Some(vec![1, 4]));
+                assert!(matches!(error, DatabaseError::ConflictError { .. }));
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 12);
+        tx.commit().unwrap();
+
+        // 読み取っていないキーの変更は競合とならない
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(2, 21).unwrap();
+        tx.database.versions.insert(2, 100);
+        tx.commit().unwrap();
+        assert_eq!(db.key_version(&2), db.version());
+
+        // 削除されたキーのバージョンは保持しない
+        let mut tx = db.begin_transaction().unwrap();
+        tx.delete(2).unwrap();
+        tx.commit().unwrap();
+        assert!(!db.versions.contains_key(&2));
+        assert_eq!(db.key_version(&2), 0);
+        db.truncate().unwrap();
+        assert!(db.versions.is_empty());
+        db.extend_transaction(vec![(1, 10)]).unwrap();
+        db.clear().unwrap();
+        assert!(db.versions.is_empty());
+    }
+
+    #[test]
+    fn group_commit_sync_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig::builder()
+            .data_dir(dir.path())
+            .enable_group_commit(true)
+            .build();
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![(1, 10)]).unwrap();
+
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(2, 20).unwrap();
+        // 他のトランザクションのCommitによるfsyncが失敗したものとして扱う
+        let target: SyncTarget = Arc::new(|| Result::Err(std::io::Error::other("EIO")));
+        let manager = tx.database.group_commit.as_ref().unwrap();
+        assert!(manager.request(target).wait().is_err());
+        assert!(matches!(
+            tx.commit(),
+            Result::Err(DatabaseError::SyncFailed { .. })
+        ));
+        assert!(matches!(
+            db.begin_transaction().map(|_| ()),
+            Result::Err(DatabaseError::SyncFailed { .. })
+        ));
+        assert!(matches!(
+            db.compact_wal(),
+            Result::Err(DatabaseError::SyncFailed { .. })
+        ));
+        let pairs: Vec<(i32, i32)> = db.scan_all().unwrap().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![(1, 10)]);
+        drop(db);
+
+        // Drop時のチェックポイントは行われず、ログに永続化された内容から復元される
+        let db: Database<i32, i32> = Database::new(config).unwrap();
+        let pairs: Vec<(i32, i32)> = db.scan_all().unwrap().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![(1, 10)]);
     }
 }
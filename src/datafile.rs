@@ -0,0 +1,619 @@
+use crate::error::DatabaseError;
+use crate::serialization::{CompressionLevel, DataFormat, JsonBackend, SerializationBackend};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// データファイルの先頭に置かれるチェックサムのフィールド
+///
+/// データファイルは`{"__checksum__":"<hex>","version":2,"header":...,"data":...}`の形式で書き出され、
+/// チェックサムは`{"version":2,"header":...,"data":...}`(チェックサムを除いた内容)のSHA256である。
+const CHECKSUM_PREFIX: &str = "{\"__checksum__\":\"";
+
+/// JSON以外の形式のデータファイルの先頭に置かれる識別子
+///
+/// JSON以外の形式のデータファイルは
+/// `[MAGIC (5 bytes)][format (1 byte)][version (4 bytes)][SHA256 (32 bytes)][body]`の形式で書き出され、
+/// チェックサムは本体のSHA256である。
+const MAGIC: &[u8] = b"MKDB\x00";
+
+/// 圧縮されたデータファイルの先頭に置かれる識別子
+///
+/// 圧縮されたデータファイルは`[COMPRESSED_MAGIC (5 bytes)][zstdで圧縮した内容]`の形式で書き出され、
+/// 展開した内容は圧縮されていないデータファイルと同じ形式である。
+const COMPRESSED_MAGIC: &[u8] = b"MKDB\x01";
+
+/// JSON以外の形式のデータファイルのヘッダのバイト数
+const BINARY_HEADER_LEN: usize = 5 + 1 + 4 + 32;
+
+/// データファイルの形式のバージョン
+///
+/// データファイルの形式を変更する場合はこの値を増やし、`Database::migrate_data_file`に
+/// 以前のバージョンからの移行を追加する。
+///
+/// - 1: バイナリ形式の本体は`(BinaryHeaderV1, data, expiry)`
+/// - 2: バイナリ形式の本体は`(BinaryHeader, data, expiry)`となり、ヘッダがトランザクションの数と
+///   メタデータを持つ。JSON形式は1と同じ(ヘッダの項目が増えたのみ)
+pub const DATA_FORMAT_VERSION: u32 = 2;
+
+/// バージョンを持たないデータファイルの形式のバージョンを返す
+///
+/// バージョンが導入される前のデータファイルは、バージョン1と同じ形式である。
+fn legacy_version() -> u32 {
+    1
+}
+
+/// データファイルのバージョンのみを読み取るための型
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default = "legacy_version")]
+    version: u32,
+}
+
+/// データファイルのヘッダを表す
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DataFileHeader {
+    /// チェックポイントの作成時点でログに書き込まれていた最後のレコードのLSN
+    ///
+    /// これ以下のLSNを持つレコードの内容はデータファイルに反映済みである。
+    #[serde(default)]
+    pub checkpoint_lsn: u64,
+    /// チェックポイントの作成時にログへ書き込んだCheckpointMarkerレコードの位置(bytes)
+    ///
+    /// ログの破棄に失敗していた場合、Redoはこの位置から読み取りを開始する。
+    #[serde(default)]
+    pub wal_offset: u64,
+    /// データベースの作成以降にCommitされたトランザクションの数
+    #[serde(default)]
+    pub committed_transactions: u64,
+    /// データベースの作成以降にAbortされたトランザクションの数
+    #[serde(default)]
+    pub aborted_transactions: u64,
+    /// `Database::set_metadata`により設定された、データベース単位のメタデータ
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// バイナリ形式のデータファイルに書き込むヘッダ
+///
+/// `DataFileHeader`と同じ項目を持つが、自己記述的でない形式でも読み込めるよう、空のメタデータも
+/// 省略せずに書き込む。
+#[derive(Serialize, Deserialize)]
+struct BinaryHeader {
+    checkpoint_lsn: u64,
+    wal_offset: u64,
+    committed_transactions: u64,
+    aborted_transactions: u64,
+    metadata: BTreeMap<String, String>,
+}
+
+/// バージョン1のバイナリ形式のデータファイルのヘッダ
+#[derive(Deserialize)]
+struct BinaryHeaderV1 {
+    checkpoint_lsn: u64,
+    wal_offset: u64,
+}
+
+impl DataFileHeader {
+    fn to_binary(&self) -> BinaryHeader {
+        BinaryHeader {
+            checkpoint_lsn: self.checkpoint_lsn,
+            wal_offset: self.wal_offset,
+            committed_transactions: self.committed_transactions,
+            aborted_transactions: self.aborted_transactions,
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    fn from_binary(header: BinaryHeader) -> Self {
+        DataFileHeader {
+            checkpoint_lsn: header.checkpoint_lsn,
+            wal_offset: header.wal_offset,
+            committed_transactions: header.committed_transactions,
+            aborted_transactions: header.aborted_transactions,
+            metadata: header.metadata,
+        }
+    }
+
+    /// トランザクションの数を0として、バージョン1のヘッダから復元する
+    fn from_binary_v1(header: BinaryHeaderV1) -> Self {
+        DataFileHeader {
+            checkpoint_lsn: header.checkpoint_lsn,
+            wal_offset: header.wal_offset,
+            ..DataFileHeader::default()
+        }
+    }
+}
+
+/// データファイルに書き込む内容を表す
+#[derive(Serialize)]
+struct DataFileRef<'a, K: 'a, D: 'a> {
+    version: u32,
+    header: &'a DataFileHeader,
+    data: &'a D,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    expiry: &'a BTreeMap<K, u64>,
+}
+
+/// データファイルから読み込んだ内容を表す
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DataFile<K: Ord, V> {
+    /// データファイルの形式のバージョン
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+    pub header: DataFileHeader,
+    pub data: BTreeMap<K, V>,
+    /// 有効期限を持つキーと、その有効期限(UNIX時間、秒)
+    #[serde(default = "BTreeMap::new")]
+    pub expiry: BTreeMap<K, u64>,
+}
+
+/// ヘッダ・データ・有効期限をチェックサムと共に、formatの形式でデータファイルの内容として書き出す
+///
+/// dataはキーからバリューへのマップとして書き出されるものとする。
+pub(crate) fn encode<K, D>(
+    format: DataFormat,
+    header: &DataFileHeader,
+    data: &D,
+    expiry: &BTreeMap<K, u64>,
+) -> Result<Vec<u8>, DatabaseError>
+where
+    K: Serialize + Ord,
+    D: Serialize,
+{
+    if format == DataFormat::Json {
+        let body = String::from_utf8(JsonBackend::serialize(&DataFileRef {
+            version: DATA_FORMAT_VERSION,
+            header,
+            data,
+            expiry,
+        })?)?;
+        let content = format!("{}{}\",{}", CHECKSUM_PREFIX, checksum(&body), &body[1..]);
+        return Result::Ok(content.into_bytes());
+    }
+    let body = format.serialize(&(header.to_binary(), data, expiry))?;
+    let mut content = Vec::with_capacity(BINARY_HEADER_LEN + body.len());
+    content.extend_from_slice(MAGIC);
+    content.push(format as u8);
+    content.extend_from_slice(&DATA_FORMAT_VERSION.to_le_bytes());
+    content.extend_from_slice(&binary_checksum(&body));
+    content.extend_from_slice(&body);
+    Result::Ok(content)
+}
+
+/// データファイルの内容をlevelで圧縮する(`CompressionLevel::None`の場合はそのまま返す)
+pub(crate) fn compress(
+    content: Vec<u8>,
+    level: CompressionLevel,
+) -> Result<Vec<u8>, DatabaseError> {
+    if level == CompressionLevel::None {
+        return Result::Ok(content);
+    }
+    let mut compressed = COMPRESSED_MAGIC.to_vec();
+    compressed.extend(level.compress(&content)?);
+    Result::Ok(compressed)
+}
+
+/// 圧縮されたデータファイルの内容を展開する(圧縮されていない場合はそのまま返す)
+pub(crate) fn decompress(content: Vec<u8>) -> Result<Vec<u8>, DatabaseError> {
+    if !is_compressed(&content) {
+        return Result::Ok(content);
+    }
+    CompressionLevel::decompress(&content[COMPRESSED_MAGIC.len()..])
+}
+
+/// データファイルの形式を返す
+///
+/// 識別子を持たないデータファイルはJSONとみなす。
+pub(crate) fn format(content: &[u8]) -> Result<DataFormat, DatabaseError> {
+    match split_binary(content)? {
+        Option::Some(binary) => Result::Ok(binary.format),
+        Option::None => Result::Ok(DataFormat::Json),
+    }
+}
+
+/// データファイルの内容がチェックサムと一致するかを検証する
+///
+/// チェックサムより先にバージョンを確認し、`DATA_FORMAT_VERSION`と異なる場合は
+/// `DatabaseError::UnsupportedDataFormatVersion`を返す。
+/// チェックサムを持たない旧形式のデータファイルはバージョン1であるため、移行が必要となる。
+pub(crate) fn verify(content: &[u8]) -> Result<(), DatabaseError> {
+    verify_version(content, DATA_FORMAT_VERSION)
+}
+
+/// `verify`と同様に、データファイルのバージョンがexpectedであり、内容がチェックサムと一致するかを検証する
+///
+/// チェックサムを持たない旧形式のデータファイルは検証せずに受け入れる。
+fn verify_version(content: &[u8], expected: u32) -> Result<(), DatabaseError> {
+    let found = version(content)?;
+    if found != expected {
+        return Result::Err(DatabaseError::UnsupportedDataFormatVersion { found, expected });
+    }
+    if let Option::Some(binary) = split_binary(content)? {
+        let actual = binary_checksum(binary.body);
+        if binary.checksum != &actual[..] {
+            return Result::Err(DatabaseError::DataFileCorrupted {
+                expected: hex(binary.checksum),
+                actual: hex(&actual),
+            });
+        }
+        return Result::Ok(());
+    }
+    if let Option::Some((expected, body)) = split_checksum(std::str::from_utf8(content)?) {
+        let actual = checksum(&body);
+        if expected != actual {
+            return Result::Err(DatabaseError::DataFileCorrupted {
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+    }
+    Result::Ok(())
+}
+
+/// データファイルの内容を復元する
+///
+/// バイナリ形式の本体はバージョンに応じた形式で読み込む(バージョン1と`DATA_FORMAT_VERSION`以外は
+/// `DatabaseError::UnsupportedDataFormatVersion`となる)。
+/// ヘッダを持たない旧形式のデータファイルは、既定のヘッダを持つものとして読み込む。
+pub(crate) fn decode<K, V>(content: &[u8]) -> Result<DataFile<K, V>, DatabaseError>
+where
+    K: DeserializeOwned + Ord,
+    V: DeserializeOwned,
+{
+    if let Option::Some(binary) = split_binary(content)? {
+        let (header, data, expiry) = match binary.version {
+            DATA_FORMAT_VERSION => {
+                let (header, data, expiry) = binary.format.deserialize(binary.body)?;
+                (DataFileHeader::from_binary(header), data, expiry)
+            }
+            1 => {
+                let (header, data, expiry) = binary.format.deserialize(binary.body)?;
+                (DataFileHeader::from_binary_v1(header), data, expiry)
+            }
+            found => {
+                return Result::Err(DatabaseError::UnsupportedDataFormatVersion {
+                    found,
+                    expected: DATA_FORMAT_VERSION,
+                })
+            }
+        };
+        return Result::Ok(DataFile {
+            version: binary.version,
+            header,
+            data,
+            expiry,
+        });
+    }
+    let content = std::str::from_utf8(content)?;
+    let content = match split_checksum(content) {
+        Option::Some((_, body)) => body,
+        Option::None => content.to_string(),
+    };
+    let content = content.as_str();
+    match serde_json::from_str::<DataFile<K, V>>(content) {
+        Result::Ok(file) => Result::Ok(file),
+        Result::Err(e) => match serde_json::from_str::<BTreeMap<K, V>>(content) {
+            Result::Ok(data) => Result::Ok(DataFile {
+                version: legacy_version(),
+                header: DataFileHeader::default(),
+                data,
+                expiry: BTreeMap::new(),
+            }),
+            Result::Err(_) => Result::Err(e.into()),
+        },
+    }
+}
+
+/// バージョンfrom_versionのデータファイルの内容を検証し、同じ形式の`DATA_FORMAT_VERSION`の内容として書き直す
+pub(crate) fn migrate<K, V>(content: &[u8], from_version: u32) -> Result<Vec<u8>, DatabaseError>
+where
+    K: Serialize + DeserializeOwned + Ord,
+    V: Serialize + DeserializeOwned,
+{
+    verify_version(content, from_version)?;
+    let file: DataFile<K, V> = decode(content)?;
+    encode(format(content)?, &file.header, &file.data, &file.expiry)
+}
+
+/// データファイルの内容が圧縮されているかどうかを返す
+pub(crate) fn is_compressed(content: &[u8]) -> bool {
+    content.starts_with(COMPRESSED_MAGIC)
+}
+
+/// データファイルの形式のバージョンを返す
+///
+/// チェックサムを持たない旧形式のデータファイル(バージョンを持ちえない)はバージョン1とみなす。
+pub(crate) fn version(content: &[u8]) -> Result<u32, DatabaseError> {
+    if let Option::Some(binary) = split_binary(content)? {
+        return Result::Ok(binary.version);
+    }
+    if !content.starts_with(CHECKSUM_PREFIX.as_bytes()) {
+        return Result::Ok(legacy_version());
+    }
+    Result::Ok(serde_json::from_slice::<VersionProbe>(content)?.version)
+}
+
+/// バイナリ形式のデータファイルのヘッダと本体を表す
+struct BinaryDataFile<'a> {
+    format: DataFormat,
+    version: u32,
+    checksum: &'a [u8],
+    body: &'a [u8],
+}
+
+/// バイナリ形式のデータファイルをヘッダと本体に分割する(識別子を持たない場合はNone)
+fn split_binary(content: &[u8]) -> Result<Option<BinaryDataFile<'_>>, DatabaseError> {
+    if !content.starts_with(MAGIC) {
+        return Result::Ok(Option::None);
+    }
+    if content.len() < BINARY_HEADER_LEN {
+        return Result::Err(DatabaseError::DataFileCorrupted {
+            expected: format!("a header of {} bytes", BINARY_HEADER_LEN),
+            actual: format!("{} bytes", content.len()),
+        });
+    }
+    let tag = content[MAGIC.len()];
+    let format =
+        DataFormat::from_byte(tag).ok_or_else(|| DatabaseError::UnsupportedDataFormat {
+            message: format!("unknown format identifier {}", tag),
+        })?;
+    let version = LittleEndian::read_u32(&content[MAGIC.len() + 1..MAGIC.len() + 5]);
+    Result::Ok(Option::Some(BinaryDataFile {
+        format,
+        version,
+        checksum: &content[MAGIC.len() + 5..BINARY_HEADER_LEN],
+        body: &content[BINARY_HEADER_LEN..],
+    }))
+}
+
+/// データファイルの内容をチェックサムとそれ以外の内容に分割する
+fn split_checksum(content: &str) -> Option<(&str, String)> {
+    let start = CHECKSUM_PREFIX.len();
+    let end = start + 64;
+    if !content.starts_with(CHECKSUM_PREFIX) || content.get(end..end + 2) != Option::Some("\",") {
+        return Option::None;
+    }
+    let expected = content.get(start..end)?;
+    Option::Some((expected, format!("{{{}", &content[end + 2..])))
+}
+
+/// 内容のSHA256を16進数の文字列として返す
+fn checksum(body: &str) -> String {
+    hex(&binary_checksum(body.as_bytes()))
+}
+
+/// 内容のSHA256を返す
+fn binary_checksum(body: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.input(body);
+    hasher.result().to_vec()
+}
+
+/// バイト列を16進数の文字列として返す
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datafile::{
+        binary_checksum, compress, decode, decompress, encode, format, migrate, verify, version,
+        DataFile, DataFileHeader, DATA_FORMAT_VERSION, MAGIC,
+    };
+    use crate::error::DatabaseError;
+    use crate::serialization::{CompressionLevel, DataFormat};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn header_round_trip() {
+        let header = DataFileHeader {
+            checkpoint_lsn: 42,
+            wal_offset: 1024,
+            committed_transactions: 5,
+            aborted_transactions: 2,
+            metadata: BTreeMap::new(),
+        };
+        let mut data = BTreeMap::new();
+        data.insert(1, 10);
+        data.insert(2, 20);
+        let mut expiry = BTreeMap::new();
+        expiry.insert(2, 1_000);
+        let content = encode(DataFormat::Json, &header, &data, &expiry).unwrap();
+        assert_eq!(
+            decode::<i32, i32>(&content).unwrap(),
+            DataFile {
+                version: DATA_FORMAT_VERSION,
+                header,
+                data,
+                expiry
+            }
+        );
+    }
+
+    #[test]
+    fn legacy_data_file() {
+        let file = decode::<i32, i32>(br#"{"1":10,"2":20}"#).unwrap();
+        assert_eq!(file.header, DataFileHeader::default());
+        assert_eq!(file.data.get(&2), Option::Some(&20));
+        assert!(file.expiry.is_empty());
+    }
+
+    #[test]
+    fn checksum() {
+        let mut data = BTreeMap::new();
+        data.insert(1, 10);
+        let content = encode(
+            DataFormat::Json,
+            &DataFileHeader::default(),
+            &data,
+            &BTreeMap::<i32, u64>::new(),
+        )
+        .unwrap();
+        let content = String::from_utf8(content).unwrap();
+        assert!(content.starts_with(r#"{"__checksum__":""#));
+        verify(content.as_bytes()).unwrap();
+
+        let corrupted = content.replace(":10}", ":11}");
+        match verify(corrupted.as_bytes()) {
+            Result::Err(DatabaseError::DataFileCorrupted { expected, actual }) => {
+                assert_ne!(expected, actual)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // チェックサムを持たない旧形式のデータファイルはバージョン1であり、移行が必要となる
+        assert!(matches!(
+            verify(br#"{"header":{"checkpoint_lsn":0},"data":{"1":11}}"#),
+            Result::Err(DatabaseError::UnsupportedDataFormatVersion { found: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn format_version() {
+        let content = encode(
+            DataFormat::Json,
+            &DataFileHeader::default(),
+            &BTreeMap::<i32, i32>::new(),
+            &BTreeMap::<i32, u64>::new(),
+        )
+        .unwrap();
+        assert_eq!(version(&content).unwrap(), DATA_FORMAT_VERSION);
+        // バージョンを持たないデータファイルはバージョン1とみなす
+        assert_eq!(version(br#"{"1":10}"#).unwrap(), 1);
+
+        let future = String::from_utf8(content)
+            .unwrap()
+            .replace(r#""version":2,"#, r#""version":3,"#);
+        match verify(future.as_bytes()) {
+            Result::Err(DatabaseError::UnsupportedDataFormatVersion { found, expected }) => {
+                assert_eq!((found, expected), (3, DATA_FORMAT_VERSION))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn binary_format() {
+        let header = DataFileHeader {
+            checkpoint_lsn: 7,
+            wal_offset: 0,
+            committed_transactions: 3,
+            aborted_transactions: 1,
+            metadata: vec![("schema_version".to_string(), "2".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        let mut data = BTreeMap::new();
+        data.insert("a".to_string(), vec![1, 2, 3]);
+        let mut expiry = BTreeMap::new();
+        expiry.insert("a".to_string(), 1_000);
+        let content = encode(DataFormat::Bincode, &header, &data, &expiry).unwrap();
+        assert!(content.starts_with(b"MKDB\x00"));
+        assert_eq!(format(&content).unwrap(), DataFormat::Bincode);
+        assert_eq!(version(&content).unwrap(), DATA_FORMAT_VERSION);
+        verify(&content).unwrap();
+        assert_eq!(
+            decode::<String, Vec<i32>>(&content).unwrap(),
+            DataFile {
+                version: DATA_FORMAT_VERSION,
+                header,
+                data,
+                expiry
+            }
+        );
+
+        let mut corrupted = content.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        match verify(&corrupted) {
+            Result::Err(DatabaseError::DataFileCorrupted { .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(format(b"{}").unwrap(), DataFormat::Json);
+    }
+
+    #[test]
+    fn compression() {
+        let mut data = BTreeMap::new();
+        for i in 0..1000 {
+            data.insert(i, i % 10);
+        }
+        let content = encode(
+            DataFormat::Json,
+            &DataFileHeader::default(),
+            &data,
+            &BTreeMap::<i32, u64>::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            compress(content.clone(), CompressionLevel::None).unwrap(),
+            content
+        );
+        assert_eq!(decompress(content.clone()).unwrap(), content);
+        let compressed = compress(content.clone(), CompressionLevel::Fast);
+        if cfg!(feature = "zstd") {
+            let compressed = compressed.unwrap();
+            assert!(compressed.starts_with(b"MKDB\x01"));
+            assert!(compressed.len() < content.len());
+            assert_eq!(decompress(compressed).unwrap(), content);
+        } else {
+            assert!(compressed.is_err());
+        }
+    }
+
+    /// バージョン1のバイナリ形式のデータファイルの内容を返す
+    fn binary_v1(data: &BTreeMap<i32, i32>) -> Vec<u8> {
+        let body = bincode::serialize(&((7u64, 0u64), data, BTreeMap::<i32, u64>::new())).unwrap();
+        let mut content = MAGIC.to_vec();
+        content.push(DataFormat::Bincode as u8);
+        content.extend_from_slice(&1u32.to_le_bytes());
+        content.extend_from_slice(&binary_checksum(&body));
+        content.extend_from_slice(&body);
+        content
+    }
+
+    #[test]
+    fn binary_version_1() {
+        // トランザクションの数を持たないバージョン1の本体は、それらを0として読み込む
+        let mut data = BTreeMap::new();
+        data.insert(1, 10);
+        let content = binary_v1(&data);
+        assert!(matches!(
+            verify(&content),
+            Result::Err(DatabaseError::UnsupportedDataFormatVersion { found: 1, .. })
+        ));
+        let file = decode::<i32, i32>(&content).unwrap();
+        assert_eq!(file.version, 1);
+        assert_eq!(file.header.checkpoint_lsn, 7);
+        assert_eq!(file.header.committed_transactions, 0);
+        assert_eq!(file.data, data);
+    }
+
+    #[test]
+    fn migrate_version_1() {
+        let mut data = BTreeMap::new();
+        data.insert(1, 10);
+        let migrated = migrate::<i32, i32>(&binary_v1(&data), 1).unwrap();
+        assert_eq!(format(&migrated).unwrap(), DataFormat::Bincode);
+        assert_eq!(version(&migrated).unwrap(), DATA_FORMAT_VERSION);
+        verify(&migrated).unwrap();
+        let file = decode::<i32, i32>(&migrated).unwrap();
+        assert_eq!(file.header.checkpoint_lsn, 7);
+        assert_eq!(file.data, data);
+
+        // バージョンが一致しない場合や、チェックサムが一致しない場合は移行しない
+        assert!(migrate::<i32, i32>(&migrated, 1).is_err());
+        let mut corrupted = binary_v1(&data);
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            migrate::<i32, i32>(&corrupted, 1),
+            Result::Err(DatabaseError::DataFileCorrupted { .. })
+        ));
+        // バージョンを持たない旧形式のJSONはバージョン1として移行する
+        let migrated = migrate::<i32, i32>(br#"{"1":10}"#, 1).unwrap();
+        assert_eq!(decode::<i32, i32>(&migrated).unwrap().data, data);
+        verify(&migrated).unwrap();
+    }
+}
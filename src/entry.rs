@@ -0,0 +1,194 @@
+use crate::error::DatabaseError;
+use crate::log::LogRecord;
+use std::fmt::Debug;
+
+/// `Entry`から操作されるトランザクションを表す
+pub(crate) trait EntryTarget<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    /// ログに書き込まず、keyに対応する値への参照を返す
+    fn value(&self, key: &K) -> Option<&V>;
+
+    /// ログに書き込まず、keyに対応する値への可変参照を返す
+    ///
+    /// 値は書き込みセットに取り込まれ、Commit時にその時点の値がログに書き込まれる。
+    fn value_mut(&mut self, key: &K) -> Option<&mut V>;
+
+    /// ログレコードを書き込む
+    fn write_entry_log(&mut self, log: &LogRecord<K, V>) -> Result<(), DatabaseError>;
+
+    /// 書き込みセットにkeyに対する操作を記録する(ログへの書き込みは行わない)
+    fn set_value(&mut self, key: K, value: Option<V>);
+}
+
+/// トランザクション上の1つのキーに対する操作を表す
+///
+/// `Transaction::entry`により取得する。
+pub enum Entry<'a, K, V>
+where
+    K: Debug + Clone,
+    V: Debug + Clone,
+{
+    /// キーが存在する場合
+    Occupied(OccupiedEntry<'a, K, V>),
+    /// キーが存在しない場合
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+/// 存在するキーに対する操作を表す
+pub struct OccupiedEntry<'a, K, V>
+where
+    K: Debug + Clone,
+    V: Debug + Clone,
+{
+    tx: &'a mut (dyn EntryTarget<K, V> + 'a),
+    key: K,
+}
+
+/// 存在しないキーに対する操作を表す
+pub struct VacantEntry<'a, K, V>
+where
+    K: Debug + Clone,
+    V: Debug + Clone,
+{
+    tx: &'a mut (dyn EntryTarget<K, V> + 'a),
+    key: K,
+}
+
+impl<'a, K, V> Entry<'a, K, V>
+where
+    K: Debug + Clone,
+    V: Debug + Clone,
+{
+    pub(crate) fn new(tx: &'a mut (dyn EntryTarget<K, V> + 'a), key: K) -> Self {
+        if tx.value(&key).is_some() {
+            Entry::Occupied(OccupiedEntry { tx, key })
+        } else {
+            Entry::Vacant(VacantEntry { tx, key })
+        }
+    }
+
+    /// キーを返す
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// キーが存在しない場合はdefaultを新規設定し、値への可変参照を返す
+    pub fn or_insert(self, default: V) -> Result<&'a mut V, DatabaseError> {
+        match self {
+            Entry::Occupied(entry) => Result::Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// キーが存在しない場合はdefaultの結果を新規設定し、値への可変参照を返す
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> Result<&'a mut V, DatabaseError> {
+        match self {
+            Entry::Occupied(entry) => Result::Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// キーが存在する場合、その値をfにより変更する
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V>
+where
+    K: Debug + Clone,
+    V: Debug + Clone,
+{
+    /// キーを返す
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// 値への参照を返す
+    pub fn get(&self) -> &V {
+        self.tx
+            .value(&self.key)
+            .expect("occupied entry must have a value")
+    }
+
+    /// 値への可変参照を返す
+    ///
+    /// 変更後の値はCommit時にログに書き込まれる。
+    pub fn get_mut(&mut self) -> &mut V {
+        self.tx
+            .value_mut(&self.key)
+            .expect("occupied entry must have a value")
+    }
+
+    /// エントリを消費し、トランザクションの生存期間に紐付いた値への可変参照を返す
+    ///
+    /// 変更後の値はCommit時にログに書き込まれる。
+    pub fn into_mut(self) -> &'a mut V {
+        self.tx
+            .value_mut(&self.key)
+            .expect("occupied entry must have a value")
+    }
+
+    /// 値をvalueとして更新し、更新前の値を返す
+    pub fn insert(&mut self, value: V) -> Result<V, DatabaseError> {
+        let old = self.get().clone();
+        let log = LogRecord::Update {
+            key: self.key.clone(),
+            value: value.clone(),
+        };
+        self.tx.write_entry_log(&log)?;
+        self.tx.set_value(self.key.clone(), Option::Some(value));
+        Result::Ok(old)
+    }
+
+    /// キーバリューペアを削除し、削除前の値を返す
+    pub fn remove(self) -> Result<V, DatabaseError> {
+        let old = self.get().clone();
+        let log = LogRecord::Delete {
+            key: self.key.clone(),
+        };
+        self.tx.write_entry_log(&log)?;
+        self.tx.set_value(self.key, Option::None);
+        Result::Ok(old)
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V>
+where
+    K: Debug + Clone,
+    V: Debug + Clone,
+{
+    /// キーを返す
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// キーに対応する値をvalueとして新規設定し、値への可変参照を返す
+    ///
+    /// 返された参照を通じた変更はCommit時にログに書き込まれる。
+    pub fn insert(self, value: V) -> Result<&'a mut V, DatabaseError> {
+        let log = LogRecord::Create {
+            key: self.key.clone(),
+            value: value.clone(),
+        };
+        self.tx.write_entry_log(&log)?;
+        self.tx.set_value(self.key.clone(), Option::Some(value));
+        Result::Ok(
+            self.tx
+                .value_mut(&self.key)
+                .expect("inserted entry must have a value"),
+        )
+    }
+}
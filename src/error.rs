@@ -1,23 +1,213 @@
+use crate::serialization::DataFormat;
+use serde::de::DeserializeOwned;
 use std::convert::From;
+use thiserror::Error;
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum DatabaseError {
-    #[fail(display = "Transaction log error")]
+    #[error("Transaction log error")]
     TransactionLogError,
-    #[fail(display = "IO Error: {:?}", error)]
-    IOError { error: std::io::Error },
-    #[fail(display = "IO Error: {:?}", error)]
-    PersistError { error: tempfile::PersistError },
-    #[fail(display = "Invalid json format: {:?}", error)]
-    JSONError { error: serde_json::Error },
-    #[fail(display = "Invalid format: {:?}", error)]
-    NumberFormatError { error: std::num::ParseIntError },
-    #[fail(display = "Invalid log format: {:?}", message)]
+    #[error("IO Error: {error:?}")]
+    IOError {
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("IO Error: {error:?}")]
+    PersistError {
+        #[source]
+        error: tempfile::PersistError,
+    },
+    #[error("Invalid json format: {error:?}")]
+    JSONError {
+        #[source]
+        error: serde_json::Error,
+    },
+    #[error("Invalid binary format: {error:?}")]
+    BincodeError {
+        #[source]
+        error: bincode::Error,
+    },
+    #[error("Invalid format: {error:?}")]
+    NumberFormatError {
+        #[source]
+        error: std::num::ParseIntError,
+    },
+    #[cfg(feature = "cbor")]
+    #[error("Invalid CBOR format: {error:?}")]
+    CborError {
+        #[source]
+        error: serde_cbor::Error,
+    },
+    #[error("Invalid input format: {message}")]
+    InvalidInputFormat { message: String },
+    #[error("Invalid log format: {message:?}")]
     InvalidLogError { message: String },
-    #[fail(display = "Key Duplication")]
+    #[error(
+        "Corrupt log: the frame at offset {corrupt_offset} of {total_bytes} bytes is unreadable ({message})"
+    )]
+    CorruptWALOnOpen {
+        corrupt_offset: u64,
+        total_bytes: u64,
+        message: String,
+    },
+    #[error("Legacy log format (JSON) detected; migrate it with WALManager::migrate_log_format")]
+    LegacyLogFormat,
+    #[error("Checkpoint required: the log reached its size limit")]
+    CheckpointRequired,
+    #[error("Checkpoint mismatch: the data file is at LSN {data_file}, but the log expects {log}")]
+    CheckpointMismatchError { data_file: u64, log: u64 },
+    #[error("Data file corrupted: expected checksum {expected}, but {actual}")]
+    DataFileCorrupted { expected: String, actual: String },
+    #[error("Unsupported data file format version {found} (expected {expected})")]
+    UnsupportedDataFormatVersion { found: u32, expected: u32 },
+    #[error(
+        "Data file format mismatch: the data file is {found:?}, but {expected:?} is configured"
+    )]
+    DataFormatMismatch {
+        found: DataFormat,
+        expected: DataFormat,
+    },
+    #[error("Unsupported data file format: {message}")]
+    UnsupportedDataFormat { message: String },
+    #[error("Unsupported operation: {operation} requires the Sorted storage backend")]
+    UnsupportedOperation { operation: String },
+    #[error("Transient error: {message}")]
+    TransientError { message: String },
+    #[error("Database locked: {lock_path} is held by another instance")]
+    DatabaseLocked { lock_path: String },
+    #[error("Read-only database: the operation requires write access")]
+    ReadOnlyDatabase,
+    #[error(
+        "Sync failed: the log could not be persisted ({message}); reopen the database to recover"
+    )]
+    SyncFailed { message: String },
+    #[error("Lock poisoned: another thread panicked while holding the database")]
+    LockPoisonedError,
+    #[error("Key Duplication")]
     KeyDuplicationError,
-    #[fail(display = "Key Not Found")]
+    #[error("Key Not Found")]
     KeyNotFoundError,
+    #[error("Numeric overflow")]
+    NumericOverflowError,
+    #[error("Key too large: {size} bytes exceeds the limit of {max} bytes")]
+    KeyTooLarge { size: usize, max: usize },
+    #[error("Value too large: {size} bytes exceeds the limit of {max} bytes")]
+    ValueTooLarge { size: usize, max: usize },
+    #[error("Transaction timeout: the deadline set by Transaction::set_timeout has passed")]
+    TransactionTimeout,
+    #[error(
+        "Conflict: keys {conflicting_keys:?} were modified by another transaction after being read"
+    )]
+    /// `conflicting_keys`はキーをJSONで表したもの。`DatabaseError::conflicting_keys`により元のキーに戻せる。
+    ConflictError { conflicting_keys: Vec<String> },
+    #[error("Savepoint Not Found")]
+    SavepointNotFoundError,
+    #[error(
+        "Stale snapshot: version {snapshot} is no longer available (current version is {current})"
+    )]
+    StaleSnapshotError { snapshot: u64, current: u64 },
+    #[error(
+        "Replication gap: records up to LSN {checkpoint_lsn} were discarded by a checkpoint (requested LSN {start_lsn})"
+    )]
+    ReplicationGap { start_lsn: u64, checkpoint_lsn: u64 },
+    #[error("Transaction failed: {source}")]
+    TransactionError {
+        #[source]
+        source: Box<DatabaseError>,
+    },
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<DatabaseError>,
+    },
+}
+
+impl DatabaseError {
+    /// `DatabaseError::Context`による説明を取り除いた元のエラーを返す
+    pub fn root(&self) -> &DatabaseError {
+        match self {
+            DatabaseError::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// `DatabaseError::ConflictError`であれば、競合したキーを返す
+    ///
+    /// `DatabaseError::Context`による説明は取り除いて判定する。キーをKとして復元できない場合は`None`を返す。
+    pub fn conflicting_keys<K: DeserializeOwned>(&self) -> Option<Vec<K>> {
+        match self.root() {
+            DatabaseError::ConflictError { conflicting_keys } => conflicting_keys
+                .iter()
+                .map(|key| serde_json::from_str(key).ok())
+                .collect(),
+            _ => Option::None,
+        }
+    }
+}
+
+/// 発生箇所の説明を付与した`DatabaseError`
+///
+/// `source`は元の`DatabaseError`を返す。`?`などにより`DatabaseError::Context`に変換できる。
+#[derive(Debug, Error)]
+#[error("{context}: {error}")]
+pub struct DatabaseErrorContext {
+    context: String,
+    #[source]
+    error: DatabaseError,
+}
+
+impl DatabaseErrorContext {
+    /// 付与された説明を返す
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    /// 元のエラーを返す
+    pub fn error(&self) -> &DatabaseError {
+        &self.error
+    }
+
+    /// 元のエラーを取り出す
+    pub fn into_inner(self) -> DatabaseError {
+        self.error
+    }
+}
+
+/// `DatabaseError`に発生箇所の説明を付与する
+pub trait DatabaseErrorExt {
+    type Output;
+
+    /// msgを説明として付与する
+    fn context(self, msg: impl Into<String>) -> Self::Output;
+}
+
+impl DatabaseErrorExt for DatabaseError {
+    type Output = DatabaseErrorContext;
+
+    fn context(self, msg: impl Into<String>) -> DatabaseErrorContext {
+        DatabaseErrorContext {
+            context: msg.into(),
+            error: self,
+        }
+    }
+}
+
+impl<T> DatabaseErrorExt for Result<T, DatabaseError> {
+    type Output = Result<T, DatabaseErrorContext>;
+
+    fn context(self, msg: impl Into<String>) -> Result<T, DatabaseErrorContext> {
+        self.map_err(|error| error.context(msg))
+    }
+}
+
+impl From<DatabaseErrorContext> for DatabaseError {
+    fn from(error: DatabaseErrorContext) -> Self {
+        DatabaseError::Context {
+            context: error.context,
+            source: Box::new(error.error),
+        }
+    }
 }
 
 impl From<std::io::Error> for DatabaseError {
@@ -32,6 +222,30 @@ impl From<serde_json::Error> for DatabaseError {
     }
 }
 
+impl From<bincode::Error> for DatabaseError {
+    fn from(error: bincode::Error) -> Self {
+        DatabaseError::BincodeError { error }
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl From<serde_cbor::Error> for DatabaseError {
+    fn from(error: serde_cbor::Error) -> Self {
+        DatabaseError::CborError { error }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for DatabaseError {
+    fn from(error: csv::Error) -> Self {
+        let message = error.to_string();
+        match error.into_kind() {
+            csv::ErrorKind::Io(error) => DatabaseError::IOError { error },
+            _ => DatabaseError::InvalidInputFormat { message },
+        }
+    }
+}
+
 impl From<std::num::ParseIntError> for DatabaseError {
     fn from(error: std::num::ParseIntError) -> Self {
         DatabaseError::NumberFormatError { error }
@@ -46,8 +260,83 @@ impl From<std::string::FromUtf8Error> for DatabaseError {
     }
 }
 
+impl From<std::str::Utf8Error> for DatabaseError {
+    fn from(_: std::str::Utf8Error) -> Self {
+        DatabaseError::InvalidLogError {
+            message: "Invalid UTF-8 format".to_string(),
+        }
+    }
+}
+
 impl From<tempfile::PersistError> for DatabaseError {
     fn from(error: tempfile::PersistError) -> Self {
         DatabaseError::PersistError { error }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{DatabaseError, DatabaseErrorExt};
+    use std::error::Error;
+
+    #[test]
+    fn source_chain() {
+        let error: DatabaseError = std::io::Error::other("disk").into();
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), "disk");
+        assert!(DatabaseError::KeyNotFoundError.source().is_none());
+    }
+
+    #[test]
+    fn context_chain() {
+        let result: Result<(), DatabaseError> = Result::Err(std::io::Error::other("disk").into());
+        let error = result.context("writing commit record").unwrap_err();
+        assert_eq!(error.context(), "writing commit record");
+        assert_eq!(
+            error.to_string(),
+            "writing commit record: IO Error: Custom { kind: Other, error: \"disk\" }"
+        );
+        let source = error.source().unwrap();
+        assert!(source.downcast_ref::<DatabaseError>().is_some());
+        assert_eq!(source.source().unwrap().to_string(), "disk");
+        assert!(matches!(error.into_inner(), DatabaseError::IOError { .. }));
+    }
+
+    #[test]
+    fn context_variant() {
+        let result: Result<(), DatabaseError> = Result::Err(std::io::Error::other("disk").into());
+        let error: DatabaseError = result.context("writing commit record").unwrap_err().into();
+        assert!(matches!(error, DatabaseError::Context { .. }));
+        assert!(matches!(error.root(), DatabaseError::IOError { .. }));
+        let source = error.source().unwrap();
+        assert_eq!(source.source().unwrap().to_string(), "disk");
+        assert!(matches!(
+            DatabaseError::KeyNotFoundError.root(),
+            DatabaseError::KeyNotFoundError
+        ));
+    }
+
+    #[test]
+    fn conflicting_keys() {
+        let error = DatabaseError::ConflictError {
+            conflicting_keys: vec![
+                serde_json::to_string("a").unwrap(),
+                serde_json::to_string("b\"c").unwrap(),
+            ],
+        };
+        assert_eq!(
+            error.conflicting_keys::<String>(),
+            Option::Some(vec!["a".to_string(), "b\"c".to_string()])
+        );
+        // 元のキーの型として復元できない場合
+        assert_eq!(error.conflicting_keys::<i32>(), Option::None);
+
+        let result: Result<(), DatabaseError> = Result::Err(error);
+        let error: DatabaseError = result.context("committing").unwrap_err().into();
+        assert_eq!(error.conflicting_keys::<String>().unwrap().len(), 2);
+        assert_eq!(
+            DatabaseError::KeyNotFoundError.conflicting_keys::<String>(),
+            Option::None
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use crate::changeset::Change;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// `Database::subscribe`により通知されるデータベースの出来事を表す
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatabaseEvent<K, V> {
+    /// トランザクションがCommitされた(反映された変更をキーの昇順に持つ)
+    Committed(Vec<Change<K, V>>),
+    /// トランザクションがAbortされた
+    Aborted,
+    /// チェックポイントが作成された
+    Checkpointed,
+}
+
+/// `Database::subscribe`による購読を識別する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionHandle(u64);
+
+/// 購読しているチャネルの一覧
+pub(crate) struct Subscribers<K, V> {
+    senders: Vec<(SubscriptionHandle, Sender<DatabaseEvent<K, V>>)>,
+    next_id: u64,
+}
+
+impl<K, V> Subscribers<K, V>
+where
+    K: Clone,
+    V: Clone,
+{
+    pub(crate) fn new() -> Self {
+        Subscribers {
+            senders: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// 購読しているチャネルが存在しないかどうかを返す
+    pub(crate) fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// 新たなチャネルを登録する
+    pub(crate) fn subscribe(&mut self) -> (SubscriptionHandle, Receiver<DatabaseEvent<K, V>>) {
+        let (sender, receiver) = mpsc::channel();
+        let handle = SubscriptionHandle(self.next_id);
+        self.next_id += 1;
+        self.senders.push((handle, sender));
+        (handle, receiver)
+    }
+
+    /// handleに対応するチャネルの登録を解除する(解除した場合はtrueを返す)
+    pub(crate) fn unsubscribe(&mut self, handle: SubscriptionHandle) -> bool {
+        let len = self.senders.len();
+        self.senders.retain(|(h, _)| *h != handle);
+        self.senders.len() < len
+    }
+
+    /// すべてのチャネルにeventを送る
+    ///
+    /// 受信側が破棄されたチャネルは、この時点で登録を解除する。
+    pub(crate) fn publish(&mut self, event: DatabaseEvent<K, V>) {
+        self.senders
+            .retain(|(_, sender)| sender.send(event.clone()).is_ok());
+    }
+}
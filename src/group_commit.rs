@@ -0,0 +1,253 @@
+use crate::error::DatabaseError;
+
+use std::io;
+use std::result::Result;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+/// fsyncの対象を表す
+pub(crate) type SyncTarget = Arc<dyn Fn() -> io::Result<()> + Send + Sync>;
+
+/// 複数のトランザクションのfsyncを1回にまとめるグループコミットを管理する
+///
+/// Commitレコードを書き込んだトランザクションは`request`によりfsyncを要求し、返された
+/// `PendingSync`の`wait`で完了を待つ。バックグラウンドのスレッドは、その時点までに要求された
+/// すべての要求に対して1回のfsyncを行い、待機しているトランザクションに通知する。
+///
+/// スレッドがパニックした場合、スレッドは終了し、以降の待機はそれぞれのトランザクション自身による
+/// fsyncとなる。fsyncに失敗した場合は、以降のfsyncが成功しても内容が永続化された保証がないため、
+/// 失敗を記録してスレッドを終了し、以降の待機と`check`は`DatabaseError::SyncFailed`を返す。
+pub(crate) struct GroupCommitManager {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// 最後に要求されたfsyncの番号
+    requested: u64,
+    /// fsyncが完了した最後の番号
+    synced: u64,
+    /// 最後に要求されたfsyncの対象
+    target: Option<SyncTarget>,
+    /// スレッドの終了が要求されたかどうか
+    shutdown: bool,
+    /// スレッドが終了したかどうか
+    stopped: bool,
+    /// 失敗したfsyncのエラーの内容
+    failed: Option<String>,
+}
+
+/// fsyncの完了を待つトランザクションを表す
+pub(crate) struct PendingSync {
+    shared: Arc<Shared>,
+    ticket: u64,
+    target: SyncTarget,
+}
+
+/// スレッドの終了時(パニックによる場合を含む)に、待機しているトランザクションに通知する
+struct StopGuard(Arc<Shared>);
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.condvar.wait(state).unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 要求されたfsyncを、終了が要求されるまでまとめて実行する
+    fn run(&self) {
+        loop {
+            let (requested, target) = {
+                let mut state = self.lock();
+                while state.requested == state.synced && !state.shutdown {
+                    state = self.wait(state);
+                }
+                if state.requested == state.synced {
+                    return;
+                }
+                (state.requested, state.target.clone())
+            };
+            if let Option::Some(target) = target {
+                if let Result::Err(e) = target() {
+                    self.lock().failed = Option::Some(e.to_string());
+                    return;
+                }
+            }
+            self.lock().synced = requested;
+            self.condvar.notify_all();
+        }
+    }
+}
+
+impl State {
+    /// fsyncに失敗している場合は`DatabaseError::SyncFailed`を返す
+    fn check(&self) -> Result<(), DatabaseError> {
+        match &self.failed {
+            Option::Some(message) => Result::Err(DatabaseError::SyncFailed {
+                message: message.clone(),
+            }),
+            Option::None => Result::Ok(()),
+        }
+    }
+}
+
+impl Drop for StopGuard {
+    fn drop(&mut self) {
+        self.0.lock().stopped = true;
+        self.0.condvar.notify_all();
+    }
+}
+
+impl GroupCommitManager {
+    /// fsyncを行うバックグラウンドのスレッドを開始する
+    pub(crate) fn new() -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            condvar: Condvar::new(),
+        });
+        let guard = StopGuard(Arc::clone(&shared));
+        let thread = thread::spawn(move || guard.0.run());
+        GroupCommitManager {
+            shared,
+            thread: Option::Some(thread),
+        }
+    }
+
+    /// targetのfsyncを要求する
+    ///
+    /// 要求より前に書き込まれた内容は、同じファイルに対する以降のfsyncで永続化されるため、
+    /// スレッドは最後に要求された対象のみをfsyncする。
+    pub(crate) fn request(&self, target: SyncTarget) -> PendingSync {
+        let mut state = self.shared.lock();
+        state.requested += 1;
+        state.target = Option::Some(Arc::clone(&target));
+        self.shared.condvar.notify_all();
+        PendingSync {
+            shared: Arc::clone(&self.shared),
+            ticket: state.requested,
+            target,
+        }
+    }
+
+    /// 以前のfsyncに失敗している場合は`DatabaseError::SyncFailed`を返す
+    pub(crate) fn check(&self) -> Result<(), DatabaseError> {
+        self.shared.lock().check()
+    }
+}
+
+impl Drop for GroupCommitManager {
+    /// 要求済みのfsyncを完了した上でスレッドを終了する
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.condvar.notify_all();
+        if let Option::Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl PendingSync {
+    /// 要求したfsyncが完了するまでブロックする
+    ///
+    /// スレッドが既に終了している場合、その場でfsyncを行う。fsyncに失敗した場合は
+    /// `DatabaseError::SyncFailed`を返す。
+    pub(crate) fn wait(self) -> Result<(), DatabaseError> {
+        let mut state = self.shared.lock();
+        loop {
+            if state.synced >= self.ticket {
+                return Result::Ok(());
+            }
+            state.check()?;
+            if state.stopped {
+                drop(state);
+                if let Result::Err(e) = (self.target)() {
+                    let mut state = self.shared.lock();
+                    state.failed = Option::Some(e.to_string());
+                    return state.check();
+                }
+                return Result::Ok(());
+            }
+            state = self.shared.wait(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::DatabaseError;
+    use crate::group_commit::{GroupCommitManager, SyncTarget};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn batched_sync() {
+        let syncs = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&syncs);
+        let target: SyncTarget = Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            Result::Ok(())
+        });
+        let manager = GroupCommitManager::new();
+        let barrier = Barrier::new(16);
+        thread::scope(|s| {
+            for _ in 0..16 {
+                s.spawn(|| {
+                    barrier.wait();
+                    for _ in 0..10 {
+                        manager.request(Arc::clone(&target)).wait().unwrap();
+                    }
+                });
+            }
+        });
+        assert!(syncs.load(Ordering::SeqCst) < 160);
+    }
+
+    #[test]
+    fn panicked_thread() {
+        let syncs = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&syncs);
+        let target: SyncTarget = Arc::new(move || {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("sync failed");
+            }
+            Result::Ok(())
+        });
+        let manager = GroupCommitManager::new();
+        // 1回目のfsyncでスレッドがパニックした後は、待機側がfsyncを行う
+        manager.request(Arc::clone(&target)).wait().unwrap();
+        manager.request(Arc::clone(&target)).wait().unwrap();
+        assert_eq!(syncs.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn failed_sync() {
+        let target: SyncTarget = Arc::new(|| Result::Err(std::io::Error::other("EIO")));
+        let manager = GroupCommitManager::new();
+        assert!(manager.check().is_ok());
+        match manager.request(Arc::clone(&target)).wait() {
+            Result::Err(DatabaseError::SyncFailed { message }) => assert_eq!(message, "EIO"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // 以降のfsyncが成功する場合でも、失敗は記録されたままとなる
+        let target: SyncTarget = Arc::new(|| Result::Ok(()));
+        assert!(matches!(
+            manager.request(target).wait(),
+            Result::Err(DatabaseError::SyncFailed { .. })
+        ));
+        assert!(matches!(
+            manager.check(),
+            Result::Err(DatabaseError::SyncFailed { .. })
+        ));
+    }
+}
@@ -0,0 +1,86 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::ops::Bound;
+
+/// コミット済みのデータと書き込みセットを、キー順にマージしながら走査するイテレータ
+///
+/// 同じキーが両方に存在する場合は書き込みセットの内容を優先し、
+/// 書き込みセット上で削除されたキー(`None`)は結果から除外する。
+pub(crate) struct MergeIter<'a, K, V, I, J>
+where
+    K: 'a + Ord,
+    V: 'a,
+    I: Iterator<Item = (&'a K, &'a V)>,
+    J: Iterator<Item = (&'a K, Option<&'a V>)>,
+{
+    data: Peekable<I>,
+    writeset: Peekable<J>,
+    reverse: bool,
+}
+
+impl<'a, K, V, I, J> MergeIter<'a, K, V, I, J>
+where
+    K: 'a + Ord,
+    V: 'a,
+    I: Iterator<Item = (&'a K, &'a V)>,
+    J: Iterator<Item = (&'a K, Option<&'a V>)>,
+{
+    /// 2つのイテレータをマージする
+    ///
+    /// reverseを設定した場合、両イテレータはキーの降順に並んでいるものとして扱う。
+    pub(crate) fn new(data: I, writeset: J, reverse: bool) -> Self {
+        MergeIter {
+            data: data.peekable(),
+            writeset: writeset.peekable(),
+            reverse,
+        }
+    }
+}
+
+impl<'a, K, V, I, J> Iterator for MergeIter<'a, K, V, I, J>
+where
+    K: 'a + Ord,
+    V: 'a,
+    I: Iterator<Item = (&'a K, &'a V)>,
+    J: Iterator<Item = (&'a K, Option<&'a V>)>,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Less: コミット済みのデータが先, Greater: 書き込みセットが先
+            let order = match (self.data.peek(), self.writeset.peek()) {
+                (Option::None, Option::None) => return Option::None,
+                (Option::Some(_), Option::None) => Ordering::Less,
+                (Option::None, Option::Some(_)) => Ordering::Greater,
+                (Option::Some((dk, _)), Option::Some((wk, _))) => {
+                    if self.reverse {
+                        wk.cmp(dk)
+                    } else {
+                        dk.cmp(wk)
+                    }
+                }
+            };
+            if order == Ordering::Less {
+                return self.data.next();
+            }
+            if order == Ordering::Equal {
+                self.data.next();
+            }
+            if let Option::Some((k, Option::Some(v))) = self.writeset.next() {
+                return Option::Some((k, v));
+            }
+        }
+    }
+}
+
+/// `BTreeMap::range`がpanicせずに扱える範囲かどうかを判定する
+pub(crate) fn is_valid_range<K: Ord>(start: &Bound<K>, end: &Bound<K>) -> bool {
+    match (start, end) {
+        (Bound::Excluded(s), Bound::Excluded(e)) => s < e,
+        (Bound::Included(s), Bound::Included(e))
+        | (Bound::Included(s), Bound::Excluded(e))
+        | (Bound::Excluded(s), Bound::Included(e)) => s <= e,
+        _ => true,
+    }
+}
@@ -1,13 +1,57 @@
-#[macro_use]
-extern crate failure;
+extern crate base64;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate bincode;
 extern crate byteorder;
+extern crate crc32c;
+#[cfg(feature = "csv")]
+extern crate csv;
+// logクレートはモジュール`log`と名前が衝突するため、`::log::error!`のように参照する
+#[cfg(feature = "cbor")]
+extern crate serde_cbor;
 extern crate serde_json;
 extern crate sha2;
 extern crate tempfile;
+extern crate thiserror;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+
+#[macro_use]
+mod trace;
 
+#[cfg(feature = "tokio")]
+pub mod async_db;
+pub mod bytes;
+pub mod changeset;
+pub mod collection;
+pub mod comparator;
+pub mod config;
+#[cfg(feature = "csv")]
+mod csv_file;
+pub mod cursor;
 pub mod database;
+mod datafile;
+pub mod entry;
 pub mod error;
-mod log;
+pub mod event;
+mod group_commit;
+mod iter;
+mod lock;
+pub mod log;
+pub mod numeric;
+pub mod paginator;
+pub mod prefix;
+pub mod replication;
+pub mod segment;
+pub mod serialization;
+#[cfg(feature = "sync")]
+pub mod shared;
+pub mod snapshot;
+pub mod stats;
+pub mod store;
+mod writeset;
@@ -0,0 +1,70 @@
+use crate::error::DatabaseError;
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// データベースのファイルを排他的に使用するためのロックファイルを表す
+///
+/// ロックファイルを作成し、アドバイザリロック(Unixでは`flock(2)`)を取得した状態で保持する。
+/// Dropの際にロックファイルを削除し、ロックを解放する。
+#[derive(Debug)]
+pub(crate) struct LockFile {
+    path: PathBuf,
+    // Dropまでロックを保持するために開いたままにする
+    _file: File,
+}
+
+impl LockFile {
+    /// pathにロックファイルを作成し、ロックを取得する
+    ///
+    /// 他のインスタンスがロックを保持している場合は`DatabaseError::DatabaseLocked`となる。
+    /// ロックファイルが既に存在していても、ロックが解放されている場合(クラッシュしたプロセスが
+    /// 残したものなど)はそれを引き継ぐ。
+    pub(crate) fn acquire(path: PathBuf) -> Result<Self, DatabaseError> {
+        loop {
+            let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Result::Ok(file) => file,
+                Result::Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    match OpenOptions::new().write(true).open(&path) {
+                        Result::Ok(file) => file,
+                        // ロックを保持していたインスタンスが削除した直後であれば、作成からやり直す
+                        Result::Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                        Result::Err(e) => return Result::Err(e.into()),
+                    }
+                }
+                Result::Err(e) => return Result::Err(e.into()),
+            };
+            match file.try_lock() {
+                Result::Ok(()) => {}
+                Result::Err(TryLockError::WouldBlock) => {
+                    return Result::Err(DatabaseError::DatabaseLocked {
+                        lock_path: path.display().to_string(),
+                    })
+                }
+                Result::Err(TryLockError::Error(e)) => return Result::Err(e.into()),
+            }
+            // ロックの取得までの間に、ロックを保持していたインスタンスがファイルを削除した場合
+            if !path.exists() {
+                continue;
+            }
+            return Result::Ok(LockFile { path, _file: file });
+        }
+    }
+
+    /// ロックファイルのパスを返す
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        match std::fs::remove_file(&self.path) {
+            Result::Err(e) if e.kind() != ErrorKind::NotFound => {
+                ::log::warn!("mikrodb: failed to remove {}: {}", self.path.display(), e);
+            }
+            _ => {}
+        }
+    }
+}
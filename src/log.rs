@@ -1,77 +1,691 @@
-use crate::error::DatabaseError;
+use crate::error::{DatabaseError, DatabaseErrorExt};
+use crate::group_commit::SyncTarget;
+use crate::segment::SegmentedLog;
+use crate::stats::Statistics;
 
 use std::fmt::Debug;
-use std::fs::{File, OpenOptions};
+use std::fs::File;
 use std::io::prelude::*;
+use std::io::{BufWriter, Cursor, SeekFrom};
+use std::marker::PhantomData;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
 use std::result::Result;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_json;
 use sha2::{Digest, Sha256};
-use tempfile::NamedTempFile;
 
 /// WALレコードを表す
 ///
 /// # レコードタイプ
-/// 現在、6種類のレコードタイプをサポートする
+/// 現在、28種類のレコードタイプをサポートする
 /// - Create: キーバリューペアの新規作成
+/// - CreateWithTTL: 有効期限を持つキーバリューペアの新規作成
 /// - Read: キーを元にバリューをルックアップする(Redoには使用しないが)
+/// - Exists: キーの存在を確認する(Redoには使用しないが)
+/// - ReadBatch: 複数のキーを元にバリューをまとめてルックアップする(Redoには使用しないが)
 /// - Update: キーに紐付くバリューの更新
+/// - Upsert: キーバリューペアの新規作成、またはキーに紐付くバリューの更新
+/// - Scan: キーの範囲を元にバリューを走査する(Redoには使用しないが)
+/// - ScanPrefix: キーの接頭辞を元にバリューを走査する(Redoには使用しないが)
+/// - CAS: キーに紐付くバリューが期待する値と一致する場合のみ、バリューを更新する
+/// - Increment: キーに紐付く数値のバリューに加算する(Redoには加算後の値を使用する)
+/// - Decrement: キーに紐付く数値のバリューから減算する(Redoには減算後の値を使用する)
+/// - ClearExpiry: 期限切れのキーに値が書き込まれた際に、その有効期限を解除する
 /// - Delete: キーを元にキーバリューペアの削除を行う
+/// - DeleteRange: キーの範囲を元にキーバリューペアの削除を行う(Redo時点のデータに対して範囲を適用する)
+/// - Savepoint: トランザクション内のセーブポイントを記録する
+/// - RollbackToSavepoint: 対応するSavepointからの変更を破棄する
 /// - Commit: ファイルの開始、または直前のCommit/Abortからの変更を反映する
 /// - Abort: ファイルの開始、または直前のCommit/Abortからの変更を破棄する
+/// - CheckpointMarker: チェックポイントの完了を記録する(これ以前のレコードはRedoに使用しない)
+/// - Noop: 定期的に書き込まれるハートビート(データの整合性には影響せず、Redoには使用しない)
+/// - Prepare: 2相コミットの第1相の完了を記録する(後続のCommit/Abortにより結果が確定する)
+/// - Truncate: すべてのキーバリューペアの削除を行う(Redo時点のデータをすべて削除する)
+/// - ScanReverse: キーの範囲を元にバリューを降順に走査する(Redoには使用しないが)
+/// - BeforeImage: 書き込みの直前のキーの値を記録する(Undo用であり、Redoには使用しない)
+/// - Batch: 複数のキーバリューペアの書き込み・削除をまとめて行う(Redo時に個々の操作として反映する)
+/// - Rename: キーに紐付くバリューを、有効期限と共に別のキーへ移動する
+/// - Swap: 2つのキーに紐付くバリューを交換する(有効期限は交換しない)
 #[derive(PartialEq, Deserialize, Serialize, Debug)]
 pub enum LogRecord<K, V>
 where
     K: Debug,
     V: Debug,
 {
-    Create { key: K, value: V },
-    Read { key: K },
-    Update { key: K, value: V },
-    Delete { key: K },
+    Create {
+        key: K,
+        value: V,
+    },
+    CreateWithTTL {
+        key: K,
+        value: V,
+        expiry_secs: u64,
+    },
+    Read {
+        key: K,
+    },
+    Exists {
+        key: K,
+    },
+    ReadBatch {
+        keys: Vec<K>,
+    },
+    Update {
+        key: K,
+        value: V,
+    },
+    Upsert {
+        key: K,
+        value: V,
+    },
+    Scan {
+        start: Bound<K>,
+        end: Bound<K>,
+    },
+    ScanPrefix {
+        prefix: K,
+    },
+    #[allow(clippy::upper_case_acronyms)]
+    CAS {
+        key: K,
+        expected: V,
+        new_value: V,
+    },
+    Increment {
+        key: K,
+        delta: V,
+        value: V,
+    },
+    Decrement {
+        key: K,
+        delta: V,
+        value: V,
+    },
+    ClearExpiry {
+        key: K,
+    },
+    Delete {
+        key: K,
+    },
+    DeleteRange {
+        start: Bound<K>,
+        end: Bound<K>,
+    },
+    Savepoint {
+        id: u32,
+        name: String,
+    },
+    RollbackToSavepoint {
+        id: u32,
+    },
     Commit,
     Abort,
+    CheckpointMarker {
+        checkpoint_lsn: u64,
+    },
+    Noop {
+        timestamp_secs: u64,
+    },
+    Prepare,
+    Truncate,
+    ScanReverse {
+        start: Bound<K>,
+        end: Bound<K>,
+    },
+    BeforeImage {
+        key: K,
+        old_value: Option<V>,
+    },
+    Batch {
+        ops: Vec<BatchOp<K, V>>,
+    },
+    Rename {
+        old_key: K,
+        new_key: K,
+    },
+    Swap {
+        key_a: K,
+        key_b: K,
+    },
 }
 
+impl<K, V> LogRecord<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    /// レコードタイプの名前を返す
+    pub fn record_type(&self) -> &'static str {
+        match self {
+            LogRecord::Create { .. } => "Create",
+            LogRecord::CreateWithTTL { .. } => "CreateWithTTL",
+            LogRecord::Read { .. } => "Read",
+            LogRecord::Exists { .. } => "Exists",
+            LogRecord::ReadBatch { .. } => "ReadBatch",
+            LogRecord::Update { .. } => "Update",
+            LogRecord::Upsert { .. } => "Upsert",
+            LogRecord::Scan { .. } => "Scan",
+            LogRecord::ScanPrefix { .. } => "ScanPrefix",
+            LogRecord::CAS { .. } => "CAS",
+            LogRecord::Increment { .. } => "Increment",
+            LogRecord::Decrement { .. } => "Decrement",
+            LogRecord::ClearExpiry { .. } => "ClearExpiry",
+            LogRecord::Delete { .. } => "Delete",
+            LogRecord::DeleteRange { .. } => "DeleteRange",
+            LogRecord::Savepoint { .. } => "Savepoint",
+            LogRecord::RollbackToSavepoint { .. } => "RollbackToSavepoint",
+            LogRecord::Commit => "Commit",
+            LogRecord::Abort => "Abort",
+            LogRecord::CheckpointMarker { .. } => "CheckpointMarker",
+            LogRecord::Noop { .. } => "Noop",
+            LogRecord::Prepare => "Prepare",
+            LogRecord::Truncate => "Truncate",
+            LogRecord::ScanReverse { .. } => "ScanReverse",
+            LogRecord::BeforeImage { .. } => "BeforeImage",
+            LogRecord::Batch { .. } => "Batch",
+            LogRecord::Rename { .. } => "Rename",
+            LogRecord::Swap { .. } => "Swap",
+        }
+    }
+}
+
+/// Batchレコードに含まれる個々の操作を表す
+#[derive(PartialEq, Deserialize, Serialize, Debug, Clone)]
+pub enum BatchOp<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    Upsert(K, V),
+    Delete(K),
+}
+
+impl<K, V> BatchOp<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    /// 操作の対象のキーを返す
+    pub fn key(&self) -> &K {
+        match self {
+            BatchOp::Upsert(key, _) | BatchOp::Delete(key) => key,
+        }
+    }
+}
+
+/// LSNとWALレコードの組
+pub type LsnRecord<K, V> = (u64, LogRecord<K, V>);
+
+/// `Database::export_wal_as_json`により書き出される、フレームの開始位置とWALレコードの組
+#[derive(PartialEq, Deserialize, Serialize, Debug)]
+pub struct ExportedRecord<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    /// フレームの開始位置(ログの先頭からのbytes)
+    pub offset: u64,
+    /// WALレコード
+    pub record: LogRecord<K, V>,
+}
+
+/// クラッシュリカバリの際にログの破損をどのように扱うかを表す
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalRecoveryMode {
+    /// 最初に読み取れなかったフレーム以降を読み取らない
+    ///
+    /// 読み取れなかったフレームが書き込み途中の末尾のフレームではない場合、`Database::new`は
+    /// `DatabaseError::CorruptWALOnOpen`を返す。
+    #[default]
+    Strict,
+    /// 読み取れないフレームを読み飛ばし、以降で読み取れるフレームを読み取る
+    ///
+    /// 破損したフレームを含むトランザクションと、Commitレコードを持たない末尾のトランザクションは
+    /// Abortされたものとして扱う。
+    Lenient,
+}
+
+/// 寛容な読み取りの際に読み飛ばしたログの破損を表す
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptionEvent {
+    /// 破損の開始位置(ログの先頭からのbytes)
+    pub offset: u64,
+    /// 読み飛ばしたバイト数
+    pub skipped: u64,
+    /// 破損の直前に読み取れたレコードのLSN(ログの先頭が破損していた場合は0)
+    pub last_valid_lsn: u64,
+    /// 破損の開始位置のフレームを読み取った際のエラー
+    pub message: String,
+}
+
+/// `WALManager::verify_integrity`による、ログ全体の検証の結果を表す
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// チェックサムを検証できたレコードの数
+    pub total_records: usize,
+    /// 読み取れなかった箇所の数(連続した破損は1つと数える)
+    pub corrupt_records: usize,
+    /// 読み取れなかった箇所の開始位置(ログの先頭からのbytes)
+    pub corrupt_offsets: Vec<u64>,
+    /// ログ上のトランザクションの概要(LSNの昇順)
+    pub transactions: Vec<TransactionSummary>,
+}
+
+/// ログ上の1つのトランザクションの概要を表す
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary {
+    /// 最初のレコードのLSN
+    pub first_lsn: u64,
+    /// 最後のレコードのLSN
+    pub last_lsn: u64,
+    /// Commit/Abortレコードを含むレコードの数
+    pub record_count: usize,
+    pub outcome: TransactionOutcome,
+}
+
+/// ログ上のトランザクションの結果を表す
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    Commit,
+    Abort,
+    /// Commit/Abortレコードが書き込まれていない
+    Incomplete,
+}
+
+/// `WALManager::iter_records`により返される、ログ上のレコードを1つずつ読み取るイテレータ
+///
+/// Drop時に、ログの読み書きの位置を末尾に戻す。
+#[derive(Debug)]
+pub struct WALIterator<'a, K, V> {
+    wal: &'a mut WALManager,
+    start: u64,
+    position: u64,
+    record_offset: u64,
+    lsn: u64,
+    done: bool,
+    finished: bool,
+    error: Option<DatabaseError>,
+    /// 読み取れなかったフレームの開始位置と、その際のエラー
+    stopped: Option<(u64, String)>,
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'a, K, V> WALIterator<'a, K, V> {
+    /// 最後に読み取ったレコードのLSNを返す(まだ読み取っていない場合は0)
+    pub fn lsn(&self) -> u64 {
+        self.lsn
+    }
+
+    /// 最後に読み取ったレコードのフレームの開始位置(ログの先頭からのbytes)を返す
+    pub fn offset(&self) -> u64 {
+        self.record_offset
+    }
+
+    /// 読み書きの位置をログの末尾に戻し、以降に書き込まれるレコードのLSNを読み取ったLSNより大きくする
+    fn finish(&mut self) -> Result<(), DatabaseError> {
+        if self.finished {
+            return Result::Ok(());
+        }
+        self.finished = true;
+        self.done = true;
+        self.wal.advance_lsn(self.lsn);
+        self.wal.bytes_since_checkpoint = self.wal.file.seek(SeekFrom::End(0))?;
+        Result::Ok(())
+    }
+}
+
+impl<'a, K, V> Iterator for WALIterator<'a, K, V>
+where
+    K: DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    type Item = Result<LogRecord<K, V>, DatabaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Option::Some(e) = self.error.take() {
+            self.done = true;
+            return Option::Some(Result::Err(e));
+        }
+        if self.done {
+            return Option::None;
+        }
+        let error = match self.wal.read_log_entry(self.position) {
+            Result::Ok((lsn, _, _)) if self.lsn > 0 && lsn <= self.lsn => {
+                let message = format!("Non-increasing LSN {} after {}", lsn, self.lsn);
+                self.stopped = Option::Some((self.position, message));
+                Option::None
+            }
+            Result::Ok((lsn, record, len)) => {
+                self.lsn = lsn;
+                self.record_offset = self.position;
+                self.position += len as u64;
+                return Option::Some(Result::Ok(record));
+            }
+            Result::Err(DatabaseError::LegacyLogFormat) => {
+                Option::Some(DatabaseError::LegacyLogFormat)
+            }
+            Result::Err(e) if self.start == 0 && self.lsn == 0 => {
+                match self.wal.is_legacy_layout() {
+                    Result::Ok(true) => Option::Some(DatabaseError::LegacyLogFormat),
+                    Result::Ok(false) => {
+                        self.stopped = Option::Some((self.position, e.to_string()));
+                        Option::None
+                    }
+                    Result::Err(e) => Option::Some(e),
+                }
+            }
+            Result::Err(e) => {
+                self.stopped = Option::Some((self.position, e.to_string()));
+                Option::None
+            }
+        };
+        self.done = true;
+        error.map(Result::Err)
+    }
+}
+
+impl<'a, K, V> Drop for WALIterator<'a, K, V> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// 寛容な読み取りの結果(読み取れたレコードと、読み飛ばした破損)を表す
+pub type LenientRead<T> = (Vec<T>, Vec<CorruptionEvent>);
+
+/// フレームの整合性の検証に用いるチェックサムのアルゴリズムを表す
+///
+/// フレームは`[LSN (8 bytes)][algorithm (1 byte)][checksum][len (8 bytes)][body]`の形式で記録され、
+/// チェックサムはLSNと本体の両方を対象とする。アルゴリズムはフレームごとに記録されるため、
+/// 異なるアルゴリズムで書き込まれたフレームが混在するログも読み取ることができる。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumAlgorithm {
+    /// SHA-256 (32 bytes)
+    #[default]
+    Sha256 = 0,
+    /// CRC32C (4 bytes)。改ざんは検出できないが、SHA-256に比べて高速に計算できる
+    Crc32c = 1,
+}
+
+impl ChecksumAlgorithm {
+    /// フレームに記録されたアルゴリズムを表すバイトから復元する
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Option::Some(ChecksumAlgorithm::Sha256),
+            1 => Option::Some(ChecksumAlgorithm::Crc32c),
+            _ => Option::None,
+        }
+    }
+
+    /// チェックサムのバイト数を返す
+    fn checksum_len(self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 => 32,
+            ChecksumAlgorithm::Crc32c => 4,
+        }
+    }
+
+    /// フレームのヘッダ(LSN・アルゴリズム・チェックサム・本体の長さ)のバイト数を返す
+    fn frame_header_len(self) -> usize {
+        8 + 1 + self.checksum_len() + 8
+    }
+}
+
+/// fsyncによりストレージに書き出された内容を永続化する方法を表す
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncMode {
+    /// 内容とメタデータの両方を永続化する(`File::sync_all`)
+    #[default]
+    Full,
+    /// 内容と、その読み取りに必要なメタデータ(ファイルサイズなど)のみを永続化する(`File::sync_data`)
+    ///
+    /// Linuxではfdatasync(2)となり、更新日時などのメタデータの書き込みを省略する。
+    DataOnly,
+    /// 明示的な永続化を行わず、OSによる書き出しに任せる
+    ///
+    /// プロセスのクラッシュでは内容は失われないが、OSのクラッシュや電源断に対する永続性は保証されない。
+    /// クラッシュ時の安全性が不要な一括の読み込みなどに用いる。
+    None,
+}
+
+impl SyncMode {
+    /// fileの内容をこのモードに従って永続化する
+    pub(crate) fn sync_file(self, file: &File) -> std::io::Result<()> {
+        match self {
+            SyncMode::Full => file.sync_all(),
+            SyncMode::DataOnly => file.sync_data(),
+            SyncMode::None => Result::Ok(()),
+        }
+    }
+}
+
+/// WALの格納先として利用できるストレージを表す
+pub trait ReadWrite: Read + Write + Seek + Debug + Send + Sync {
+    /// 書き込まれた内容を永続化する
+    fn sync_all(&mut self) -> std::io::Result<()>;
+
+    /// 書き込まれた内容を、その読み取りに必要なメタデータと共に永続化する
+    ///
+    /// 既定では`sync_all`と同じである。
+    fn sync_data(&mut self) -> std::io::Result<()> {
+        self.sync_all()
+    }
+
+    /// 格納されている内容をすべて破棄する
+    fn truncate(&mut self) -> std::io::Result<()>;
+
+    /// 以降の書き込みを新たなセグメントに行う(セグメントに分割しないストレージでは何もしない)
+    fn start_segment(&mut self) -> std::io::Result<()> {
+        Result::Ok(())
+    }
+
+    /// 現在書き込み中のセグメントのバイト数を返す(セグメントに分割しないストレージでは0)
+    fn segment_len(&self) -> u64 {
+        0
+    }
+
+    /// 書き込み中のファイルのハンドルを複製する(ファイルを持たないストレージではNone)
+    ///
+    /// 複製したハンドルは、ストレージへの排他的なアクセスを保持せずにfsyncを行うために用いる。
+    fn try_clone_file(&self) -> Option<File> {
+        Option::None
+    }
+}
+
+impl ReadWrite for File {
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        File::sync_all(self)
+    }
+
+    fn sync_data(&mut self) -> std::io::Result<()> {
+        File::sync_data(self)
+    }
+
+    fn truncate(&mut self) -> std::io::Result<()> {
+        self.set_len(0)
+    }
+
+    fn try_clone_file(&self) -> Option<File> {
+        self.try_clone().ok()
+    }
+}
+
+impl ReadWrite for Cursor<Vec<u8>> {
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        Result::Ok(())
+    }
+
+    fn truncate(&mut self) -> std::io::Result<()> {
+        self.get_mut().clear();
+        self.set_position(0);
+        Result::Ok(())
+    }
+}
+
+/// 書き込みバッファの既定のサイズ(bytes)
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
 /// WALレコードの読み書きに関する一連の手続きを表す
+///
+/// fsyncを伴わない書き込みは書き込みバッファに蓄積され、バッファが一杯になった時点、
+/// fsyncを伴う書き込みの時点、または`flush_buffer`の呼び出し時点でストレージに書き出される。
+#[derive(Debug)]
 pub struct WALManager {
-    file: File,
-    file_path: String,
+    file: BufWriter<Box<dyn ReadWrite>>,
+    records: usize,
+    commits: usize,
+    bytes_since_checkpoint: u64,
+    size_limit: u64,
+    segment_size: u64,
+    next_lsn: u64,
+    checksum_algorithm: ChecksumAlgorithm,
+    sync_mode: SyncMode,
+    stats: Arc<Statistics>,
+    /// セグメントファイルを格納するディレクトリ(ファイルにログを記録しない場合はNone)
+    path: Option<PathBuf>,
 }
 
 impl WALManager {
-    /// WALマネージャを初期化する
-    pub fn new(logpath: &str) -> Result<Self, DatabaseError> {
-        let logfile = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .read(true)
-            .open(logpath)?;
-        Result::Ok(WALManager {
-            file: logfile,
-            file_path: logpath.to_string(),
-        })
+    /// ディレクトリ内の番号付きのセグメントファイルにログを記録するWALマネージャを初期化する
+    ///
+    /// 同じパスにセグメントに分割されていない旧形式のログファイルが存在する場合、
+    /// それを最初のセグメントとして引き継ぐ。
+    pub fn new<P: AsRef<Path>>(logdir: P) -> Result<Self, DatabaseError> {
+        let log = SegmentedLog::open(&logdir)?;
+        let mut wal = WALManager::with_storage(Box::new(log));
+        wal.path = Option::Some(logdir.as_ref().to_path_buf());
+        Result::Ok(wal)
+    }
+
+    /// メモリ上にログを記録するWALマネージャを初期化する
+    pub fn in_memory() -> Self {
+        WALManager::with_storage(Box::new(Cursor::new(Vec::new())))
+    }
+
+    /// 任意のストレージにログを記録するWALマネージャを初期化する
+    pub fn with_storage(storage: Box<dyn ReadWrite>) -> Self {
+        WALManager {
+            file: BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, storage),
+            records: 0,
+            commits: 0,
+            bytes_since_checkpoint: 0,
+            size_limit: 0,
+            segment_size: 0,
+            next_lsn: 1,
+            checksum_algorithm: ChecksumAlgorithm::default(),
+            sync_mode: SyncMode::default(),
+            stats: Arc::default(),
+            path: Option::None,
+        }
+    }
+
+    /// セグメントファイルを格納するディレクトリのパスを返す(`new`以外により初期化された場合はNone)
+    pub fn log_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 前回のクリア以降にログに記録されているレコードの数を返す
+    pub fn record_count(&self) -> usize {
+        self.records
+    }
+
+    /// 前回のクリア以降にログに記録されているCommitレコードの数を返す
+    pub fn commit_count(&self) -> usize {
+        self.commits
+    }
+
+    /// 前回のクリア以降にログに書き込まれたバイト数を返す
+    pub fn bytes_since_checkpoint(&self) -> u64 {
+        self.bytes_since_checkpoint
+    }
+
+    /// ログの末尾の位置(bytes)を返す
+    ///
+    /// 次に書き込まれるフレームはこの位置から始まる。ログのクリアにより0に戻る。
+    pub fn current_offset(&self) -> u64 {
+        self.bytes_since_checkpoint
+    }
+
+    /// 最後に書き込まれたレコードのLSNを返す(まだ書き込まれていない場合は0)
+    ///
+    /// LSNはログのクリアを跨いで単調に増加する。
+    pub fn current_lsn(&self) -> u64 {
+        self.next_lsn - 1
+    }
+
+    /// 以降に書き込まれるレコードのLSNがlsnより大きくなるようにする
+    pub fn advance_lsn(&mut self, lsn: u64) {
+        self.next_lsn = self.next_lsn.max(lsn + 1);
+    }
+
+    /// ログへの書き込みを記録する統計情報を設定する
+    pub fn set_statistics(&mut self, stats: Arc<Statistics>) {
+        self.stats = stats;
+    }
+
+    /// 以降に書き込まれるフレームのチェックサムのアルゴリズムを設定する
+    pub fn set_checksum_algorithm(&mut self, algorithm: ChecksumAlgorithm) {
+        self.checksum_algorithm = algorithm;
+    }
+
+    /// fsyncを伴う書き込みの際の永続化の方法を設定する
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.sync_mode = mode;
+    }
+
+    /// 書き込みバッファのサイズ(bytes)を設定する(0の場合はバッファリングしない)
+    ///
+    /// 設定の前に、バッファに蓄積されている内容はストレージに書き出される。
+    pub fn set_buffer_size(&mut self, size: usize) -> Result<(), DatabaseError> {
+        self.file.flush()?;
+        let placeholder: Box<dyn ReadWrite> = Box::new(Cursor::new(Vec::new()));
+        let file = std::mem::replace(&mut self.file, BufWriter::new(placeholder));
+        let (storage, _) = file.into_parts();
+        self.file = BufWriter::with_capacity(size, storage);
+        Result::Ok(())
+    }
+
+    /// ログの容量の上限(bytes)を設定する(0の場合は無制限)
+    ///
+    /// 上限を超える書き込みは`DatabaseError::CheckpointRequired`として拒否されるため、
+    /// 呼び出し側はチェックポイントを作成したうえで再度書き込む必要がある。
+    pub fn set_size_limit(&mut self, size_limit: u64) {
+        self.size_limit = size_limit;
+    }
+
+    /// セグメントの容量の上限(bytes)を設定する(0の場合は分割しない)
+    ///
+    /// 書き込みによって現在のセグメントが上限を超える場合、書き込みの前に新たなセグメントを開始する。
+    /// ただし、空のセグメントには上限を超えるフレームも書き込まれる。
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segment_size = segment_size;
     }
 
     /// WALマネージャにより管理されるログをファイルシステム上・メモリ上から破棄する
+    ///
+    /// セグメントに分割されたログでは、すべてのセグメントが削除される。
     pub fn clear(&mut self) -> Result<(), DatabaseError> {
-        let file = NamedTempFile::new_in(std::env::current_dir()?)?;
-        file.persist(&self.file_path)?;
-        self.file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .read(true)
-            .open(&self.file_path)?;
-        self.file.sync_all()?;
+        self.file.flush()?;
+        self.file.get_mut().truncate()?;
+        self.sync()?;
+        self.records = 0;
+        self.commits = 0;
+        self.bytes_since_checkpoint = 0;
         Result::Ok(())
     }
 
     /// ログレコードをファイルシステムに書き込む
     ///
     /// フラグsyncを設定することで、fsyncにより確実に永続化されることが保証される。
+    /// レコード本体はbincodeで記録される(feature `json-wal` 有効時はJSON)。
+    ///
+    /// 書き込みによってログの容量の上限を超える場合、何も書き込まずに
+    /// `DatabaseError::CheckpointRequired`を返す。
     pub fn write_log<K, V>(
         &mut self,
         record: &LogRecord<K, V>,
@@ -81,70 +695,742 @@ impl WALManager {
         K: Serialize + Debug,
         V: Serialize + Debug,
     {
-        let body = serde_json::to_string(record)?;
-        let body = body.as_bytes();
+        let body = encode_record(record)?;
+        let frame_len = (self.checksum_algorithm.frame_header_len() + body.len()) as u64;
+        if self.size_limit > 0
+            && self.bytes_since_checkpoint > 0
+            && self.bytes_since_checkpoint + frame_len > self.size_limit
+        {
+            return Result::Err(DatabaseError::CheckpointRequired);
+        }
+        self.write_frame(&body, sync).map_err(|e| {
+            e.context(format!(
+                "writing the {} record to the WAL",
+                record.record_type()
+            ))
+        })?;
+        trace_event!(
+            record = record.record_type(),
+            bytes = frame_len,
+            "write_log"
+        );
+        if let LogRecord::Commit = record {
+            self.commits += 1;
+        }
+        Result::Ok(())
+    }
 
-        let mut hasher = Sha256::new();
-        hasher.input(body);
-        let hash = hasher.result();
+    /// 現在時刻(UNIX時間、秒)を持つNoopレコードをfsyncせずに書き込む
+    ///
+    /// ハートビートはデータの整合性には影響しないが、ログ上の時刻と位置の対応を記録することで、
+    /// ログ全体を読み取らずに位置を推定できるようにする。レコード数・Commit数には数えない。
+    /// ログの容量の上限に達している場合は何も書き込まない。
+    pub fn write_heartbeat(&mut self) -> Result<(), DatabaseError> {
+        let timestamp_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let record: LogRecord<(), ()> = LogRecord::Noop { timestamp_secs };
+        let records = self.records;
+        match self.write_log(&record, false) {
+            Result::Ok(()) | Result::Err(DatabaseError::CheckpointRequired) => {
+                self.records = records;
+                Result::Ok(())
+            }
+            Result::Err(e) => Result::Err(e),
+        }
+    }
+
+    /// ログの容量の上限を無視してログレコードを書き込む
+    pub(crate) fn write_log_unchecked<K, V>(
+        &mut self,
+        record: &LogRecord<K, V>,
+        sync: bool,
+    ) -> Result<(), DatabaseError>
+    where
+        K: Serialize + Debug,
+        V: Serialize + Debug,
+    {
+        let limit = std::mem::replace(&mut self.size_limit, 0);
+        let result = self.write_log(record, sync);
+        self.size_limit = limit;
+        result
+    }
+
+    /// レコード本体を新たなLSN・チェックサム・長さと共にフレームとして書き込む
+    fn write_frame(&mut self, body: &[u8], sync: bool) -> Result<(), DatabaseError> {
+        let lsn = self.next_lsn;
+        let algorithm = self.checksum_algorithm;
+        let checksum = frame_checksum(algorithm, lsn, body);
         let len = body.len();
+        let frame_len = (algorithm.frame_header_len() + len) as u64;
 
-        self.file.write_all(&hash[..])?;
+        if self.segment_size > 0 {
+            let segment_len = self.file.get_ref().segment_len() + self.file.buffer().len() as u64;
+            if segment_len > 0 && segment_len + frame_len > self.segment_size {
+                self.file.flush()?;
+                self.file.get_mut().start_segment()?;
+            }
+        }
+        self.file.write_u64::<LittleEndian>(lsn)?;
+        self.file.write_u8(algorithm as u8)?;
+        self.file.write_all(&checksum)?;
         self.file.write_u64::<LittleEndian>(len as u64)?;
         self.file.write_all(body)?;
+        self.next_lsn += 1;
+        self.records += 1;
+        self.bytes_since_checkpoint += frame_len;
+        self.stats.record_wal_write(frame_len);
         if sync {
-            self.file.sync_all()?;
+            self.file.flush()?;
+            self.sync()?;
         }
         Result::Ok(())
     }
 
+    /// 書き込みバッファの内容をfsyncせずにストレージに書き出す
+    ///
+    /// OSのページキャッシュに書き出されるため、プロセスがクラッシュしても内容は失われないが、
+    /// OSのクラッシュや電源断に対する永続性は保証されない。
+    pub fn flush_buffer(&mut self) -> Result<(), DatabaseError> {
+        self.file.flush()?;
+        Result::Ok(())
+    }
+
+    /// 書き込みバッファの内容を書き出した上で、グループコミットによりfsyncを行う対象を返す
+    ///
+    /// ストレージがファイルのハンドルの複製に対応していない場合は、その場でfsyncを行いNoneを返す。
+    /// `SyncMode::None`の場合は書き出しのみを行い、Noneを返す。
+    pub(crate) fn sync_target(&mut self) -> Result<Option<SyncTarget>, DatabaseError> {
+        self.file.flush()?;
+        let mode = self.sync_mode;
+        if mode == SyncMode::None {
+            return Result::Ok(Option::None);
+        }
+        match self.file.get_ref().try_clone_file() {
+            Option::Some(file) => Result::Ok(Option::Some(Arc::new(move || mode.sync_file(&file)))),
+            Option::None => {
+                self.sync()?;
+                Result::Ok(Option::None)
+            }
+        }
+    }
+
+    /// 書き出された内容を`SyncMode`に従って永続化する
+    fn sync(&mut self) -> std::io::Result<()> {
+        match self.sync_mode {
+            SyncMode::Full => self.file.get_mut().sync_all(),
+            SyncMode::DataOnly => self.file.get_mut().sync_data(),
+            SyncMode::None => Result::Ok(()),
+        }
+    }
+
     /// 現在ファイルシステム上に書き込まれているレコードを可能な限り取得し、ファイルをクリアする。
     pub fn read_log<K, V>(&mut self) -> Result<Vec<LogRecord<K, V>>, DatabaseError>
     where
         K: DeserializeOwned + Debug,
         V: DeserializeOwned + Debug,
     {
-        let mut result = Vec::new();
-        while let Result::Ok(val) = self.read_log_entry() {
-            result.push(val);
+        let records = self.read_log_with_lsn()?;
+        Result::Ok(records.into_iter().map(|(_, record)| record).collect())
+    }
+
+    /// 現在書き込まれているレコードを、それぞれのLSNと共に可能な限り取得する
+    ///
+    /// LSNが単調に増加していないレコードに到達した場合、それ以降は読み取らない。
+    /// 読み取った最後のLSNの次から、以降のレコードのLSNが割り当てられる。
+    pub fn read_log_with_lsn<K, V>(&mut self) -> Result<Vec<LsnRecord<K, V>>, DatabaseError>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        self.read_log_with_lsn_from(0)
+    }
+
+    /// ログの先頭からoffset(bytes)の位置以降に書き込まれているレコードを、
+    /// それぞれのLSNと共に可能な限り取得する
+    ///
+    /// offsetはフレームの先頭を指している必要がある。そうでない場合、またはoffsetがログの末尾を
+    /// 超える場合は、空の結果を返す。旧形式のログの検出はoffsetが0の場合のみ行う。
+    pub fn read_log_with_lsn_from<K, V>(
+        &mut self,
+        offset: u64,
+    ) -> Result<Vec<LsnRecord<K, V>>, DatabaseError>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        let (records, _) = self.read_records_from(offset)?;
+        Result::Ok(records)
+    }
+
+    /// `read_log_with_lsn_from`と同様にレコードを取得し、読み取れないフレームの位置以降に
+    /// 書き込み途中のフレームではない内容が残っている場合は`DatabaseError::CorruptWALOnOpen`を返す
+    ///
+    /// ログの末尾を超える長さを持つフレームと、0で埋められた末尾は、クラッシュにより書き込みが
+    /// 中断されたものとして扱う。
+    pub(crate) fn read_log_with_lsn_checked<K, V>(
+        &mut self,
+        offset: u64,
+    ) -> Result<Vec<LsnRecord<K, V>>, DatabaseError>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        let (records, stopped) = self.read_records_from(offset)?;
+        let (corrupt_offset, message) = match stopped {
+            Option::Some(stopped) => stopped,
+            Option::None => return Result::Ok(records),
+        };
+        let (total_bytes, rest) = self
+            .read_rest(corrupt_offset)
+            .map_err(|e| e.context(format!("reading the WAL after offset {}", corrupt_offset)))?;
+        if rest.iter().all(|byte| *byte == 0) || is_truncated_frame(&rest) {
+            return Result::Ok(records);
+        }
+        Result::Err(DatabaseError::CorruptWALOnOpen {
+            corrupt_offset,
+            total_bytes,
+            message,
+        })
+    }
+
+    /// ログの全体のバイト数と、offsetの位置からログの末尾までの内容を返す
+    fn read_rest(&mut self, offset: u64) -> Result<(u64, Vec<u8>), DatabaseError> {
+        let total_bytes = self.file.seek(SeekFrom::End(0))?;
+        let mut rest = Vec::new();
+        if offset < total_bytes {
+            self.file.get_mut().seek(SeekFrom::Start(offset))?;
+            self.file.get_mut().read_to_end(&mut rest)?;
+        }
+        Result::Ok((total_bytes, rest))
+    }
+
+    /// offsetの位置以降のレコードを読み取り、読み取れないフレームに到達した場合はその位置とエラーを返す
+    #[allow(clippy::type_complexity)]
+    fn read_records_from<K, V>(
+        &mut self,
+        offset: u64,
+    ) -> Result<(Vec<LsnRecord<K, V>>, Option<(u64, String)>), DatabaseError>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        let mut iter = self.iter_records_from(offset);
+        let mut records: Vec<LsnRecord<K, V>> = Vec::new();
+        let result = loop {
+            match iter.next() {
+                Option::Some(Result::Ok(record)) => records.push((iter.lsn(), record)),
+                Option::Some(Result::Err(e)) => break Result::Err(e),
+                Option::None => break Result::Ok(()),
+            }
+        };
+        let stopped = iter.stopped.take();
+        iter.finish()?;
+        drop(iter);
+        result?;
+        self.set_read_records(&records);
+        Result::Ok((records, stopped))
+    }
+
+    /// 現在書き込まれているレコードを先頭から1つずつ読み取るイテレータを返す
+    ///
+    /// `read_log`と異なり、すべてのレコードを一度にメモリ上に読み込むことはない。読み取れないフレーム、
+    /// またはLSNが単調に増加していないフレームに到達した場合、それ以降は読み取らない。
+    /// イテレータのDrop後、以降の書き込みはログの末尾に追記される。
+    pub fn iter_records<K, V>(&mut self) -> WALIterator<'_, K, V>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        self.iter_records_from(0)
+    }
+
+    /// ログの先頭からoffset(bytes)の位置以降のレコードを1つずつ読み取るイテレータを返す
+    fn iter_records_from<K, V>(&mut self, offset: u64) -> WALIterator<'_, K, V>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        let error = self.file.seek(SeekFrom::Start(offset)).err();
+        WALIterator {
+            wal: self,
+            start: offset,
+            position: offset,
+            record_offset: offset,
+            lsn: 0,
+            done: false,
+            finished: false,
+            error: error.map(DatabaseError::from),
+            stopped: Option::None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// 読み取れないフレームを読み飛ばしながら、書き込まれているレコードを可能な限り取得する
+    ///
+    /// 読み飛ばした破損はそれぞれ`CorruptionEvent`として返される。連続した破損は1つにまとめられる。
+    pub fn read_log_lenient<K, V>(&mut self) -> Result<LenientRead<LogRecord<K, V>>, DatabaseError>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        let (records, events) = self.read_log_lenient_with_lsn()?;
+        let records = records.into_iter().map(|(_, record)| record).collect();
+        Result::Ok((records, events))
+    }
+
+    /// 読み取れないフレームを読み飛ばしながら、書き込まれているレコードをそれぞれのLSNと共に取得する
+    ///
+    /// フレームを読み取れない位置、またはLSNが単調に増加していない位置では、1 byteずつ位置を進めて
+    /// 次に読み取れるフレームを探す。
+    pub fn read_log_lenient_with_lsn<K, V>(
+        &mut self,
+    ) -> Result<LenientRead<LsnRecord<K, V>>, DatabaseError>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        let content = self.read_all().context("reading the WAL")?;
+        let (frames, events) = scan_frames::<K, V>(&content);
+        let records: Vec<LsnRecord<K, V>> = frames
+            .into_iter()
+            .map(|(_, lsn, record)| (lsn, record))
+            .collect();
+        // 以降の書き込みがログの末尾に追記されるようにする
+        self.bytes_since_checkpoint = self.file.seek(SeekFrom::End(0))?;
+        self.set_read_records(&records);
+        Result::Ok((records, events))
+    }
+
+    /// ログの先頭からすべてのフレームのチェックサムを検証し、レコードを反映せずに結果を返す
+    ///
+    /// 読み取れない位置は`read_log_lenient`と同様に読み飛ばす。ログは変更せず、以降の書き込みは
+    /// ログの末尾に追記される。CheckpointMarker・Noopレコードはいずれのトランザクションにも含めない。
+    pub fn verify_integrity<K, V>(&mut self) -> Result<VerificationReport, DatabaseError>
+    where
+        K: DeserializeOwned + Debug,
+        V: DeserializeOwned + Debug,
+    {
+        let content = self.read_all()?;
+        self.file.seek(SeekFrom::End(0))?;
+        let (records, events) = scan_frames::<K, V>(&content);
+        let mut transactions = Vec::new();
+        let mut current: Option<TransactionSummary> = Option::None;
+        for (_, lsn, record) in &records {
+            let outcome = match record {
+                LogRecord::CheckpointMarker { .. } | LogRecord::Noop { .. } => continue,
+                LogRecord::Commit => TransactionOutcome::Commit,
+                LogRecord::Abort => TransactionOutcome::Abort,
+                _ => TransactionOutcome::Incomplete,
+            };
+            let summary = current.get_or_insert(TransactionSummary {
+                first_lsn: *lsn,
+                last_lsn: *lsn,
+                record_count: 0,
+                outcome,
+            });
+            summary.last_lsn = *lsn;
+            summary.record_count += 1;
+            summary.outcome = outcome;
+            if outcome != TransactionOutcome::Incomplete {
+                transactions.extend(current.take());
+            }
+        }
+        transactions.extend(current);
+        Result::Ok(VerificationReport {
+            total_records: records.len(),
+            corrupt_records: events.len(),
+            corrupt_offsets: events.iter().map(|event| event.offset).collect(),
+            transactions,
+        })
+    }
+
+    /// ログの先頭から末尾までの内容を読み取る
+    fn read_all(&mut self) -> Result<Vec<u8>, DatabaseError> {
+        self.file.seek(SeekFrom::Start(0))?;
+        let mut content = Vec::new();
+        self.file.get_mut().read_to_end(&mut content)?;
+        Result::Ok(content)
+    }
+
+    /// 読み取ったレコードをもとに、以降のLSNとレコード数・Commit数を設定する
+    fn set_read_records<K, V>(&mut self, records: &[LsnRecord<K, V>])
+    where
+        K: Debug,
+        V: Debug,
+    {
+        if let Option::Some((lsn, _)) = records.last() {
+            self.advance_lsn(*lsn);
+        }
+        self.records = records.len();
+        self.commits = records
+            .iter()
+            .filter(|(_, r)| matches!(r, LogRecord::Commit))
+            .count();
+    }
+
+    /// ログの先頭が旧形式(チェックサムのアルゴリズム、またはLSNを持たない)のフレームとして
+    /// 解釈できるかどうかを返す
+    fn is_legacy_layout(&mut self) -> Result<bool, DatabaseError> {
+        self.file.seek(SeekFrom::Start(0))?;
+        if self.read_sha256_frame().is_ok() {
+            return Result::Ok(true);
+        }
+        self.file.seek(SeekFrom::Start(0))?;
+        Result::Ok(self.read_legacy_frame().is_ok())
+    }
+
+    /// 旧形式のログを現在の形式に書き換える
+    ///
+    /// 旧形式には、レコード本体がJSONで記録されたもの、フレームがチェックサムのアルゴリズムを
+    /// 持たないもの、フレームがLSNを持たないものがある。
+    /// 読み取れたレコードの数を返す。
+    pub fn migrate_log_format<K, V>(&mut self) -> Result<usize, DatabaseError>
+    where
+        K: Serialize + DeserializeOwned + Debug,
+        V: Serialize + DeserializeOwned + Debug,
+    {
+        self.file.seek(SeekFrom::Start(0))?;
+        let mut bodies = Vec::new();
+        while let Result::Ok((_, body, _)) = self.read_frame() {
+            bodies.push(body);
+        }
+        if bodies.is_empty() {
+            self.file.seek(SeekFrom::Start(0))?;
+            while let Result::Ok((_, body)) = self.read_sha256_frame() {
+                bodies.push(body);
+            }
+        }
+        if bodies.is_empty() {
+            self.file.seek(SeekFrom::Start(0))?;
+            while let Result::Ok(body) = self.read_legacy_frame() {
+                bodies.push(body);
+            }
+        }
+        let mut records: Vec<LogRecord<K, V>> = Vec::new();
+        for body in bodies {
+            let record = match decode_record(&body) {
+                Result::Err(DatabaseError::LegacyLogFormat) => serde_json::from_slice(&body)?,
+                other => other?,
+            };
+            records.push(record);
+        }
+        self.clear()?;
+        for record in &records {
+            self.write_log(record, false)?;
+        }
+        self.file.flush()?;
+        self.file.get_mut().sync_all()?;
+        Result::Ok(records.len())
+    }
+
+    /// `Database::export_wal_as_json`により書き出されたJSONのレコードを、ログの末尾に書き込む
+    ///
+    /// 書き出された際のフレームの開始位置とLSNは使用せず、新たなLSNを割り当てる。
+    /// 書き込んだレコードの数を返す。
+    pub fn import_from_json<K, V>(&mut self, path: impl AsRef<Path>) -> Result<usize, DatabaseError>
+    where
+        K: Serialize + DeserializeOwned + Debug,
+        V: Serialize + DeserializeOwned + Debug,
+    {
+        let content = std::fs::read(path)?;
+        let exported: Vec<ExportedRecord<K, V>> = serde_json::from_slice(&content)?;
+        for entry in &exported {
+            self.write_log(&entry.record, false)?;
         }
-        return Result::Ok(result);
+        self.file.flush()?;
+        self.file.get_mut().sync_all()?;
+        Result::Ok(exported.len())
     }
 
     /// 現在ファイルシステム上に書き込まれているレコードを1つ読み取る。
-    fn read_log_entry<K, V>(&mut self) -> Result<LogRecord<K, V>, DatabaseError>
+    ///
+    /// offsetは読み取るフレームの開始位置で、レコード本体を解釈できなかった場合のエラーに含める。
+    fn read_log_entry<K, V>(
+        &mut self,
+        offset: u64,
+    ) -> Result<(u64, LogRecord<K, V>, usize), DatabaseError>
     where
         K: DeserializeOwned + Debug,
         V: DeserializeOwned + Debug,
     {
+        let (lsn, body, len) = self.read_frame()?;
+        let record = match decode_record(&body) {
+            Result::Ok(record) => record,
+            Result::Err(DatabaseError::LegacyLogFormat) => {
+                return Result::Err(DatabaseError::LegacyLogFormat)
+            }
+            Result::Err(e) => {
+                return Result::Err(DatabaseError::InvalidLogError {
+                    message: format!("Failed to parse the record at offset {}: {}", offset, e),
+                })
+            }
+        };
+        Result::Ok((lsn, record, len))
+    }
+
+    /// フレームを1つ読み取り、チェックサムを検証した上でLSNとレコード本体、フレームのバイト数を返す
+    fn read_frame(&mut self) -> Result<(u64, Vec<u8>, usize), DatabaseError> {
+        let lsn = self.file.get_mut().read_u64::<LittleEndian>()?;
+        let byte = self.file.get_mut().read_u8()?;
+        let algorithm = match ChecksumAlgorithm::from_byte(byte) {
+            Option::Some(algorithm) => algorithm,
+            Option::None => {
+                return Result::Err(DatabaseError::InvalidLogError {
+                    message: format!("Unknown checksum algorithm {} at LSN {}", byte, lsn),
+                })
+            }
+        };
+        let mut actual_checksum = vec![0u8; algorithm.checksum_len()];
+        self.file.get_mut().read_exact(&mut actual_checksum)?;
+        let len = self.file.get_mut().read_u64::<LittleEndian>()?;
+        let buf = self.read_body(len)?;
+
+        let expected_checksum = frame_checksum(algorithm, lsn, &buf);
+        if actual_checksum != expected_checksum {
+            return Result::Err(DatabaseError::InvalidLogError {
+                message: format!(
+                    "Checksum mismatch at LSN {}: expected {:x?}, but {:x?}. Body was {:x?}",
+                    lsn, expected_checksum, actual_checksum, buf
+                ),
+            });
+        }
+        let len = 8 + 1 + algorithm.checksum_len() + 8 + buf.len();
+        Result::Ok((lsn, buf, len))
+    }
+
+    /// チェックサムのアルゴリズムを持たない旧形式のフレームを1つ読み取り、
+    /// ハッシュを検証した上でLSNとレコード本体を返す
+    ///
+    /// 旧形式のフレームは`[LSN (8 bytes)][SHA256 (32 bytes)][len (8 bytes)][body]`の形式で記録される。
+    fn read_sha256_frame(&mut self) -> Result<(u64, Vec<u8>), DatabaseError> {
+        let lsn = self.file.get_mut().read_u64::<LittleEndian>()?;
+        let mut actual_hash = [0u8; 32];
+        self.file.get_mut().read_exact(&mut actual_hash)?;
+        let len = self.file.get_mut().read_u64::<LittleEndian>()?;
+        let buf = self.read_body(len)?;
+
+        let expected_hash = frame_checksum(ChecksumAlgorithm::Sha256, lsn, &buf);
+        if actual_hash != expected_hash[..] {
+            return Result::Err(DatabaseError::InvalidLogError {
+                message: format!(
+                    "Hash mismatch at LSN {}: expected {:x?}, but {:x?}. Body was {:x?}",
+                    lsn, expected_hash, actual_hash, buf
+                ),
+            });
+        }
+        Result::Ok((lsn, buf))
+    }
+
+    /// LSNを持たない旧形式のフレームを1つ読み取り、ハッシュを検証した上でレコード本体を返す
+    fn read_legacy_frame(&mut self) -> Result<Vec<u8>, DatabaseError> {
         let mut actual_hash = [0u8; 32];
-        self.file.read_exact(&mut actual_hash)?;
-        let len = self.file.read_u64::<LittleEndian>()? as usize;
-        let mut buf = vec![0u8; len];
-        self.file.read_exact(&mut buf[0..len])?;
+        self.file.get_mut().read_exact(&mut actual_hash)?;
+        let len = self.file.get_mut().read_u64::<LittleEndian>()?;
+        let buf = self.read_body(len)?;
 
         let mut hasher = Sha256::new();
         hasher.input(&buf[..]);
         let expected_hash = hasher.result();
 
-        if &actual_hash != &expected_hash[..] {
+        if actual_hash != expected_hash[..] {
             return Result::Err(DatabaseError::InvalidLogError {
                 message: format!(
                     "Hash mismatch: expected {:x?}, but {:x?}. Body was {:x?}",
                     expected_hash, actual_hash, buf
-                )
-                .to_string(),
+                ),
+            });
+        }
+        Result::Ok(buf)
+    }
+
+    /// レコード本体をlenバイト読み取る
+    ///
+    /// 破損したフレームの長さを信用して巨大な領域を確保しないよう、実際に読み取れた分だけを確保する。
+    fn read_body(&mut self, len: u64) -> Result<Vec<u8>, DatabaseError> {
+        let mut buf = Vec::new();
+        Read::by_ref(self.file.get_mut())
+            .take(len)
+            .read_to_end(&mut buf)?;
+        if (buf.len() as u64) < len {
+            return Result::Err(DatabaseError::InvalidLogError {
+                message: format!("Truncated frame: expected {} bytes, but {}", len, buf.len()),
             });
         }
-        let body = String::from_utf8(buf)?;
-        let entry: LogRecord<K, V> = serde_json::from_str(body.as_str())?;
-        return Result::Ok(entry);
+        Result::Ok(buf)
+    }
+}
+
+/// contentに含まれるフレームを、読み取れない位置を1 byteずつ読み飛ばしながら先頭から解釈する
+///
+/// 読み取れたレコードをフレームの開始位置・LSNと共に返し、連続した破損は1つの`CorruptionEvent`にまとめる。
+/// LSNが単調に増加していないフレームは破損として扱う。
+fn scan_frames<K, V>(content: &[u8]) -> LenientRead<(u64, u64, LogRecord<K, V>)>
+where
+    K: DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    let mut records: Vec<(u64, u64, LogRecord<K, V>)> = Vec::new();
+    let mut events: Vec<CorruptionEvent> = Vec::new();
+    let mut position = 0;
+    let mut corrupted = false;
+    while position < content.len() {
+        let last_lsn = records.last().map_or(0, |(_, lsn, _)| *lsn);
+        let result = parse_frame(&content[position..]).and_then(|(lsn, body, len)| {
+            if lsn <= last_lsn {
+                return Result::Err(DatabaseError::InvalidLogError {
+                    message: format!("Non-increasing LSN {} after {}", lsn, last_lsn),
+                });
+            }
+            Result::Ok((lsn, decode_record(body)?, len))
+        });
+        match result {
+            Result::Ok((lsn, record, len)) => {
+                records.push((position as u64, lsn, record));
+                position += len;
+                corrupted = false;
+            }
+            Result::Err(e) => {
+                match events.last_mut() {
+                    Option::Some(event) if corrupted => event.skipped += 1,
+                    _ => events.push(CorruptionEvent {
+                        offset: position as u64,
+                        skipped: 1,
+                        last_valid_lsn: last_lsn,
+                        message: e.to_string(),
+                    }),
+                }
+                position += 1;
+                corrupted = true;
+            }
+        }
+    }
+    (records, events)
+}
+
+/// 指定されたアルゴリズムでdataのチェックサムを計算する
+fn compute_checksum(algorithm: ChecksumAlgorithm, data: &[u8]) -> Vec<u8> {
+    match algorithm {
+        ChecksumAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            hasher.input(data);
+            hasher.result().to_vec()
+        }
+        ChecksumAlgorithm::Crc32c => crc32c::crc32c(data).to_le_bytes().to_vec(),
     }
 }
 
+/// dataの先頭のフレームを解釈し、チェックサムを検証した上でLSN・レコード本体・フレームのバイト数を返す
+fn parse_frame(data: &[u8]) -> Result<(u64, &[u8], usize), DatabaseError> {
+    let truncated = || DatabaseError::InvalidLogError {
+        message: format!("Truncated frame: only {} bytes remain", data.len()),
+    };
+    if data.len() < 9 {
+        return Result::Err(truncated());
+    }
+    let lsn = LittleEndian::read_u64(&data[..8]);
+    let algorithm = match ChecksumAlgorithm::from_byte(data[8]) {
+        Option::Some(algorithm) => algorithm,
+        Option::None => {
+            return Result::Err(DatabaseError::InvalidLogError {
+                message: format!("Unknown checksum algorithm {} at LSN {}", data[8], lsn),
+            })
+        }
+    };
+    let header_len = algorithm.frame_header_len();
+    if data.len() < header_len {
+        return Result::Err(truncated());
+    }
+    let actual_checksum = &data[9..header_len - 8];
+    let len = LittleEndian::read_u64(&data[header_len - 8..header_len]);
+    if len > (data.len() - header_len) as u64 {
+        return Result::Err(truncated());
+    }
+    let body = &data[header_len..header_len + len as usize];
+    let expected_checksum = frame_checksum(algorithm, lsn, body);
+    if actual_checksum != &expected_checksum[..] {
+        return Result::Err(DatabaseError::InvalidLogError {
+            message: format!("Checksum mismatch at LSN {}", lsn),
+        });
+    }
+    Result::Ok((lsn, body, header_len + len as usize))
+}
+
+/// dataの先頭のフレームが、dataの末尾を超える長さを持つかどうかを返す
+fn is_truncated_frame(data: &[u8]) -> bool {
+    if data.len() < 9 {
+        return true;
+    }
+    let header_len = match ChecksumAlgorithm::from_byte(data[8]) {
+        Option::Some(algorithm) => algorithm.frame_header_len(),
+        Option::None => return false,
+    };
+    if data.len() < header_len {
+        return true;
+    }
+    let len = LittleEndian::read_u64(&data[header_len - 8..header_len]);
+    len > (data.len() - header_len) as u64
+}
+
+/// LSNとレコード本体を対象とするチェックサムを計算する
+fn frame_checksum(algorithm: ChecksumAlgorithm, lsn: u64, body: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + body.len());
+    data.extend_from_slice(&lsn.to_le_bytes());
+    data.extend_from_slice(body);
+    compute_checksum(algorithm, &data)
+}
+
+#[cfg(not(feature = "json-wal"))]
+fn encode_record<K, V>(record: &LogRecord<K, V>) -> Result<Vec<u8>, DatabaseError>
+where
+    K: Serialize + Debug,
+    V: Serialize + Debug,
+{
+    Result::Ok(bincode::serialize(record)?)
+}
+
+#[cfg(feature = "json-wal")]
+fn encode_record<K, V>(record: &LogRecord<K, V>) -> Result<Vec<u8>, DatabaseError>
+where
+    K: Serialize + Debug,
+    V: Serialize + Debug,
+{
+    Result::Ok(serde_json::to_vec(record)?)
+}
+
+/// レコード本体を復元する
+///
+/// bincodeとして解釈できないがJSONとしては解釈できる場合、旧形式のログとみなして
+/// `DatabaseError::LegacyLogFormat`を返す。
+#[cfg(not(feature = "json-wal"))]
+fn decode_record<K, V>(body: &[u8]) -> Result<LogRecord<K, V>, DatabaseError>
+where
+    K: DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    match bincode::deserialize(body) {
+        Result::Ok(entry) => Result::Ok(entry),
+        Result::Err(e) => match serde_json::from_slice::<LogRecord<K, V>>(body) {
+            Result::Ok(_) => Result::Err(DatabaseError::LegacyLogFormat),
+            Result::Err(_) => Result::Err(e.into()),
+        },
+    }
+}
+
+#[cfg(feature = "json-wal")]
+fn decode_record<K, V>(body: &[u8]) -> Result<LogRecord<K, V>, DatabaseError>
+where
+    K: DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    let body = String::from_utf8(body.to_vec())?;
+    Result::Ok(serde_json::from_str(body.as_str())?)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::log::{LogRecord, WALManager};
+    use crate::error::DatabaseError;
+    use crate::log::{ChecksumAlgorithm, LogRecord, WALManager};
 
     #[test]
     fn log_rw() {
@@ -164,4 +1450,285 @@ mod tests {
             assert_eq!(result[0], record);
         }
     }
+
+    #[test]
+    fn corrupt_frame_is_reported() {
+        let mut wal = WALManager::in_memory();
+        let records: Vec<LogRecord<i32, i32>> =
+            vec![LogRecord::Create { key: 1, value: 2 }, LogRecord::Commit];
+        for record in &records {
+            wal.write_log(record, false).unwrap();
+        }
+        assert_eq!(
+            wal.read_log_with_lsn_checked::<i32, i32>(0).unwrap().len(),
+            2
+        );
+        // 解釈できないレコードの後にもフレームが続く
+        match wal.read_log_with_lsn_checked::<String, Vec<String>>(0) {
+            Result::Err(DatabaseError::CorruptWALOnOpen {
+                corrupt_offset,
+                total_bytes,
+                message,
+            }) => {
+                assert_eq!(corrupt_offset, 0);
+                assert_eq!(total_bytes, wal.bytes_since_checkpoint());
+                assert!(message.contains("at offset 0"), "{}", message);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn in_memory_log_rw() {
+        let mut wal = WALManager::in_memory();
+        let records = vec![
+            LogRecord::Create { key: 1, value: 2 },
+            LogRecord::Commit,
+            LogRecord::Delete { key: 1 },
+        ];
+        for record in &records {
+            wal.write_log(record, false).unwrap();
+        }
+        assert_eq!(wal.read_log::<i32, i32>().unwrap(), records);
+        wal.write_log(&LogRecord::<i32, i32>::Abort, true).unwrap();
+        assert_eq!(wal.read_log::<i32, i32>().unwrap().len(), 4);
+        wal.clear().unwrap();
+        assert!(wal.read_log::<i32, i32>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn iter_records() {
+        let mut wal = WALManager::in_memory();
+        for key in 0..3 {
+            wal.write_log(&LogRecord::Create { key, value: key }, false)
+                .unwrap();
+        }
+        let lsn = wal.current_lsn();
+        {
+            // 途中までの読み取りでDropしても、以降の書き込みは末尾に追記される
+            let mut iter = wal.iter_records::<i32, i32>();
+            assert_eq!(
+                iter.next().unwrap().unwrap(),
+                LogRecord::Create { key: 0, value: 0 }
+            );
+            assert_eq!(iter.lsn(), lsn - 2);
+        }
+        wal.write_log(&LogRecord::<i32, i32>::Commit, false)
+            .unwrap();
+        let records: Vec<LogRecord<i32, i32>> = wal
+            .iter_records()
+            .collect::<Result<Vec<_>, DatabaseError>>()
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[3], LogRecord::Commit);
+        assert_eq!(wal.read_log::<i32, i32>().unwrap(), records);
+    }
+
+    #[test]
+    fn write_buffer() {
+        let mut wal = WALManager::new("write_buffer.log").unwrap();
+        wal.clear().unwrap();
+        wal.write_log(&LogRecord::Create { key: 1, value: 2 }, false)
+            .unwrap();
+        let mut reader = WALManager::new("write_buffer.log").unwrap();
+        assert!(reader.read_log::<i32, i32>().unwrap().is_empty());
+
+        wal.flush_buffer().unwrap();
+        assert_eq!(reader.read_log::<i32, i32>().unwrap().len(), 1);
+
+        // fsyncを伴う書き込みはバッファの内容も書き出す
+        wal.write_log(&LogRecord::Update { key: 1, value: 3 }, false)
+            .unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Commit, true).unwrap();
+        assert_eq!(reader.read_log::<i32, i32>().unwrap().len(), 3);
+
+        wal.set_buffer_size(0).unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Abort, false).unwrap();
+        assert_eq!(reader.read_log::<i32, i32>().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn lsn() {
+        let mut wal = WALManager::in_memory();
+        assert_eq!(wal.current_lsn(), 0);
+        wal.write_log(&LogRecord::Create { key: 1, value: 2 }, false)
+            .unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Commit, false)
+            .unwrap();
+        assert_eq!(wal.current_lsn(), 2);
+        let lsns: Vec<u64> = wal
+            .read_log_with_lsn::<i32, i32>()
+            .unwrap()
+            .into_iter()
+            .map(|(lsn, _)| lsn)
+            .collect();
+        assert_eq!(lsns, vec![1, 2]);
+
+        // LSNはログのクリアを跨いで単調に増加する
+        wal.clear().unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Abort, false).unwrap();
+        assert_eq!(wal.current_lsn(), 3);
+        wal.advance_lsn(10);
+        wal.write_log(&LogRecord::<i32, i32>::Abort, false).unwrap();
+        assert_eq!(wal.current_lsn(), 11);
+    }
+
+    #[test]
+    fn lsn_restored_on_open() {
+        {
+            let mut wal = WALManager::new("lsn_restored.log").unwrap();
+            wal.clear().unwrap();
+            for _ in 0..3 {
+                wal.write_log(&LogRecord::<i32, i32>::Commit, true).unwrap();
+            }
+        }
+        let mut wal = WALManager::new("lsn_restored.log").unwrap();
+        assert_eq!(wal.read_log::<i32, i32>().unwrap().len(), 3);
+        assert_eq!(wal.current_lsn(), 3);
+    }
+
+    #[test]
+    fn lenient_read() {
+        let records: Vec<LogRecord<i32, i32>> = (1..=3)
+            .map(|x| LogRecord::Create {
+                key: x,
+                value: x * 10,
+            })
+            .collect();
+        let _ = std::fs::remove_dir_all("lenient_log.log");
+        let mut wal = WALManager::new("lenient_log.log").unwrap();
+        for record in &records {
+            wal.write_log(record, true).unwrap();
+        }
+        // 2番目のフレームの本体を壊し、末尾に書き込み途中のフレームを残す
+        let path = "lenient_log.log/lenient_log.000001.wal";
+        let mut content = std::fs::read(path).unwrap();
+        let frame_len = content.len() / 3;
+        content[frame_len * 2 - 1] ^= 0xff;
+        content.extend_from_slice(&[0u8; 10]);
+        std::fs::write(path, &content).unwrap();
+
+        let mut wal = WALManager::new("lenient_log.log").unwrap();
+        assert_eq!(wal.read_log::<i32, i32>().unwrap(), records[..1]);
+        let (result, events) = wal.read_log_lenient::<i32, i32>().unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0], records[0]);
+        assert_eq!(result[1], records[2]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].offset, frame_len as u64);
+        assert_eq!(events[0].skipped, frame_len as u64);
+        assert_eq!(events[0].last_valid_lsn, 1);
+        assert_eq!(events[1].offset, frame_len as u64 * 3);
+        assert_eq!(events[1].skipped, 10);
+    }
+
+    #[test]
+    fn mixed_checksum_algorithms() {
+        let mut wal = WALManager::in_memory();
+        let records = vec![
+            LogRecord::Create { key: 1, value: 2 },
+            LogRecord::Update { key: 1, value: 3 },
+            LogRecord::Commit,
+        ];
+        wal.write_log(&records[0], false).unwrap();
+        wal.set_checksum_algorithm(ChecksumAlgorithm::Crc32c);
+        wal.write_log(&records[1], false).unwrap();
+        wal.set_checksum_algorithm(ChecksumAlgorithm::Sha256);
+        wal.write_log(&records[2], false).unwrap();
+        assert_eq!(wal.read_log::<i32, i32>().unwrap(), records);
+    }
+
+    #[test]
+    fn sha256_frame_migration() {
+        use std::io::Write;
+
+        let record = LogRecord::Create {
+            key: 123,
+            value: 456,
+        };
+        {
+            // チェックサムのアルゴリズムを持たない旧形式のフレーム: [LSN][SHA256][len][body]
+            let body = super::encode_record(&record).unwrap();
+            let hash = super::frame_checksum(ChecksumAlgorithm::Sha256, 7, &body);
+            let _ = std::fs::remove_dir_all("sha256_frame_log.log");
+            let mut file = std::fs::File::create("sha256_frame_log.log").unwrap();
+            file.write_all(&7u64.to_le_bytes()).unwrap();
+            file.write_all(&hash).unwrap();
+            file.write_all(&(body.len() as u64).to_le_bytes()).unwrap();
+            file.write_all(&body).unwrap();
+        }
+        {
+            let mut wal = WALManager::new("sha256_frame_log.log").unwrap();
+            match wal.read_log::<i32, i32>() {
+                Result::Err(DatabaseError::LegacyLogFormat) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert_eq!(wal.migrate_log_format::<i32, i32>().unwrap(), 1);
+        }
+        let mut wal = WALManager::new("sha256_frame_log.log").unwrap();
+        assert_eq!(wal.read_log().unwrap(), vec![record]);
+    }
+
+    #[test]
+    fn pre_lsn_log_migration() {
+        use crate::error::DatabaseError;
+        use sha2::{Digest, Sha256};
+        use std::io::Write;
+
+        let record = LogRecord::Create {
+            key: 123,
+            value: 456,
+        };
+        {
+            // LSNを持たない旧形式のフレーム: [SHA256][len][body]
+            let body = super::encode_record(&record).unwrap();
+            let mut hasher = Sha256::new();
+            hasher.input(&body);
+            let _ = std::fs::remove_dir_all("pre_lsn_log.log");
+            let mut file = std::fs::File::create("pre_lsn_log.log").unwrap();
+            file.write_all(&hasher.result()[..]).unwrap();
+            file.write_all(&(body.len() as u64).to_le_bytes()).unwrap();
+            file.write_all(&body).unwrap();
+        }
+        {
+            let mut wal = WALManager::new("pre_lsn_log.log").unwrap();
+            match wal.read_log::<i32, i32>() {
+                Result::Err(DatabaseError::LegacyLogFormat) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert_eq!(wal.migrate_log_format::<i32, i32>().unwrap(), 1);
+        }
+        let mut wal = WALManager::new("pre_lsn_log.log").unwrap();
+        assert_eq!(wal.read_log_with_lsn().unwrap(), vec![(1, record)]);
+    }
+
+    #[cfg(not(feature = "json-wal"))]
+    #[test]
+    fn legacy_log_migration() {
+        use crate::error::DatabaseError;
+
+        let record = LogRecord::Create {
+            key: 123,
+            value: 456,
+        };
+        {
+            let mut wal = WALManager::new("legacy_log.log").unwrap();
+            wal.clear().unwrap();
+            let body = serde_json::to_vec(&record).unwrap();
+            wal.write_frame(&body, true).unwrap();
+        }
+        {
+            let mut wal = WALManager::new("legacy_log.log").unwrap();
+            match wal.read_log::<i32, i32>() {
+                Result::Err(DatabaseError::LegacyLogFormat) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert_eq!(wal.migrate_log_format::<i32, i32>().unwrap(), 1);
+        }
+        {
+            let mut wal = WALManager::new("legacy_log.log").unwrap();
+            let result = wal.read_log().unwrap();
+            assert_eq!(result, vec![record]);
+        }
+    }
 }
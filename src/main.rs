@@ -4,12 +4,12 @@ use mikrodb::database::Database;
 use std::result::Result;
 
 fn main() {
-    let mut db: Database<i32, i32> = Database::new("main.log", "main.db").unwrap();
+    let mut db: Database<i32, i32> = Database::with_defaults("main.log", "main.db").unwrap();
     let mut tx = db.begin_transaction().unwrap();
 
     println!("Start");
     for k in 0..100000 {
-        match tx.read(k) {
+        match tx.read_silent(k) {
             Result::Err(_) => {
                 println!("Record ({}, NA)", k);
                 tx.create(k, -1).unwrap();
@@ -0,0 +1,59 @@
+use std::ops::{Add, Sub};
+
+/// 値が数値として加減算できることを表す
+///
+/// `Transaction::atomic_increment`・`Transaction::atomic_decrement`で使用する。
+/// 整数型の加減算がオーバーフローする場合、`checked_add`・`checked_sub`は`None`を返す。
+pub trait Numeric: Copy + Add<Output = Self> + Sub<Output = Self> {
+    /// 加法の単位元を返す
+    fn zero() -> Self;
+
+    /// オーバーフローしない場合のみ、self + rhsを返す
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    /// オーバーフローしない場合のみ、self - rhsを返す
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_numeric_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                fn zero() -> Self {
+                    0
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_numeric_for_float {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                fn zero() -> Self {
+                    0.0
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    Option::Some(self + rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    Option::Some(self - rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_numeric_for_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_numeric_for_float!(f32, f64);
@@ -0,0 +1,73 @@
+use crate::error::DatabaseError;
+use crate::store::{KVStore, Store, StoreRange};
+use serde::de::DeserializeOwned;
+use std::hash::Hash;
+use std::ops::Bound;
+
+/// コミット済みの内容をキーの昇順に一定の件数ずつ読み取るページネータを表す
+///
+/// 最後に返したキーのみを保持し、次のページはそのキーより大きいキーから読み取る。
+pub struct Paginator<'a, K, V>
+where
+    K: Ord + Clone + Hash,
+    V: Clone + DeserializeOwned,
+{
+    data: &'a Store<K, V>,
+    page_size: usize,
+    last_key: Option<K>,
+}
+
+impl<'a, K, V> Paginator<'a, K, V>
+where
+    K: Ord + Clone + Hash,
+    V: Clone + DeserializeOwned,
+{
+    pub(crate) fn new(data: &'a Store<K, V>, page_size: usize) -> Self {
+        Paginator {
+            data,
+            page_size,
+            last_key: Option::None,
+        }
+    }
+
+    /// 最後に返したキーより後のpage_size件を返す(page_sizeが0の場合は常に空)
+    ///
+    /// `StorageBackend::Hashed`の場合は`DatabaseError::UnsupportedOperation`となる。
+    pub fn next_page(&mut self) -> Result<Vec<(K, V)>, DatabaseError> {
+        let page: Vec<(K, V)> = self
+            .remaining()?
+            .take(self.page_size)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        if let Option::Some((k, _)) = page.last() {
+            self.last_key = Option::Some(k.clone());
+        }
+        Result::Ok(page)
+    }
+
+    /// 次のページが1件以上のキーバリューペアを持つかどうかを返す
+    pub fn has_next(&self) -> bool {
+        self.remaining()
+            .map(|mut iter| iter.next().is_some())
+            .unwrap_or(false)
+    }
+
+    /// 最初のページに戻る
+    pub fn reset(&mut self) {
+        self.last_key = Option::None;
+    }
+
+    /// 最後に返したキー(まだ返していない場合はNone)
+    pub fn last_key(&self) -> Option<&K> {
+        self.last_key.as_ref()
+    }
+
+    /// 最後に返したキーより後のキーバリューペアを走査する
+    fn remaining(&self) -> Result<StoreRange<'a, K, V>, DatabaseError> {
+        let start = match &self.last_key {
+            Option::Some(k) => Bound::Excluded(k.clone()),
+            Option::None => Bound::Unbounded,
+        };
+        self.data.range(start, Bound::Unbounded)
+    }
+}
@@ -0,0 +1,20 @@
+/// キーが別のキーを接頭辞として持つかどうかを判定できることを表す
+///
+/// `Transaction::scan_prefix`で使用する。接頭辞を共有するキーは、キーの順序において
+/// 接頭辞以上の連続した範囲に並んでいなければならない。
+pub trait HasPrefix {
+    /// prefixを接頭辞として持つかどうかを返す
+    fn starts_with(&self, prefix: &Self) -> bool;
+}
+
+impl HasPrefix for String {
+    fn starts_with(&self, prefix: &Self) -> bool {
+        self.as_str().starts_with(prefix.as_str())
+    }
+}
+
+impl HasPrefix for Vec<u8> {
+    fn starts_with(&self, prefix: &Self) -> bool {
+        self.as_slice().starts_with(prefix.as_slice())
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::DatabaseError;
+use crate::log::{LogRecord, WALIterator};
+
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+
+/// Commitされた1つのトランザクションを、レプリカに反映するための情報を表す
+///
+/// 範囲の削除などは反映先の内容に依存するため、変更の一覧ではなく内容を変更するWALレコードを
+/// ログに書き込まれた順に保持する。セーブポイントへのロールバックにより破棄されたレコードは含まない。
+#[derive(Debug, PartialEq)]
+pub struct ReplicationEvent<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    lsn: u64,
+    records: Vec<LogRecord<K, V>>,
+}
+
+impl<K, V> ReplicationEvent<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    /// トランザクションのCommitレコードのLSNを返す
+    pub fn lsn(&self) -> u64 {
+        self.lsn
+    }
+
+    /// 内容を変更するWALレコードを返す
+    pub fn records(&self) -> &[LogRecord<K, V>] {
+        &self.records
+    }
+
+    pub(crate) fn into_records(self) -> Vec<LogRecord<K, V>> {
+        self.records
+    }
+}
+
+/// `Database::replication_stream`により作成される、Commitされたトランザクションを順に読み取るイテレータ
+///
+/// 読み取れないフレームに到達した場合、それ以降は読み取らない。
+pub struct ReplicationStream<'a, K, V>
+where
+    K: DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    records: Option<WALIterator<'a, K, V>>,
+    error: Option<DatabaseError>,
+    start_lsn: u64,
+    checkpoint_lsn: u64,
+    queue: Vec<LogRecord<K, V>>,
+}
+
+impl<'a, K, V> ReplicationStream<'a, K, V>
+where
+    K: DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    pub(crate) fn new(records: WALIterator<'a, K, V>, start_lsn: u64, checkpoint_lsn: u64) -> Self {
+        ReplicationStream {
+            records: Option::Some(records),
+            error: Option::None,
+            start_lsn,
+            checkpoint_lsn,
+            queue: Vec::new(),
+        }
+    }
+
+    /// 最初にerrorを返して終了するストリームを作成する
+    pub(crate) fn failed(error: DatabaseError) -> Self {
+        ReplicationStream {
+            records: Option::None,
+            error: Option::Some(error),
+            start_lsn: 0,
+            checkpoint_lsn: 0,
+            queue: Vec::new(),
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for ReplicationStream<'a, K, V>
+where
+    K: DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    type Item = Result<ReplicationEvent<K, V>, DatabaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Option::Some(e) = self.error.take() {
+            return Option::Some(Result::Err(e));
+        }
+        let records = self.records.as_mut()?;
+        loop {
+            let record = match records.next()? {
+                Result::Ok(record) => record,
+                Result::Err(e) => return Option::Some(Result::Err(e)),
+            };
+            let lsn = records.lsn();
+            if lsn <= self.checkpoint_lsn {
+                continue;
+            }
+            match record {
+                LogRecord::Commit => {
+                    let records: Vec<LogRecord<K, V>> = std::mem::take(&mut self.queue)
+                        .into_iter()
+                        .filter(|record| !matches!(record, LogRecord::Savepoint { .. }))
+                        .collect();
+                    if lsn >= self.start_lsn && !records.is_empty() {
+                        return Option::Some(Result::Ok(ReplicationEvent { lsn, records }));
+                    }
+                }
+                LogRecord::Abort | LogRecord::CheckpointMarker { .. } => self.queue.clear(),
+                LogRecord::RollbackToSavepoint { id } => {
+                    // 対応するセーブポイントのレコードは、再度戻る場合に備えて残しておく
+                    while let Option::Some(record) = self.queue.last() {
+                        match record {
+                            LogRecord::Savepoint { id: saved, .. } if *saved == id => break,
+                            _ => self.queue.pop(),
+                        };
+                    }
+                }
+                LogRecord::Create { .. }
+                | LogRecord::CreateWithTTL { .. }
+                | LogRecord::Update { .. }
+                | LogRecord::Upsert { .. }
+                | LogRecord::CAS { .. }
+                | LogRecord::Increment { .. }
+                | LogRecord::Decrement { .. }
+                | LogRecord::ClearExpiry { .. }
+                | LogRecord::Delete { .. }
+                | LogRecord::DeleteRange { .. }
+                | LogRecord::Truncate
+                | LogRecord::Batch { .. }
+                | LogRecord::Rename { .. }
+                | LogRecord::Swap { .. }
+                | LogRecord::Savepoint { .. } => self.queue.push(record),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use crate::log::ReadWrite;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::result::Result;
+
+/// 番号付きのセグメントファイルに分割して格納されるログを表す
+///
+/// セグメントはディレクトリ内に`<base>.000001.wal`、`<base>.000002.wal`...として格納され
+/// (`<base>`はディレクトリ名から拡張子を除いたもの)、読み取りの際は番号順に連結した1つのログとして扱う。
+/// 書き込みは常に最後のセグメントの末尾に追記される。書き込みの位置は最後のセグメントのバイト数として
+/// 管理し、Unixではその位置へのpwrite(2)により書き込む。読み取りには書き込み用とは別のファイルを開く。
+#[derive(Debug)]
+pub struct SegmentedLog {
+    dir: PathBuf,
+    base: String,
+    /// セグメントの番号とバイト数(番号の昇順)
+    ///
+    /// 最後のセグメントのバイト数は、次の書き込みの位置を表す。
+    segments: Vec<(u64, u64)>,
+    /// 書き込み中のセグメント(書き込み専用)
+    current: File,
+    /// 読み取り中のセグメントの位置と、そのファイル・ファイル内の位置
+    reader: Option<(usize, File, u64)>,
+    position: u64,
+}
+
+impl SegmentedLog {
+    /// ディレクトリに格納されたログを開く
+    ///
+    /// ディレクトリが存在しない場合は作成する。同じパスにセグメントに分割されていない旧形式の
+    /// ログファイルが存在する場合、それを最初のセグメントとしてディレクトリに移動する。
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, io::Error> {
+        let dir = dir.as_ref().to_path_buf();
+        let base = match dir.file_stem() {
+            Option::Some(stem) => stem.to_string_lossy().into_owned(),
+            Option::None => "wal".to_string(),
+        };
+        let legacy = legacy_path(&dir);
+        if dir.is_file() {
+            fs::rename(&dir, &legacy)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let mut segments = list_segments(&dir, &base)?;
+        if segments.is_empty() {
+            let path = segment_path(&dir, &base, 1);
+            if legacy.is_file() {
+                fs::rename(&legacy, &path)?;
+            } else {
+                File::create(&path)?;
+            }
+            sync_dir(&dir)?;
+            segments = list_segments(&dir, &base)?;
+        }
+        let last = segments.len() - 1;
+        let (current, len) = open_segment(&segment_path(&dir, &base, segments[last].0))?;
+        segments[last].1 = len;
+        Result::Ok(SegmentedLog {
+            dir,
+            base,
+            segments,
+            current,
+            reader: Option::None,
+            position: 0,
+        })
+    }
+
+    /// セグメントの数を返す
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// 全セグメントの合計のバイト数を返す
+    fn total_len(&self) -> u64 {
+        self.segments.iter().map(|(_, len)| len).sum()
+    }
+
+    /// 最後のセグメントの次の番号で、新たなセグメントを作成する
+    fn create_next_segment(&mut self) -> Result<(), io::Error> {
+        let number = self.segments.last().map_or(1, |(number, _)| number + 1);
+        let (current, len) = open_segment(&segment_path(&self.dir, &self.base, number))?;
+        self.current = current;
+        sync_dir(&self.dir)?;
+        self.segments.push((number, len));
+        Result::Ok(())
+    }
+
+    /// ディレクトリ内のセグメントの一覧とバイト数を読み直す
+    fn refresh(&mut self) -> Result<(), io::Error> {
+        let segments = list_segments(&self.dir, &self.base)?;
+        let last = segments.last().map(|(number, _)| *number);
+        if last.is_none() {
+            self.segments.clear();
+            return self.create_next_segment();
+        }
+        let mut segments = segments;
+        if last != self.segments.last().map(|(number, _)| *number) {
+            let (current, len) = open_segment(&segment_path(&self.dir, &self.base, last.unwrap()))?;
+            self.current = current;
+            if let Option::Some((_, last_len)) = segments.last_mut() {
+                *last_len = len;
+            }
+        }
+        self.segments = segments;
+        self.reader = Option::None;
+        Result::Ok(())
+    }
+}
+
+impl Read for SegmentedLog {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let mut offset = self.position;
+        let mut index = 0;
+        while index < self.segments.len() && offset >= self.segments[index].1 {
+            offset -= self.segments[index].1;
+            index += 1;
+        }
+        if index == self.segments.len() || buf.is_empty() {
+            return Result::Ok(0);
+        }
+        let remaining = self.segments[index].1 - offset;
+        let reader = match self.reader.take() {
+            Option::Some((i, file, file_offset)) if i == index && file_offset == offset => file,
+            _ => {
+                let number = self.segments[index].0;
+                let mut file = File::open(segment_path(&self.dir, &self.base, number))?;
+                file.seek(SeekFrom::Start(offset))?;
+                file
+            }
+        };
+        let mut reader = reader;
+        let max = remaining.min(buf.len() as u64) as usize;
+        let n = reader.read(&mut buf[..max])?;
+        self.reader = Option::Some((index, reader, offset + n as u64));
+        self.position += n as u64;
+        Result::Ok(n)
+    }
+}
+
+impl Write for SegmentedLog {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        let offset = self.segment_len();
+        let n = write_at(&mut self.current, buf, offset)?;
+        if let Option::Some((_, len)) = self.segments.last_mut() {
+            *len += n as u64;
+        }
+        self.position = self.total_len();
+        Result::Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.current.flush()
+    }
+}
+
+impl Seek for SegmentedLog {
+    /// ディレクトリ内のセグメントを読み直した上で、連結したログの中の位置に移動する
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, io::Error> {
+        self.refresh()?;
+        let position = match pos {
+            SeekFrom::Start(n) => Option::Some(n),
+            SeekFrom::End(n) => self.total_len().checked_add_signed(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+        };
+        match position {
+            Option::Some(position) => {
+                self.position = position;
+                Result::Ok(position)
+            }
+            Option::None => Result::Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+impl ReadWrite for SegmentedLog {
+    fn sync_all(&mut self) -> Result<(), io::Error> {
+        self.current.sync_all()
+    }
+
+    fn sync_data(&mut self) -> Result<(), io::Error> {
+        self.current.sync_data()
+    }
+
+    /// すべてのセグメントを削除し、次の番号の空のセグメントを作成する
+    ///
+    /// 空のセグメントが1つのみの場合は何もしない。既に削除されていたセグメントは無視する。
+    fn truncate(&mut self) -> Result<(), io::Error> {
+        if self.segments.len() == 1 && self.segment_len() == 0 {
+            self.reader = Option::None;
+            self.position = 0;
+            return Result::Ok(());
+        }
+        let numbers: Vec<u64> = self.segments.iter().map(|(number, _)| *number).collect();
+        self.create_next_segment()?;
+        // 古いセグメントから順に削除し、途中でクラッシュしても新しい側のみが残るようにする
+        for number in numbers {
+            match fs::remove_file(segment_path(&self.dir, &self.base, number)) {
+                Result::Err(e) if e.kind() != io::ErrorKind::NotFound => return Result::Err(e),
+                _ => {}
+            }
+        }
+        sync_dir(&self.dir)?;
+        self.segments.drain(..self.segments.len() - 1);
+        self.reader = Option::None;
+        self.position = 0;
+        Result::Ok(())
+    }
+
+    fn start_segment(&mut self) -> Result<(), io::Error> {
+        self.current.sync_all()?;
+        self.create_next_segment()
+    }
+
+    fn segment_len(&self) -> u64 {
+        self.segments.last().map_or(0, |(_, len)| *len)
+    }
+
+    fn try_clone_file(&self) -> Option<File> {
+        self.current.try_clone().ok()
+    }
+}
+
+/// セグメントのパスを返す
+fn segment_path(dir: &Path, base: &str, number: u64) -> PathBuf {
+    dir.join(format!("{}.{:06}.wal", base, number))
+}
+
+/// ディレクトリへの移動中の旧形式のログファイルのパスを返す
+fn legacy_path(dir: &Path) -> PathBuf {
+    let mut path = dir.as_os_str().to_os_string();
+    path.push(".migrating");
+    PathBuf::from(path)
+}
+
+/// ディレクトリ内のセグメントの番号とバイト数を、番号の昇順に返す
+fn list_segments(dir: &Path, base: &str) -> Result<Vec<(u64, u64)>, io::Error> {
+    let prefix = format!("{}.", base);
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let number = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix))
+            .and_then(|name| name.strip_suffix(".wal"))
+            .and_then(|number| number.parse::<u64>().ok());
+        if let Option::Some(number) = number {
+            segments.push((number, entry.metadata()?.len()));
+        }
+    }
+    segments.sort_unstable();
+    Result::Ok(segments)
+}
+
+/// セグメントを書き込み用に開き、そのファイルとバイト数を返す
+///
+/// 追記モードは使用せず、書き込みの位置は呼び出し側が管理する。ファイルの位置は末尾に合わせておく。
+fn open_segment(path: &Path) -> Result<(File, u64), io::Error> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    let len = file.seek(SeekFrom::End(0))?;
+    Result::Ok((file, len))
+}
+
+/// fileのoffsetの位置にbufを書き込む
+///
+/// pwrite(2)によりファイルの位置を変更せずに書き込むため、書き込みごとのシークを必要としない。
+#[cfg(unix)]
+fn write_at(file: &mut File, buf: &[u8], offset: u64) -> Result<usize, io::Error> {
+    use std::os::unix::fs::FileExt;
+    file.write_at(buf, offset)
+}
+
+/// fileのoffsetの位置にbufを書き込む
+///
+/// ファイルの位置は常に書き込み済みの末尾にあるため、そのまま書き込む。
+#[cfg(not(unix))]
+fn write_at(file: &mut File, buf: &[u8], _offset: u64) -> Result<usize, io::Error> {
+    file.write(buf)
+}
+
+/// renameの結果を永続化するため、ディレクトリをfsyncする
+#[cfg(unix)]
+pub(crate) fn sync_dir(dir: &Path) -> Result<(), io::Error> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn sync_dir(_dir: &Path) -> Result<(), io::Error> {
+    Result::Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::log::ReadWrite;
+    use crate::segment::SegmentedLog;
+    use std::io::prelude::*;
+    use std::io::SeekFrom;
+
+    #[test]
+    fn concatenated_segments() {
+        let _ = std::fs::remove_dir_all("segmented_log.log");
+        let mut log = SegmentedLog::open("segmented_log.log").unwrap();
+        log.write_all(b"abc").unwrap();
+        log.start_segment().unwrap();
+        log.write_all(b"defg").unwrap();
+        assert_eq!(log.segment_count(), 2);
+        assert_eq!(log.segment_len(), 4);
+        assert!(std::path::Path::new("segmented_log.log/segmented_log.000002.wal").is_file());
+
+        let mut log = SegmentedLog::open("segmented_log.log").unwrap();
+        let mut content = Vec::new();
+        log.seek(SeekFrom::Start(2)).unwrap();
+        log.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"cdefg");
+
+        log.truncate().unwrap();
+        assert_eq!(log.segment_count(), 1);
+        assert_eq!(log.seek(SeekFrom::End(0)).unwrap(), 0);
+        assert!(std::path::Path::new("segmented_log.log/segmented_log.000003.wal").is_file());
+
+        // 空のログを破棄しても新たなセグメントは作成されない
+        log.truncate().unwrap();
+        assert_eq!(log.segment_count(), 1);
+        assert!(std::path::Path::new("segmented_log.log/segmented_log.000003.wal").is_file());
+    }
+
+    #[test]
+    fn reopen_appends() {
+        let _ = std::fs::remove_dir_all("segmented_reopen.log");
+        {
+            let mut log = SegmentedLog::open("segmented_reopen.log").unwrap();
+            log.write_all(b"abc").unwrap();
+        }
+        let mut log = SegmentedLog::open("segmented_reopen.log").unwrap();
+        assert_eq!(log.segment_len(), 3);
+        // 読み取りの位置は書き込みの位置に影響しない
+        log.seek(SeekFrom::Start(1)).unwrap();
+        log.write_all(b"de").unwrap();
+        let mut content = Vec::new();
+        log.seek(SeekFrom::Start(0)).unwrap();
+        log.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"abcde");
+    }
+
+    #[test]
+    fn legacy_log_file() {
+        let _ = std::fs::remove_dir_all("segmented_legacy.log");
+        std::fs::write("segmented_legacy.log", b"frames").unwrap();
+        let mut log = SegmentedLog::open("segmented_legacy.log").unwrap();
+        let mut content = Vec::new();
+        log.seek(SeekFrom::Start(0)).unwrap();
+        log.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"frames");
+        assert!(std::path::Path::new("segmented_legacy.log").is_dir());
+    }
+}
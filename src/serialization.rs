@@ -0,0 +1,208 @@
+use crate::error::DatabaseError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// データファイルの内容の直列化の方式を表す
+pub trait SerializationBackend {
+    /// vをバイト列に直列化する
+    fn serialize<T: Serialize>(v: &T) -> Result<Vec<u8>, DatabaseError>;
+
+    /// バイト列からTを復元する
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DatabaseError>;
+}
+
+/// JSONによる直列化(人間が読める形式)
+pub struct JsonBackend;
+
+impl SerializationBackend for JsonBackend {
+    fn serialize<T: Serialize>(v: &T) -> Result<Vec<u8>, DatabaseError> {
+        Result::Ok(serde_json::to_vec(v)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DatabaseError> {
+        Result::Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// bincodeによる直列化
+pub struct BincodeBackend;
+
+impl SerializationBackend for BincodeBackend {
+    fn serialize<T: Serialize>(v: &T) -> Result<Vec<u8>, DatabaseError> {
+        Result::Ok(bincode::serialize(v)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DatabaseError> {
+        Result::Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+/// CBORによる直列化
+#[cfg(feature = "cbor")]
+pub struct CborBackend;
+
+#[cfg(feature = "cbor")]
+impl SerializationBackend for CborBackend {
+    fn serialize<T: Serialize>(v: &T) -> Result<Vec<u8>, DatabaseError> {
+        Result::Ok(serde_cbor::to_vec(v)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DatabaseError> {
+        Result::Ok(serde_cbor::from_slice(bytes)?)
+    }
+}
+
+/// データファイルの形式を表す
+///
+/// 形式はデータファイルのヘッダに記録され、データベースの初期化時に設定された形式と
+/// 一致しない場合は`DatabaseError::DataFormatMismatch`となる。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataFormat {
+    /// JSON(`JsonBackend`)
+    #[default]
+    Json = 0,
+    /// bincode(`BincodeBackend`)
+    Bincode = 1,
+    /// CBOR(`CborBackend`)。`cbor` featureが必要
+    Cbor = 2,
+}
+
+impl DataFormat {
+    /// データファイルのヘッダに記録された形式を表すバイトから復元する
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Option::Some(DataFormat::Json),
+            1 => Option::Some(DataFormat::Bincode),
+            2 => Option::Some(DataFormat::Cbor),
+            _ => Option::None,
+        }
+    }
+
+    /// この形式の`SerializationBackend`でvを直列化する
+    pub fn serialize<T: Serialize>(self, v: &T) -> Result<Vec<u8>, DatabaseError> {
+        match self {
+            DataFormat::Json => JsonBackend::serialize(v),
+            DataFormat::Bincode => BincodeBackend::serialize(v),
+            #[cfg(feature = "cbor")]
+            DataFormat::Cbor => CborBackend::serialize(v),
+            #[cfg(not(feature = "cbor"))]
+            DataFormat::Cbor => Result::Err(self.unavailable()),
+        }
+    }
+
+    /// この形式の`SerializationBackend`でバイト列からTを復元する
+    pub fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, DatabaseError> {
+        match self {
+            DataFormat::Json => JsonBackend::deserialize(bytes),
+            DataFormat::Bincode => BincodeBackend::deserialize(bytes),
+            #[cfg(feature = "cbor")]
+            DataFormat::Cbor => CborBackend::deserialize(bytes),
+            #[cfg(not(feature = "cbor"))]
+            DataFormat::Cbor => Result::Err(self.unavailable()),
+        }
+    }
+
+    /// 有効になっていない形式を使用しようとした場合のエラーを返す
+    #[cfg(not(feature = "cbor"))]
+    fn unavailable(self) -> DatabaseError {
+        DatabaseError::UnsupportedDataFormat {
+            message: format!("{:?} requires the `cbor` feature", self),
+        }
+    }
+}
+
+/// データファイルの圧縮の度合いを表す
+///
+/// `None`以外は`zstd` featureが必要である。圧縮されたデータファイルはヘッダから判別されるため、
+/// 読み込みの際にはこの設定に関わらず展開される。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionLevel {
+    /// 圧縮しない
+    #[default]
+    None,
+    /// 圧縮率よりも速度を優先する(zstdのレベル1)
+    Fast,
+    /// zstdの既定のレベル(3)
+    Default,
+    /// 速度よりも圧縮率を優先する(zstdのレベル19)
+    Best,
+}
+
+impl CompressionLevel {
+    /// zstdの圧縮レベルを返す(`None`の場合は0)
+    pub fn zstd_level(self) -> i32 {
+        match self {
+            CompressionLevel::None => 0,
+            CompressionLevel::Fast => 1,
+            CompressionLevel::Default => 3,
+            CompressionLevel::Best => 19,
+        }
+    }
+
+    /// bytesをこのレベルで圧縮する
+    #[cfg(feature = "zstd")]
+    pub(crate) fn compress(self, bytes: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+        Result::Ok(zstd::encode_all(bytes, self.zstd_level())?)
+    }
+
+    /// bytesをこのレベルで圧縮する
+    #[cfg(not(feature = "zstd"))]
+    pub(crate) fn compress(self, _bytes: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+        Result::Err(compression_unavailable())
+    }
+
+    /// 圧縮されたbytesを展開する
+    #[cfg(feature = "zstd")]
+    pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+        Result::Ok(zstd::decode_all(bytes)?)
+    }
+
+    /// 圧縮されたbytesを展開する
+    #[cfg(not(feature = "zstd"))]
+    pub(crate) fn decompress(_bytes: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+        Result::Err(compression_unavailable())
+    }
+}
+
+/// 圧縮が有効になっていない場合のエラーを返す
+#[cfg(not(feature = "zstd"))]
+fn compression_unavailable() -> DatabaseError {
+    DatabaseError::UnsupportedDataFormat {
+        message: "compressed data files require the `zstd` feature".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serialization::{BincodeBackend, DataFormat, JsonBackend, SerializationBackend};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn round_trip() {
+        let mut map = BTreeMap::new();
+        map.insert(1, "a".to_string());
+        map.insert(2, "b".to_string());
+        let json = JsonBackend::serialize(&map).unwrap();
+        assert_eq!(json, br#"{"1":"a","2":"b"}"#);
+        assert_eq!(
+            JsonBackend::deserialize::<BTreeMap<i32, String>>(&json).unwrap(),
+            map
+        );
+        let bincode = BincodeBackend::serialize(&map).unwrap();
+        assert_eq!(
+            BincodeBackend::deserialize::<BTreeMap<i32, String>>(&bincode).unwrap(),
+            map
+        );
+        if cfg!(feature = "cbor") {
+            let cbor = DataFormat::Cbor.serialize(&map).unwrap();
+            assert_eq!(
+                DataFormat::Cbor
+                    .deserialize::<BTreeMap<i32, String>>(&cbor)
+                    .unwrap(),
+                map
+            );
+        } else {
+            assert!(DataFormat::Cbor.serialize(&map).is_err());
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::config::DatabaseConfig;
+use crate::database::{Database, ReadTransaction, Transaction};
+use crate::error::{DatabaseError, DatabaseErrorExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// `SharedDatabase`から開始された更新トランザクション
+pub type SharedTransaction<'tx, K, V> =
+    Transaction<'tx, K, V, RwLockWriteGuard<'tx, Database<K, V>>>;
+
+/// `SharedDatabase`から開始された読み取り専用トランザクション
+pub type SharedReadTransaction<'tx, K, V> =
+    ReadTransaction<'tx, K, V, RwLockReadGuard<'tx, Database<K, V>>>;
+
+/// スレッド間で共有可能なデータベースを表す
+///
+/// 内部で`Arc<RwLock<Database>>`を保持しており、`clone`したハンドルを各スレッドに渡して使用する。
+/// 更新トランザクションは書き込みロックを、読み取り専用トランザクションは読み込みロックを
+/// トランザクションの終了まで保持する。MVCCは提供しないため、更新トランザクションは完全に直列化され、
+/// 実行中は読み取り専用トランザクションも開始できない。
+pub struct SharedDatabase<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    inner: Arc<RwLock<Database<K, V>>>,
+}
+
+impl<K, V> SharedDatabase<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    /// データベースを共有可能にする
+    pub fn new(database: Database<K, V>) -> Self {
+        SharedDatabase {
+            inner: Arc::new(RwLock::new(database)),
+        }
+    }
+
+    /// 書き込みロックを取得し、トランザクションを開始する
+    ///
+    /// 他のトランザクションが実行中の場合、それが終了するまでブロックする。
+    pub fn begin_transaction(&self) -> Result<SharedTransaction<'_, K, V>, DatabaseError> {
+        let guard = self
+            .inner
+            .write()
+            .map_err(|_| DatabaseError::LockPoisonedError)?;
+        Result::Ok(Transaction::new(guard))
+    }
+
+    /// 読み込みロックを取得し、読み取り専用トランザクションを開始する
+    ///
+    /// 更新トランザクションが実行中の場合、それが終了するまでブロックする。
+    pub fn begin_read_transaction(&self) -> Result<SharedReadTransaction<'_, K, V>, DatabaseError> {
+        let guard = self
+            .inner
+            .read()
+            .map_err(|_| DatabaseError::LockPoisonedError)?;
+        Result::Ok(ReadTransaction::new(guard))
+    }
+
+    /// 書き込みロックの取得を試み、取得できた場合はトランザクションを開始する
+    ///
+    /// ブロックはせず、他のトランザクションが実行中の場合(またはロックが汚染されている場合)は
+    /// `None`を返す。
+    pub fn try_begin_transaction(&self) -> Option<SharedTransaction<'_, K, V>> {
+        match self.inner.try_write() {
+            Result::Ok(guard) => Option::Some(Transaction::new(guard)),
+            Result::Err(_) => Option::None,
+        }
+    }
+
+    /// 読み込みロックの取得を試み、取得できた場合は読み取り専用トランザクションを開始する
+    ///
+    /// ブロックはせず、更新トランザクションが実行中の場合(またはロックが汚染されている場合)は
+    /// `None`を返す。
+    pub fn try_begin_read_transaction(&self) -> Option<SharedReadTransaction<'_, K, V>> {
+        match self.inner.try_read() {
+            Result::Ok(guard) => Option::Some(ReadTransaction::new(guard)),
+            Result::Err(_) => Option::None,
+        }
+    }
+}
+
+impl<K, V> SharedDatabase<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash + Send + Sync + 'static,
+    V: Debug + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// 期限切れのキーを定期的に削除するスレッドを開始する
+    ///
+    /// スレッドは`DatabaseConfig::expiry_check_interval`ごとに書き込みロックを取得し、
+    /// `Database::purge_expired`を実行する。返されたハンドルの`stop`(またはDrop)により停止する。
+    pub fn start_expiry_thread(&self) -> ExpiryHandle {
+        let interval = self.read_config(|config| config.expiry_check_interval);
+        self.spawn_periodic(interval, |db| db.purge_expired().map(|_| ()))
+    }
+
+    /// ログにハートビートを定期的に書き込むスレッドを開始する
+    ///
+    /// スレッドは`DatabaseConfig::heartbeat_interval`ごとに書き込みロックを取得し、
+    /// `Database::write_heartbeat`を実行する。間隔が設定されていない場合や、読み取り専用の
+    /// データベースの場合はスレッドを開始せずに`None`を返す。
+    pub fn start_heartbeat_thread(&self) -> Option<BackgroundHandle> {
+        let interval = self.read_config(|config| config.heartbeat_interval)?;
+        if self.read_database(Database::is_read_only) {
+            return Option::None;
+        }
+        Option::Some(self.spawn_periodic(interval, Database::write_heartbeat))
+    }
+
+    /// ロックの汚染に関わらず、データベースの読み取りを行う
+    fn read_database<T, F>(&self, f: F) -> T
+    where
+        F: FnOnce(&Database<K, V>) -> T,
+    {
+        match self.inner.read() {
+            Result::Ok(db) => f(&db),
+            Result::Err(e) => f(&e.into_inner()),
+        }
+    }
+
+    /// ロックの汚染に関わらず、データベースの設定を読み取る
+    fn read_config<T, F>(&self, f: F) -> T
+    where
+        F: FnOnce(&DatabaseConfig) -> T,
+    {
+        self.read_database(|db| f(db.config()))
+    }
+
+    /// interval毎に書き込みロックを取得してfを実行するスレッドを開始する
+    fn spawn_periodic<F>(&self, interval: Duration, mut f: F) -> BackgroundHandle
+    where
+        F: FnMut(&mut Database<K, V>) -> Result<(), DatabaseError> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        let (sender, receiver) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            while let Result::Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(interval) {
+                let mut db = match inner.write() {
+                    Result::Ok(db) => db,
+                    Result::Err(_) => break,
+                };
+                if let Result::Err(e) = f(&mut db).context("background task failed") {
+                    ::log::warn!("mikrodb: {}", e);
+                }
+            }
+        });
+        BackgroundHandle {
+            stop: Option::Some(sender),
+            thread: Option::Some(thread),
+        }
+    }
+}
+
+/// 期限切れのキーを削除するスレッドのハンドル
+pub type ExpiryHandle = BackgroundHandle;
+
+/// バックグラウンドで定期的に処理を行うスレッドのハンドル
+///
+/// Dropした場合もスレッドを停止する。
+pub struct BackgroundHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BackgroundHandle {
+    /// スレッドを停止し、終了を待つ
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // 送信側を破棄すると、スレッドの待機が直ちに終了する
+        self.stop.take();
+        if let Option::Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for BackgroundHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl<K, V> Clone for SharedDatabase<K, V>
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    fn clone(&self) -> Self {
+        SharedDatabase {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
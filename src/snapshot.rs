@@ -0,0 +1,144 @@
+use crate::iter::is_valid_range;
+use std::cmp::Ordering;
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// ある時点のデータベースの内容を表す
+///
+/// 作成時点のコミット済みの内容(期限切れのキーを除く)を複製して保持するため、
+/// 以降のCommitの影響を受けない。`clone`は内容を複製せずに共有する。
+#[derive(Debug)]
+pub struct Snapshot<K, V> {
+    data: Arc<BTreeMap<K, V>>,
+}
+
+/// 2つのスナップショットの間の差分を表す
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry<K, V> {
+    /// 比較対象にのみ存在するキーバリューペア
+    Added(K, V),
+    /// 比較元にのみ存在するキーバリューペア
+    Removed(K, V),
+    /// 値が異なるキーと、比較元の値・比較対象の値
+    Modified(K, V, V),
+}
+
+impl<K, V> Snapshot<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    pub(crate) fn new(data: BTreeMap<K, V>) -> Self {
+        Snapshot {
+            data: Arc::new(data),
+        }
+    }
+
+    /// keyに対応する値を返す
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.data.get(key)
+    }
+
+    /// startからendまでの範囲のキーバリューペアをキーの昇順に走査する
+    ///
+    /// startがendより大きい場合は何も返さない。
+    pub fn scan_range(&self, start: Bound<K>, end: Bound<K>) -> impl Iterator<Item = (&K, &V)> {
+        let range = if is_valid_range(&start, &end) {
+            Option::Some(self.data.range((start, end)))
+        } else {
+            Option::None
+        };
+        range.into_iter().flatten()
+    }
+
+    /// すべてのキーを昇順に走査する
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.data.keys()
+    }
+
+    /// すべての値をキーの昇順に走査する
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.data.values()
+    }
+
+    /// キーバリューペアの数を返す
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// キーバリューペアが存在しないかどうかを返す
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// selfを比較元、otherを比較対象として、キーの昇順に差分を返す
+    pub fn diff<'a>(
+        &'a self,
+        other: &'a Snapshot<K, V>,
+    ) -> impl Iterator<Item = DiffEntry<K, V>> + 'a
+    where
+        V: PartialEq,
+    {
+        Diff {
+            old: self.data.iter().peekable(),
+            new: other.data.iter().peekable(),
+        }
+    }
+}
+
+impl<K, V> Clone for Snapshot<K, V> {
+    fn clone(&self) -> Self {
+        Snapshot {
+            data: Arc::clone(&self.data),
+        }
+    }
+}
+
+/// 2つのスナップショットをキー順にマージしながら差分を求めるイテレータ
+struct Diff<'a, K, V> {
+    old: Peekable<btree_map::Iter<'a, K, V>>,
+    new: Peekable<btree_map::Iter<'a, K, V>>,
+}
+
+impl<'a, K, V> Iterator for Diff<'a, K, V>
+where
+    K: Ord + Clone,
+    V: Clone + PartialEq,
+{
+    type Item = DiffEntry<K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.old.peek(), self.new.peek()) {
+                (Option::None, Option::None) => return Option::None,
+                (Option::Some(_), Option::None) => Ordering::Less,
+                (Option::None, Option::Some(_)) => Ordering::Greater,
+                (Option::Some((old, _)), Option::Some((new, _))) => old.cmp(new),
+            };
+            match order {
+                Ordering::Less => {
+                    let (k, v) = self.old.next()?;
+                    return Option::Some(DiffEntry::Removed(k.clone(), v.clone()));
+                }
+                Ordering::Greater => {
+                    let (k, v) = self.new.next()?;
+                    return Option::Some(DiffEntry::Added(k.clone(), v.clone()));
+                }
+                Ordering::Equal => {
+                    let (k, old) = self.old.next()?;
+                    let (_, new) = self.new.next()?;
+                    if old != new {
+                        return Option::Some(DiffEntry::Modified(
+                            k.clone(),
+                            old.clone(),
+                            new.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// データベースの動作に関する統計情報を表す
+///
+/// 各値は`Database::stats`で取得したハンドルから、別のスレッドでも読み取ることができる。
+#[derive(Debug, Default)]
+pub struct Statistics {
+    transactions_committed: AtomicU64,
+    transactions_aborted: AtomicU64,
+    wal_bytes_written: AtomicU64,
+    checkpoints: AtomicU64,
+    record_count: AtomicUsize,
+    wal_records: AtomicU64,
+}
+
+/// `Database::size_on_disk`により返される、ファイルシステム上の使用量(bytes)を表す
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiskUsage {
+    /// データファイルのバイト数
+    pub data_bytes: u64,
+    /// ログのセグメントファイルの合計のバイト数
+    pub wal_bytes: u64,
+    /// data_bytesとwal_bytesの合計
+    pub total_bytes: u64,
+}
+
+impl Statistics {
+    /// Commitされたトランザクションの数を返す
+    pub fn total_transactions_committed(&self) -> u64 {
+        self.transactions_committed.load(Ordering::Relaxed)
+    }
+
+    /// Abortされたトランザクションの数を返す
+    pub fn total_transactions_aborted(&self) -> u64 {
+        self.transactions_aborted.load(Ordering::Relaxed)
+    }
+
+    /// ログに書き込まれたバイト数を返す
+    pub fn total_wal_bytes_written(&self) -> u64 {
+        self.wal_bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// 作成されたチェックポイントの数を返す
+    pub fn total_checkpoints(&self) -> u64 {
+        self.checkpoints.load(Ordering::Relaxed)
+    }
+
+    /// 現在コミット済みのキーバリューペアの数を返す
+    pub fn current_record_count(&self) -> usize {
+        self.record_count.load(Ordering::Relaxed)
+    }
+
+    /// ログに書き込まれたレコードの数を返す
+    pub fn wal_record_count(&self) -> u64 {
+        self.wal_records.load(Ordering::Relaxed)
+    }
+
+    /// 累積値をすべて0に戻す
+    ///
+    /// 現在の状態を表す`current_record_count`は変更しない。
+    pub fn reset(&self) {
+        self.transactions_committed.store(0, Ordering::Relaxed);
+        self.transactions_aborted.store(0, Ordering::Relaxed);
+        self.wal_bytes_written.store(0, Ordering::Relaxed);
+        self.checkpoints.store(0, Ordering::Relaxed);
+        self.wal_records.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_commit(&self) {
+        self.transactions_committed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_abort(&self) {
+        self.transactions_aborted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_wal_write(&self, bytes: u64) {
+        self.wal_bytes_written.fetch_add(bytes, Ordering::Relaxed);
+        self.wal_records.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_checkpoint(&self) {
+        self.checkpoints.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_record_count(&self, count: usize) {
+        self.record_count.store(count, Ordering::Relaxed);
+    }
+}
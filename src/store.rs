@@ -0,0 +1,493 @@
+use crate::datafile::{self, DataFileHeader};
+use crate::error::DatabaseError;
+use crate::serialization::DataFormat;
+use serde::de::DeserializeOwned;
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+use serde_json::value::RawValue;
+
+use std::collections::{btree_map, hash_map, BTreeMap, HashMap};
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::ops::{Bound, RangeBounds};
+use std::sync::OnceLock;
+
+use std::option::Option;
+use std::result::Result;
+
+/// コミット済みの内容を保持するデータ構造を表す
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    /// キーの順序を保つ(`BTreeMap`)
+    #[default]
+    Sorted,
+    /// キーのハッシュにより格納する(`HashMap`)
+    ///
+    /// 範囲の走査やキーの順序に依存する操作は`DatabaseError::UnsupportedOperation`となり、
+    /// すべての内容の走査はキーの順序を保証しない。
+    Hashed,
+}
+
+/// キーバリューペアを格納するデータ構造に共通する操作
+///
+/// 値を参照時に復元するデータ構造では、参照する値を復元できない場合に`get`・`iter`・`range`が
+/// `DatabaseError::JSONError`となる。
+pub(crate) trait KVStore<K, V> {
+    type Iter<'a>: Iterator<Item = (&'a K, &'a V)>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type Range<'a>: Iterator<Item = (&'a K, &'a V)>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    /// keyにvalueを格納する(置き換えられた値は返さない)
+    fn insert(&mut self, key: K, value: V);
+    fn get(&self, key: &K) -> Result<Option<&V>, DatabaseError>;
+    /// keyを削除し、keyが存在したかどうかを返す
+    fn remove(&mut self, key: &K) -> bool;
+    /// すべての内容を走査する(順序はデータ構造による)
+    fn iter(&self) -> Result<Self::Iter<'_>, DatabaseError>;
+    /// startからendまでの内容をキーの昇順に走査する
+    ///
+    /// キーの順序を保たない場合は`DatabaseError::UnsupportedOperation`となる。
+    fn range(&self, start: Bound<K>, end: Bound<K>) -> Result<Self::Range<'_>, DatabaseError>;
+}
+
+impl<K: Ord, V> KVStore<K, V> for BTreeMap<K, V> {
+    type Iter<'a>
+        = btree_map::Iter<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+    type Range<'a>
+        = btree_map::Range<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+
+    fn insert(&mut self, key: K, value: V) {
+        BTreeMap::insert(self, key, value);
+    }
+
+    fn get(&self, key: &K) -> Result<Option<&V>, DatabaseError> {
+        Result::Ok(BTreeMap::get(self, key))
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        BTreeMap::remove(self, key).is_some()
+    }
+
+    fn iter(&self) -> Result<Self::Iter<'_>, DatabaseError> {
+        Result::Ok(BTreeMap::iter(self))
+    }
+
+    fn range(&self, start: Bound<K>, end: Bound<K>) -> Result<Self::Range<'_>, DatabaseError> {
+        Result::Ok(BTreeMap::range(self, (start, end)))
+    }
+}
+
+impl<K: Hash + Eq, V> KVStore<K, V> for HashMap<K, V> {
+    type Iter<'a>
+        = hash_map::Iter<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+    type Range<'a>
+        = std::iter::Empty<(&'a K, &'a V)>
+    where
+        K: 'a,
+        V: 'a;
+
+    fn insert(&mut self, key: K, value: V) {
+        HashMap::insert(self, key, value);
+    }
+
+    fn get(&self, key: &K) -> Result<Option<&V>, DatabaseError> {
+        Result::Ok(HashMap::get(self, key))
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        HashMap::remove(self, key).is_some()
+    }
+
+    fn iter(&self) -> Result<Self::Iter<'_>, DatabaseError> {
+        Result::Ok(HashMap::iter(self))
+    }
+
+    fn range(&self, _start: Bound<K>, _end: Bound<K>) -> Result<Self::Range<'_>, DatabaseError> {
+        Result::Err(unsupported("range"))
+    }
+}
+
+/// データファイルから読み込んだ内容のまま保持し、最初に参照された時点で復元する値
+#[derive(Debug, Clone)]
+pub(crate) struct LazyValue<V> {
+    /// データファイル上の内容(読み込み後に格納された値はNone)
+    raw: Option<Box<RawValue>>,
+    value: OnceLock<V>,
+}
+
+impl<V: DeserializeOwned> LazyValue<V> {
+    fn raw(raw: Box<RawValue>) -> Self {
+        LazyValue {
+            raw: Option::Some(raw),
+            value: OnceLock::new(),
+        }
+    }
+
+    fn loaded(value: V) -> Self {
+        LazyValue {
+            raw: Option::None,
+            value: OnceLock::from(value),
+        }
+    }
+
+    /// 値を返す(復元されていない場合は復元する)
+    ///
+    /// データファイルの内容を値の型として復元できない場合は`DatabaseError::JSONError`を返す。
+    /// 復元に失敗した値は保持せず、次回の参照時に再び復元を試みる。
+    fn try_get(&self) -> Result<&V, DatabaseError> {
+        if let Option::Some(value) = self.value.get() {
+            return Result::Ok(value);
+        }
+        let raw = self
+            .raw
+            .as_ref()
+            .expect("a loaded value is always initialized");
+        let value = serde_json::from_str(raw.get())?;
+        Result::Ok(self.value.get_or_init(|| value))
+    }
+}
+
+/// 値を参照時に復元するデータ構造の内容を、すべての値を復元した上で列挙する
+///
+/// 復元できない値が含まれる場合は`DatabaseError::JSONError`を返す。
+fn decode_all<'a, K, V, I>(entries: I) -> Result<std::vec::IntoIter<(&'a K, &'a V)>, DatabaseError>
+where
+    V: DeserializeOwned + 'a,
+    I: Iterator<Item = (&'a K, &'a LazyValue<V>)>,
+{
+    let entries: Vec<(&K, &V)> = entries
+        .map(|(k, v)| v.try_get().map(|v| (k, v)))
+        .collect::<Result<_, _>>()?;
+    Result::Ok(entries.into_iter())
+}
+
+/// JSON形式のデータファイルに書き出すバリュー
+///
+/// 読み込み後に格納されていない値は、復元の有無にかかわらず読み込んだ内容のまま書き出す。
+#[derive(Serialize)]
+#[serde(untagged)]
+enum JsonValue<'a, V> {
+    Raw(&'a RawValue),
+    Value(&'a V),
+}
+
+/// `Store::Lazy`の内容をJSON形式のデータファイルに書き出すためのマップ
+struct RawJson<'a, K, V>(&'a BTreeMap<K, LazyValue<V>>);
+
+impl<'a, K: Serialize, V: Serialize + DeserializeOwned> Serialize for RawJson<'a, K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Option::Some(self.0.len()))?;
+        for (k, v) in self.0 {
+            let value = match &v.raw {
+                Option::Some(raw) => JsonValue::Raw(raw),
+                Option::None => JsonValue::Value(v.try_get().map_err(ser::Error::custom)?),
+            };
+            map.serialize_entry(k, &value)?;
+        }
+        map.end()
+    }
+}
+
+/// `StorageBackend`に応じたデータ構造でコミット済みの内容を保持する
+#[derive(Debug, Clone)]
+pub(crate) enum Store<K, V> {
+    Sorted(BTreeMap<K, V>),
+    Hashed(HashMap<K, V>),
+    /// キーの順序を保ち、値を参照時に復元する(`DatabaseConfig::lazy_loading`)
+    Lazy(BTreeMap<K, LazyValue<V>>),
+}
+
+/// `Store::iter`が返すイテレータ
+pub(crate) enum StoreIter<'a, K, V> {
+    Sorted(btree_map::Iter<'a, K, V>),
+    Hashed(hash_map::Iter<'a, K, V>),
+    /// 復元済みの値
+    Lazy(std::vec::IntoIter<(&'a K, &'a V)>),
+}
+
+impl<'a, K, V> Iterator for StoreIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            StoreIter::Sorted(iter) => iter.next(),
+            StoreIter::Hashed(iter) => iter.next(),
+            StoreIter::Lazy(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            StoreIter::Sorted(iter) => iter.size_hint(),
+            StoreIter::Hashed(iter) => iter.size_hint(),
+            StoreIter::Lazy(iter) => iter.size_hint(),
+        }
+    }
+}
+
+/// `Store::range`が返すイテレータ
+pub(crate) enum StoreRange<'a, K, V> {
+    Sorted(btree_map::Range<'a, K, V>),
+    /// 復元済みの値
+    Lazy(std::vec::IntoIter<(&'a K, &'a V)>),
+}
+
+impl<'a, K, V> Iterator for StoreRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            StoreRange::Sorted(iter) => iter.next(),
+            StoreRange::Lazy(iter) => iter.next(),
+        }
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for StoreRange<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            StoreRange::Sorted(iter) => iter.next_back(),
+            StoreRange::Lazy(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K: Ord + Hash, V: DeserializeOwned> Store<K, V> {
+    /// データファイルから読み込んだ内容をbackendに応じたデータ構造に格納する
+    pub(crate) fn from_map(backend: StorageBackend, data: BTreeMap<K, V>) -> Self {
+        match backend {
+            StorageBackend::Sorted => Store::Sorted(data),
+            StorageBackend::Hashed => Store::Hashed(HashMap::from_iter(data)),
+        }
+    }
+
+    /// JSON形式のデータファイルから読み込んだ内容を、値を復元せずに格納する
+    pub(crate) fn from_raw(data: BTreeMap<K, Box<RawValue>>) -> Self {
+        Store::Lazy(
+            data.into_iter()
+                .map(|(k, raw)| (k, LazyValue::raw(raw)))
+                .collect(),
+        )
+    }
+
+    /// キーの順序を保つかどうかを確認する
+    ///
+    /// キーの順序を保たない場合は、operationを対象とする`DatabaseError::UnsupportedOperation`となる。
+    pub(crate) fn ensure_sorted(&self, operation: &str) -> Result<(), DatabaseError> {
+        match self {
+            Store::Sorted(_) | Store::Lazy(_) => Result::Ok(()),
+            Store::Hashed(_) => Result::Err(unsupported(operation)),
+        }
+    }
+
+    pub(crate) fn contains_key(&self, key: &K) -> bool {
+        match self {
+            Store::Sorted(data) => data.contains_key(key),
+            Store::Hashed(data) => data.contains_key(key),
+            Store::Lazy(data) => data.contains_key(key),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Store::Sorted(data) => data.len(),
+            Store::Hashed(data) => data.len(),
+            Store::Lazy(data) => data.len(),
+        }
+    }
+
+    /// すべてのキーを走査する(値は復元しない)
+    pub(crate) fn keys(&self) -> Box<dyn Iterator<Item = &K> + '_> {
+        match self {
+            Store::Sorted(data) => Box::new(data.keys()),
+            Store::Hashed(data) => Box::new(data.keys()),
+            Store::Lazy(data) => Box::new(data.keys()),
+        }
+    }
+
+    /// すべてのキーを昇順(reverseの場合は降順)に走査する(値は復元しない)
+    ///
+    /// キーの順序を保たない場合、走査の順序は保証しない。
+    pub(crate) fn ordered_keys(&self, reverse: bool) -> Box<dyn Iterator<Item = &K> + '_> {
+        match self {
+            Store::Sorted(data) if reverse => Box::new(data.keys().rev()),
+            Store::Lazy(data) if reverse => Box::new(data.keys().rev()),
+            _ => self.keys(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            Store::Sorted(data) => data.clear(),
+            Store::Hashed(data) => data.clear(),
+            Store::Lazy(data) => data.clear(),
+        }
+    }
+
+    /// 最小のキーを返す(キーの順序を保たない場合はすべてのキーを確認する)
+    pub(crate) fn first_key(&self) -> Option<&K> {
+        match self {
+            Store::Sorted(data) => data.keys().next(),
+            Store::Hashed(data) => data.keys().min(),
+            Store::Lazy(data) => data.keys().next(),
+        }
+    }
+
+    /// 最大のキーを返す(キーの順序を保たない場合はすべてのキーを確認する)
+    pub(crate) fn last_key(&self) -> Option<&K> {
+        match self {
+            Store::Sorted(data) => data.keys().next_back(),
+            Store::Hashed(data) => data.keys().max(),
+            Store::Lazy(data) => data.keys().next_back(),
+        }
+    }
+
+    /// startからendまでのキーの数を返す(キーの順序を保たない場合はすべてのキーを確認する)
+    pub(crate) fn count_in_range(&self, start: Bound<K>, end: Bound<K>) -> usize {
+        match self {
+            Store::Sorted(data) => data.range((start, end)).count(),
+            Store::Hashed(data) => {
+                let bounds = (start, end);
+                data.keys().filter(|key| bounds.contains(*key)).count()
+            }
+            Store::Lazy(data) => data.range((start, end)).count(),
+        }
+    }
+
+    /// startからendまでのキーを返す(キーの順序を保たない場合はすべてのキーを確認する)
+    pub(crate) fn keys_in_range(&self, start: Bound<K>, end: Bound<K>) -> Vec<K>
+    where
+        K: Clone,
+    {
+        match self {
+            Store::Sorted(data) => data.range((start, end)).map(|(k, _)| k.clone()).collect(),
+            Store::Hashed(data) => {
+                let bounds = (start, end);
+                data.keys()
+                    .filter(|key| bounds.contains(*key))
+                    .cloned()
+                    .collect()
+            }
+            Store::Lazy(data) => data.range((start, end)).map(|(k, _)| k.clone()).collect(),
+        }
+    }
+
+    /// ヘッダ・有効期限と共に、formatの形式でデータファイルの内容として書き出す
+    ///
+    /// 値を参照時に復元する場合、JSON形式では読み込み後に格納されていない値を復元せずに書き出す。
+    pub(crate) fn encode(
+        &self,
+        format: DataFormat,
+        header: &DataFileHeader,
+        expiry: &BTreeMap<K, u64>,
+    ) -> Result<Vec<u8>, DatabaseError>
+    where
+        K: Serialize,
+        V: Serialize,
+    {
+        match self {
+            Store::Lazy(data) if format == DataFormat::Json => {
+                datafile::encode(format, header, &RawJson(data), expiry)
+            }
+            _ => datafile::encode(format, header, self, expiry),
+        }
+    }
+}
+
+impl<K: Ord + Hash, V: DeserializeOwned> KVStore<K, V> for Store<K, V> {
+    type Iter<'a>
+        = StoreIter<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+    type Range<'a>
+        = StoreRange<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+
+    fn insert(&mut self, key: K, value: V) {
+        match self {
+            Store::Sorted(data) => KVStore::insert(data, key, value),
+            Store::Hashed(data) => KVStore::insert(data, key, value),
+            Store::Lazy(data) => {
+                data.insert(key, LazyValue::loaded(value));
+            }
+        }
+    }
+
+    fn get(&self, key: &K) -> Result<Option<&V>, DatabaseError> {
+        match self {
+            Store::Sorted(data) => KVStore::get(data, key),
+            Store::Hashed(data) => KVStore::get(data, key),
+            Store::Lazy(data) => data.get(key).map(LazyValue::try_get).transpose(),
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        match self {
+            Store::Sorted(data) => KVStore::remove(data, key),
+            Store::Hashed(data) => KVStore::remove(data, key),
+            Store::Lazy(data) => data.remove(key).is_some(),
+        }
+    }
+
+    /// 値を参照時に復元する場合は、走査を始める前にすべての値を復元する
+    fn iter(&self) -> Result<Self::Iter<'_>, DatabaseError> {
+        match self {
+            Store::Sorted(data) => KVStore::iter(data).map(StoreIter::Sorted),
+            Store::Hashed(data) => KVStore::iter(data).map(StoreIter::Hashed),
+            Store::Lazy(data) => decode_all(data.iter()).map(StoreIter::Lazy),
+        }
+    }
+
+    /// 値を参照時に復元する場合は、走査を始める前に範囲内のすべての値を復元する
+    fn range(&self, start: Bound<K>, end: Bound<K>) -> Result<Self::Range<'_>, DatabaseError> {
+        match self {
+            Store::Sorted(data) => KVStore::range(data, start, end).map(StoreRange::Sorted),
+            Store::Hashed(_) => Result::Err(unsupported("range")),
+            Store::Lazy(data) => decode_all(data.range((start, end))).map(StoreRange::Lazy),
+        }
+    }
+}
+
+impl<K: Serialize, V: Serialize + DeserializeOwned> Serialize for Store<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Store::Sorted(data) => data.serialize(serializer),
+            Store::Hashed(data) => data.serialize(serializer),
+            Store::Lazy(data) => {
+                let mut map = serializer.serialize_map(Option::Some(data.len()))?;
+                for (k, v) in data {
+                    map.serialize_entry(k, v.try_get().map_err(ser::Error::custom)?)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+fn unsupported(operation: &str) -> DatabaseError {
+    DatabaseError::UnsupportedOperation {
+        operation: operation.to_string(),
+    }
+}
@@ -0,0 +1,15 @@
+/// `tracing::debug!`によりイベントを記録する(feature `tracing`が無効な場合は何もしない)
+macro_rules! debug_event {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)+);
+    };
+}
+
+/// `tracing::trace!`によりイベントを記録する(feature `tracing`が無効な場合は何もしない)
+macro_rules! trace_event {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)+);
+    };
+}
@@ -0,0 +1,257 @@
+use std::collections::{btree_map, btree_set, BTreeMap, BTreeSet};
+use std::iter::FromIterator;
+use std::ops::RangeBounds;
+
+/// トランザクションの書き込みセットを表す
+///
+/// 作成・更新されたキーは値と共に、削除されたキーはキーのみを別々に保持する。
+/// 同じキーが両方に含まれることはない。
+#[derive(Debug, Clone)]
+pub(crate) struct WriteSet<K, V> {
+    inserts_updates: BTreeMap<K, V>,
+    deletes: BTreeSet<K>,
+}
+
+impl<K, V> Default for WriteSet<K, V> {
+    fn default() -> Self {
+        WriteSet {
+            inserts_updates: BTreeMap::new(),
+            deletes: BTreeSet::new(),
+        }
+    }
+}
+
+impl<K: Ord, V> WriteSet<K, V> {
+    pub(crate) fn new() -> Self {
+        WriteSet::default()
+    }
+
+    /// 書き込まれたキーの数を返す
+    pub(crate) fn len(&self) -> usize {
+        self.inserts_updates.len() + self.deletes.len()
+    }
+
+    /// 書き込みが含まれないかどうかを返す
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inserts_updates.is_empty() && self.deletes.is_empty()
+    }
+
+    /// keyへの書き込みを返す(削除された場合は`Some(None)`、書き込まれていない場合は`None`)
+    pub(crate) fn get(&self, key: &K) -> Option<Option<&V>> {
+        if self.deletes.contains(key) {
+            return Option::Some(Option::None);
+        }
+        self.inserts_updates.get(key).map(Option::Some)
+    }
+
+    /// keyに書き込まれた値への可変参照を返す(削除された場合・書き込まれていない場合は`None`)
+    pub(crate) fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inserts_updates.get_mut(key)
+    }
+
+    /// keyへの書き込みが含まれるかどうかを返す
+    pub(crate) fn contains_key(&self, key: &K) -> bool {
+        self.deletes.contains(key) || self.inserts_updates.contains_key(key)
+    }
+
+    /// keyの値としてvalueを書き込む
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        self.deletes.remove(&key);
+        self.inserts_updates.insert(key, value);
+    }
+
+    /// keyを削除する
+    pub(crate) fn delete(&mut self, key: K) {
+        self.inserts_updates.remove(&key);
+        self.deletes.insert(key);
+    }
+
+    /// valueが`Some`の場合は書き込み、`None`の場合は削除する
+    pub(crate) fn set(&mut self, key: K, value: Option<V>) {
+        match value {
+            Option::Some(value) => self.insert(key, value),
+            Option::None => self.delete(key),
+        }
+    }
+
+    /// 削除されたキーが含まれるかどうかを返す
+    pub(crate) fn has_deletes(&self) -> bool {
+        !self.deletes.is_empty()
+    }
+
+    /// 書き込みをキーの昇順に走査する
+    pub(crate) fn iter(&self) -> Merge<btree_map::Iter<'_, K, V>, btree_set::Iter<'_, K>> {
+        Merge::new(self.inserts_updates.iter(), self.deletes.iter())
+    }
+
+    /// 書き込まれたキーを昇順に走査する
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// rangeに含まれるキーへの書き込みをキーの昇順に走査する
+    pub(crate) fn range<R>(
+        &self,
+        range: R,
+    ) -> Merge<btree_map::Range<'_, K, V>, btree_set::Range<'_, K>>
+    where
+        R: RangeBounds<K> + Clone,
+    {
+        Merge::new(
+            self.inserts_updates.range(range.clone()),
+            self.deletes.range(range),
+        )
+    }
+}
+
+impl<K: Ord, V> IntoIterator for WriteSet<K, V> {
+    type Item = (K, Option<V>);
+    type IntoIter = Merge<btree_map::IntoIter<K, V>, btree_set::IntoIter<K>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Merge::new(self.inserts_updates.into_iter(), self.deletes.into_iter())
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, Option<V>)> for WriteSet<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, Option<V>)>>(iter: I) -> Self {
+        let mut writeset = WriteSet::new();
+        for (key, value) in iter {
+            writeset.set(key, value);
+        }
+        writeset
+    }
+}
+
+/// 書き込まれたキーバリューペアと削除されたキーを、キー順にマージしながら走査するイテレータ
+///
+/// 削除されたキーは`(key, None)`として返す。両端から走査できる。
+pub(crate) struct Merge<I: Iterator, J: Iterator> {
+    inserts: I,
+    deletes: J,
+    front: (Option<I::Item>, Option<J::Item>),
+    back: (Option<I::Item>, Option<J::Item>),
+}
+
+impl<I: Iterator, J: Iterator> Merge<I, J> {
+    fn new(inserts: I, deletes: J) -> Self {
+        Merge {
+            inserts,
+            deletes,
+            front: (Option::None, Option::None),
+            back: (Option::None, Option::None),
+        }
+    }
+}
+
+impl<K, V, I, J> Iterator for Merge<I, J>
+where
+    K: Ord,
+    I: Iterator<Item = (K, V)>,
+    J: Iterator<Item = K>,
+{
+    type Item = (K, Option<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // 一方の端から走査し尽くした場合、残りは反対側の端で先読みした要素のみである
+        if self.front.0.is_none() {
+            self.front.0 = self.inserts.next().or_else(|| self.back.0.take());
+        }
+        if self.front.1.is_none() {
+            self.front.1 = self.deletes.next().or_else(|| self.back.1.take());
+        }
+        let delete_first = match &self.front {
+            (_, Option::None) => false,
+            (Option::None, Option::Some(_)) => true,
+            (Option::Some((key, _)), Option::Some(deleted)) => deleted < key,
+        };
+        if delete_first {
+            self.front.1.take().map(|key| (key, Option::None))
+        } else {
+            self.front
+                .0
+                .take()
+                .map(|(key, value)| (key, Option::Some(value)))
+        }
+    }
+}
+
+impl<K, V, I, J> DoubleEndedIterator for Merge<I, J>
+where
+    K: Ord,
+    I: DoubleEndedIterator<Item = (K, V)>,
+    J: DoubleEndedIterator<Item = K>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.back.0.is_none() {
+            self.back.0 = self.inserts.next_back().or_else(|| self.front.0.take());
+        }
+        if self.back.1.is_none() {
+            self.back.1 = self.deletes.next_back().or_else(|| self.front.1.take());
+        }
+        let delete_first = match &self.back {
+            (_, Option::None) => false,
+            (Option::None, Option::Some(_)) => true,
+            (Option::Some((key, _)), Option::Some(deleted)) => deleted > key,
+        };
+        if delete_first {
+            self.back.1.take().map(|key| (key, Option::None))
+        } else {
+            self.back
+                .0
+                .take()
+                .map(|(key, value)| (key, Option::Some(value)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::writeset::WriteSet;
+
+    #[test]
+    fn merge_both_ends() {
+        let mut writeset: WriteSet<i32, i32> = WriteSet::new();
+        for key in 0..10 {
+            writeset.insert(key, key * 10);
+        }
+        for key in (1..10).step_by(3) {
+            writeset.delete(key);
+        }
+        assert_eq!(writeset.len(), 10);
+        assert_eq!(writeset.get(&1), Option::Some(Option::None));
+        assert_eq!(writeset.get(&2), Option::Some(Option::Some(&20)));
+        assert_eq!(writeset.get(&10), Option::None);
+
+        let expected: Vec<(i32, Option<i32>)> = (0..10)
+            .map(|key| (key, Option::Some(key * 10).filter(|_| key % 3 != 1)))
+            .collect();
+        let forward: Vec<(i32, Option<i32>)> =
+            writeset.iter().map(|(k, v)| (*k, v.copied())).collect();
+        assert_eq!(forward, expected);
+        let backward: Vec<(i32, Option<i32>)> = writeset
+            .iter()
+            .rev()
+            .map(|(k, v)| (*k, v.copied()))
+            .collect();
+        assert_eq!(backward, expected.iter().rev().cloned().collect::<Vec<_>>());
+
+        // 両端から交互に走査しても、各要素はちょうど1回だけ返される
+        let mut iter = writeset.range(2..9);
+        let mut seen = Vec::new();
+        while let Option::Some((k, _)) = iter.next() {
+            seen.push(*k);
+            if let Option::Some((k, _)) = iter.next_back() {
+                seen.push(*k);
+            }
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, (2..9).collect::<Vec<_>>());
+
+        let owned: Vec<(i32, Option<i32>)> = writeset.into_iter().collect();
+        assert_eq!(owned, expected);
+        let rebuilt: WriteSet<i32, i32> = owned.into_iter().collect();
+        assert!(rebuilt.has_deletes());
+        assert_eq!(rebuilt.iter().count(), 10);
+    }
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "tokio")]
+extern crate mikrodb;
+extern crate tokio;
+
+use mikrodb::async_db::AsyncDatabase;
+use mikrodb::database::Database;
+use std::ops::Bound;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn interleaved_transactions() {
+    let db: AsyncDatabase<i32, i32> = AsyncDatabase::new(Database::in_memory().unwrap());
+    {
+        let mut tx = db.begin_transaction().await;
+        tx.create(0, 0).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    let tasks: Vec<_> = (1..=8)
+        .map(|t| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                for i in 0..50 {
+                    let mut tx = db.begin_transaction().await;
+                    let counter = tx.read_silent(0).await.unwrap();
+                    tx.update(0, counter + 1).await.unwrap();
+                    tx.create(t * 1000 + i, i).await.unwrap();
+                    tx.commit().await.unwrap();
+                    tokio::task::yield_now().await;
+
+                    let mut tx = db.begin_transaction().await;
+                    assert_eq!(tx.read_silent(t * 1000 + i).await.unwrap(), i);
+                    tx.abort().await.unwrap();
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let mut tx = db.begin_transaction().await;
+    assert_eq!(tx.read_silent(0).await.unwrap(), 400);
+    let items = tx
+        .scan_range(Bound::Included(1000), Bound::Excluded(2000))
+        .await
+        .unwrap();
+    assert_eq!(items, (0..50).map(|i| (1000 + i, i)).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn dropped_transaction_aborts() {
+    let db: AsyncDatabase<i32, i32> = AsyncDatabase::new(Database::in_memory().unwrap());
+    {
+        let mut tx = db.begin_transaction().await;
+        tx.create(1, 10).await.unwrap();
+    }
+    let mut tx = db.begin_transaction().await;
+    assert!(tx.read_silent(1).await.is_err());
+    tx.create(1, 20).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let mut tx = db.begin_transaction().await;
+    assert_eq!(tx.read_silent(1).await.unwrap(), 20);
+}
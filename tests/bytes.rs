@@ -0,0 +1,58 @@
+extern crate mikrodb;
+
+mod common;
+
+use common::crash;
+use mikrodb::bytes::{Bytes, BytesDatabase};
+use mikrodb::database::Database;
+
+#[test]
+fn put_and_get() {
+    let mut db: BytesDatabase = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.put(&[0, 1, 2], b"first").unwrap();
+    tx.put(&[0xff], &[]).unwrap();
+    assert_eq!(tx.get(&[0, 1, 2]).unwrap(), Option::Some(&b"first"[..]));
+    tx.put(&[0, 1, 2], b"second").unwrap();
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.get(&[0, 1, 2]).unwrap(), Option::Some(&b"second"[..]));
+    assert_eq!(tx.get(&[0xff]).unwrap(), Option::Some(&[][..]));
+    assert_eq!(tx.get(&[0, 1]).unwrap(), Option::None);
+    let keys: Vec<Bytes> = tx
+        .scan_prefix(&Bytes(vec![0]))
+        .map(|r| r.unwrap().0)
+        .collect();
+    assert_eq!(keys, vec![Bytes(vec![0, 1, 2])]);
+    tx.commit().unwrap();
+}
+
+#[test]
+fn redo_binary_keys() {
+    // 最後の段階でログを残したまま終了するため、別のfeatureで書き込まれたログを取り除く
+    let _ = std::fs::remove_dir_all("redo_bytes.log");
+    {
+        let mut db: BytesDatabase =
+            Database::with_defaults("redo_bytes.log", "redo_bytes.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.put(&[0, 0], &[1, 2, 3]).unwrap();
+        tx.commit().unwrap();
+    }
+    {
+        let mut db: BytesDatabase =
+            Database::with_defaults("redo_bytes.log", "redo_bytes.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.put(&[0xfe, 0xff], &[0; 16]).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    let db: BytesDatabase = Database::with_defaults("redo_bytes.log", "redo_bytes.db").unwrap();
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(Bytes(vec![0, 0])).unwrap(), Bytes(vec![1, 2, 3]));
+    assert_eq!(
+        tx.read(Bytes(vec![0xfe, 0xff])).unwrap(),
+        Bytes(vec![0; 16])
+    );
+}
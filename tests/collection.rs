@@ -0,0 +1,72 @@
+extern crate mikrodb;
+
+mod common;
+
+use common::crash;
+use mikrodb::database::Database;
+
+#[test]
+fn independent_collections() {
+    let mut db: Database<String, String> = Database::in_memory().unwrap();
+    {
+        let mut users = db.collection::<i32, String>("users");
+        let mut tx = users.begin_transaction().unwrap();
+        tx.create(1, "alice".to_string()).unwrap();
+        tx.create(2, "bob".to_string()).unwrap();
+        tx.commit().unwrap();
+    }
+    {
+        let mut scores = db.collection::<i32, u64>("scores");
+        let mut tx = scores.begin_transaction().unwrap();
+        tx.create(1, 100).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(scores.len(), 1);
+    }
+
+    let mut users = db.collection::<i32, String>("users");
+    assert_eq!(users.name(), "users");
+    assert_eq!(users.len(), 2);
+    let mut tx = users.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), "alice");
+    tx.delete(1).unwrap();
+    assert_eq!(tx.scan().unwrap(), vec![(2, "bob".to_string())]);
+    tx.commit().unwrap();
+
+    let mut scores = db.collection::<i32, u64>("scores");
+    let mut tx = scores.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 100);
+    assert!(tx.read_silent(2).is_err());
+    tx.commit().unwrap();
+
+    assert_eq!(
+        db.keys().cloned().collect::<Vec<_>>(),
+        vec!["scores:1".to_string(), "users:2".to_string()]
+    );
+}
+
+#[test]
+#[should_panic]
+fn collection_name_with_separator() {
+    let mut db: Database<String, String> = Database::in_memory().unwrap();
+    db.collection::<i32, i32>("a:b");
+}
+
+#[test]
+fn redo_collection() {
+    {
+        let mut db: Database<String, String> =
+            Database::with_defaults("redo_collection.log", "redo_collection.db").unwrap();
+        db.try_clear().unwrap();
+        let mut users = db.collection::<i32, String>("users");
+        let mut tx = users.begin_transaction().unwrap();
+        tx.create(1, "alice".to_string()).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    let mut db: Database<String, String> =
+        Database::with_defaults("redo_collection.log", "redo_collection.db").unwrap();
+    let mut users = db.collection::<i32, String>("users");
+    let mut tx = users.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), "alice");
+    tx.commit().unwrap();
+}
@@ -0,0 +1,22 @@
+use mikrodb::database::Database;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::Path;
+
+/// Dropを行わずにdbを破棄し、プロセスのクラッシュを模倣する
+///
+/// クラッシュしたプロセスのロックはOSにより解放されるため、同じプロセス内で開き直せるように
+/// ロックファイルを削除する。
+pub fn crash<K, V>(db: Database<K, V>)
+where
+    K: Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: Debug + Clone + Serialize + DeserializeOwned,
+{
+    let lock_path = db.lock_path().map(Path::to_path_buf);
+    std::mem::forget(db);
+    if let Option::Some(lock_path) = lock_path {
+        std::fs::remove_file(lock_path).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+extern crate mikrodb;
+
+mod common;
+
+use common::crash;
+use mikrodb::comparator::{CaseInsensitiveComparator, ComparatorDatabase, ComparatorKey};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::error::DatabaseError;
+use mikrodb::store::StorageBackend;
+use std::ops::Bound;
+
+type Key = ComparatorKey<String, CaseInsensitiveComparator>;
+
+fn key(s: &str) -> Key {
+    ComparatorKey::new(s.to_string())
+}
+
+#[test]
+fn case_insensitive_keys_collide() {
+    for &backend in &[StorageBackend::Sorted, StorageBackend::Hashed] {
+        let config = DatabaseConfig::builder()
+            .in_memory(true)
+            .storage_backend(backend)
+            .build();
+        let mut db: ComparatorDatabase<String, i32, CaseInsensitiveComparator> =
+            Database::new(config).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(key("Alice"), 1).unwrap();
+        assert!(matches!(
+            tx.create(key("ALICE"), 2),
+            Result::Err(DatabaseError::KeyDuplicationError)
+        ));
+        assert!(tx.upsert(key("alice"), 3).unwrap());
+        tx.commit().unwrap();
+
+        assert_eq!(db.len(), 1);
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(key("aLiCe")).unwrap(), 3);
+        tx.delete(key("ALICE")).unwrap();
+        assert!(tx.get_ref(&key("Alice")).unwrap().is_none());
+        tx.commit().unwrap();
+        assert!(db.is_empty());
+    }
+}
+
+#[test]
+fn case_insensitive_order() {
+    let mut db: ComparatorDatabase<String, i32, CaseInsensitiveComparator> =
+        Database::in_memory().unwrap();
+    db.extend_transaction(vec![
+        (key("banana"), 2),
+        (key("Cherry"), 3),
+        (key("apple"), 1),
+        (key("Banana"), 4),
+    ])
+    .unwrap();
+    // 最初に書き込まれたキーが保持される
+    let keys: Vec<&str> = db.keys().map(|k| k.key().as_str()).collect();
+    assert_eq!(keys, vec!["apple", "banana", "Cherry"]);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(key("BLUEBERRY"), 5).unwrap();
+    let scanned: Vec<(String, i32)> = tx
+        .scan_range(Bound::Included(key("B")), Bound::Excluded(key("c")))
+        .map(|r| r.map(|(k, v)| (k.into_inner(), v)).unwrap())
+        .collect();
+    assert_eq!(
+        scanned,
+        vec![("banana".to_string(), 4), ("BLUEBERRY".to_string(), 5)]
+    );
+    tx.commit().unwrap();
+}
+
+#[test]
+fn redo_case_insensitive_keys() {
+    let _ = std::fs::remove_dir_all("comparator.log");
+    let _ = std::fs::remove_file("comparator.db");
+    let config = DatabaseConfig::builder()
+        .log_file("comparator.log")
+        .data_file("comparator.db")
+        .build();
+    {
+        let mut db: ComparatorDatabase<String, i32, CaseInsensitiveComparator> =
+            Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![(key("Alice"), 1)]).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.upsert(key("ALICE"), 2).unwrap();
+        tx.create(key("bob"), 3).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    let db: ComparatorDatabase<String, i32, CaseInsensitiveComparator> =
+        Database::new(config).unwrap();
+    let pairs: Vec<(&str, i32)> = db
+        .scan_all()
+        .unwrap()
+        .map(|(k, v)| (k.key().as_str(), *v))
+        .collect();
+    assert_eq!(pairs, vec![("Alice", 2), ("bob", 3)]);
+}
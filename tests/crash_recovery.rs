@@ -1,29 +1,58 @@
 extern crate mikrodb;
 
-use mikrodb::database::Database;
+mod common;
+
+use common::crash;
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::{Database, DATA_FORMAT_VERSION};
+use mikrodb::error::DatabaseError;
+use mikrodb::log::{
+    ChecksumAlgorithm, LogRecord, SyncMode, TransactionOutcome, WALManager, WalRecoveryMode,
+};
+#[cfg(feature = "zstd")]
+use mikrodb::serialization::CompressionLevel;
+use mikrodb::serialization::DataFormat;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
 use std::mem;
+use std::ops::Bound;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// ログのディレクトリ内のセグメントファイルの合計のバイト数を返す
+fn log_size(dir: &str) -> u64 {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len())
+        .sum()
+}
 
 #[test]
 fn forget1() {
     {
-        let mut db: Database<i32, i32> = Database::new("forget1.log", "forget1.db").unwrap();
-        db.clear().unwrap();
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("forget1.log", "forget1.db").unwrap();
+        db.try_clear().unwrap();
         let mut tx = db.begin_transaction().unwrap();
         tx.create(1, 123).unwrap();
         tx.commit().unwrap();
     }
     {
-        let mut db: Database<i32, i32> = Database::new("forget1.log", "forget1.db").unwrap();
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("forget1.log", "forget1.db").unwrap();
         let mut tx = db.begin_transaction().unwrap();
-        assert_eq!(tx.read(1).unwrap(), 123);
+        assert_eq!(tx.read_silent(1).unwrap(), 123);
         tx.update(1, 456).unwrap();
         mem::forget(tx);
-        mem::forget(db);
+        crash(db);
     }
     {
-        let mut db: Database<i32, i32> = Database::new("forget1.log", "forget1.db").unwrap();
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("forget1.log", "forget1.db").unwrap();
         let mut tx = db.begin_transaction().unwrap();
-        assert_eq!(tx.read(1).unwrap(), 123);
+        assert_eq!(tx.read_silent(1).unwrap(), 123);
         tx.abort().unwrap();
     }
 }
@@ -31,24 +60,1852 @@ fn forget1() {
 #[test]
 fn redo1() {
     {
-        let mut db: Database<i32, i32> = Database::new("redo1.log", "redo1.db").unwrap();
-        db.clear().unwrap();
+        let mut db: Database<i32, i32> = Database::with_defaults("redo1.log", "redo1.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 123).unwrap();
+        tx.commit().unwrap();
+    }
+    {
+        let mut db: Database<i32, i32> = Database::with_defaults("redo1.log", "redo1.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 123);
+        tx.update(1, 456).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i32> = Database::with_defaults("redo1.log", "redo1.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 456);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn redo_upsert() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_upsert.log", "redo_upsert.db").unwrap();
+        db.try_clear().unwrap();
         let mut tx = db.begin_transaction().unwrap();
         tx.create(1, 123).unwrap();
         tx.commit().unwrap();
     }
     {
-        let mut db: Database<i32, i32> = Database::new("redo1.log", "redo1.db").unwrap();
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_upsert.log", "redo_upsert.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.upsert(1, 456).unwrap();
+        tx.upsert(2, 789).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_upsert.log", "redo_upsert.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 456);
+        assert_eq!(tx.read_silent(2).unwrap(), 789);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn checkpoint_interrupted() {
+    if std::env::var("MIKRODB_CHECKPOINT_INTERRUPTED").is_ok() {
+        // 子プロセス: 一時ファイルへの書き込み後、renameの前にプロセスが落ちた状態を再現する
+        let mut file = File::create("checkpoint_interrupted.tmp").unwrap();
+        file.write_all(b"{\"1\":4").unwrap();
+        file.sync_all().unwrap();
+        std::process::abort();
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("checkpoint_interrupted.log", "checkpoint_interrupted.db")
+                .unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 123).unwrap();
+        tx.commit().unwrap();
+    }
+    let before = std::fs::read("checkpoint_interrupted.db").unwrap();
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["checkpoint_interrupted", "--exact", "--test-threads=1"])
+        .env("MIKRODB_CHECKPOINT_INTERRUPTED", "1")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    assert_eq!(std::fs::read("checkpoint_interrupted.db").unwrap(), before);
+    std::fs::remove_file("checkpoint_interrupted.tmp").unwrap();
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("checkpoint_interrupted.log", "checkpoint_interrupted.db")
+                .unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 123);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn redo_compare_and_swap() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_cas.log", "redo_cas.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.create(2, 20).unwrap();
+        tx.commit().unwrap();
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_cas.log", "redo_cas.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert!(tx.compare_and_swap(1, &10, 11).unwrap());
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert!(tx.compare_and_swap(2, &20, 21).unwrap());
+        mem::forget(tx);
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_cas.log", "redo_cas.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 11);
+        assert_eq!(tx.read_silent(2).unwrap(), 20);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn skip_checkpointed_records() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("skip_checkpointed.log", "skip_checkpointed.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    // チェックポイントの作成後にログの破棄だけが失敗した状況を再現するため、ログを退避する
+    let stale_log: Vec<_> = std::fs::read_dir("skip_checkpointed.log")
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let content = std::fs::read(&path).unwrap();
+            (path, content)
+        })
+        .collect();
+    let checkpoint_lsn = {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("skip_checkpointed.log", "skip_checkpointed.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(1, 20).unwrap();
+        tx.commit().unwrap();
+        drop(db);
+        let db: Database<i32, i32> =
+            Database::with_defaults("skip_checkpointed.log", "skip_checkpointed.db").unwrap();
+        db.checkpoint_lsn()
+    };
+    assert!(checkpoint_lsn >= 4);
+    std::fs::remove_dir_all("skip_checkpointed.log").unwrap();
+    std::fs::create_dir("skip_checkpointed.log").unwrap();
+    for (path, content) in stale_log {
+        std::fs::write(path, content).unwrap();
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("skip_checkpointed.log", "skip_checkpointed.db").unwrap();
+        assert!(db.checkpoint_lsn() >= checkpoint_lsn);
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 20);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn redo_entry() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_entry.log", "redo_entry.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_entry.log", "redo_entry.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.entry(1).unwrap().and_modify(|v| *v += 1);
+        *tx.entry(2).unwrap().or_insert(20).unwrap() += 2;
+        tx.commit().unwrap();
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_entry.log", "redo_entry.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 11);
+        assert_eq!(tx.read_silent(2).unwrap(), 22);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn redo_savepoint() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_savepoint.log", "redo_savepoint.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        let savepoint = tx.savepoint("a").unwrap();
+        tx.update(1, 11).unwrap();
+        tx.create(2, 20).unwrap();
+        tx.rollback_to_savepoint(savepoint).unwrap();
+        tx.create(3, 30).unwrap();
+        tx.rollback_to_savepoint(savepoint).unwrap();
+        tx.create(4, 40).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_savepoint.log", "redo_savepoint.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 10);
+        assert!(tx.read_silent(2).is_err());
+        assert!(tx.read_silent(3).is_err());
+        assert_eq!(tx.read_silent(4).unwrap(), 40);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn redo_savepoint_after_relog() {
+    let config = || {
+        DatabaseConfig::builder()
+            .log_file("redo_savepoint_relog.log")
+            .data_file("redo_savepoint_relog.db")
+            .sync_on_commit(false)
+            // fsyncを行わないCommitがプロセスのクラッシュで失われないようにする
+            .wal_buffer_size(0)
+            .max_wal_bytes(1024)
+            .build()
+    };
+    {
+        let mut db: Database<i32, i32> = Database::new(config()).unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(0, 0).unwrap();
+        tx.create(-1, -1).unwrap();
+        let savepoint = tx.savepoint("before_bulk").unwrap();
+        // ログの容量の上限に達し、トランザクションの途中でチェックポイントが作成される
+        for x in 1..100 {
+            tx.create(x, x).unwrap();
+        }
+        tx.rollback_to_savepoint(savepoint).unwrap();
+        tx.update(0, 1).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i32> = Database::new(config()).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(0).unwrap(), 1);
+        assert_eq!(tx.read_silent(-1).unwrap(), -1);
+        for x in 1..100 {
+            assert!(tx.read_silent(x).is_err());
+        }
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn checkpoint_marker() {
+    // 最後にCheckpointMarkerと一致しないデータファイルを残すため、前回の実行の内容を取り除く
+    let _ = std::fs::remove_dir_all("checkpoint_marker.log");
+    let _ = std::fs::remove_file("checkpoint_marker.db");
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("checkpoint_marker.log", "checkpoint_marker.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+    }
+    let data_file_lsn = || {
+        let content = std::fs::read_to_string("checkpoint_marker.db").unwrap();
+        let content: serde_json::Value = serde_json::from_str(&content).unwrap();
+        content["header"]["checkpoint_lsn"].as_u64().unwrap()
+    };
+    let write_log = |checkpoint_lsn: u64, marker_lsn: u64| {
+        let mut wal = WALManager::new("checkpoint_marker.log").unwrap();
+        wal.clear().unwrap();
+        wal.advance_lsn(checkpoint_lsn);
+        let records = vec![
+            LogRecord::Upsert { key: 1, value: 99 },
+            LogRecord::Commit,
+            LogRecord::CheckpointMarker {
+                checkpoint_lsn: marker_lsn,
+            },
+            LogRecord::Create { key: 2, value: 20 },
+            LogRecord::Commit,
+        ];
+        for record in &records {
+            wal.write_log(record, true).unwrap();
+        }
+    };
+
+    // CheckpointMarker以前のレコードは反映済みとして読み飛ばされる
+    let checkpoint_lsn = data_file_lsn();
+    write_log(checkpoint_lsn, checkpoint_lsn);
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("checkpoint_marker.log", "checkpoint_marker.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 10);
+        assert_eq!(tx.read_silent(2).unwrap(), 20);
+        tx.delete(2).unwrap();
+        tx.commit().unwrap();
+    }
+
+    // データファイルがCheckpointMarkerより古い場合はエラーとなる
+    let checkpoint_lsn = data_file_lsn();
+    write_log(checkpoint_lsn, checkpoint_lsn + 100);
+    let result =
+        Database::<i32, i32>::with_defaults("checkpoint_marker.log", "checkpoint_marker.db");
+    match result.as_ref().map_err(DatabaseError::root) {
+        Result::Err(DatabaseError::CheckpointMismatchError { data_file, log }) => {
+            assert_eq!(*data_file, checkpoint_lsn);
+            assert_eq!(*log, checkpoint_lsn + 100);
+        }
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+}
+
+#[test]
+fn redo_delete_range() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_delete_range.log", "redo_delete_range.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        for x in 0..10 {
+            tx.create(x, x).unwrap();
+        }
+        tx.commit().unwrap();
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_delete_range.log", "redo_delete_range.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(20, 20).unwrap();
+        tx.delete_range(Bound::Excluded(5), Bound::Unbounded)
+            .unwrap();
+        tx.create(30, 30).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    {
+        let db: Database<i32, i32> =
+            Database::with_defaults("redo_delete_range.log", "redo_delete_range.db").unwrap();
+        assert_eq!(
+            db.keys().cloned().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5, 30]
+        );
+    }
+}
+
+#[test]
+fn redo_mixed_checksum_algorithms() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_mixed_checksum.log", "redo_mixed_checksum.db").unwrap();
+        db.try_clear().unwrap();
+    }
+    {
+        let content = std::fs::read_to_string("redo_mixed_checksum.db").unwrap();
+        let content: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let mut wal = WALManager::new("redo_mixed_checksum.log").unwrap();
+        wal.advance_lsn(content["header"]["checkpoint_lsn"].as_u64().unwrap());
+        wal.set_checksum_algorithm(ChecksumAlgorithm::Crc32c);
+        wal.write_log(&LogRecord::Create { key: 1, value: 123 }, false)
+            .unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Commit, false)
+            .unwrap();
+        wal.set_checksum_algorithm(ChecksumAlgorithm::Sha256);
+        wal.write_log(&LogRecord::Create { key: 2, value: 456 }, false)
+            .unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Commit, true).unwrap();
+    }
+    let config = DatabaseConfig::builder()
+        .log_file("redo_mixed_checksum.log")
+        .data_file("redo_mixed_checksum.db")
+        .checksum_algorithm(ChecksumAlgorithm::Crc32c)
+        .build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 123);
+    assert_eq!(tx.read_silent(2).unwrap(), 456);
+    tx.commit().unwrap();
+}
+
+#[test]
+fn data_file_corrupted() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("data_file_corrupted.log", "data_file_corrupted.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+        db.verify_integrity().unwrap();
+    }
+    let content = std::fs::read_to_string("data_file_corrupted.db").unwrap();
+    let corrupted = content.replace(r#""1":10"#, r#""1":11"#);
+    assert_ne!(content, corrupted);
+    std::fs::write("data_file_corrupted.db", &corrupted).unwrap();
+    match Database::<i32, i32>::with_defaults("data_file_corrupted.log", "data_file_corrupted.db") {
+        Result::Err(DatabaseError::DataFileCorrupted { .. }) => {}
+        Result::Err(e) => panic!("unexpected error: {:?}", e),
+        Result::Ok(_) => panic!("corruption was not detected"),
+    }
+
+    std::fs::write("data_file_corrupted.db", &content).unwrap();
+    let db: Database<i32, i32> =
+        Database::with_defaults("data_file_corrupted.log", "data_file_corrupted.db").unwrap();
+    db.verify_integrity().unwrap();
+    std::fs::write("data_file_corrupted.db", &corrupted).unwrap();
+    assert!(db.verify_integrity().is_err());
+}
+
+#[test]
+fn redo_increment() {
+    {
+        let mut db: Database<i32, i64> =
+            Database::with_defaults("redo_increment.log", "redo_increment.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+    }
+    {
+        let mut db: Database<i32, i64> =
+            Database::with_defaults("redo_increment.log", "redo_increment.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.atomic_increment(1, 5).unwrap();
+        tx.atomic_decrement(1, 20).unwrap();
+        tx.atomic_increment(2, 3).unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.atomic_increment(2, 100).unwrap();
+        tx.abort().unwrap();
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i64> =
+            Database::with_defaults("redo_increment.log", "redo_increment.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), -5);
+        assert_eq!(tx.read_silent(2).unwrap(), 3);
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn redo_ttl() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_ttl.log", "redo_ttl.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create_with_ttl(1, 10, Duration::from_secs(3600))
+            .unwrap();
+        tx.create_with_ttl(2, 20, Duration::from_secs(0)).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    thread::sleep(Duration::from_millis(1100));
+    for _ in 0..2 {
+        // 1回目はRedoにより、2回目はデータファイルから有効期限が復元される
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_ttl.log", "redo_ttl.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 10);
+        assert!(tx.read_silent(2).is_err());
+        tx.commit().unwrap();
+        assert_eq!(db.len(), 2);
+    }
+    let content = std::fs::read_to_string("redo_ttl.db").unwrap();
+    let content: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(content["expiry"].as_object().unwrap().len(), 2);
+}
+
+#[test]
+fn redo_recreate_after_delete_clears_ttl() {
+    {
+        let mut db: Database<i32, i32> = Database::with_defaults(
+            "redo_recreate_after_delete_clears_ttl.log",
+            "redo_recreate_after_delete_clears_ttl.db",
+        )
+        .unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create_with_ttl(1, 10, Duration::from_secs(1)).unwrap();
+        tx.create_with_ttl(2, 20, Duration::from_secs(1)).unwrap();
+        tx.create_with_ttl(3, 30, Duration::from_secs(1)).unwrap();
+        tx.commit().unwrap();
+
+        // 有効期限内に削除して作成し直したキーは、コミット済みの有効期限を引き継がない
+        let mut tx = db.begin_transaction().unwrap();
+        tx.delete(1).unwrap();
+        tx.create(1, 11).unwrap();
+        tx.write_batch(vec![(2, Option::None), (2, Option::Some(21))])
+            .unwrap();
+        tx.write_batch(vec![(3, Option::None)]).unwrap();
+        tx.write_batch(vec![(3, Option::Some(31))]).unwrap();
+        tx.commit().unwrap();
+        thread::sleep(Duration::from_millis(2100));
+
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 11);
+        assert_eq!(tx.read_silent(2).unwrap(), 21);
+        assert_eq!(tx.read_silent(3).unwrap(), 31);
+        tx.commit().unwrap();
+        crash(db);
+    }
+    // Redoの結果もクラッシュ前と一致する
+    let mut db: Database<i32, i32> = Database::with_defaults(
+        "redo_recreate_after_delete_clears_ttl.log",
+        "redo_recreate_after_delete_clears_ttl.db",
+    )
+    .unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 11);
+    assert_eq!(tx.read_silent(2).unwrap(), 21);
+    assert_eq!(tx.read_silent(3).unwrap(), 31);
+    tx.commit().unwrap();
+    assert_eq!(db.purge_expired().unwrap(), 0);
+}
+
+#[test]
+fn redo_segmented_log() {
+    let config = DatabaseConfig::builder()
+        .log_file("redo_segmented_log.log")
+        .data_file("redo_segmented_log.db")
+        .wal_segment_size(512)
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.try_clear().unwrap();
+        for x in 0..100 {
+            let mut tx = db.begin_transaction().unwrap();
+            tx.create(x, x * 10).unwrap();
+            tx.commit().unwrap();
+        }
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(0, -1).unwrap();
+        tx.abort().unwrap();
+        crash(db);
+    }
+    let segments = std::fs::read_dir("redo_segmented_log.log").unwrap().count();
+    assert!(segments > 1);
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        for x in 0..100 {
+            assert_eq!(tx.read_silent(x).unwrap(), x * 10);
+        }
+        tx.commit().unwrap();
+    }
+    // チェックポイントの作成により、すべてのセグメントが破棄される
+    assert_eq!(
+        std::fs::read_dir("redo_segmented_log.log").unwrap().count(),
+        1
+    );
+}
+
+#[test]
+fn legacy_single_file_log() {
+    let _ = std::fs::remove_dir_all("legacy_single_file.log");
+    {
+        // セグメントに分割されていない旧形式のログファイルを用意する
+        let mut wal =
+            WALManager::with_storage(Box::new(File::create("legacy_single_file.log").unwrap()));
+        let records: Vec<LogRecord<i32, i32>> =
+            vec![LogRecord::Create { key: 1, value: 10 }, LogRecord::Commit];
+        for record in &records {
+            wal.write_log(record, true).unwrap();
+        }
+    }
+    let _ = std::fs::remove_file("legacy_single_file.db");
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("legacy_single_file.log", "legacy_single_file.db").unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 10);
+    tx.commit().unwrap();
+    assert!(std::path::Path::new("legacy_single_file.log").is_dir());
+}
+
+#[test]
+fn redo_from_checkpoint_offset() {
+    let config = DatabaseConfig::builder()
+        .log_file("redo_from_offset.log")
+        .data_file("redo_from_offset.db")
+        .build();
+    let stale_log: Vec<_> = {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.try_clear().unwrap();
+        for x in 0..1000 {
+            let mut tx = db.begin_transaction().unwrap();
+            tx.create(x, x).unwrap();
+            tx.commit().unwrap();
+        }
+        // ログの破棄に失敗した状況を再現するため、チェックポイントの作成前のログを退避する
+        let stale_log = std::fs::read_dir("redo_from_offset.log")
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let content = std::fs::read(&path).unwrap();
+                (path, content)
+            })
+            .collect();
+        let tx = db.begin_transaction().unwrap();
+        let start_offset = tx.start_offset();
+        mem::forget(tx);
+        drop(db);
+        let content = std::fs::read_to_string("redo_from_offset.db").unwrap();
+        let content: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(content["header"]["wal_offset"], start_offset);
+        stale_log
+    };
+    std::fs::remove_dir_all("redo_from_offset.log").unwrap();
+    std::fs::create_dir("redo_from_offset.log").unwrap();
+    for (path, content) in &stale_log {
+        std::fs::write(path, content).unwrap();
+    }
+    {
+        // 破棄されなかったログの末尾に、CheckpointMarkerと以降のトランザクションが続く
+        let mut wal = WALManager::new("redo_from_offset.log").unwrap();
+        wal.read_log_with_lsn::<i32, i32>().unwrap();
+        let marker: LogRecord<i32, i32> = LogRecord::CheckpointMarker {
+            checkpoint_lsn: wal.current_lsn(),
+        };
+        wal.write_log(&marker, false).unwrap();
+        let create: LogRecord<i32, i32> = LogRecord::Create {
+            key: 1000,
+            value: 1000,
+        };
+        wal.write_log(&create, false).unwrap();
+        wal.write_log::<i32, i32>(&LogRecord::Commit, true).unwrap();
+    }
+    // チェックポイント以前のレコードを壊しても、Redoはそれを読み取らない
+    let (path, _) = &stale_log[0];
+    let mut content = std::fs::read(path).unwrap();
+    content[0] ^= 0xff;
+    std::fs::write(path, content).unwrap();
+
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(0).unwrap(), 0);
+    assert_eq!(tx.read_silent(1000).unwrap(), 1000);
+    tx.commit().unwrap();
+}
+
+#[test]
+fn open_read_only() {
+    // 最後の段階でログを残したまま終了するため、別のfeatureで書き込まれたログを取り除く
+    let _ = std::fs::remove_dir_all("open_read_only.log");
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("open_read_only.log", "open_read_only.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.create(2, 20).unwrap();
+        tx.commit().unwrap();
+        assert!(!db.is_read_only());
+    }
+    let content = std::fs::read("open_read_only.db").unwrap();
+    {
+        let mut db: Database<i32, i32> = Database::open_read_only("open_read_only.db").unwrap();
+        assert!(db.is_read_only());
+        assert_eq!(db.len(), 2);
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 10);
+        match tx.update(1, 11) {
+            Result::Err(DatabaseError::ReadOnlyDatabase) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match tx.commit() {
+            Result::Err(DatabaseError::ReadOnlyDatabase) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(db.compact_wal().is_err());
+        assert!(db.clear().is_err());
+    }
+    // 読み取り専用のデータベースはファイルを変更しない
+    assert_eq!(std::fs::read("open_read_only.db").unwrap(), content);
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("open_read_only.log", "open_read_only.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(1, 100).unwrap();
+        tx.commit().unwrap();
+        drop(db);
+
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("open_read_only.log", "open_read_only.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(2, 200).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    // チェックポイントに反映されていない操作は見えない
+    let db: Database<i32, i32> = Database::open_read_only("open_read_only.db").unwrap();
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(1).unwrap(), 100);
+    assert_eq!(tx.read(2).unwrap(), 20);
+}
+
+#[test]
+fn database_lock() {
+    let _ = std::fs::remove_dir_all("database_lock.log");
+    let _ = std::fs::remove_file("database_lock.db");
+    let config = DatabaseConfig::builder()
+        .log_file("database_lock.log")
+        .data_file("database_lock.db")
+        .build();
+    {
+        let db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        assert_eq!(
+            db.lock_path(),
+            Option::Some(std::path::Path::new("database_lock.db.lock"))
+        );
+        match Database::<i32, i32>::new(config.clone()) {
+            Result::Err(DatabaseError::DatabaseLocked { lock_path }) => {
+                assert_eq!(lock_path, "database_lock.db.lock");
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+        // 読み取り専用では開くことができる
+        let read_only: Database<i32, i32> = Database::open_read_only("database_lock.db").unwrap();
+        assert_eq!(read_only.lock_path(), Option::None);
+    }
+    // Drop時にロックファイルは削除される
+    assert!(!std::path::Path::new("database_lock.db.lock").exists());
+
+    // ロックが解放されたロックファイルは引き継ぐ
+    File::create("database_lock.db.lock").unwrap();
+    let db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+    assert!(db.lock_path().is_some());
+    drop(db);
+    assert!(!std::path::Path::new("database_lock.db.lock").exists());
+
+    let db: Database<i32, i32> = Database::new_unchecked(config).unwrap();
+    assert_eq!(db.lock_path(), Option::None);
+    assert!(!std::path::Path::new("database_lock.db.lock").exists());
+}
+
+#[test]
+fn unsupported_data_format_version() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("data_format_version.log", "data_format_version.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+    }
+    let content = std::fs::read_to_string("data_format_version.db").unwrap();
+    assert!(content.contains(r#""version":2,"#));
+    Database::<i32, i32>::migrate_data_file(2, DATA_FORMAT_VERSION, "data_format_version.db")
+        .unwrap();
+    assert!(Database::<i32, i32>::migrate_data_file(1, 2, "data_format_version.db").is_err());
+    assert!(Database::<i32, i32>::migrate_data_file(2, 3, "data_format_version.db").is_err());
+
+    // 未知のバージョンのデータファイルはJSONとして解釈せずにエラーとする
+    std::fs::write(
+        "data_format_version.db",
+        content.replace(r#""version":2,"#, r#""version":3,"header":[],"#),
+    )
+    .unwrap();
+    let result: Result<Database<i32, i32>, _> =
+        Database::with_defaults("data_format_version.log", "data_format_version.db");
+    match result {
+        Result::Err(DatabaseError::UnsupportedDataFormatVersion { found, expected }) => {
+            assert_eq!((found, expected), (3, DATA_FORMAT_VERSION))
+        }
+        Result::Err(e) => panic!("unexpected error: {:?}", e),
+        Result::Ok(_) => panic!("unexpected success"),
+    }
+    match Database::<i32, i32>::open_read_only("data_format_version.db") {
+        Result::Err(DatabaseError::UnsupportedDataFormatVersion { .. }) => {}
+        Result::Err(e) => panic!("unexpected error: {:?}", e),
+        Result::Ok(_) => panic!("unexpected success"),
+    }
+    std::fs::write("data_format_version.db", content).unwrap();
+}
+
+#[test]
+fn migrate_data_file_from_version_1() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = DatabaseConfig::builder().data_dir(dir.path()).build();
+    let datapath = config.data_path();
+    // バージョンを持たない旧形式のデータファイルはバージョン1である
+    std::fs::write(&datapath, r#"{"1":10,"2":20}"#).unwrap();
+    match Database::<i32, i32>::new(config.clone()) {
+        Result::Err(DatabaseError::UnsupportedDataFormatVersion { found, expected }) => {
+            assert_eq!((found, expected), (1, DATA_FORMAT_VERSION))
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    Database::<i32, i32>::migrate_data_file(1, DATA_FORMAT_VERSION, &datapath).unwrap();
+    let content = std::fs::read_to_string(&datapath).unwrap();
+    assert!(content.contains(r#""version":2,"#));
+    let db: Database<i32, i32> = Database::new(config).unwrap();
+    let pairs: Vec<(i32, i32)> = db.scan_all().unwrap().map(|(k, v)| (*k, *v)).collect();
+    assert_eq!(pairs, vec![(1, 10), (2, 20)]);
+}
+
+#[test]
+fn data_file_read_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = DatabaseConfig::builder().data_dir(dir.path()).build();
+    let datapath = config.data_path();
+    // 読み込めないデータファイルは、存在しないものとして扱わない
+    std::fs::create_dir(&datapath).unwrap();
+    let error = Database::<i32, i32>::new(config).map(|_| ()).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with(&format!("reading the data file {}: ", datapath.display())));
+    assert!(matches!(error.root(), DatabaseError::IOError { .. }));
+}
+
+#[test]
+fn data_format() {
+    let config = |format| {
+        DatabaseConfig::builder()
+            .log_file("data_format.log")
+            .data_file("data_format.db")
+            .data_format(format)
+            .build()
+    };
+    let _ = std::fs::remove_file("data_format.db");
+    {
+        let mut db: Database<String, Vec<i32>> =
+            Database::new(config(DataFormat::Bincode)).unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create("a".to_string(), vec![1, 2]).unwrap();
+        tx.commit().unwrap();
+        db.compact_wal().unwrap();
+    }
+    assert!(std::fs::read("data_format.db")
+        .unwrap()
+        .starts_with(b"MKDB"));
+    {
+        let db: Database<String, Vec<i32>> = Database::new(config(DataFormat::Bincode)).unwrap();
+        let tx = db.begin_read_transaction().unwrap();
+        assert_eq!(tx.read("a".to_string()).unwrap(), vec![1, 2]);
+    }
+    let db: Database<String, Vec<i32>> = Database::open_read_only("data_format.db").unwrap();
+    assert_eq!(db.len(), 1);
+    drop(db);
+
+    // 異なる形式で開こうとした場合は、内容を解釈せずにエラーとする
+    match Database::<String, Vec<i32>>::new(config(DataFormat::Json)) {
+        Result::Err(DatabaseError::DataFormatMismatch { found, expected }) => {
+            assert_eq!((found, expected), (DataFormat::Bincode, DataFormat::Json))
+        }
+        Result::Err(e) => panic!("unexpected error: {:?}", e),
+        Result::Ok(_) => panic!("unexpected success"),
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_data_format() {
+    let config = |format| {
+        DatabaseConfig::builder()
+            .log_file("cbor_data_format.log")
+            .data_file("cbor_data_format.db")
+            .data_format(format)
+            .build()
+    };
+    let _ = std::fs::remove_file("cbor_data_format.db");
+    {
+        let mut db: Database<i32, String> = Database::new(config(DataFormat::Cbor)).unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, "a".to_string()).unwrap();
+        tx.commit().unwrap();
+        db.compact_wal().unwrap();
+    }
+    {
+        let db: Database<i32, String> = Database::new(config(DataFormat::Cbor)).unwrap();
+        let tx = db.begin_read_transaction().unwrap();
+        assert_eq!(tx.read(1).unwrap(), "a");
+    }
+    match Database::<i32, String>::new(config(DataFormat::Json)) {
+        Result::Err(DatabaseError::DataFormatMismatch { found, .. }) => {
+            assert_eq!(found, DataFormat::Cbor)
+        }
+        Result::Err(e) => panic!("unexpected error: {:?}", e),
+        Result::Ok(_) => panic!("unexpected success"),
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn data_compression() {
+    let config = |level| {
+        DatabaseConfig::builder()
+            .log_file("data_compression.log")
+            .data_file("data_compression.db")
+            .data_compression(level)
+            .build()
+    };
+    let mut sizes = Vec::new();
+    for &level in &[CompressionLevel::None, CompressionLevel::Best] {
+        {
+            let mut db: Database<i32, String> = Database::new(config(level)).unwrap();
+            db.try_clear().unwrap();
+            let mut tx = db.begin_transaction().unwrap();
+            for i in 0..1000 {
+                tx.upsert(i, "value".repeat(10)).unwrap();
+            }
+            tx.commit().unwrap();
+            db.compact_wal().unwrap();
+        }
+        let content = std::fs::read("data_compression.db").unwrap();
+        assert_eq!(
+            content.starts_with(b"MKDB\x01"),
+            level != CompressionLevel::None
+        );
+        sizes.push(content.len());
+
+        // 圧縮の有無はヘッダから判別されるため、設定に関わらず読み込める
+        let db: Database<i32, String> = Database::new(config(CompressionLevel::None)).unwrap();
+        assert_eq!(db.len(), 1000);
+        drop(db);
+        let db: Database<i32, String> = Database::open_read_only("data_compression.db").unwrap();
+        let tx = db.begin_read_transaction().unwrap();
+        assert_eq!(tx.read(999).unwrap(), "value".repeat(10));
+    }
+    assert!(sizes[1] < sizes[0]);
+}
+
+#[test]
+fn redo_skips_heartbeat() {
+    let _ = std::fs::remove_dir_all("redo_heartbeat.log");
+    let _ = std::fs::remove_file("redo_heartbeat.db");
+    {
+        let mut wal = WALManager::new("redo_heartbeat.log").unwrap();
+        wal.write_heartbeat().unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Create { key: 1, value: 10 }, false)
+            .unwrap();
+        wal.write_heartbeat().unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Commit, false)
+            .unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Create { key: 2, value: 20 }, false)
+            .unwrap();
+        wal.write_heartbeat().unwrap();
+        wal.write_log(&LogRecord::<i32, i32>::Abort, false).unwrap();
+        wal.write_heartbeat().unwrap();
+        wal.flush_buffer().unwrap();
+        // ハートビートはレコード数に数えない
+        assert_eq!(wal.record_count(), 4);
+
+        let records = wal.read_log::<i32, i32>().unwrap();
+        assert_eq!(records.len(), 8);
+        match records[0] {
+            LogRecord::Noop { timestamp_secs } => assert!(timestamp_secs > 0),
+            ref other => panic!("unexpected record: {:?}", other),
+        }
+    }
+    let db: Database<i32, i32> =
+        Database::with_defaults("redo_heartbeat.log", "redo_heartbeat.db").unwrap();
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(1).unwrap(), 10);
+    assert!(tx.read(2).is_err());
+    assert_eq!(db.len(), 1);
+}
+
+#[test]
+fn rebuild_from_wal() {
+    // 最後の段階でログを残したまま終了するため、別のfeatureで書き込まれたログを取り除く
+    let _ = std::fs::remove_dir_all("rebuild_from_wal.log");
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("rebuild_from_wal.log", "rebuild_from_wal.db").unwrap();
+        db.try_clear().unwrap();
+        db.compact_wal().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.create(2, 20).unwrap();
+        tx.create_with_ttl(3, 30, Duration::from_secs(3600))
+            .unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(1, 11).unwrap();
+        tx.delete(2).unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(4, 40).unwrap();
+        tx.abort().unwrap();
+        crash(db);
+    }
+    std::fs::remove_file("rebuild_from_wal.db").unwrap();
+    assert!(Database::<i32, i32>::rebuild_from_wal("missing.log", "missing.db").is_err());
+    let log = log_size("rebuild_from_wal.log");
+    Database::<i32, i32>::rebuild_from_wal("rebuild_from_wal.log", "rebuild_from_wal.db").unwrap();
+    // ログは変更されない
+    assert_eq!(log_size("rebuild_from_wal.log"), log);
+
+    let content = std::fs::read_to_string("rebuild_from_wal.db").unwrap();
+    let content: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert!(content["expiry"].get("3").is_some());
+
+    // 同じログと共に開いても、操作は二重に反映されない
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("rebuild_from_wal.log", "rebuild_from_wal.db").unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &11), (&3, &30)]
+    );
+    let mut tx = db.begin_transaction().unwrap();
+    assert!(tx.create(4, 40).is_ok());
+    tx.commit().unwrap();
+}
+
+/// SHA256のチェックサムで書き込まれたログ上の各フレームの開始位置を返す
+fn frame_offsets(content: &[u8]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut position = 0;
+    while position < content.len() {
+        offsets.push(position);
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&content[position + 41..position + 49]);
+        position += 49 + u64::from_le_bytes(len) as usize;
+    }
+    offsets
+}
+
+#[test]
+fn lenient_recovery() {
+    let config = DatabaseConfig::builder()
+        .log_file("lenient_recovery.log")
+        .data_file("lenient_recovery.db")
+        .wal_buffer_size(0)
+        .wal_recovery_mode(WalRecoveryMode::Lenient)
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(2, 20).unwrap();
+        tx.create(3, 30).unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(4, 40).unwrap();
+        tx.commit().unwrap();
+        // Commitされないまま終了したトランザクション
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(5, 50).unwrap();
+        mem::forget(tx);
+        crash(db);
+    }
+    let segment = std::fs::read_dir("lenient_recovery.log")
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let mut content = std::fs::read(&segment).unwrap();
+    // [Create 1][Commit][Create 2][Create 3][Commit][Create 4][Commit][Create 5]
+    let offsets = frame_offsets(&content);
+    assert_eq!(offsets.len(), 8);
+    content[offsets[3] + 50] ^= 0xff;
+    std::fs::write(&segment, &content).unwrap();
+
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    assert_eq!(db.corruption_events().len(), 1);
+    assert_eq!(db.corruption_events()[0].offset, offsets[3] as u64);
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 10);
+    // 破損したレコードを含むトランザクションはAbortされたものとして扱う
+    assert!(tx.read_silent(2).is_err());
+    assert!(tx.read_silent(3).is_err());
+    assert_eq!(tx.read_silent(4).unwrap(), 40);
+    assert!(tx.read_silent(5).is_err());
+    tx.commit().unwrap();
+}
+
+#[test]
+fn verify_wal_integrity() {
+    let _ = std::fs::remove_dir_all("verify_wal_integrity.log");
+    let _ = std::fs::remove_file("verify_wal_integrity.db");
+    let config = DatabaseConfig::builder()
+        .log_file("verify_wal_integrity.log")
+        .data_file("verify_wal_integrity.db")
+        .wal_buffer_size(0)
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(2, 20).unwrap();
+        tx.create(3, 30).unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(4, 40).unwrap();
+        tx.abort().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(5, 50).unwrap();
+        mem::forget(tx);
+        crash(db);
+    }
+    let segment = std::fs::read_dir("verify_wal_integrity.log")
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let mut content = std::fs::read(&segment).unwrap();
+    // [Create 1][Commit][Create 2][Create 3][Commit][Create 4][Abort][Create 5]
+    let offsets = frame_offsets(&content);
+    assert_eq!(offsets.len(), 8);
+    content[offsets[3] + 50] ^= 0xff;
+    content[offsets[6] + 50] ^= 0xff;
+    std::fs::write(&segment, &content).unwrap();
+
+    let mut wal = WALManager::new("verify_wal_integrity.log").unwrap();
+    let report = wal.verify_integrity::<i32, i32>().unwrap();
+    assert_eq!(report.total_records, 6);
+    assert_eq!(report.corrupt_records, 2);
+    assert_eq!(
+        report.corrupt_offsets,
+        vec![offsets[3] as u64, offsets[6] as u64]
+    );
+    let summaries: Vec<(u64, u64, usize, TransactionOutcome)> = report
+        .transactions
+        .iter()
+        .map(|t| (t.first_lsn, t.last_lsn, t.record_count, t.outcome))
+        .collect();
+    // 初期化時のチェックポイントでLSN 1が使われている。Abortレコードが破損したトランザクションは、
+    // 後続のCommitされていないトランザクションとまとめられる
+    assert_eq!(
+        summaries,
+        vec![
+            (2, 3, 2, TransactionOutcome::Commit),
+            (4, 6, 2, TransactionOutcome::Commit),
+            (7, 9, 2, TransactionOutcome::Incomplete),
+        ]
+    );
+    // ログは変更されず、繰り返し検証できる
+    assert_eq!(wal.verify_integrity::<i32, i32>().unwrap(), report);
+    drop(wal);
+    assert_eq!(std::fs::read(&segment).unwrap(), content);
+}
+
+#[test]
+fn undo_before_images() {
+    let _ = std::fs::remove_dir_all("undo_before_images.log");
+    let _ = std::fs::remove_file("undo_before_images.db");
+    let config = DatabaseConfig::builder()
+        .log_file("undo_before_images.log")
+        .data_file("undo_before_images.db")
+        .wal_buffer_size(0)
+        .undo_logging(true)
+        .build();
+    let committed: BTreeMap<i32, i32> = vec![(1, 10), (2, 20), (3, 30)].into_iter().collect();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(committed.clone()).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(1, 11).unwrap();
+        tx.delete(2).unwrap();
+        tx.create(4, 40).unwrap();
+        tx.upsert(1, 12).unwrap();
+        tx.atomic_increment(3, 5).unwrap();
+        assert!(tx.compare_and_swap(4, &40, 41).unwrap());
+        // Commitされないままクラッシュする
+        mem::forget(tx);
+        crash(db);
+    }
+    let records = WALManager::new("undo_before_images.log")
+        .unwrap()
+        .read_log::<i32, i32>()
+        .unwrap();
+    let last_commit = records
+        .iter()
+        .rposition(|record| *record == LogRecord::Commit)
+        .unwrap();
+    let before_images: Vec<(i32, Option<i32>)> = records[last_commit + 1..]
+        .iter()
+        .filter_map(|record| match record {
+            LogRecord::BeforeImage { key, old_value } => Option::Some((*key, *old_value)),
+            _ => Option::None,
+        })
+        .collect();
+    assert_eq!(
+        before_images,
+        vec![
+            (1, Option::Some(10)),
+            (2, Option::Some(20)),
+            (4, Option::None),
+            (1, Option::Some(11)),
+            (3, Option::Some(30)),
+            (4, Option::Some(40)),
+        ]
+    );
+
+    // 書き込みが反映された状態に、BeforeImageを逆順に適用するとトランザクション開始前の状態に戻る
+    let mut state: BTreeMap<i32, i32> = vec![(1, 12), (3, 35), (4, 41)].into_iter().collect();
+    for (key, old_value) in before_images.into_iter().rev() {
+        match old_value {
+            Option::Some(value) => state.insert(key, value),
+            Option::None => state.remove(&key),
+        };
+    }
+    assert_eq!(state, committed);
+
+    // BeforeImageレコードはRedoに使用されない
+    let db: Database<i32, i32> = Database::new(config).unwrap();
+    let recovered: BTreeMap<i32, i32> = db.scan_all().unwrap().map(|(k, v)| (*k, *v)).collect();
+    assert_eq!(recovered, committed);
+}
+
+#[test]
+fn crash_after_prepare() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("crash_after_prepare.log", "crash_after_prepare.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 123).unwrap();
+        tx.commit().unwrap();
         let mut tx = db.begin_transaction().unwrap();
-        assert_eq!(tx.read(1).unwrap(), 123);
         tx.update(1, 456).unwrap();
+        tx.create(2, 789).unwrap();
+        let prepared = tx.prepare().unwrap();
+        // Commit・Rollbackを行わないままクラッシュする
+        mem::forget(prepared);
+        crash(db);
+    }
+    {
+        // Prepareレコードまではfsyncされている
+        let mut wal = WALManager::new("crash_after_prepare.log").unwrap();
+        let records = wal.read_log::<i32, i32>().unwrap();
+        assert_eq!(records.last(), Option::Some(&LogRecord::Prepare));
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("crash_after_prepare.log", "crash_after_prepare.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 123);
+        assert!(tx.read_silent(2).is_err());
+        tx.create(2, 0).unwrap();
         tx.commit().unwrap();
-        mem::forget(db);
+        crash(db);
     }
     {
-        let mut db: Database<i32, i32> = Database::new("redo1.log", "redo1.db").unwrap();
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("crash_after_prepare.log", "crash_after_prepare.db").unwrap();
         let mut tx = db.begin_transaction().unwrap();
-        assert_eq!(tx.read(1).unwrap(), 456);
+        assert_eq!(tx.read_silent(1).unwrap(), 123);
+        assert_eq!(tx.read_silent(2).unwrap(), 0);
         tx.commit().unwrap();
     }
 }
+
+#[test]
+fn redo_prepared() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_prepared.log", "redo_prepared.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 123).unwrap();
+        tx.prepare().unwrap().commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(2, 456).unwrap();
+        tx.prepare().unwrap().rollback().unwrap();
+        assert_eq!(db.len(), 1);
+        crash(db);
+    }
+    {
+        let mut wal = WALManager::new("redo_prepared.log").unwrap();
+        let records = wal.read_log::<i32, i32>().unwrap();
+        let tail: Vec<&LogRecord<i32, i32>> = records.iter().rev().take(2).collect();
+        assert_eq!(tail, vec![&LogRecord::Abort, &LogRecord::Prepare]);
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_prepared.log", "redo_prepared.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 123);
+        assert!(tx.read_silent(2).is_err());
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn export_import_wal_json() {
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("export_wal.log", "export_wal.db").unwrap();
+    db.try_clear().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.create(2, 20).unwrap();
+    tx.commit().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 11).unwrap();
+    tx.delete(2).unwrap();
+    tx.commit().unwrap();
+    let size = log_size("export_wal.log");
+
+    assert_eq!(db.export_wal_as_json("export_wal.json").unwrap(), 6);
+    // ログは変更しない
+    assert_eq!(log_size("export_wal.log"), size);
+    let exported: serde_json::Value =
+        serde_json::from_slice(&std::fs::read("export_wal.json").unwrap()).unwrap();
+    let entries = exported.as_array().unwrap();
+    assert_eq!(entries.len(), 6);
+    assert_eq!(entries[0]["offset"], 0);
+    assert_eq!(
+        entries[0]["record"],
+        serde_json::json!({"Create": {"key": 1, "value": 10}})
+    );
+    assert_eq!(entries[5]["record"], serde_json::json!("Commit"));
+    let offsets: Vec<u64> = entries
+        .iter()
+        .map(|entry| entry["offset"].as_u64().unwrap())
+        .collect();
+    assert!(offsets.windows(2).all(|w| w[0] < w[1]));
+    assert!(offsets[5] < size);
+
+    // データファイルとログを失った状態から、書き出した内容をログに書き込む
+    crash(db);
+    std::fs::remove_dir_all("export_wal.log").unwrap();
+    let _ = std::fs::remove_file("export_wal.db");
+    {
+        let mut wal = WALManager::new("export_wal.log").unwrap();
+        assert_eq!(
+            wal.import_from_json::<i32, i32>("export_wal.json").unwrap(),
+            6
+        );
+    }
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("export_wal.log", "export_wal.db").unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 11);
+    assert!(tx.read_silent(2).is_err());
+    tx.commit().unwrap();
+    std::fs::remove_file("export_wal.json").unwrap();
+}
+
+#[test]
+fn redo_truncate() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_truncate.log", "redo_truncate.db").unwrap();
+        db.try_clear().unwrap();
+        db.extend_transaction(vec![(1, 10), (2, 20)]).unwrap();
+        db.truncate().unwrap();
+        assert_eq!(db.len(), 0);
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(3, 30).unwrap();
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(4, 40).unwrap();
+        assert_eq!(tx.truncate().unwrap(), 2);
+        tx.create(5, 50).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(db.len(), 1);
+        crash(db);
+    }
+    {
+        let mut wal = WALManager::new("redo_truncate.log").unwrap();
+        let records = wal.read_log::<i32, i32>().unwrap();
+        assert!(records.contains(&LogRecord::Truncate));
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("redo_truncate.log", "redo_truncate.db").unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        for key in 1..=4 {
+            assert!(tx.read_silent(key).is_err());
+        }
+        assert_eq!(tx.read_silent(5).unwrap(), 50);
+        tx.commit().unwrap();
+        assert_eq!(db.len(), 1);
+    }
+}
+
+#[test]
+fn transaction_timeout() {
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("transaction_timeout.log", "transaction_timeout.db").unwrap();
+        db.try_clear().unwrap();
+        let mut tx = db
+            .begin_transaction_with_timeout(Duration::from_millis(50))
+            .unwrap();
+        tx.create(1, 123).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(matches!(
+            tx.create(2, 456),
+            Result::Err(DatabaseError::TransactionTimeout)
+        ));
+        assert!(matches!(
+            tx.commit(),
+            Result::Err(DatabaseError::TransactionTimeout)
+        ));
+        assert_eq!(db.len(), 0);
+        crash(db);
+    }
+    {
+        let mut wal = WALManager::new("transaction_timeout.log").unwrap();
+        let records = wal.read_log::<i32, i32>().unwrap();
+        // Abortレコードは期限切れを検出した時点で1つだけ書き込まれる
+        assert_eq!(records.last(), Option::Some(&LogRecord::Abort));
+        assert_eq!(
+            records
+                .iter()
+                .filter(|record| **record == LogRecord::Abort)
+                .count(),
+            1
+        );
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("transaction_timeout.log", "transaction_timeout.db").unwrap();
+        assert!(db.is_empty());
+        // 期限内に完了したトランザクションは影響を受けない
+        let mut tx = db
+            .begin_transaction_with_timeout(Duration::from_secs(60))
+            .unwrap();
+        tx.create(1, 123).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(db.len(), 1);
+    }
+}
+
+#[test]
+fn read_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = DatabaseConfig::builder().data_dir(dir.path()).build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![(1, 10)]).unwrap();
+        let mut tx = db
+            .begin_transaction_with_timeout(Duration::from_millis(50))
+            .unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 10);
+        thread::sleep(Duration::from_millis(100));
+        // ログに書き込まない読み取りも、期限を過ぎた後はエラーとなる(peekは値を返さない)
+        assert_eq!(tx.peek(&1), Option::None);
+        assert!(matches!(
+            tx.get_ref(&1),
+            Result::Err(DatabaseError::TransactionTimeout)
+        ));
+        assert!(matches!(
+            tx.read_silent(1),
+            Result::Err(DatabaseError::TransactionTimeout)
+        ));
+        assert!(matches!(
+            tx.get_or_default(2, 0),
+            Result::Err(DatabaseError::TransactionTimeout)
+        ));
+        assert!(matches!(
+            tx.contains_key(&1),
+            Result::Err(DatabaseError::TransactionTimeout)
+        ));
+        assert!(matches!(
+            tx.get_many(&[1]),
+            Result::Err(DatabaseError::TransactionTimeout)
+        ));
+        assert!(tx
+            .scan_range(Bound::Unbounded, Bound::Unbounded)
+            .all(|result| matches!(result, Result::Err(DatabaseError::TransactionTimeout))));
+        drop(tx);
+        crash(db);
+    }
+    // read_silentが期限切れを検出した時点でAbortレコードが1つだけ書き込まれる
+    let mut wal = WALManager::new(config.log_path()).unwrap();
+    let records = wal.read_log::<i32, i32>().unwrap();
+    assert_eq!(records.last(), Option::Some(&LogRecord::Abort));
+    assert_eq!(
+        records
+            .iter()
+            .filter(|record| **record == LogRecord::Abort)
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn transaction_counters() {
+    let _ = std::fs::remove_dir_all("transaction_counters.log");
+    let _ = std::fs::remove_file("transaction_counters.db");
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("transaction_counters.log", "transaction_counters.db").unwrap();
+        for x in 0..3 {
+            let mut tx = db.begin_transaction().unwrap();
+            tx.create(x, x).unwrap();
+            tx.commit().unwrap();
+        }
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(10, 10).unwrap();
+        tx.abort().unwrap();
+        assert_eq!(db.committed_transactions(), 3);
+        assert_eq!(db.aborted_transactions(), 1);
+    }
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("transaction_counters.log", "transaction_counters.db").unwrap();
+        assert_eq!(db.committed_transactions(), 3);
+        assert_eq!(db.aborted_transactions(), 1);
+        for x in 3..5 {
+            let mut tx = db.begin_transaction().unwrap();
+            tx.create(x, x).unwrap();
+            tx.commit().unwrap();
+        }
+        crash(db);
+    }
+    {
+        // チェックポイント以降の数はログから数え直す
+        let db: Database<i32, i32> =
+            Database::with_defaults("transaction_counters.log", "transaction_counters.db").unwrap();
+        assert_eq!(db.committed_transactions(), 5);
+        assert_eq!(db.aborted_transactions(), 1);
+        assert_eq!(db.len(), 5);
+    }
+}
+
+#[test]
+fn redo_write_batch() {
+    let _ = std::fs::remove_dir_all("redo_write_batch.log");
+    let _ = std::fs::remove_file("redo_write_batch.db");
+    let config = DatabaseConfig::builder()
+        .log_file("redo_write_batch.log")
+        .data_file("redo_write_batch.db")
+        .wal_buffer_size(0)
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![(1, 10), (2, 20)]).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.write_batch(vec![
+            (1, Option::Some(11)),
+            (2, Option::None),
+            (3, Option::Some(30)),
+            // 存在しないキーの削除は無視される
+            (4, Option::None),
+            (3, Option::Some(31)),
+        ])
+        .unwrap();
+        assert_eq!(tx.peek(&1), Option::Some(11));
+        assert_eq!(tx.peek(&2), Option::None);
+        assert_eq!(tx.peek(&3), Option::Some(31));
+        tx.commit().unwrap();
+        crash(db);
+    }
+    // 書き込みはまとめて1つのBatchレコードとして記録される
+    let records = WALManager::new("redo_write_batch.log")
+        .unwrap()
+        .read_log::<i32, i32>()
+        .unwrap();
+    let last_commit = records
+        .iter()
+        .rposition(|record| *record == LogRecord::Commit)
+        .unwrap();
+    assert!(matches!(
+        &records[last_commit - 1],
+        LogRecord::Batch { ops } if ops.len() == 5
+    ));
+
+    let db: Database<i32, i32> = Database::new(config).unwrap();
+    assert_eq!(
+        db.scan_all()
+            .unwrap()
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>(),
+        vec![(1, 11), (3, 31)]
+    );
+}
+
+#[test]
+fn migrate_after_crash() {
+    let _ = std::fs::remove_dir_all("migrate.log");
+    let _ = std::fs::remove_file("migrate.db");
+    let config = DatabaseConfig::builder()
+        .log_file("migrate.log")
+        .data_file("migrate.db")
+        .build();
+    // 値の形式を"name"から"name:age"に変更し、年齢が不明なものは削除する
+    let transform = |key: String, value: String| -> Option<String> {
+        if value.contains(':') {
+            return Option::Some(value);
+        }
+        match key.as_str() {
+            "alice" => Option::Some(format!("{}:30", value)),
+            "bob" => Option::Some(format!("{}:25", value)),
+            _ => Option::None,
+        }
+    };
+    {
+        let mut db: Database<String, String> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![
+            ("alice".to_string(), "Alice".to_string()),
+            ("bob".to_string(), "Bob".to_string()),
+            ("carol".to_string(), "Carol".to_string()),
+            ("dave".to_string(), "Dave:40".to_string()),
+        ])
+        .unwrap();
+        // 値が変わらないキーは数えない
+        assert_eq!(db.migrate(transform).unwrap(), 3);
+        assert_eq!(db.migrate(transform).unwrap(), 0);
+        crash(db);
+    }
+    {
+        let mut db: Database<String, String> = Database::new(config.clone()).unwrap();
+        let pairs: Vec<(&str, &str)> = db
+            .scan_all()
+            .unwrap()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("alice", "Alice:30"),
+                ("bob", "Bob:25"),
+                ("dave", "Dave:40")
+            ]
+        );
+
+        let committed = db.committed_transactions();
+        let modified = db
+            .migrate_chunked(2, |_, value| Option::Some(value.to_uppercase()))
+            .unwrap();
+        assert_eq!(modified, 3);
+        assert_eq!(db.committed_transactions(), committed + 2);
+        crash(db);
+    }
+    let db: Database<String, String> = Database::new(config).unwrap();
+    let values: Vec<&str> = db.values().unwrap().map(String::as_str).collect();
+    assert_eq!(values, vec!["ALICE:30", "BOB:25", "DAVE:40"]);
+}
+
+#[test]
+fn corrupt_wal_on_open() {
+    let _ = std::fs::remove_dir_all("corrupt_wal_on_open.log");
+    let _ = std::fs::remove_file("corrupt_wal_on_open.db");
+    let config = DatabaseConfig::builder()
+        .log_file("corrupt_wal_on_open.log")
+        .data_file("corrupt_wal_on_open.db")
+        .wal_buffer_size(0)
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        for key in 1..=3 {
+            let mut tx = db.begin_transaction().unwrap();
+            tx.create(key, key * 10).unwrap();
+            tx.commit().unwrap();
+        }
+        crash(db);
+    }
+    let segment = std::fs::read_dir("corrupt_wal_on_open.log")
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let content = std::fs::read(&segment).unwrap();
+    // [Create 1][Commit][Create 2][Commit][Create 3][Commit]
+    let offsets = frame_offsets(&content);
+    assert_eq!(offsets.len(), 6);
+
+    // 書き込み途中の末尾のフレームは、クラッシュによるものとして読み飛ばす
+    std::fs::write(&segment, &content[..content.len() - 1]).unwrap();
+    {
+        let db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        assert_eq!(db.len(), 2);
+        crash(db);
+    }
+
+    // チェックポイントにより反映された内容を取り除き、元のログのみから開き直す
+    std::fs::remove_file("corrupt_wal_on_open.db").unwrap();
+    std::fs::remove_dir_all("corrupt_wal_on_open.log").unwrap();
+    std::fs::create_dir("corrupt_wal_on_open.log").unwrap();
+    let mut corrupted = content.clone();
+    corrupted[offsets[2] + 50] ^= 0xff;
+    std::fs::write(&segment, &corrupted).unwrap();
+    let result = Database::<i32, i32>::new(config.clone());
+    match result.as_ref().map_err(DatabaseError::root) {
+        Result::Err(DatabaseError::CorruptWALOnOpen {
+            corrupt_offset,
+            total_bytes,
+            ..
+        }) => {
+            assert_eq!(*corrupt_offset, offsets[2] as u64);
+            assert_eq!(*total_bytes, content.len() as u64);
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    // 開くことができなかった場合、ログは変更されない
+    assert_eq!(std::fs::read(&segment).unwrap(), corrupted);
+
+    // 寛容なリカバリでは、破損したトランザクションのみを破棄して開くことができる
+    let config = DatabaseConfig {
+        wal_recovery_mode: WalRecoveryMode::Lenient,
+        ..config
+    };
+    let db: Database<i32, i32> = Database::new(config).unwrap();
+    assert_eq!(
+        db.scan_all()
+            .unwrap()
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>(),
+        vec![(1, 10), (3, 30)]
+    );
+}
+
+#[test]
+fn redo_rename() {
+    let _ = std::fs::remove_dir_all("redo_rename.log");
+    let _ = std::fs::remove_file("redo_rename.db");
+    let config = DatabaseConfig::builder()
+        .log_file("redo_rename.log")
+        .data_file("redo_rename.db")
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![(1, 10), (2, 20)]).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.rename(1, 100).unwrap();
+        tx.create_with_ttl(3, 30, Duration::from_secs(3600))
+            .unwrap();
+        tx.rename(3, 300).unwrap();
+        let savepoint = tx.savepoint("before_discard").unwrap();
+        tx.rename(2, 200).unwrap();
+        tx.rollback_to_savepoint(savepoint).unwrap();
+        tx.commit().unwrap();
+        // Commitされていない移動は反映されない
+        let mut tx = db.begin_transaction().unwrap();
+        tx.rename(2, 201).unwrap();
+        mem::forget(tx);
+        crash(db);
+    }
+    let db: Database<i32, i32> = Database::new(config).unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&2, &20), (&100, &10), (&300, &30)]
+    );
+    drop(db);
+    // 有効期限は移動先のキーに引き継がれる
+    let content = std::fs::read_to_string("redo_rename.db").unwrap();
+    let content: serde_json::Value = serde_json::from_str(&content).unwrap();
+    let expiry = content["expiry"].as_object().unwrap();
+    assert_eq!(expiry.keys().collect::<Vec<_>>(), vec!["300"]);
+}
+
+#[test]
+fn redo_swap() {
+    let _ = std::fs::remove_dir_all("redo_swap.log");
+    let _ = std::fs::remove_file("redo_swap.db");
+    let config = DatabaseConfig::builder()
+        .log_file("redo_swap.log")
+        .data_file("redo_swap.db")
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![(1, 10), (2, 20), (3, 30)])
+            .unwrap();
+        db.swap(1, 2).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.entry(3).unwrap().and_modify(|v| *v += 1);
+        tx.swap(3, 1).unwrap();
+        tx.commit().unwrap();
+        // Commitされる前にクラッシュした交換は反映されない
+        let mut tx = db.begin_transaction().unwrap();
+        tx.swap(2, 3).unwrap();
+        mem::forget(tx);
+        crash(db);
+    }
+    let db: Database<i32, i32> = Database::new(config).unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &31), (&2, &10), (&3, &20)]
+    );
+}
+
+#[test]
+fn redo_each_sync_mode() {
+    for &mode in &[SyncMode::Full, SyncMode::DataOnly, SyncMode::None] {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig::builder()
+            .data_dir(dir.path())
+            .wal_segment_size(256)
+            .sync_mode(mode)
+            .build();
+        {
+            let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+            db.extend_transaction((0..50).map(|k| (k, k))).unwrap();
+            db.compact_wal().unwrap();
+            let mut tx = db.begin_transaction().unwrap();
+            tx.update(1, 100).unwrap();
+            tx.delete(2).unwrap();
+            tx.commit().unwrap();
+            crash(db);
+        }
+        let db: Database<i32, i32> = Database::new(config).unwrap();
+        let expected: Vec<(i32, i32)> = (0..50)
+            .filter(|&k| k != 2)
+            .map(|k| (k, if k == 1 { 100 } else { k }))
+            .collect();
+        let pairs: Vec<(i32, i32)> = db.scan_all().unwrap().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, expected, "{:?}", mode);
+    }
+}
+
+#[test]
+fn checkpoint_error_context() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_dir = dir.path().join("data");
+    std::fs::create_dir(&data_dir).unwrap();
+    let config = DatabaseConfig::builder().data_dir(&data_dir).build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    db.extend_transaction((0..10).map(|k| (k, k))).unwrap();
+
+    // データファイルの一時ファイルを作成できないようにする
+    std::fs::remove_dir_all(&data_dir).unwrap();
+    let error = db.compact_wal().unwrap_err();
+    assert!(error.to_string().starts_with("writing the data file "));
+    assert!(matches!(error.root(), DatabaseError::IOError { .. }));
+
+    // sourceを辿ると説明を除いたエラー、その元のio::Errorに到達する
+    let source = std::error::Error::source(&error).unwrap();
+    assert_eq!(source.to_string(), error.root().to_string());
+    let io = source.source().unwrap();
+    assert_eq!(
+        io.downcast_ref::<std::io::Error>().unwrap().kind(),
+        std::io::ErrorKind::NotFound
+    );
+}
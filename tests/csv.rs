@@ -0,0 +1,72 @@
+#![cfg(feature = "csv")]
+extern crate mikrodb;
+
+use mikrodb::database::Database;
+use mikrodb::error::DatabaseError;
+
+const FIXTURE: &str = "tests/fixtures/users.csv";
+
+#[test]
+fn import_csv() {
+    let mut db: Database<String, String> = Database::in_memory().unwrap();
+    assert_eq!(db.import_csv(FIXTURE, 0, 2).unwrap(), 4);
+    // 重複するキーは後の行の値で更新される
+    let pairs: Vec<(&str, &str)> = db
+        .scan_all()
+        .unwrap()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    assert_eq!(pairs, vec![("1", "Tokyo"), ("2", "Nagoya"), ("3", "Kyoto")]);
+
+    // 既存のキーも更新される
+    assert_eq!(db.import_csv(FIXTURE, 1, 0).unwrap(), 4);
+    assert_eq!(db.len(), 7);
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent("Bobby".to_string()).unwrap(), "2");
+    tx.commit().unwrap();
+}
+
+#[test]
+fn export_csv_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.csv");
+    let mut db: Database<String, String> = Database::in_memory().unwrap();
+    db.import_csv(FIXTURE, 1, 2).unwrap();
+    assert_eq!(db.export_csv(&path).unwrap(), 4);
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "key,value\nAlice,Tokyo\nBob,\"Osaka, Japan\"\nBobby,Nagoya\nCarol,Kyoto\n"
+    );
+
+    let mut imported: Database<String, String> = Database::in_memory().unwrap();
+    assert_eq!(imported.import_csv(&path, 0, 1).unwrap(), 4);
+    assert_eq!(
+        imported.scan_all().unwrap().collect::<Vec<_>>(),
+        db.scan_all().unwrap().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn import_invalid_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("invalid.csv");
+    let mut db: Database<String, String> = Database::in_memory().unwrap();
+
+    // 列の数が揃っていない
+    std::fs::write(&path, "key,value\na,1\nb\n").unwrap();
+    assert!(matches!(
+        db.import_csv(&path, 0, 1),
+        Result::Err(DatabaseError::InvalidInputFormat { .. })
+    ));
+    // 指定された列が存在しない
+    std::fs::write(&path, "key,value\na,1\n").unwrap();
+    assert!(matches!(
+        db.import_csv(&path, 0, 2),
+        Result::Err(DatabaseError::InvalidInputFormat { .. })
+    ));
+    assert!(matches!(
+        db.import_csv(dir.path().join("missing.csv"), 0, 1),
+        Result::Err(DatabaseError::IOError { .. })
+    ));
+    assert!(db.is_empty());
+}
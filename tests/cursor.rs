@@ -0,0 +1,51 @@
+extern crate mikrodb;
+
+use mikrodb::database::Database;
+
+fn setup() -> Database<i32, i32> {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for x in &[10, 20, 30, 40] {
+        tx.create(*x, x * 2).unwrap();
+    }
+    tx.commit().unwrap();
+    db
+}
+
+#[test]
+fn bidirectional() {
+    let db = setup();
+    let mut cursor = db.cursor().unwrap();
+    assert_eq!(cursor.next(), Some((&10, &20)));
+    assert_eq!(cursor.next(), Some((&20, &40)));
+    assert_eq!(cursor.prev(), Some((&20, &40)));
+    assert_eq!(cursor.prev(), Some((&10, &20)));
+    assert_eq!(cursor.prev(), None);
+    assert_eq!(cursor.next(), Some((&10, &20)));
+
+    cursor.seek_to_last();
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.prev(), Some((&40, &80)));
+    assert_eq!(cursor.prev(), Some((&30, &60)));
+
+    cursor.seek_to_first();
+    let keys: Vec<i32> = cursor.map(|(k, _)| *k).collect();
+    assert_eq!(keys, vec![10, 20, 30, 40]);
+}
+
+#[test]
+fn seek() {
+    let db = setup();
+    let mut cursor = db.cursor().unwrap();
+    cursor.seek(&20);
+    assert_eq!(cursor.next(), Some((&20, &40)));
+    cursor.seek(&25);
+    assert_eq!(cursor.next(), Some((&30, &60)));
+    cursor.seek(&25);
+    assert_eq!(cursor.prev(), Some((&20, &40)));
+    cursor.seek(&5);
+    assert_eq!(cursor.prev(), None);
+    cursor.seek(&45);
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.prev(), Some((&40, &80)));
+}
@@ -1,13 +1,25 @@
 extern crate mikrodb;
 
+mod common;
+
+use common::crash;
+use mikrodb::config::DatabaseConfig;
 use mikrodb::database::Database;
 
+/// ログのディレクトリ内のセグメントファイルの合計のバイト数を返す
+fn log_size(dir: &str) -> u64 {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len())
+        .sum()
+}
+
 #[test]
 fn many_transaction() {
     {
         let mut db: Database<i32, i32> =
-            Database::new("many_transaction.log", "many_transaction.db").unwrap();
-        db.clear().unwrap();
+            Database::with_defaults("many_transaction.log", "many_transaction.db").unwrap();
+        db.try_clear().unwrap();
         for x in 0..1000 {
             let mut tx = db.begin_transaction().unwrap();
             tx.create(x, x).unwrap();
@@ -21,12 +33,35 @@ fn many_transaction() {
     }
     {
         let mut db: Database<i32, i32> =
-            Database::new("many_transaction.log", "many_transaction.db").unwrap();
+            Database::with_defaults("many_transaction.log", "many_transaction.db").unwrap();
+        let before = db.stats().total_wal_bytes_written();
         for x in 0..1000 {
             let mut tx = db.begin_transaction().unwrap();
-            assert_eq!(tx.read(x).unwrap(), x + 1);
+            assert_eq!(tx.read_silent(x).unwrap(), x + 1);
             tx.commit().unwrap();
         }
+        let silent_bytes = db.stats().total_wal_bytes_written() - before;
+
+        let before = db.stats().total_wal_bytes_written();
+        for x in 0..1000 {
+            let mut tx = db.begin_transaction().unwrap();
+            #[allow(deprecated)]
+            let value = tx.read(x).unwrap();
+            assert_eq!(value, x + 1);
+            tx.commit().unwrap();
+        }
+        let logged_bytes = db.stats().total_wal_bytes_written() - before;
+
+        let before = db.stats().total_wal_bytes_written();
+        for _ in 0..1000 {
+            db.begin_transaction().unwrap().commit().unwrap();
+        }
+        let commit_bytes = db.stats().total_wal_bytes_written() - before;
+
+        // read_silentのトランザクションはCommitレコードのみを書き込む
+        // (bincode・SHA256の場合、110,000 bytesから53,000 bytesへ約52%削減される)
+        assert_eq!(silent_bytes, commit_bytes);
+        assert!(silent_bytes < logged_bytes);
     }
 }
 
@@ -34,21 +69,80 @@ fn many_transaction() {
 fn many_checkpoint() {
     {
         let mut db: Database<i32, String> =
-            Database::new("many_checkpoint.log", "many_checkpoint.db").unwrap();
-        db.clear().unwrap();
+            Database::with_defaults("many_checkpoint.log", "many_checkpoint.db").unwrap();
+        db.try_clear().unwrap();
     }
     for x in 0..1000 {
         let mut db: Database<i32, String> =
-            Database::new("many_checkpoint.log", "many_checkpoint.db").unwrap();
+            Database::with_defaults("many_checkpoint.log", "many_checkpoint.db").unwrap();
         let mut tx = db.begin_transaction().unwrap();
         tx.create(x, x.to_string()).unwrap();
         tx.commit().unwrap();
     }
     let mut db: Database<i32, String> =
-        Database::new("many_checkpoint.log", "many_checkpoint.db").unwrap();
+        Database::with_defaults("many_checkpoint.log", "many_checkpoint.db").unwrap();
     let mut tx = db.begin_transaction().unwrap();
     for x in 0..1000 {
         tx.delete(x).unwrap();
     }
     tx.commit().unwrap();
 }
+
+#[test]
+fn wal_size_limit() {
+    let config = || {
+        DatabaseConfig::builder()
+            .log_file("wal_size_limit.log")
+            .data_file("wal_size_limit.db")
+            .sync_on_commit(false)
+            // fsyncを行わないCommitがプロセスのクラッシュで失われないようにする
+            .wal_buffer_size(0)
+            .max_wal_bytes(1024)
+            .build()
+    };
+    {
+        let mut db: Database<i32, i32> = Database::new(config()).unwrap();
+        db.try_clear().unwrap();
+        for x in 0..100 {
+            let mut tx = db.begin_transaction().unwrap();
+            tx.create(x, x).unwrap();
+            tx.commit().unwrap();
+            assert!(log_size("wal_size_limit.log") <= 1024);
+        }
+        // 1つのトランザクションの途中で上限に達した場合
+        let mut tx = db.begin_transaction().unwrap();
+        for x in 100..200 {
+            tx.create(x, x).unwrap();
+        }
+        tx.delete(0).unwrap();
+        tx.commit().unwrap();
+        crash(db);
+    }
+    {
+        let mut db: Database<i32, i32> = Database::new(config()).unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        assert!(tx.read_silent(0).is_err());
+        for x in 1..200 {
+            assert_eq!(tx.read_silent(x).unwrap(), x);
+        }
+        tx.commit().unwrap();
+    }
+}
+
+#[test]
+fn auto_checkpoint_after_commits() {
+    let config = DatabaseConfig::builder()
+        .log_file("auto_checkpoint_after_commits.log")
+        .data_file("auto_checkpoint_after_commits.db")
+        .auto_checkpoint_after_n_commits(3)
+        .build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    db.try_clear().unwrap();
+    for x in 0..3 {
+        assert!(x == 0 || log_size("auto_checkpoint_after_commits.log") > 0);
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(x, x).unwrap();
+        tx.commit().unwrap();
+    }
+    assert_eq!(log_size("auto_checkpoint_after_commits.log"), 0);
+}
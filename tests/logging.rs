@@ -0,0 +1,52 @@
+extern crate mikrodb;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use std::sync::Mutex;
+
+/// 記録されたログを蓄積するロガー
+struct TestLogger {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for TestLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: TestLogger = TestLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn drop_error_is_logged() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+
+    let _ = std::fs::remove_dir_all("drop_error");
+    std::fs::create_dir("drop_error").unwrap();
+    let config = DatabaseConfig::builder().data_dir("drop_error").build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.commit().unwrap();
+    // ディレクトリを失ったため、Drop時のチェックポイントの作成に失敗する
+    std::fs::remove_dir_all("drop_error").unwrap();
+    drop(db);
+
+    let records = LOGGER.records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].0, Level::Error);
+    assert!(records[0].1.starts_with("mikrodb: "));
+}
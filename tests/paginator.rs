@@ -0,0 +1,62 @@
+extern crate mikrodb;
+
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::error::DatabaseError;
+use mikrodb::store::StorageBackend;
+use std::collections::BTreeSet;
+
+#[test]
+fn visit_all_entries_once() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction((0..1000).map(|x| (x * 3, x)))
+        .unwrap();
+
+    let mut paginator = db.paginate(30);
+    let mut visited = BTreeSet::new();
+    let mut pages = 0;
+    while paginator.has_next() {
+        let page = paginator.next_page().unwrap();
+        assert!(page.len() <= 30);
+        for (key, value) in page {
+            assert_eq!(key, value * 3);
+            assert!(visited.insert(key), "{} was visited twice", key);
+        }
+        pages += 1;
+    }
+    assert_eq!(pages, 34);
+    assert_eq!(visited.len(), 1000);
+    assert_eq!(paginator.last_key(), Option::Some(&2997));
+    assert!(paginator.next_page().unwrap().is_empty());
+
+    paginator.reset();
+    assert_eq!(
+        paginator.next_page().unwrap(),
+        (0..30).map(|x| (x * 3, x)).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn empty_database() {
+    let db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut paginator = db.paginate(10);
+    assert!(!paginator.has_next());
+    assert!(paginator.next_page().unwrap().is_empty());
+    assert_eq!(paginator.last_key(), Option::None);
+}
+
+#[test]
+fn hashed_backend() {
+    let config = DatabaseConfig::builder()
+        .in_memory(true)
+        .storage_backend(StorageBackend::Hashed)
+        .build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    db.extend_transaction(vec![(1, 1)]).unwrap();
+    let mut paginator = db.paginate(10);
+    assert!(!paginator.has_next());
+    assert!(matches!(
+        paginator.next_page(),
+        Result::Err(DatabaseError::UnsupportedOperation { .. })
+    ));
+}
@@ -0,0 +1,85 @@
+extern crate mikrodb;
+
+use mikrodb::database::Database;
+use mikrodb::error::DatabaseError;
+use std::ops::Bound;
+use std::time::Duration;
+
+/// primaryのstart_lsn以降のイベントをreplicaに反映し、最後に反映したイベントのLSNを返す
+fn replicate(
+    primary: &mut Database<i32, i64>,
+    replica: &mut Database<i32, i64>,
+    start_lsn: u64,
+) -> u64 {
+    let events: Vec<_> = primary
+        .replication_stream(start_lsn)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let mut last_lsn = start_lsn - 1;
+    for event in events {
+        assert!(event.lsn() > last_lsn);
+        last_lsn = event.lsn();
+        replica.apply_replication_event(event).unwrap();
+    }
+    last_lsn
+}
+
+#[test]
+fn replicate_to_replica() {
+    let mut primary: Database<i32, i64> = Database::in_memory().unwrap();
+    let mut replica: Database<i32, i64> = Database::in_memory().unwrap();
+    let start_lsn = primary.checkpoint_lsn() + 1;
+
+    primary
+        .extend_transaction((0..10).map(|k| (k, k as i64 * 10)))
+        .unwrap();
+    let mut tx = primary.begin_transaction().unwrap();
+    tx.update(1, 11).unwrap();
+    tx.delete(2).unwrap();
+    let savepoint = tx.savepoint("before_discard").unwrap();
+    // セーブポイントへのロールバックにより破棄された操作は反映されない
+    tx.upsert(100, 1).unwrap();
+    tx.delete(3).unwrap();
+    tx.rollback_to_savepoint(savepoint).unwrap();
+    assert!(tx.compare_and_swap(4, &40, 44).unwrap());
+    tx.atomic_increment(5, 5).unwrap();
+    tx.commit().unwrap();
+    // Abortされたトランザクションは反映されない
+    let mut tx = primary.begin_transaction().unwrap();
+    tx.create(200, 2).unwrap();
+    tx.abort().unwrap();
+
+    let last_lsn = replicate(&mut primary, &mut replica, start_lsn);
+    assert_eq!(
+        replica.scan_all().unwrap().collect::<Vec<_>>(),
+        primary.scan_all().unwrap().collect::<Vec<_>>()
+    );
+
+    // 続きから反映する
+    let mut tx = primary.begin_transaction().unwrap();
+    tx.delete_range(Bound::Included(6), Bound::Excluded(8))
+        .unwrap();
+    tx.create_with_ttl(300, 3, Duration::from_secs(3600))
+        .unwrap();
+    tx.rename(8, 80).unwrap();
+    tx.commit().unwrap();
+    let last_lsn = replicate(&mut primary, &mut replica, last_lsn + 1);
+    assert_eq!(
+        replica.scan_all().unwrap().collect::<Vec<_>>(),
+        primary.scan_all().unwrap().collect::<Vec<_>>()
+    );
+    assert_eq!(primary.replication_stream(last_lsn + 1).count(), 0);
+
+    // チェックポイントにより破棄されたレコードは読み取れない
+    primary.compact_wal().unwrap();
+    assert!(primary
+        .replication_stream(primary.checkpoint_lsn() + 1)
+        .next()
+        .is_none());
+    let mut stream = primary.replication_stream(start_lsn);
+    assert!(matches!(
+        stream.next(),
+        Option::Some(Result::Err(DatabaseError::ReplicationGap { .. }))
+    ));
+    assert!(stream.next().is_none());
+}
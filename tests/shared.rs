@@ -0,0 +1,198 @@
+#![cfg(feature = "sync")]
+extern crate mikrodb;
+
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::Database;
+use mikrodb::shared::SharedDatabase;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn concurrent_transactions() {
+    let db: SharedDatabase<i32, i32> = SharedDatabase::new(Database::in_memory().unwrap());
+    {
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(0, 0).unwrap();
+        tx.commit().unwrap();
+    }
+
+    let handles: Vec<_> = (1..=8)
+        .map(|t| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for i in 0..100 {
+                    let mut tx = db.begin_transaction().unwrap();
+                    let counter = tx.read_silent(0).unwrap();
+                    tx.update(0, counter + 1).unwrap();
+                    tx.create(t * 1000 + i, i).unwrap();
+                    tx.commit().unwrap();
+
+                    let tx = db.begin_read_transaction().unwrap();
+                    assert_eq!(tx.read(t * 1000 + i).unwrap(), i);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(0).unwrap(), 800);
+    for t in 1..=8 {
+        for i in 0..100 {
+            assert_eq!(tx.read(t * 1000 + i).unwrap(), i);
+        }
+    }
+}
+
+#[test]
+fn try_begin_transaction() {
+    let db: SharedDatabase<i32, i32> = SharedDatabase::new(Database::in_memory().unwrap());
+    {
+        let mut tx = db.try_begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        // 更新トランザクションの実行中は、いずれのトランザクションも開始できない
+        assert!(db.try_begin_transaction().is_none());
+        assert!(db.try_begin_read_transaction().is_none());
+        tx.commit().unwrap();
+    }
+    {
+        let tx = db.try_begin_read_transaction().unwrap();
+        // 読み取り専用トランザクションは同時に実行できるが、更新トランザクションは開始できない
+        let other = db.try_begin_read_transaction().unwrap();
+        assert!(db.try_begin_transaction().is_none());
+        assert_eq!(tx.read(1).unwrap(), 10);
+        assert_eq!(other.read(1).unwrap(), 10);
+    }
+    assert!(db.try_begin_transaction().is_some());
+}
+
+#[test]
+fn read_then_concurrent_commit() {
+    let db: SharedDatabase<i32, i32> = SharedDatabase::new(Database::in_memory().unwrap());
+    {
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(0, 0).unwrap();
+        tx.commit().unwrap();
+    }
+
+    // 読み取った後、Commitする前に、他のスレッドが同じキーを変更するトランザクションをCommitしようとする
+    let mut tx = db.begin_transaction().unwrap();
+    let counter = tx.read_silent(0).unwrap();
+    let committed = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let db = db.clone();
+        let committed = Arc::clone(&committed);
+        thread::spawn(move || {
+            let mut tx = db.begin_transaction().unwrap();
+            let counter = tx.read_silent(0).unwrap();
+            tx.update(0, counter + 10).unwrap();
+            tx.commit().unwrap();
+            committed.store(true, Ordering::SeqCst);
+        })
+    };
+    thread::sleep(Duration::from_millis(100));
+    // SharedDatabaseでは更新トランザクションが順に実行されるため、他のスレッドのCommitは
+    // このトランザクションの終了まで待たされ、読み取ったキーが競合することはない
+    assert!(!committed.load(Ordering::SeqCst));
+    tx.update(0, counter + 1).unwrap();
+    tx.commit().unwrap();
+    handle.join().unwrap();
+    assert!(committed.load(Ordering::SeqCst));
+
+    // いずれの変更も失われない
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(0).unwrap(), 11);
+}
+
+#[test]
+fn heartbeat_thread() {
+    let db: SharedDatabase<i32, i32> = SharedDatabase::new(Database::in_memory().unwrap());
+    assert!(db.start_heartbeat_thread().is_none());
+
+    let config = DatabaseConfig::builder()
+        .in_memory(true)
+        .heartbeat_interval(Duration::from_millis(20))
+        .build();
+    let database: Database<i32, i32> = Database::new(config).unwrap();
+    let stats = database.stats();
+    let db = SharedDatabase::new(database);
+    let handle = db.start_heartbeat_thread().unwrap();
+    thread::sleep(Duration::from_millis(200));
+    handle.stop();
+    let written = stats.wal_record_count();
+    assert!(written > 0);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(stats.wal_record_count(), written);
+}
+
+#[test]
+fn expiry_thread() {
+    let config = DatabaseConfig::builder()
+        .in_memory(true)
+        .expiry_check_interval(Duration::from_millis(50))
+        .build();
+    let db: SharedDatabase<i32, i32> = SharedDatabase::new(Database::new(config).unwrap());
+    let handle = db.start_expiry_thread();
+    {
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create_with_ttl(1, 10, Duration::from_secs(0)).unwrap();
+        tx.create_with_ttl(2, 20, Duration::from_secs(3600))
+            .unwrap();
+        tx.create(3, 30).unwrap();
+        tx.commit().unwrap();
+    }
+    thread::sleep(Duration::from_millis(1300));
+    handle.stop();
+
+    let tx = db.begin_read_transaction().unwrap();
+    let keys: Vec<i32> = tx
+        .scan_range(Bound::Unbounded, Bound::Unbounded)
+        .map(|entry| entry.unwrap().0)
+        .collect();
+    assert_eq!(keys, vec![2, 3]);
+}
+
+#[test]
+fn group_commit() {
+    let config = DatabaseConfig::builder()
+        .log_file("group_commit.log")
+        .data_file("group_commit.db")
+        .enable_group_commit(true)
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.try_clear().unwrap();
+        let db = SharedDatabase::new(db);
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    for i in 0..50 {
+                        let mut tx = db.begin_transaction().unwrap();
+                        tx.create(t * 1000 + i, i).unwrap();
+                        tx.commit().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        // チェックポイントを作成せずに終了し、Commit済みの内容がログから復元されることを確認する
+        // (クラッシュしたプロセスと同様に、ロックは解放されたものとしてロックファイルを削除する)
+        std::mem::forget(db);
+        std::fs::remove_file("group_commit.db.lock").unwrap();
+    }
+    let db: Database<i32, i32> = Database::new(config).unwrap();
+    let tx = db.begin_read_transaction().unwrap();
+    for t in 0..8 {
+        for i in 0..50 {
+            assert_eq!(tx.read(t * 1000 + i).unwrap(), i);
+        }
+    }
+}
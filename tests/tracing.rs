@@ -0,0 +1,82 @@
+#![cfg(feature = "tracing")]
+extern crate mikrodb;
+
+use mikrodb::database::Database;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing::Level;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// 書き込まれた内容をメモリ上に蓄積する出力先
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn lines(&self) -> Vec<String> {
+        let buf = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buf)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Result::Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Output {
+    type Writer = Output;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[test]
+fn transaction_events() {
+    let output = Output::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(Level::TRACE)
+        .with_ansi(false)
+        .with_writer(output.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, || {
+        let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(1, 10).unwrap();
+        tx.update(1, 11).unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 11);
+        tx.commit().unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.delete(1).unwrap();
+        tx.abort().unwrap();
+        db.compact_wal().unwrap();
+    });
+
+    let lines = output.lines();
+    let find = |pattern: &[&str]| {
+        lines
+            .iter()
+            .position(|line| pattern.iter().all(|p| line.contains(p)))
+            .unwrap_or_else(|| panic!("{:?} not found in {:#?}", pattern, lines))
+    };
+    let create = find(&["DEBUG", "transaction{", "create", "key=1", "value=10"]);
+    let update = find(&["DEBUG", "transaction{", "update", "key=1", "value=11"]);
+    let read = find(&["DEBUG", "transaction{", "read", "key=1"]);
+    let commit = find(&["DEBUG", "transaction{", "outcome=\"commit\""]);
+    let delete = find(&["DEBUG", "transaction{", "delete", "key=1"]);
+    let abort = find(&["DEBUG", "transaction{", "outcome=\"abort\""]);
+    // 初期化時のチェックポイントと区別するため、AbortレコードのLSNを指定する
+    let checkpoint = find(&["DEBUG", "checkpoint:", "checkpoint_lsn=5"]);
+    assert!(create < update && update < read && read < commit);
+    assert!(commit < delete && delete < abort && abort < checkpoint);
+    find(&["TRACE", "write_log", "record=\"Create\"", "bytes="]);
+    find(&["TRACE", "write_log", "record=\"Commit\"", "bytes="]);
+}
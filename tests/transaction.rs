@@ -0,0 +1,1978 @@
+extern crate mikrodb;
+
+mod common;
+
+use common::crash;
+use mikrodb::changeset::{Change, Changeset};
+use mikrodb::config::DatabaseConfig;
+use mikrodb::database::{Database, MergeStrategy, ReadTransaction};
+use mikrodb::entry::Entry;
+use mikrodb::error::DatabaseError;
+use mikrodb::event::DatabaseEvent;
+use mikrodb::serialization::DataFormat;
+use mikrodb::snapshot::DiffEntry;
+use mikrodb::stats::DiskUsage;
+use mikrodb::store::StorageBackend;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+use std::time::Duration;
+
+/// ログのディレクトリ内のセグメントファイルの合計のバイト数を返す
+fn log_size(dir: &str) -> u64 {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len())
+        .sum()
+}
+
+#[test]
+fn try_clear() {
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("try_clear.log", "try_clear.db").unwrap();
+    db.extend_transaction(vec![(1, 10)]).unwrap();
+    // 初期化時のチェックポイントによりデータファイルが作成されている
+    assert!(db.try_clear().unwrap());
+    assert!(db.is_empty());
+    assert!(!Path::new("try_clear.db").exists());
+    assert!(!db.try_clear().unwrap());
+    db.clear().unwrap();
+
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    assert!(!db.try_clear().unwrap());
+}
+
+#[test]
+fn read_transaction() {
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("read_transaction.log", "read_transaction.db").unwrap();
+    db.try_clear().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for x in 0..10 {
+        tx.create(x, x * 10).unwrap();
+    }
+    tx.commit().unwrap();
+
+    let wal_size = log_size("read_transaction.log");
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(3).unwrap(), 30);
+    assert!(tx.read(100).is_err());
+    let scanned: Vec<(i32, i32)> = tx
+        .scan_range(Bound::Included(2), Bound::Excluded(5))
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(scanned, vec![(2, 20), (3, 30), (4, 40)]);
+    assert_eq!(
+        tx.scan_range(Bound::Included(5), Bound::Excluded(2))
+            .count(),
+        0
+    );
+    tx.commit().unwrap();
+    assert_eq!(log_size("read_transaction.log"), wal_size);
+}
+
+#[test]
+fn read_transaction_is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<ReadTransaction<i32, i32>>();
+}
+
+#[test]
+fn upsert() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert!(!tx.upsert(1, 10).unwrap());
+    assert!(tx.upsert(1, 11).unwrap());
+    tx.create(2, 20).unwrap();
+    tx.delete(2).unwrap();
+    assert!(!tx.upsert(2, 21).unwrap());
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 11);
+    assert_eq!(tx.read_silent(2).unwrap(), 21);
+    tx.commit().unwrap();
+}
+
+#[test]
+fn create_if_absent() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    assert!(db.create_if_absent(1, 10).unwrap());
+    assert!(!db.create_if_absent(1, 11).unwrap());
+    assert_eq!(db.values().unwrap().collect::<Vec<_>>(), vec![&10]);
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert!(!tx.create_if_absent(1, 12).unwrap());
+    assert!(!tx.is_dirty());
+    assert!(tx.create_if_absent(2, 20).unwrap());
+    assert!(!tx.create_if_absent(2, 21).unwrap());
+    tx.delete(2).unwrap();
+    assert!(tx.create_if_absent(2, 22).unwrap());
+    tx.commit().unwrap();
+    assert_eq!(db.values().unwrap().collect::<Vec<_>>(), vec![&10, &22]);
+}
+
+#[test]
+fn find() {
+    for &backend in &[StorageBackend::Sorted, StorageBackend::Hashed] {
+        let config = DatabaseConfig::builder()
+            .in_memory(true)
+            .storage_backend(backend)
+            .build();
+        let mut db: Database<i32, i32> = Database::new(config).unwrap();
+        let stats = db.stats();
+        db.extend_transaction((0..10).map(|k| (k, k * 10))).unwrap();
+        assert_eq!(db.find(|_, v| *v >= 35).unwrap(), Option::Some((4, 40)));
+        assert_eq!(db.find(|_, v| *v > 1000).unwrap(), Option::None);
+        assert_eq!(
+            db.find_all(|k, _| k % 3 == 0).unwrap(),
+            vec![(0, 0), (3, 30), (6, 60), (9, 90)]
+        );
+
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(2, 1000).unwrap();
+        tx.delete(4).unwrap();
+        tx.create(-5, 2000).unwrap();
+        let written = stats.total_wal_bytes_written();
+        // 書き込みセットのみにある値
+        assert_eq!(
+            tx.find(|_, v| *v >= 1000).unwrap(),
+            Option::Some((-5, 2000))
+        );
+        assert_eq!(tx.find(|k, _| *k == 2).unwrap(), Option::Some((2, 1000)));
+        // コミット済みの値のみ(削除・上書きされたキーは含まない)
+        assert_eq!(
+            tx.find(|_, v| *v >= 35 && *v < 1000).unwrap(),
+            Option::Some((5, 50))
+        );
+        assert_eq!(tx.find(|_, v| *v == 20).unwrap(), Option::None);
+        // 両方
+        assert_eq!(tx.find(|k, _| *k > 1).unwrap(), Option::Some((2, 1000)));
+        assert_eq!(
+            tx.find(|k, _| k % 2 != 0).unwrap(),
+            Option::Some((-5, 2000))
+        );
+        assert_eq!(stats.total_wal_bytes_written(), written);
+        tx.abort().unwrap();
+        assert_eq!(db.find(|_, v| *v >= 1000).unwrap(), Option::None);
+    }
+}
+
+#[test]
+fn size_limits() {
+    let config = DatabaseConfig::builder()
+        .in_memory(true)
+        .max_key_size(8)
+        .max_value_size(16)
+        .build();
+    let mut db: Database<String, String> = Database::new(config).unwrap();
+    let stats = db.stats();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create("a".to_string(), "small".to_string()).unwrap();
+    let written = stats.total_wal_bytes_written();
+    // JSONでは引用符を含めて数える
+    assert!(matches!(
+        tx.create("b".to_string(), "x".repeat(15)),
+        Result::Err(DatabaseError::ValueTooLarge { size: 17, max: 16 })
+    ));
+    assert!(matches!(
+        tx.create("long key".to_string(), "v".to_string()),
+        Result::Err(DatabaseError::KeyTooLarge { size: 10, max: 8 })
+    ));
+    assert!(matches!(
+        tx.update("a".to_string(), "x".repeat(100)),
+        Result::Err(DatabaseError::ValueTooLarge { .. })
+    ));
+    assert!(matches!(
+        tx.write_batch(vec![
+            ("c".to_string(), Option::Some("ok".to_string())),
+            ("d".to_string(), Option::Some("x".repeat(100))),
+        ]),
+        Result::Err(DatabaseError::ValueTooLarge { .. })
+    ));
+    // 拒否された書き込みはログにも書き込みセットにも反映されない
+    assert_eq!(stats.total_wal_bytes_written(), written);
+    assert_eq!(tx.len(), 1);
+    assert_eq!(tx.peek(&"a".to_string()), Option::Some("small".to_string()));
+    tx.create("b".to_string(), "x".repeat(14)).unwrap();
+    tx.abort().unwrap();
+    assert!(db.is_empty());
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create("a".to_string(), "small".to_string()).unwrap();
+    assert!(tx.upsert("a".to_string(), "x".repeat(100)).is_err());
+    tx.commit().unwrap();
+    assert_eq!(db.values().unwrap().collect::<Vec<_>>(), vec!["small"]);
+}
+
+#[test]
+fn rename() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction(vec![(1, 10), (2, 20)]).unwrap();
+    let stats = db.stats();
+
+    let mut tx = db.begin_transaction().unwrap();
+    let written = stats.total_wal_bytes_written();
+    assert!(matches!(
+        tx.rename(3, 4),
+        Result::Err(DatabaseError::KeyNotFoundError)
+    ));
+    assert!(matches!(
+        tx.rename(1, 2),
+        Result::Err(DatabaseError::KeyDuplicationError)
+    ));
+    assert_eq!(stats.total_wal_bytes_written(), written);
+    tx.rename(1, 3).unwrap();
+    assert_eq!(tx.peek(&1), Option::None);
+    assert_eq!(tx.peek(&3), Option::Some(10));
+    // 移動元のキーには再び作成できる
+    tx.create(1, 11).unwrap();
+    // 書き込みセットのみに存在するキーの移動
+    tx.create(5, 50).unwrap();
+    tx.rename(5, 6).unwrap();
+    tx.rename(6, 7).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &11), (&2, &20), (&3, &10), (&7, &50)]
+    );
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.rename(2, 20).unwrap();
+    tx.abort().unwrap();
+    assert_eq!(db.keys().collect::<Vec<_>>(), vec![&1, &2, &3, &7]);
+}
+
+#[test]
+fn drain_commit_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_dir = dir.path().join("data");
+    std::fs::create_dir(&data_dir).unwrap();
+    let config = DatabaseConfig::builder()
+        .data_dir(&data_dir)
+        .max_wal_bytes(1)
+        .build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    db.extend_transaction((0..10).map(|k| (k, k))).unwrap();
+    db.compact_wal().unwrap();
+
+    // Batchレコードの後のCommitレコードがチェックポイントを要求し、そのチェックポイントが失敗する
+    std::fs::remove_dir_all(&data_dir).unwrap();
+    let error = db.drain(|k, _| k % 2 == 0).unwrap_err();
+    match &error {
+        DatabaseError::TransactionError { source } => {
+            assert!(matches!(source.root(), DatabaseError::IOError { .. }));
+        }
+        error => panic!("unexpected error: {:?}", error),
+    }
+    assert!(error.to_string().starts_with("Transaction failed: "));
+    assert_eq!(db.len(), 10);
+}
+
+#[test]
+fn write_failure_context() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_dir = dir.path().join("data");
+    std::fs::create_dir(&data_dir).unwrap();
+    let config = DatabaseConfig::builder()
+        .data_dir(&data_dir)
+        .max_wal_bytes(1)
+        .build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    db.extend_transaction(vec![(1, 10)]).unwrap();
+    db.compact_wal().unwrap();
+
+    // 2つ目のCreateレコードがチェックポイントを要求し、そのチェックポイントが失敗する
+    std::fs::remove_dir_all(&data_dir).unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(2, 20).unwrap();
+    let error = tx.create(3, 30).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("writing the create record for key 3: "));
+    assert!(matches!(error.root(), DatabaseError::IOError { .. }));
+}
+
+#[test]
+fn drain() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction((0..10).map(|k| (k, k * 10))).unwrap();
+    assert_eq!(
+        db.drain(|k, _| k % 4 == 0).unwrap(),
+        vec![(0, 0), (4, 40), (8, 80)]
+    );
+    assert_eq!(db.drain(|k, _| k % 4 == 0).unwrap(), vec![]);
+    assert_eq!(db.len(), 7);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 1000).unwrap();
+    tx.delete(2).unwrap();
+    tx.create(20, 2000).unwrap();
+    // 書き込みセットのみの値・コミット済みのみの値の両方が対象となり、上書き・削除されたキーは
+    // 書き込みセットの内容で判定される
+    assert_eq!(
+        tx.drain(|k, v| *v >= 1000 || *k == 2 || *k == 3).unwrap(),
+        vec![(1, 1000), (3, 30), (20, 2000)]
+    );
+    assert_eq!(tx.drain(|_, v| *v == 10).unwrap(), vec![]);
+    assert_eq!(
+        tx.drain_range(Bound::Included(5), Bound::Excluded(7))
+            .unwrap(),
+        vec![(5, 50), (6, 60)]
+    );
+    tx.create(6, 61).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&6, &61), (&7, &70), (&9, &90)]
+    );
+
+    // Abortした場合は何も削除されない
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.drain(|_, _| true).unwrap().len(), 3);
+    tx.abort().unwrap();
+    assert_eq!(db.len(), 3);
+
+    let config = DatabaseConfig::builder()
+        .in_memory(true)
+        .storage_backend(StorageBackend::Hashed)
+        .build();
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    db.extend_transaction((0..10).map(|k| (k, k))).unwrap();
+    assert_eq!(
+        db.drain(|k, _| *k >= 7).unwrap(),
+        vec![(7, 7), (8, 8), (9, 9)]
+    );
+    let mut tx = db.begin_transaction().unwrap();
+    assert!(matches!(
+        tx.drain_range(Bound::Unbounded, Bound::Unbounded),
+        Result::Err(DatabaseError::UnsupportedOperation { .. })
+    ));
+}
+
+#[test]
+fn swap() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction(vec![(1, 10), (2, 20), (3, 30)])
+        .unwrap();
+    let stats = db.stats();
+    db.swap(1, 2).unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &20), (&2, &10), (&3, &30)]
+    );
+    // 同じキーの交換は何もしない
+    db.swap(3, 3).unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    let written = stats.total_wal_bytes_written();
+    tx.swap(3, 3).unwrap();
+    assert_eq!(stats.total_wal_bytes_written(), written);
+    assert!(!tx.is_dirty());
+    tx.abort().unwrap();
+    assert!(matches!(
+        db.swap(1, 4),
+        Result::Err(DatabaseError::KeyNotFoundError)
+    ));
+    assert!(matches!(
+        db.swap(4, 4),
+        Result::Err(DatabaseError::KeyNotFoundError)
+    ));
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(4, 40).unwrap();
+    tx.swap(3, 4).unwrap();
+    tx.update(3, 41).unwrap();
+    tx.swap(1, 3).unwrap();
+    tx.delete(2).unwrap();
+    assert!(matches!(
+        tx.swap(2, 1),
+        Result::Err(DatabaseError::KeyNotFoundError)
+    ));
+    tx.commit().unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &41), (&3, &20), (&4, &30)]
+    );
+}
+
+#[test]
+fn get_or_default() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction(vec![(1, 10)]).unwrap();
+    let stats = db.stats();
+
+    let mut tx = db.begin_transaction().unwrap();
+    let written = stats.total_wal_bytes_written();
+    assert_eq!(tx.get_or_default(1, 0).unwrap(), 10);
+    assert_eq!(tx.get_or_default(2, 0).unwrap(), 0);
+    assert_eq!(tx.get_or_insert(1, 0).unwrap(), 10);
+    // 読み取りのみの場合はログに書き込まず、キーも作成しない
+    assert_eq!(stats.total_wal_bytes_written(), written);
+    assert!(!tx.is_dirty());
+    assert_eq!(tx.peek(&2), Option::None);
+
+    assert_eq!(tx.get_or_insert(2, 20).unwrap(), 20);
+    assert!(tx.is_dirty());
+    let written = stats.total_wal_bytes_written();
+    assert_eq!(tx.get_or_insert(2, 21).unwrap(), 20);
+    assert_eq!(tx.get_or_default(2, 0).unwrap(), 20);
+    assert_eq!(stats.total_wal_bytes_written(), written);
+    tx.delete(1).unwrap();
+    assert_eq!(tx.get_or_default(1, -1).unwrap(), -1);
+    tx.commit().unwrap();
+    assert_eq!(db.scan_all().unwrap().collect::<Vec<_>>(), vec![(&2, &20)]);
+}
+
+#[test]
+fn scan_range() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for x in 0..10 {
+        tx.create(x, x).unwrap();
+    }
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(3, 30).unwrap();
+    tx.delete(4).unwrap();
+    tx.create(15, 150).unwrap();
+    tx.create(-1, -10).unwrap();
+    let scanned: Vec<(i32, i32)> = tx
+        .scan_range(Bound::Included(2), Bound::Excluded(16))
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(
+        scanned,
+        vec![
+            (2, 2),
+            (3, 30),
+            (5, 5),
+            (6, 6),
+            (7, 7),
+            (8, 8),
+            (9, 9),
+            (15, 150)
+        ]
+    );
+    let keys: Vec<i32> = tx
+        .scan_range(Bound::Unbounded, Bound::Included(1))
+        .map(|r| r.unwrap().0)
+        .collect();
+    assert_eq!(keys, vec![-1, 0, 1]);
+    assert_eq!(
+        tx.scan_range(Bound::Excluded(3), Bound::Excluded(3))
+            .count(),
+        0
+    );
+    tx.abort().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(
+        tx.scan_range(Bound::Unbounded, Bound::Unbounded).count(),
+        10
+    );
+    tx.commit().unwrap();
+}
+
+#[test]
+fn scan_reverse() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction((0..10).map(|x| (x, x))).unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(3, 30).unwrap();
+    tx.delete(4).unwrap();
+    tx.delete(9).unwrap();
+    tx.create(15, 150).unwrap();
+    tx.create(-1, -10).unwrap();
+    tx.delete(15).unwrap();
+    tx.create(12, 120).unwrap();
+    let scanned: Vec<(i32, i32)> = tx
+        .scan_reverse(Bound::Included(2), Bound::Excluded(16))
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(
+        scanned,
+        vec![(12, 120), (8, 8), (7, 7), (6, 6), (5, 5), (3, 30), (2, 2)]
+    );
+    // 末尾からN件を読み取る
+    let keys: Vec<i32> = tx
+        .scan_reverse(Bound::Unbounded, Bound::Unbounded)
+        .take(3)
+        .map(|r| r.unwrap().0)
+        .collect();
+    assert_eq!(keys, vec![12, 8, 7]);
+    let keys: Vec<i32> = tx
+        .scan_reverse(Bound::Unbounded, Bound::Included(1))
+        .map(|r| r.unwrap().0)
+        .collect();
+    assert_eq!(keys, vec![1, 0, -1]);
+    assert_eq!(
+        tx.scan_reverse(Bound::Excluded(3), Bound::Excluded(3))
+            .count(),
+        0
+    );
+    tx.commit().unwrap();
+
+    assert_eq!(
+        db.begin_transaction()
+            .unwrap()
+            .scan_reverse(Bound::Unbounded, Bound::Unbounded)
+            .map(|r| r.unwrap().0)
+            .collect::<Vec<_>>(),
+        vec![12, 8, 7, 6, 5, 3, 2, 1, 0, -1]
+    );
+}
+
+#[test]
+fn scan_prefix() {
+    let mut db: Database<String, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for key in &[
+        "user:1:name",
+        "user:1:age",
+        "user:10:name",
+        "user:2:name",
+        "usr",
+    ] {
+        tx.create(key.to_string(), 0).unwrap();
+    }
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update("user:1:age".to_string(), 20).unwrap();
+    tx.delete("user:1:name".to_string()).unwrap();
+    tx.create("user:1:mail".to_string(), 1).unwrap();
+    let scanned: Vec<(String, i32)> = tx
+        .scan_prefix(&"user:1:".to_string())
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(
+        scanned,
+        vec![
+            ("user:1:age".to_string(), 20),
+            ("user:1:mail".to_string(), 1)
+        ]
+    );
+    let keys: Vec<String> = tx
+        .scan_prefix(&"user:".to_string())
+        .map(|r| r.unwrap().0)
+        .collect();
+    assert_eq!(
+        keys,
+        vec!["user:10:name", "user:1:age", "user:1:mail", "user:2:name"]
+    );
+    assert_eq!(tx.scan_prefix(&"admin:".to_string()).count(), 0);
+    tx.abort().unwrap();
+
+    let mut db: Database<Vec<u8>, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(vec![1, 2], 12).unwrap();
+    tx.create(vec![1, 2, 3], 123).unwrap();
+    tx.create(vec![1, 3], 13).unwrap();
+    let keys: Vec<Vec<u8>> = tx.scan_prefix(&vec![1, 2]).map(|r| r.unwrap().0).collect();
+    assert_eq!(keys, vec![vec![1, 2], vec![1, 2, 3]]);
+}
+
+#[test]
+fn entry() {
+    let mut db: Database<String, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for word in "a b a c b a".split(' ') {
+        *tx.entry(word.to_string()).unwrap().or_insert(0).unwrap() += 1;
+    }
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent("a".to_string()).unwrap(), 3);
+    assert_eq!(tx.read_silent("b".to_string()).unwrap(), 2);
+    assert_eq!(tx.read_silent("c".to_string()).unwrap(), 1);
+    for word in &["a", "d"] {
+        tx.entry(word.to_string())
+            .unwrap()
+            .and_modify(|v| *v *= 10)
+            .or_insert(-1)
+            .unwrap();
+    }
+    match tx.entry("b".to_string()).unwrap() {
+        Entry::Occupied(mut entry) => {
+            assert_eq!(*entry.get(), 2);
+            assert_eq!(entry.insert(20).unwrap(), 2);
+        }
+        Entry::Vacant(_) => panic!("b must exist"),
+    }
+    match tx.entry("c".to_string()).unwrap() {
+        Entry::Occupied(entry) => assert_eq!(entry.remove().unwrap(), 1),
+        Entry::Vacant(_) => panic!("c must exist"),
+    }
+    match tx.entry("c".to_string()).unwrap() {
+        Entry::Occupied(_) => panic!("c must be removed"),
+        Entry::Vacant(entry) => assert_eq!(entry.key(), "c"),
+    }
+    tx.commit().unwrap();
+
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read("a".to_string()).unwrap(), 30);
+    assert_eq!(tx.read("b".to_string()).unwrap(), 20);
+    assert!(tx.read("c".to_string()).is_err());
+    assert_eq!(tx.read("d".to_string()).unwrap(), -1);
+}
+
+#[test]
+fn statistics() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let stats = db.stats();
+    assert_eq!(stats.total_checkpoints(), 1);
+    assert_eq!(stats.wal_record_count(), 0);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 1).unwrap();
+    tx.create(2, 2).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(stats.total_transactions_committed(), 1);
+    assert_eq!(stats.current_record_count(), 2);
+    assert_eq!(stats.wal_record_count(), 3);
+    let written = stats.total_wal_bytes_written();
+    assert!(written > 0);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.delete(1).unwrap();
+    tx.abort().unwrap();
+    {
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(3, 3).unwrap();
+    }
+    assert_eq!(stats.total_transactions_aborted(), 2);
+    assert_eq!(stats.current_record_count(), 2);
+    assert_eq!(stats.wal_record_count(), 7);
+    assert!(stats.total_wal_bytes_written() > written);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.delete(1).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(stats.current_record_count(), 1);
+    drop(db);
+    assert_eq!(stats.total_checkpoints(), 2);
+
+    stats.reset();
+    assert_eq!(stats.total_transactions_committed(), 0);
+    assert_eq!(stats.total_transactions_aborted(), 0);
+    assert_eq!(stats.total_wal_bytes_written(), 0);
+    assert_eq!(stats.total_checkpoints(), 0);
+    assert_eq!(stats.wal_record_count(), 0);
+    assert_eq!(stats.current_record_count(), 1);
+}
+
+#[test]
+fn savepoint() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    let first = tx.savepoint("first").unwrap();
+    tx.update(1, 11).unwrap();
+    tx.create(2, 20).unwrap();
+    let second = tx.savepoint("second").unwrap();
+    tx.delete(2).unwrap();
+
+    tx.rollback_to_savepoint(second).unwrap();
+    assert_eq!(tx.read_silent(2).unwrap(), 20);
+    tx.create(3, 30).unwrap();
+    tx.rollback_to_savepoint(first).unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 10);
+    assert!(tx.read_silent(2).is_err());
+    assert!(tx.read_silent(3).is_err());
+    // 戻った先のセーブポイントは残り、それ以降のセーブポイントは破棄される
+    tx.update(1, 12).unwrap();
+    tx.rollback_to_savepoint(first).unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 10);
+    match tx.rollback_to_savepoint(second) {
+        Result::Err(DatabaseError::SavepointNotFoundError) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    tx.create(4, 40).unwrap();
+    tx.commit().unwrap();
+
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(1).unwrap(), 10);
+    assert!(tx.read(2).is_err());
+    assert_eq!(tx.read(4).unwrap(), 40);
+}
+
+#[test]
+fn compact_wal() {
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("compact_wal.log", "compact_wal.db").unwrap();
+    db.try_clear().unwrap();
+    for round in 0..3 {
+        let mut tx = db.begin_transaction().unwrap();
+        for x in 0..10 {
+            tx.upsert(x, x * round).unwrap();
+        }
+        tx.create(100 + round, round).unwrap();
+        tx.commit().unwrap();
+
+        let wal_size = log_size("compact_wal.log");
+        assert!(wal_size > 0);
+        assert_eq!(db.compact_wal().unwrap(), (wal_size, 11 + round as usize));
+        assert_eq!(log_size("compact_wal.log"), 0);
+        assert_eq!(db.compact_wal().unwrap(), (0, 11 + round as usize));
+    }
+    drop(db);
+
+    let db: Database<i32, i32> =
+        Database::with_defaults("compact_wal.log", "compact_wal.db").unwrap();
+    let tx = db.begin_read_transaction().unwrap();
+    for x in 0..10 {
+        assert_eq!(tx.read(x).unwrap(), x * 2);
+    }
+    for round in 0..3 {
+        assert_eq!(tx.read(100 + round).unwrap(), round);
+    }
+}
+
+#[test]
+fn scan_all() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for x in &[3, 1, 2] {
+        tx.create(*x, x * 10).unwrap();
+    }
+    tx.commit().unwrap();
+    {
+        let mut tx = db.begin_transaction().unwrap();
+        tx.create(4, 40).unwrap();
+        tx.delete(1).unwrap();
+        tx.abort().unwrap();
+    }
+    let pairs: Vec<(&i32, &i32)> = db.scan_all().unwrap().collect();
+    assert_eq!(pairs, vec![(&1, &10), (&2, &20), (&3, &30)]);
+    assert_eq!(db.keys().cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(
+        db.values().unwrap().cloned().collect::<Vec<_>>(),
+        vec![10, 20, 30]
+    );
+}
+
+#[test]
+fn get_many() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.create(2, 20).unwrap();
+    tx.commit().unwrap();
+
+    let stats = db.stats();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.delete(2).unwrap();
+    tx.create(3, 30).unwrap();
+    let records = stats.wal_record_count();
+    assert_eq!(
+        tx.get_many(&[3, 2, 1, 4, 1]).unwrap(),
+        vec![Some(30), None, Some(10), None, Some(10)]
+    );
+    assert_eq!(stats.wal_record_count(), records + 1);
+    assert_eq!(tx.get_many(&[]).unwrap(), vec![]);
+    tx.abort().unwrap();
+}
+
+#[test]
+fn contains_key() {
+    let mut db: Database<i32, String> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, "one".to_string()).unwrap();
+    tx.create(2, "two".to_string()).unwrap();
+    tx.commit().unwrap();
+
+    let stats = db.stats();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.delete(2).unwrap();
+    tx.create(3, "three".to_string()).unwrap();
+    let records = stats.wal_record_count();
+    assert!(tx.contains_key(&1).unwrap());
+    assert!(!tx.contains_key(&2).unwrap());
+    assert!(tx.contains_key(&3).unwrap());
+    assert!(!tx.contains_key(&4).unwrap());
+    assert_eq!(stats.wal_record_count(), records + 4);
+
+    assert_eq!(tx.get_ref(&1).unwrap().map(String::as_str), Some("one"));
+    assert_eq!(tx.get_ref(&2).unwrap(), None);
+    assert_eq!(tx.get_ref(&3).unwrap().map(String::as_str), Some("three"));
+    assert_eq!(stats.wal_record_count(), records + 4);
+    tx.abort().unwrap();
+}
+
+#[test]
+fn peek() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.create(2, 20).unwrap();
+    tx.commit().unwrap();
+
+    let stats = db.stats();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 11).unwrap();
+    tx.delete(2).unwrap();
+    let records = stats.wal_record_count();
+    assert_eq!(tx.peek(&1), Some(11));
+    assert_eq!(tx.peek(&2), None);
+    assert_eq!(tx.peek(&3), None);
+    assert_eq!(stats.wal_record_count(), records);
+    tx.abort().unwrap();
+}
+
+#[test]
+fn delete_range() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for x in 0..10 {
+        tx.create(x, x).unwrap();
+    }
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(15, 15).unwrap();
+    tx.delete(3).unwrap();
+    assert_eq!(
+        tx.delete_range(Bound::Included(2), Bound::Excluded(5))
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        tx.delete_range(Bound::Included(2), Bound::Excluded(5))
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        tx.delete_range(Bound::Excluded(7), Bound::Excluded(7))
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        tx.delete_range(Bound::Excluded(8), Bound::Unbounded)
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        tx.delete_range(Bound::Unbounded, Bound::Included(0))
+            .unwrap(),
+        1
+    );
+    tx.commit().unwrap();
+    assert_eq!(db.keys().cloned().collect::<Vec<_>>(), vec![1, 5, 6, 7, 8]);
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(
+        tx.delete_range(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        5
+    );
+    tx.abort().unwrap();
+    assert_eq!(db.keys().count(), 5);
+}
+
+#[test]
+fn delete_range_expired() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create_with_ttl(1, 10, Duration::from_secs(0)).unwrap();
+    tx.create(2, 20).unwrap();
+    tx.create_with_ttl(3, 30, Duration::from_secs(0)).unwrap();
+    tx.create(4, 40).unwrap();
+    tx.commit().unwrap();
+    thread::sleep(Duration::from_millis(1100));
+
+    // 期限切れのキーは削除されるが、削除した数には含まれない
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(3, 31).unwrap();
+    let drained = tx
+        .drain_range(Bound::Unbounded, Bound::Included(3))
+        .unwrap();
+    tx.abort().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(3, 31).unwrap();
+    assert_eq!(
+        tx.delete_range(Bound::Unbounded, Bound::Included(3))
+            .unwrap(),
+        drained.len()
+    );
+    assert_eq!(drained, vec![(2, 20), (3, 31)]);
+    tx.commit().unwrap();
+    assert_eq!(db.keys().cloned().collect::<Vec<_>>(), vec![4]);
+}
+
+#[test]
+fn snapshot_version() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.create(3, 30).unwrap();
+    tx.commit().unwrap();
+    let before = db.version();
+    assert_eq!(
+        db.begin_read_transaction().unwrap().snapshot_version(),
+        before
+    );
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 11).unwrap();
+    tx.create(2, 20).unwrap();
+    tx.delete(3).unwrap();
+    tx.commit().unwrap();
+    let after = db.version();
+    assert_eq!(after, before + 1);
+
+    assert_eq!(db.read_at(&1, before).unwrap(), Some(&10));
+    assert_eq!(db.read_at(&2, before).unwrap(), None);
+    assert_eq!(db.read_at(&3, before).unwrap(), Some(&30));
+    assert_eq!(db.read_at(&1, after).unwrap(), Some(&11));
+    assert_eq!(db.read_at(&2, after).unwrap(), Some(&20));
+    assert_eq!(db.read_at(&3, after).unwrap(), None);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(2, 21).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(db.read_at(&2, after).unwrap(), Some(&20));
+    match db.read_at(&1, before) {
+        Result::Err(DatabaseError::StaleSnapshotError { snapshot, current }) => {
+            assert_eq!(snapshot, before);
+            assert_eq!(current, before + 2);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    let tx = db.begin_read_transaction().unwrap();
+    let scanned: Vec<(i32, i32)> = tx
+        .scan_range(Bound::Unbounded, Bound::Unbounded)
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(scanned, vec![(1, 11), (2, 21)]);
+}
+
+#[test]
+fn len() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    assert_eq!(db.len(), 0);
+    assert!(db.is_empty());
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 1).unwrap();
+    tx.create(2, 2).unwrap();
+    tx.update(2, 3).unwrap();
+    assert_eq!(tx.pending_writes(), 2);
+    tx.commit().unwrap();
+    assert_eq!(db.len(), 2);
+    assert!(!db.is_empty());
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(3, 3).unwrap();
+    tx.delete(1).unwrap();
+    tx.delete(2).unwrap();
+    assert_eq!(tx.pending_writes(), 3);
+    tx.abort().unwrap();
+    assert_eq!(db.len(), 2);
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.pending_writes(), 0);
+    tx.delete(1).unwrap();
+    tx.delete(2).unwrap();
+    tx.commit().unwrap();
+    assert!(db.is_empty());
+}
+
+#[test]
+fn transaction_len() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.len(), 0);
+    assert!(!tx.is_dirty());
+    tx.create(1, 1).unwrap();
+    tx.create(2, 2).unwrap();
+    assert_eq!(tx.len(), 2);
+    // 同じキーへの書き込みは1件と数える
+    tx.update(1, 10).unwrap();
+    tx.upsert(3, 3).unwrap();
+    assert_eq!(tx.len(), 3);
+    assert!(tx.is_dirty());
+    assert!(!tx.has_uncommitted_deletes());
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.len(), 0);
+    assert!(!tx.is_dirty());
+    tx.delete(1).unwrap();
+    assert_eq!(tx.len(), 1);
+    assert!(tx.has_uncommitted_deletes());
+    // 削除したキーを作成し直すと、削除は残らない
+    tx.create(1, 100).unwrap();
+    assert_eq!(tx.len(), 1);
+    assert!(!tx.has_uncommitted_deletes());
+    tx.delete(2).unwrap();
+    assert_eq!(tx.len(), 2);
+    assert!(tx.has_uncommitted_deletes());
+    tx.commit().unwrap();
+    assert_eq!(db.len(), 2);
+
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.len(), 0);
+    assert!(!tx.is_dirty());
+    assert!(!tx.has_uncommitted_deletes());
+}
+
+#[test]
+fn transaction_with() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let sum = db
+        .transaction_with(|tx| {
+            tx.create(1, 10)?;
+            tx.create(2, 20)?;
+            Ok(tx.read_silent(1)? + tx.read_silent(2)?)
+        })
+        .unwrap();
+    assert_eq!(sum, 30);
+    assert_eq!(db.len(), 2);
+
+    let result = db.transaction_with(|tx| {
+        tx.delete(1)?;
+        tx.create(2, 30)?;
+        Ok(())
+    });
+    match result {
+        Result::Err(DatabaseError::KeyDuplicationError) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(db.len(), 2);
+    assert_eq!(db.stats().total_transactions_aborted(), 1);
+}
+
+#[test]
+fn transaction_retry() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut attempts = 0;
+    let result = db.transaction_retry(3, |tx| {
+        attempts += 1;
+        tx.upsert(attempts, attempts)?;
+        if attempts < 3 {
+            return Err(DatabaseError::TransientError {
+                message: "busy".to_string(),
+            });
+        }
+        Ok(attempts)
+    });
+    assert_eq!(result.unwrap(), 3);
+    assert_eq!(db.keys().cloned().collect::<Vec<_>>(), vec![3]);
+
+    let mut attempts = 0;
+    let result: Result<(), DatabaseError> = db.transaction_retry(2, |_| {
+        attempts += 1;
+        Err(DatabaseError::TransientError {
+            message: "busy".to_string(),
+        })
+    });
+    match result {
+        Result::Err(DatabaseError::TransientError { .. }) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(attempts, 3);
+
+    // 一時的でないエラーは再試行しない
+    let mut attempts = 0;
+    let result: Result<(), DatabaseError> = db.transaction_retry(2, |tx| {
+        attempts += 1;
+        tx.create(3, 3)
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+fn from_iter() {
+    let mut db: Database<i32, i32> = (0..10).map(|x| (x, x * 10)).collect();
+    assert_eq!(db.len(), 10);
+    assert_eq!(db.stats().total_transactions_committed(), 1);
+
+    db.extend_transaction(vec![(5, 0), (10, 100)]).unwrap();
+    assert_eq!(db.len(), 11);
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(5).unwrap(), 0);
+    assert_eq!(tx.read_silent(10).unwrap(), 100);
+    tx.commit().unwrap();
+
+    let config = DatabaseConfig::builder().in_memory(true).build();
+    let db = Database::from_iter_fallible(vec![(1, 1), (1, 2)], config).unwrap();
+    assert_eq!(db.values().unwrap().cloned().collect::<Vec<_>>(), vec![2]);
+}
+
+#[test]
+fn diff() {
+    let mut primary: Database<i32, i32> = (0..3).map(|x| (x, x)).collect();
+    let mut replica: Database<i32, i32> = (0..3).map(|x| (x, x)).collect();
+
+    let mut tx = primary.begin_transaction().unwrap();
+    tx.update(0, 10).unwrap();
+    tx.delete(1).unwrap();
+    tx.create(3, 30).unwrap();
+    tx.create(4, 40).unwrap();
+    tx.delete(4).unwrap();
+    let changeset = tx.diff();
+    assert_eq!(
+        changeset.iter().cloned().collect::<Vec<_>>(),
+        vec![
+            Change::Update(0, 10),
+            Change::Delete(1),
+            Change::Create(3, 30)
+        ]
+    );
+    tx.commit().unwrap();
+
+    let json = serde_json::to_string(&changeset).unwrap();
+    let changeset: Changeset<i32, i32> = serde_json::from_str(&json).unwrap();
+    replica.apply_changeset(changeset.clone()).unwrap();
+    assert_eq!(
+        replica.scan_all().unwrap().collect::<Vec<_>>(),
+        primary.scan_all().unwrap().collect::<Vec<_>>()
+    );
+
+    // 反映できない変更を含む場合は何も反映しない
+    assert!(replica.apply_changeset(changeset).is_err());
+    assert_eq!(replica.len(), 3);
+}
+
+#[test]
+fn snapshot() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let empty = db.snapshot().unwrap();
+    db.extend_transaction(vec![(1, 10), (2, 20), (3, 30)])
+        .unwrap();
+    let populated = db.snapshot().unwrap();
+    let copy = populated.clone();
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.delete(1).unwrap();
+    tx.update(2, 21).unwrap();
+    tx.create(4, 40).unwrap();
+    tx.commit().unwrap();
+    let current = db.snapshot().unwrap();
+
+    // スナップショットは作成後のCommitの影響を受けない
+    assert!(empty.is_empty());
+    assert_eq!(populated.get(&1), Option::Some(&10));
+    assert_eq!(copy.keys().collect::<Vec<_>>(), vec![&1, &2, &3]);
+    assert_eq!(copy.values().collect::<Vec<_>>(), vec![&10, &20, &30]);
+    assert_eq!(
+        current
+            .scan_range(Bound::Included(2), Bound::Unbounded)
+            .collect::<Vec<_>>(),
+        vec![(&2, &21), (&3, &30), (&4, &40)]
+    );
+    assert_eq!(
+        current
+            .scan_range(Bound::Included(3), Bound::Excluded(2))
+            .count(),
+        0
+    );
+
+    assert_eq!(
+        empty.diff(&populated).collect::<Vec<_>>(),
+        vec![
+            DiffEntry::Added(1, 10),
+            DiffEntry::Added(2, 20),
+            DiffEntry::Added(3, 30)
+        ]
+    );
+    assert_eq!(
+        populated.diff(&empty).collect::<Vec<_>>(),
+        vec![
+            DiffEntry::Removed(1, 10),
+            DiffEntry::Removed(2, 20),
+            DiffEntry::Removed(3, 30)
+        ]
+    );
+    assert_eq!(
+        populated.diff(&current).collect::<Vec<_>>(),
+        vec![
+            DiffEntry::Removed(1, 10),
+            DiffEntry::Modified(2, 20, 21),
+            DiffEntry::Added(4, 40)
+        ]
+    );
+    assert_eq!(populated.diff(&copy).count(), 0);
+    assert_eq!(empty.diff(&empty).count(), 0);
+}
+
+#[test]
+fn merge() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut other: Database<i32, i32> = Database::in_memory().unwrap();
+    other.extend_transaction(vec![(1, 10), (2, 20)]).unwrap();
+    db.merge(other, |_, _, _| unreachable!()).unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &10), (&2, &20)]
+    );
+
+    // 空のデータベースを統合しても何も変わらない
+    let version = db.version();
+    db.merge(Database::in_memory().unwrap(), |_, _, _| unreachable!())
+        .unwrap();
+    assert_eq!(db.len(), 2);
+
+    let mut other: Database<i32, i32> = Database::in_memory().unwrap();
+    other
+        .extend_transaction(vec![(1, 10), (2, 21), (3, 30)])
+        .unwrap();
+    db.merge(other, |key, ours, theirs| {
+        assert_eq!((key, ours, theirs), (2, 20, 21));
+        ours + theirs
+    })
+    .unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &10), (&2, &41), (&3, &30)]
+    );
+    assert!(db.version() > version);
+}
+
+#[test]
+fn merge_strategy() {
+    let overlapping = || {
+        let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+        db.extend_transaction(vec![(1, 100), (4, 40)]).unwrap();
+        db
+    };
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction(vec![(1, 10), (2, 20)]).unwrap();
+    db.merge_with(overlapping(), MergeStrategy::KeepSelf)
+        .unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &10), (&2, &20), (&4, &40)]
+    );
+    db.merge_with(overlapping(), MergeStrategy::LastWriteWins)
+        .unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &100), (&2, &20), (&4, &40)]
+    );
+    let mut other: Database<i32, i32> = Database::in_memory().unwrap();
+    other.extend_transaction(vec![(1, 1), (2, 200)]).unwrap();
+    db.merge_with(
+        other,
+        MergeStrategy::Custom(Box::new(|_, ours: i32, theirs: i32| ours.max(theirs))),
+    )
+    .unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &100), (&2, &200), (&4, &40)]
+    );
+}
+
+#[test]
+fn try_begin_transaction() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.try_begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.commit().unwrap();
+
+    let tx = db.try_begin_read_transaction().unwrap();
+    assert_eq!(tx.read(1).unwrap(), 10);
+}
+
+#[test]
+fn update_with() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.commit().unwrap();
+
+    let stats = db.stats();
+    let mut tx = db.begin_transaction().unwrap();
+    let records = stats.wal_record_count();
+    tx.update_with(1, |v| v + 1).unwrap();
+    tx.update_with(1, |v| v * 2).unwrap();
+    assert_eq!(stats.wal_record_count(), records + 2);
+    assert_eq!(tx.read_silent(1).unwrap(), 22);
+    match tx.update_with(2, |v| v + 1) {
+        Result::Err(DatabaseError::KeyNotFoundError) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match tx.update_with_result(1, |_| Result::Err(DatabaseError::NumericOverflowError)) {
+        Result::Err(DatabaseError::NumericOverflowError) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    tx.update_with_result(1, |v| {
+        v.checked_sub(2).ok_or(DatabaseError::NumericOverflowError)
+    })
+    .unwrap();
+    assert_eq!(stats.wal_record_count(), records + 3);
+    tx.commit().unwrap();
+
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(1).unwrap(), 20);
+}
+
+#[test]
+fn atomic_increment() {
+    let mut db: Database<String, u8> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.atomic_increment("a".to_string(), 5).unwrap(), 5);
+    assert_eq!(tx.atomic_increment("a".to_string(), 250).unwrap(), 255);
+    match tx.atomic_increment("a".to_string(), 1) {
+        Result::Err(DatabaseError::NumericOverflowError) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(tx.atomic_decrement("a".to_string(), 55).unwrap(), 200);
+    match tx.atomic_decrement("b".to_string(), 1) {
+        Result::Err(DatabaseError::NumericOverflowError) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    tx.commit().unwrap();
+
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent("a".to_string()).unwrap(), 200);
+    assert!(tx.read_silent("b".to_string()).is_err());
+    tx.commit().unwrap();
+}
+
+#[test]
+fn ttl() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create_with_ttl(1, 10, Duration::from_secs(0)).unwrap();
+    tx.create_with_ttl(2, 20, Duration::from_secs(3600))
+        .unwrap();
+    tx.create_with_ttl(3, 30, Duration::from_secs(0)).unwrap();
+    tx.create(4, 40).unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 10);
+    tx.commit().unwrap();
+    thread::sleep(Duration::from_millis(1100));
+
+    let mut tx = db.begin_transaction().unwrap();
+    match tx.read_silent(1) {
+        Result::Err(DatabaseError::KeyNotFoundError) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(tx.read_silent(2).unwrap(), 20);
+    assert_eq!(tx.read_silent(4).unwrap(), 40);
+    // 期限切れのキーは新たに作成できる
+    tx.create(1, 11).unwrap();
+    tx.commit().unwrap();
+    let tx = db.begin_read_transaction().unwrap();
+    assert!(tx.read(3).is_err());
+    tx.commit().unwrap();
+
+    assert_eq!(db.len(), 4);
+    assert_eq!(db.purge_expired().unwrap(), 1);
+    assert_eq!(db.keys().cloned().collect::<Vec<_>>(), vec![1, 2, 4]);
+    let mut tx = db.begin_transaction().unwrap();
+    assert_eq!(tx.read_silent(1).unwrap(), 11);
+    tx.commit().unwrap();
+}
+
+#[test]
+fn scan_expired() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create_with_ttl(1, 10, Duration::from_secs(0)).unwrap();
+    tx.create(2, 20).unwrap();
+    tx.create_with_ttl(3, 30, Duration::from_secs(0)).unwrap();
+    tx.commit().unwrap();
+    thread::sleep(Duration::from_millis(1100));
+
+    // 期限切れのコミット済みのキーは走査の結果に含まれず、書き込みセットの内容は含まれる
+    let mut tx = db.begin_transaction().unwrap();
+    assert!(tx.read_silent(1).is_err());
+    tx.create(3, 31).unwrap();
+    let pairs: Vec<(i32, i32)> = tx
+        .scan_range(Bound::Unbounded, Bound::Unbounded)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(pairs, vec![(2, 20), (3, 31)]);
+    let pairs: Vec<(i32, i32)> = tx
+        .scan_reverse(Bound::Unbounded, Bound::Unbounded)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(pairs, vec![(3, 31), (2, 20)]);
+    tx.abort().unwrap();
+
+    let tx = db.begin_read_transaction().unwrap();
+    let pairs: Vec<(i32, i32)> = tx
+        .scan_range(Bound::Unbounded, Bound::Unbounded)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(pairs, vec![(2, 20)]);
+}
+
+#[test]
+fn auto_checkpoint() {
+    let config = DatabaseConfig::builder()
+        .log_file("auto_checkpoint.log")
+        .data_file("auto_checkpoint.db")
+        .sync_on_commit(false)
+        // ログファイルの大きさを検査するため、バッファリングしない
+        .wal_buffer_size(0)
+        .auto_checkpoint_after_n_records(5)
+        .build();
+    let mut db: Database<i32, i32> = Database::open(config).unwrap();
+    db.try_clear().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 1).unwrap();
+    tx.create(2, 2).unwrap();
+    tx.commit().unwrap();
+    assert!(log_size("auto_checkpoint.log") > 0);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(3, 3).unwrap();
+    tx.create(4, 4).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(log_size("auto_checkpoint.log"), 0);
+    let checkpoint: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("auto_checkpoint.db").unwrap()).unwrap();
+    assert_eq!(checkpoint["data"].as_object().unwrap().len(), 4);
+    assert_eq!(checkpoint["header"]["checkpoint_lsn"], db.checkpoint_lsn());
+}
+
+#[test]
+fn compare_and_swap() {
+    let mut db: Database<i32, String> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, "a".to_string()).unwrap();
+    assert!(tx
+        .compare_and_swap(1, &"a".to_string(), "b".to_string())
+        .unwrap());
+    assert!(!tx
+        .compare_and_swap(1, &"a".to_string(), "c".to_string())
+        .unwrap());
+    assert!(tx
+        .compare_and_swap(2, &"a".to_string(), "c".to_string())
+        .is_err());
+    assert_eq!(tx.read_silent(1).unwrap(), "b");
+    tx.commit().unwrap();
+}
+
+#[test]
+fn backup_restore() {
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("backup_restore.log", "backup_restore.db").unwrap();
+    db.try_clear().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    for x in 0..10 {
+        tx.create(x, x * 10).unwrap();
+    }
+    tx.commit().unwrap();
+    let bytes = db.backup_to("backup_restore.backup.db").unwrap();
+    assert_eq!(
+        bytes,
+        std::fs::metadata("backup_restore.backup.db").unwrap().len()
+    );
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.delete_range(Bound::Included(0), Bound::Excluded(5))
+        .unwrap();
+    tx.update(5, -1).unwrap();
+    tx.commit().unwrap();
+    db.restore_from("backup_restore.backup.db").unwrap();
+    assert_eq!(log_size("backup_restore.log"), 0);
+    drop(db);
+
+    // 復元した内容はデータファイルに永続化される
+    let db: Database<i32, i32> =
+        Database::with_defaults("backup_restore.log", "backup_restore.db").unwrap();
+    let tx = db.begin_read_transaction().unwrap();
+    for x in 0..10 {
+        assert_eq!(tx.read(x).unwrap(), x * 10);
+    }
+}
+
+#[test]
+fn metadata() {
+    for &format in &[DataFormat::Json, DataFormat::Bincode] {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig::builder()
+            .data_dir(dir.path())
+            .data_format(format)
+            .build();
+        {
+            let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+            db.set_metadata("schema_version", "1");
+            db.set_metadata("application", "mikrodb-test");
+            db.set_metadata("schema_version", "2");
+            db.set_metadata("obsolete", "x");
+            db.remove_metadata("obsolete");
+            assert_eq!(db.get_metadata("schema_version"), Option::Some("2"));
+            db.extend_transaction(vec![(1, 10)]).unwrap();
+        }
+        // Drop時のチェックポイントにより永続化される
+        let db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        assert_eq!(db.get_metadata("schema_version"), Option::Some("2"));
+        assert_eq!(db.get_metadata("application"), Option::Some("mikrodb-test"));
+        assert_eq!(db.get_metadata("obsolete"), Option::None);
+        assert_eq!(db.scan_all().unwrap().collect::<Vec<_>>(), vec![(&1, &10)]);
+        drop(db);
+
+        let db: Database<i32, i32> = Database::open_read_only(config.data_path()).unwrap();
+        assert_eq!(db.get_metadata("schema_version"), Option::Some("2"));
+    }
+}
+
+#[test]
+fn restore_into_populated_database() {
+    let mut source: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = source.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.create(2, 20).unwrap();
+    tx.commit().unwrap();
+    source.backup_to("restore_populated.backup.db").unwrap();
+
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(2, -2).unwrap();
+    tx.create(3, -3).unwrap();
+    tx.commit().unwrap();
+    db.restore_from("restore_populated.backup.db").unwrap();
+    assert_eq!(db.len(), 2);
+    let tx = db.begin_read_transaction().unwrap();
+    assert_eq!(tx.read(1).unwrap(), 10);
+    assert_eq!(tx.read(2).unwrap(), 20);
+    assert!(tx.read(3).is_err());
+}
+
+#[test]
+fn subscribe() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let (handle, events) = db.subscribe();
+    let (dropped, receiver) = db.subscribe();
+    drop(receiver);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(2, 20).unwrap();
+    tx.create(1, 10).unwrap();
+    tx.commit().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 11).unwrap();
+    tx.abort().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 12).unwrap();
+    tx.delete(2).unwrap();
+    tx.create(3, 30).unwrap();
+    tx.delete(3).unwrap();
+    tx.commit().unwrap();
+    db.compact_wal().unwrap();
+
+    let received: Vec<DatabaseEvent<i32, i32>> = events.try_iter().collect();
+    assert_eq!(
+        received,
+        vec![
+            DatabaseEvent::Committed(vec![Change::Create(1, 10), Change::Create(2, 20)]),
+            DatabaseEvent::Aborted,
+            DatabaseEvent::Committed(vec![Change::Update(1, 12), Change::Delete(2)]),
+            DatabaseEvent::Checkpointed,
+        ]
+    );
+    // 受信側を破棄したチャネルは通知の際に登録を解除される
+    assert!(!db.unsubscribe(dropped));
+
+    assert!(db.unsubscribe(handle));
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(4, 40).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(events.try_recv(), Result::Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn debug_and_display() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    db.extend_transaction(vec![(1, 10)]).unwrap();
+    assert_eq!(db.to_string(), "in-memory Database: 1 records");
+    let debug = format!("{:?}", db);
+    assert!(debug.starts_with("Database {"));
+    assert!(debug.contains("len: 1"));
+    assert!(debug.contains("wal: None"));
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(2, 20).unwrap();
+    let debug = format!("{:?}", tx);
+    assert!(debug.contains("writes: 1"));
+    assert!(debug.contains("has_deletes: false"));
+    tx.delete(1).unwrap();
+    let debug = format!("{:?}", tx);
+    assert!(debug.contains("writes: 2"));
+    assert!(debug.contains("has_deletes: true"));
+    tx.abort().unwrap();
+
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults("debug_display.log", "debug_display.db").unwrap();
+        db.try_clear().unwrap();
+        assert_eq!(
+            db.to_string(),
+            "Database at debug_display.db (WAL debug_display.log): 0 records"
+        );
+        assert!(format!("{:?}", db).contains("wal: Some(\"debug_display.log\")"));
+    }
+    let db: Database<i32, i32> = Database::open_read_only("debug_display.db").unwrap();
+    assert_eq!(
+        db.to_string(),
+        "read-only Database at debug_display.db: 0 records"
+    );
+}
+
+#[test]
+fn size_on_disk() {
+    let db: Database<i32, String> = Database::in_memory().unwrap();
+    assert_eq!(db.size_on_disk().unwrap(), DiskUsage::default());
+
+    let mut db: Database<i32, String> =
+        Database::with_defaults("size_on_disk.log", "size_on_disk.db").unwrap();
+    db.try_clear().unwrap();
+    assert!(db.data_size().is_err());
+    // 1件あたり、JSONで`"1000":"xx...x",`の約107 bytes
+    db.extend_transaction((1000..2000).map(|k| (k, "x".repeat(100))))
+        .unwrap();
+    let wal_bytes = db.wal_size().unwrap();
+    assert!(wal_bytes > 100 * 1000);
+    assert_eq!(wal_bytes, log_size("size_on_disk.log"));
+
+    db.compact_wal().unwrap();
+    let usage = db.size_on_disk().unwrap();
+    assert!(usage.data_bytes > 107 * 1000);
+    assert!(usage.data_bytes < 120 * 1000);
+    assert!(usage.wal_bytes < wal_bytes);
+    assert_eq!(usage.total_bytes, usage.data_bytes + usage.wal_bytes);
+}
+
+#[test]
+fn checkpoint_snapshot() {
+    let mut db: Database<i32, i32> =
+        Database::with_defaults("checkpoint_snapshot.log", "checkpoint_snapshot.db").unwrap();
+    db.try_clear().unwrap();
+    assert!(db.checkpoint_snapshot().unwrap().is_empty());
+    db.extend_transaction(vec![(1, 10), (2, 20), (3, 30)])
+        .unwrap();
+    db.compact_wal().unwrap();
+    let checkpoint = db.checkpoint_snapshot().unwrap();
+    assert_eq!(checkpoint.diff(&db.snapshot().unwrap()).count(), 0);
+    assert_eq!(checkpoint.diff(&checkpoint.clone()).count(), 0);
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 11).unwrap();
+    tx.delete(2).unwrap();
+    tx.create(4, 40).unwrap();
+    tx.commit().unwrap();
+    // チェックポイントを作成するまで、データファイルの内容は変わらない
+    let unchanged = db.checkpoint_snapshot().unwrap();
+    assert_eq!(unchanged.len(), 3);
+    assert_eq!(unchanged.get(&1), Option::Some(&10));
+    assert_eq!(
+        unchanged.diff(&db.snapshot().unwrap()).collect::<Vec<_>>(),
+        vec![
+            DiffEntry::Modified(1, 10, 11),
+            DiffEntry::Removed(2, 20),
+            DiffEntry::Added(4, 40),
+        ]
+    );
+
+    db.compact_wal().unwrap();
+    assert_eq!(
+        db.checkpoint_snapshot()
+            .unwrap()
+            .diff(&db.snapshot().unwrap())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn paths() {
+    let db: Database<i32, i32> = Database::in_memory().unwrap();
+    assert_eq!(db.log_path(), Option::None);
+    assert_eq!(db.data_path(), Option::None);
+
+    {
+        let mut db: Database<i32, i32> =
+            Database::with_defaults(PathBuf::from("paths.log"), Path::new("paths.db")).unwrap();
+        db.try_clear().unwrap();
+        assert_eq!(db.log_path(), Option::Some(Path::new("paths.log")));
+        assert_eq!(db.data_path(), Option::Some(Path::new("paths.db")));
+    }
+    let db: Database<i32, i32> = Database::open_read_only(PathBuf::from("paths.db")).unwrap();
+    assert_eq!(db.log_path(), Option::None);
+    assert_eq!(db.data_path(), Option::Some(Path::new("paths.db")));
+}
+
+#[test]
+fn hashed_backend() {
+    let _ = std::fs::remove_dir_all("hashed_backend.log");
+    let _ = std::fs::remove_file("hashed_backend.db");
+    let config = DatabaseConfig::builder()
+        .log_file("hashed_backend.log")
+        .data_file("hashed_backend.db")
+        .storage_backend(StorageBackend::Hashed)
+        .build();
+    {
+        let mut db: Database<i32, i32> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![(3, 30), (1, 10), (2, 20)])
+            .unwrap();
+        let mut tx = db.begin_transaction().unwrap();
+        tx.update(1, 11).unwrap();
+        tx.delete(2).unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), 11);
+        // キーの順序に依存する操作は行えない
+        assert!(matches!(
+            tx.scan_range(Bound::Unbounded, Bound::Unbounded).next(),
+            Option::Some(Result::Err(DatabaseError::UnsupportedOperation { .. }))
+        ));
+        assert!(matches!(
+            tx.scan_reverse(Bound::Unbounded, Bound::Unbounded).next(),
+            Option::Some(Result::Err(DatabaseError::UnsupportedOperation { .. }))
+        ));
+        assert!(matches!(
+            tx.delete_range(Bound::Unbounded, Bound::Unbounded),
+            Result::Err(DatabaseError::UnsupportedOperation { .. })
+        ));
+        tx.commit().unwrap();
+        assert!(matches!(
+            db.cursor(),
+            Result::Err(DatabaseError::UnsupportedOperation { .. })
+        ));
+        let mut pairs: Vec<_> = db.scan_all().unwrap().collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![(&1, &11), (&3, &30)]);
+        crash(db);
+    }
+    // Redoの結果は順序を保つデータ構造でも同じになる
+    let db: Database<i32, i32> = Database::new(DatabaseConfig {
+        storage_backend: StorageBackend::Sorted,
+        ..config.clone()
+    })
+    .unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![(&1, &11), (&3, &30)]
+    );
+    drop(db);
+    let mut db: Database<i32, i32> = Database::new(config).unwrap();
+    db.truncate().unwrap();
+    assert!(db.is_empty());
+}
+
+#[test]
+fn lazy_loading() {
+    let _ = std::fs::remove_dir_all("lazy_loading.log");
+    let _ = std::fs::remove_file("lazy_loading.db");
+    let config = DatabaseConfig::builder()
+        .log_file("lazy_loading.log")
+        .data_file("lazy_loading.db")
+        .build();
+    {
+        let mut db: Database<i32, serde_json::Value> = Database::new(config.clone()).unwrap();
+        db.extend_transaction(vec![
+            (1, serde_json::json!("a")),
+            (2, serde_json::json!(2)),
+            (3, serde_json::json!("c")),
+        ])
+        .unwrap();
+    }
+    // 値の型と一致しない内容があるため、すべての値を復元しようとすると失敗する
+    assert!(Database::<i32, String>::new(config.clone()).is_err());
+
+    let lazy = DatabaseConfig {
+        lazy_loading: true,
+        ..config.clone()
+    };
+    {
+        let mut db: Database<i32, String> = Database::new(lazy.clone()).unwrap();
+        assert_eq!(db.len(), 3);
+        assert_eq!(db.keys().collect::<Vec<_>>(), vec![&1, &2, &3]);
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(1).unwrap(), "a");
+        // 値の型と一致しない内容は、参照の時点でエラーとなる
+        assert!(matches!(
+            tx.read_silent(2),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        assert!(matches!(
+            tx.get_ref(&2),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        assert_eq!(tx.peek(&2), Option::None);
+        assert!(matches!(
+            tx.entry(2).map(|_| ()),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        // 走査は値を返す前に対象の範囲の値を復元し、復元できない値があればエラーとなる
+        assert!(matches!(
+            tx.scan_range(Bound::Unbounded, Bound::Unbounded).next(),
+            Option::Some(Result::Err(DatabaseError::JSONError { .. }))
+        ));
+        assert!(matches!(
+            tx.scan_reverse(Bound::Unbounded, Bound::Unbounded).next(),
+            Option::Some(Result::Err(DatabaseError::JSONError { .. }))
+        ));
+        assert!(matches!(
+            tx.find(|_, v| v == "c"),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        let pairs: Vec<(i32, String)> = tx
+            .scan_range(Bound::Included(3), Bound::Unbounded)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(pairs, vec![(3, "c".to_string())]);
+        // キーのみを参照する操作は値を復元しない
+        assert_eq!(tx.last_key(), Option::Some(3));
+        tx.update(3, "cc".to_string()).unwrap();
+        tx.create(4, "d".to_string()).unwrap();
+        tx.commit().unwrap();
+        assert!(matches!(
+            db.scan_all().map(|_| ()),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        assert!(matches!(
+            db.find(|_, _| true),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        assert!(matches!(
+            db.snapshot(),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        assert!(matches!(
+            db.cursor().map(|_| ()),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        let mut pages = db.paginate(10);
+        assert!(matches!(
+            pages.next_page(),
+            Result::Err(DatabaseError::JSONError { .. })
+        ));
+        crash(db);
+    }
+    {
+        // Redoの結果は値を復元せずに反映され、参照されていない内容はそのまま書き出される
+        let mut db: Database<i32, String> = Database::new(lazy.clone()).unwrap();
+        assert_eq!(db.len(), 4);
+        let mut tx = db.begin_transaction().unwrap();
+        assert_eq!(tx.read_silent(4).unwrap(), "d");
+        tx.delete(1).unwrap();
+        tx.commit().unwrap();
+    }
+    let db: Database<i32, serde_json::Value> = Database::new(config).unwrap();
+    assert_eq!(
+        db.scan_all().unwrap().collect::<Vec<_>>(),
+        vec![
+            (&2, &serde_json::json!(2)),
+            (&3, &serde_json::json!("cc")),
+            (&4, &serde_json::json!("d")),
+        ]
+    );
+}
+
+#[test]
+fn count_in_range() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    assert_eq!(db.count_in_range(Bound::Unbounded, Bound::Unbounded), 0);
+    db.extend_transaction((0..10).map(|x| (x * 10, x))).unwrap();
+    assert_eq!(
+        db.count_in_range(Bound::Unbounded, Bound::Unbounded),
+        db.len()
+    );
+    assert_eq!(
+        db.count_in_range(Bound::Included(20), Bound::Excluded(50)),
+        3
+    );
+    assert_eq!(
+        db.count_in_range(Bound::Excluded(20), Bound::Excluded(30)),
+        0
+    );
+    // 開始が終了より後ろの範囲は空とみなす
+    assert_eq!(
+        db.count_in_range(Bound::Included(50), Bound::Included(20)),
+        0
+    );
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(25, 0).unwrap();
+    tx.create(35, 0).unwrap();
+    tx.delete(30).unwrap();
+    tx.update(40, 0).unwrap();
+    tx.create(95, 0).unwrap();
+    tx.delete(95).unwrap();
+    assert_eq!(
+        tx.count_in_range(Bound::Included(20), Bound::Excluded(50)),
+        4
+    );
+    assert_eq!(tx.count_in_range(Bound::Unbounded, Bound::Unbounded), 11);
+    assert_eq!(tx.count_in_range(Bound::Excluded(90), Bound::Unbounded), 0);
+    tx.commit().unwrap();
+    assert_eq!(
+        db.count_in_range(Bound::Included(20), Bound::Excluded(50)),
+        4
+    );
+}
+
+#[test]
+fn first_and_last_key() {
+    for &backend in &[StorageBackend::Sorted, StorageBackend::Hashed] {
+        let config = DatabaseConfig::builder()
+            .in_memory(true)
+            .storage_backend(backend)
+            .build();
+        let mut db: Database<i32, i32> = Database::new(config).unwrap();
+        assert_eq!(db.first_key(), Option::None);
+        assert_eq!(db.last_key(), Option::None);
+        db.extend_transaction((1..=5).map(|x| (x, x))).unwrap();
+        assert_eq!(db.first_key(), Option::Some(1));
+        assert_eq!(db.last_key(), Option::Some(5));
+
+        let mut tx = db.begin_transaction().unwrap();
+        tx.delete(1).unwrap();
+        tx.delete(2).unwrap();
+        tx.delete(5).unwrap();
+        assert_eq!(tx.first_key(), Option::Some(3));
+        assert_eq!(tx.last_key(), Option::Some(4));
+        tx.create(0, 0).unwrap();
+        assert_eq!(tx.first_key(), Option::Some(0));
+        tx.delete(0).unwrap();
+        tx.delete(3).unwrap();
+        tx.delete(4).unwrap();
+        assert_eq!(tx.first_key(), Option::None);
+        assert_eq!(tx.last_key(), Option::None);
+        tx.create(6, 6).unwrap();
+        assert_eq!(tx.first_key(), Option::Some(6));
+        assert_eq!(tx.last_key(), Option::Some(6));
+        tx.commit().unwrap();
+        assert_eq!(db.first_key(), Option::Some(6));
+        assert_eq!(db.last_key(), Option::Some(6));
+    }
+}
+
+#[test]
+fn commit_returns_changeset() {
+    let mut db: Database<i32, i32> = Database::in_memory().unwrap();
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(1, 10).unwrap();
+    tx.create(2, 20).unwrap();
+    let changeset = tx.commit().unwrap();
+    assert_eq!(
+        changeset.into_iter().collect::<Vec<_>>(),
+        vec![Change::Create(1, 10), Change::Create(2, 20)]
+    );
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.update(1, 11).unwrap();
+    tx.delete(2).unwrap();
+    // コミット済みの内容を変更しない書き込みは含まれない
+    tx.create(3, 30).unwrap();
+    tx.delete(3).unwrap();
+    let diff = tx.diff();
+    let changeset = tx.commit().unwrap();
+    assert_eq!(changeset, diff);
+    assert_eq!(
+        changeset.iter().cloned().collect::<Vec<_>>(),
+        vec![Change::Update(1, 11), Change::Delete(2)]
+    );
+
+    let mut tx = db.begin_transaction().unwrap();
+    tx.create(4, 40).unwrap();
+    tx.commit_silent().unwrap();
+    assert_eq!(db.len(), 2);
+    assert!(db.begin_transaction().unwrap().commit().unwrap().is_empty());
+}